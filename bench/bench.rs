@@ -1,5 +1,7 @@
 use clap::Parser;
-use std::path::PathBuf;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Command, ExitStatus, Stdio};
 
 const TOOLS: [&str; 2] = [
@@ -12,6 +14,8 @@ const GTFILES: [&str; 4] = [
     "gencode.v46.chr_patch_hapl_scaff.annotation.gff3.gz",
     "gencode.v46.chr_patch_hapl_scaff.annotation.gff3",
 ];
+/// Generated GENCODE-like GTF, written to the assets directory.
+const SYNTHETIC: &str = "synthetic.gencode.gtf";
 const STDOUT: &str = "*.bed";
 const CSV: &str = "bed.files.csv";
 const MD: &str = "bed.files.md";
@@ -32,6 +36,14 @@ pub struct Args {
         help = "Extra arguments to pass to hyperfine"
     )]
     hyperfine_args: Vec<String>,
+
+    #[clap(
+        long = "synthetic",
+        help = "Also benchmark a generated GENCODE-scale GTF with N transcripts (e.g. 400000), \
+                reporting peak memory with --profile",
+        value_name = "N"
+    )]
+    synthetic: Option<usize>,
 }
 
 pub struct HyperfineCall {
@@ -105,11 +117,77 @@ impl HyperfineCall {
     }
 }
 
+/// Writes a GENCODE-like GTF with `transcripts` models of 1 to 15 exons
+/// (about 8 on average), two per gene, half of them on the minus strand
+/// with exons listed 5' to 3', i.e. in descending order as in GENCODE.
+fn write_synthetic(path: &Path, transcripts: usize) -> std::io::Result<()> {
+    const EXON: u64 = 200;
+    const STEP: u64 = 300;
+    // INFO: 1-based, inclusive end of a model with `exons` exons from `start`
+    let span = |start: u64, exons: u64| start + (exons - 1) * STEP + EXON - 1;
+
+    let mut out = BufWriter::new(File::create(path)?);
+    let mut starts = [1_u64; 22];
+    for tx in 0..transcripts {
+        let (gene, chrom) = (tx / 2, tx / 2 % 22);
+        let strand = if gene % 2 == 0 { '+' } else { '-' };
+        let exons = (tx % 15 + 1) as u64;
+        let start = starts[chrom];
+        let (end, gene_end) = (span(start, exons), span(start, 15));
+        let gene_attrs = format!(
+            "gene_id \"ENSG{:011}.1\"; gene_type \"protein_coding\"; gene_name \"G{}\"; level 2;",
+            gene, gene
+        );
+        let attrs = format!(
+            "{} transcript_id \"ENST{:011}.1\"; transcript_type \"protein_coding\"; tag \"basic\";",
+            gene_attrs, tx
+        );
+        if tx % 2 == 0 {
+            writeln!(
+                out,
+                "chr{}\tHAVANA\tgene\t{start}\t{gene_end}\t.\t{strand}\t.\t{gene_attrs}",
+                chrom + 1
+            )?;
+        } else {
+            starts[chrom] = gene_end + 1_000;
+        }
+        writeln!(
+            out,
+            "chr{}\tHAVANA\ttranscript\t{start}\t{end}\t.\t{strand}\t.\t{attrs}",
+            chrom + 1
+        )?;
+        for rank in 0..exons {
+            let idx = match strand {
+                '+' => rank,
+                _ => exons - 1 - rank,
+            };
+            let exon_start = start + idx * STEP;
+            writeln!(
+                out,
+                "chr{}\tHAVANA\texon\t{exon_start}\t{}\t.\t{strand}\t.\t{attrs} exon_number {};",
+                chrom + 1,
+                exon_start + EXON - 1,
+                rank + 1
+            )?;
+        }
+    }
+    out.flush()
+}
+
 fn benchmark() -> Result<(String, String), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
     std::fs::create_dir_all("runs")?;
     let assets = args.assets.to_string_lossy();
+    let mut files = GTFILES
+        .iter()
+        .map(|s| format!("{}/{}", assets, s))
+        .collect::<Vec<_>>();
+    if let Some(transcripts) = args.synthetic {
+        let path = args.assets.join(SYNTHETIC);
+        write_synthetic(&path, transcripts)?;
+        files.push(path.to_string_lossy().into_owned());
+    }
 
     #[allow(clippy::needless_update)]
     let code = HyperfineCall {
@@ -118,13 +196,7 @@ fn benchmark() -> Result<(String, String), Box<dyn std::error::Error>> {
         max_runs: Some(20),
         export_csv: Some(format!("runs/{}", CSV).to_string()),
         export_markdown: Some(format!("runs/{}", MD).to_string()),
-        parameters: vec![(
-            "bed".to_string(),
-            GTFILES
-                .iter()
-                .map(|s| format!("{}/{}", assets, s))
-                .collect(),
-        )],
+        parameters: vec![("bed".to_string(), files)],
         setup: Some("cargo build --release".to_string()),
         cleanup: Some(format!("rm -f {} test/*.bed", STDOUT)),
         commands: TOOLS
@@ -146,6 +218,18 @@ fn benchmark() -> Result<(String, String), Box<dyn std::error::Error>> {
         return Err(format!("Benchmark failed with exit code {}", code).into());
     }
 
+    // INFO: hyperfine only times runs; one profiled run reports peak memory per phase
+    if args.synthetic.is_some() {
+        let status = Command::new("target/release/gxf2bed")
+            .arg("-i")
+            .arg(args.assets.join(SYNTHETIC))
+            .args(["-o", "./test/output.bed", "--profile"])
+            .status()?;
+        if !status.success() {
+            return Err(format!("Profiled run failed with {}", status).into());
+        }
+    }
+
     Ok((format!("runs/{}", CSV), format!("runs/{}", MD)))
 }
 
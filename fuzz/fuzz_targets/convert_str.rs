@@ -0,0 +1,12 @@
+#![no_main]
+
+use gxf2bed::{convert_str, Config, InputFormat};
+use libfuzzer_sys::fuzz_target;
+
+// whole files through grouping and BED output, as GTF and as GFF
+fuzz_target!(|data: &str| {
+    let config = Config::default();
+    for format in [InputFormat::Gtf, InputFormat::Gff] {
+        let _ = convert_str(data, format, &config);
+    }
+});
@@ -0,0 +1,20 @@
+#![no_main]
+
+use gxf2bed::gxf::{GxfRecord, KeyMatcher};
+use gxf2bed::InputCoords;
+use libfuzzer_sys::fuzz_target;
+
+// single lines through the record parser, with any attribute separator
+fuzz_target!(|data: &[u8]| {
+    let Some((&sep, line)) = data.split_first() else {
+        return;
+    };
+    let Ok(line) = std::str::from_utf8(line) else {
+        return;
+    };
+
+    let keys = KeyMatcher::new(&["gene_id", "transcript_id", "ID", "Parent"]);
+    for coords in [InputCoords::One, InputCoords::Zero] {
+        let _ = GxfRecord::parse_with(line, &keys, sep, coords);
+    }
+});
@@ -0,0 +1,247 @@
+use std::io::BufWriter;
+use std::time::Instant;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::checksum::Hashing;
+use crate::config::Config;
+use crate::convert::{
+    decode_input, emitter, records_from_str, run_children, write_output, write_to_path, RunStats,
+};
+use crate::detect::{detect_input_kind, Compression, InputKind};
+use crate::error::Result;
+use crate::output::{finish_output, Encoder};
+use crate::progress::NoProgress;
+
+/// Async counterpart of [`crate::run`] for tokio runtimes.
+///
+/// Reading `config.input` is asynchronous; decompression, parsing and
+/// writing the outputs (extra `outputs`, `child_outputs`, `tee` and the
+/// checksum included, as in [`crate::run`]) run on tokio's blocking pool so the calling
+/// runtime is never stalled.
+pub async fn run_async(config: &Config) -> Result<RunStats> {
+    let started = Instant::now();
+    // INFO: one parse feeds every child output, reading the input on the blocking pool
+    if !config.child_outputs.is_empty() {
+        let input_bytes = tokio::fs::metadata(&config.input).await?.len();
+        let config = config.clone();
+        let mut stats = blocking(move || run_children(&config, &NoProgress)).await?;
+        stats.input_bytes = input_bytes;
+        stats.elapsed = started.elapsed();
+        return Ok(stats);
+    }
+
+    let kind = detect_input_kind(&config.input)?;
+    let bytes = tokio::fs::read(&config.input).await?;
+    let input_bytes = bytes.len() as u64;
+
+    let config = config.clone();
+    let mut stats = blocking(move || {
+        let contents = decode(&config, &bytes, kind)?;
+        let records = records_from_str(&config, &contents, kind.format);
+        write_to_path(&config, records, &*emitter(&config))
+    })
+    .await?;
+
+    stats.input_bytes = input_bytes;
+    stats.elapsed = started.elapsed();
+    Ok(stats)
+}
+
+/// Same as [`run_async`], reading `kind` data from `reader` and writing
+/// uncompressed BED into `writer` (e.g. an upload body and a response stream).
+pub async fn run_async_with_io<R, W>(
+    config: &Config,
+    mut reader: R,
+    kind: InputKind,
+    mut writer: W,
+) -> Result<RunStats>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).await?;
+
+    let (out, stats) = render(config, bytes, kind, Compression::None).await?;
+    writer.write_all(&out).await?;
+    writer.flush().await?;
+
+    Ok(stats)
+}
+
+async fn render(
+    config: &Config,
+    bytes: Vec<u8>,
+    kind: InputKind,
+    compression: Compression,
+) -> Result<(Vec<u8>, RunStats)> {
+    let config = config.clone();
+
+    blocking(move || {
+        let contents = decode(&config, &bytes, kind)?;
+        let records = records_from_str(&config, &contents, kind.format);
+
+        let mut writer = BufWriter::new(Encoder::parallel(
+            Hashing::new(Vec::new(), config.checksum),
+            compression,
+            config.compress_level,
+            config.effective_threads(),
+        )?);
+        let mut stats = write_output(records, &mut writer, &*emitter(&config))?;
+        let (out, checksum) = finish_output(writer)?.finish();
+        stats.checksum = checksum;
+        Ok((out, stats))
+    })
+    .await
+}
+
+/// Decodes `kind` input `bytes` into text.
+fn decode(config: &Config, bytes: &[u8], kind: InputKind) -> Result<String> {
+    decode_input(
+        bytes,
+        kind.compression,
+        config.allow_truncated,
+        config.lossy_utf8,
+    )
+}
+
+/// Runs `op` on tokio's blocking pool.
+async fn blocking<T: Send + 'static>(op: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(op)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::InputFormat;
+
+    #[tokio::test]
+    async fn test_run_async_with_io() {
+        let gtf = "chr1\tHAVANA\ttranscript\t11\t60\t.\t+\t.\ttranscript_id \"A\";\n\
+                   chr1\tHAVANA\texon\t11\t60\t.\t+\t.\ttranscript_id \"A\";\n";
+        let config = Config::new("unused.gtf", "unused.bed");
+        let kind = InputKind {
+            format: InputFormat::Gtf,
+            compression: Compression::None,
+        };
+
+        let mut out = Vec::new();
+        let stats = run_async_with_io(&config, gtf.as_bytes(), kind, &mut out)
+            .await
+            .unwrap();
+
+        assert_eq!(stats.written, 1);
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .starts_with("chr1\t10\t60\tA\t"));
+    }
+
+    #[tokio::test]
+    async fn test_run_async_outputs() {
+        let gtf = "chr1\tHAVANA\ttranscript\t11\t60\t.\t+\t.\ttranscript_id \"A\";\n\
+                   chr1\tHAVANA\texon\t11\t60\t.\t+\t.\ttranscript_id \"A\";\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.gtf");
+        let (output, bed6) = (dir.path().join("out.bed"), dir.path().join("out.6.bed"));
+        std::fs::write(&input, gtf).unwrap();
+        let mut config = Config::new(&input, &output);
+        config.outputs = vec![format!("bed6={}", bed6.display()).parse().unwrap()];
+
+        let stats = run_async(&config).await.unwrap();
+
+        assert_eq!(stats.written, 1);
+        assert!(std::fs::read_to_string(&output)
+            .unwrap()
+            .starts_with("chr1\t10\t60\tA\t"));
+        assert_eq!(
+            std::fs::read_to_string(&bed6).unwrap(),
+            "chr1\t10\t60\tA\t0\t+\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_async_tee() {
+        let gtf = "chr1\tHAVANA\texon\t11\t60\t.\t+\t.\ttranscript_id \"A\";\n";
+        let dir = tempfile::tempdir().unwrap();
+        let (input, output) = (dir.path().join("in.gtf"), dir.path().join("out.bed"));
+        std::fs::write(&input, gtf).unwrap();
+        let config = Config::builder()
+            .input(&input)
+            .output(&output)
+            .tee(true)
+            .build()
+            .unwrap();
+
+        // INFO: bytes are counted per sink, so stdout doubles them
+        let stats = run_async(&config).await.unwrap();
+        assert_eq!(
+            stats.output_bytes,
+            2 * std::fs::metadata(&output).unwrap().len()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_async_child_outputs() {
+        let gtf = "chr1\tHAVANA\texon\t11\t60\t.\t+\t.\ttranscript_id \"A\";\n\
+                   chr1\tHAVANA\tCDS\t21\t50\t.\t+\t0\ttranscript_id \"A\";\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.gtf");
+        let (exons, cds) = (dir.path().join("exon.bed"), dir.path().join("cds.bed"));
+        std::fs::write(&input, gtf).unwrap();
+        let config = Config::builder()
+            .input(&input)
+            .bed_type(crate::BedType::Bed6)
+            .child_output("exon", &exons)
+            .child_output("CDS", &cds)
+            .build()
+            .unwrap();
+
+        let stats = run_async(&config).await.unwrap();
+
+        assert_eq!(stats.written, 2);
+        assert_eq!(
+            std::fs::read_to_string(&exons).unwrap(),
+            "chr1\t10\t60\tA\t0\t+\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&cds).unwrap(),
+            "chr1\t20\t50\tA\t0\t+\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_async_checksum() {
+        let gtf = "chr1\tHAVANA\texon\t11\t60\t.\t+\t.\ttranscript_id \"A\";\n";
+        let dir = tempfile::tempdir().unwrap();
+        let (input, output) = (dir.path().join("in.gtf"), dir.path().join("out.bed"));
+        std::fs::write(&input, gtf).unwrap();
+        let config = Config::builder()
+            .input(&input)
+            .output(&output)
+            .checksum(crate::Checksum::Md5)
+            .build()
+            .unwrap();
+        let digest = |bytes: &[u8]| {
+            let mut hashing = Hashing::new(Vec::new(), config.checksum);
+            std::io::Write::write_all(&mut hashing, bytes).unwrap();
+            hashing.finish().1
+        };
+
+        let stats = run_async(&config).await.unwrap();
+        assert_eq!(stats.checksum, digest(&std::fs::read(&output).unwrap()));
+
+        let kind = InputKind {
+            format: InputFormat::Gtf,
+            compression: Compression::None,
+        };
+        let mut out = Vec::new();
+        let stats = run_async_with_io(&config, gtf.as_bytes(), kind, &mut out)
+            .await
+            .unwrap();
+        assert!(stats.checksum.is_some());
+        assert_eq!(stats.checksum, digest(&out));
+    }
+}
@@ -0,0 +1,816 @@
+use std::io::Write;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Gxf2BedError, Result};
+use crate::gxf::{GenePred, RecordType, Strand};
+
+/// Number of standard BED columns written per record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BedType {
+    Bed3,
+    Bed4,
+    Bed5,
+    Bed6,
+    Bed9,
+    #[default]
+    Bed12,
+}
+
+impl BedType {
+    pub fn columns(&self) -> usize {
+        match self {
+            BedType::Bed3 => 3,
+            BedType::Bed4 => 4,
+            BedType::Bed5 => 5,
+            BedType::Bed6 => 6,
+            BedType::Bed9 => 9,
+            BedType::Bed12 => 12,
+        }
+    }
+}
+
+/// What each converted record is written as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Emit {
+    /// The record itself, in `bed_type` columns.
+    #[default]
+    Records,
+    /// BED6 windows around the donor and acceptor site of each intron.
+    SpliceSites,
+    /// One BED6 line per block, named `<name>_exon<k>` with exons numbered
+    /// from the 5' end.
+    Exons,
+}
+
+/// A converted BED12+N record.
+///
+/// Coordinates are 0-based, half-open. `blocks` holds absolute `(start, end)`
+/// intervals sorted by start; `extras` are written as additional columns
+/// after the standard ones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BedRecord {
+    pub chrom: String,
+    pub start: u64,
+    pub end: u64,
+    pub name: String,
+    pub score: u32,
+    pub strand: Strand,
+    pub thick_start: u64,
+    pub thick_end: u64,
+    /// Item color; written as `0` when unset.
+    pub rgb: Option<(u8, u8, u8)>,
+    pub blocks: Vec<(u64, u64)>,
+    pub extras: Vec<String>,
+}
+
+impl BedRecord {
+    /// Builds a record from a grouped gene model. Returns `Ok(None)` when the
+    /// model has no child features and therefore no blocks.
+    pub fn from_gene_pred(name: String, info: GenePred) -> Result<Option<Self>> {
+        if info.exons.is_empty() {
+            return Ok(None);
+        }
+
+        let (thick_start, thick_end) = info.get_cds();
+        if (thick_start >= thick_end) || (info.start >= info.end) {
+            return Err(Gxf2BedError::InvalidRecord(format!(
+                "start >= end in record {}",
+                name
+            )));
+        }
+        if thick_start < info.start {
+            return Err(Gxf2BedError::InvalidRecord(format!(
+                "block starts before record start in {}",
+                name
+            )));
+        }
+
+        Ok(Some(Self {
+            chrom: info.chr,
+            start: info.start,
+            end: info.end,
+            name,
+            score: 0,
+            strand: info.strand,
+            thick_start,
+            thick_end,
+            rgb: None,
+            blocks: info
+                .exons
+                .iter()
+                .map(|(start, size)| (*start, start + size))
+                .collect(),
+            extras: Vec::new(),
+        }))
+    }
+
+    /// Checks the BED12 invariants: chromStart before chromEnd, non-empty
+    /// blocks in ascending, non-overlapping order with the first starting
+    /// at chromStart and the last ending at chromEnd, and the thick span
+    /// within the record. Returns the first one broken.
+    pub fn check(&self) -> std::result::Result<(), String> {
+        if self.start >= self.end {
+            return Err(format!(
+                "chromStart {} is not before chromEnd {}",
+                self.start, self.end
+            ));
+        }
+        let (Some(first), Some(last)) = (self.blocks.first(), self.blocks.last()) else {
+            return Err("no blocks".to_string());
+        };
+        if first.0 != self.start {
+            return Err(format!(
+                "first block starts at {} instead of chromStart {}",
+                first.0, self.start
+            ));
+        }
+        if last.1 != self.end {
+            return Err(format!(
+                "last block ends at {} instead of chromEnd {}",
+                last.1, self.end
+            ));
+        }
+        if let Some(idx) = self.blocks.iter().position(|(start, end)| start >= end) {
+            return Err(format!("block {} is empty", idx + 1));
+        }
+        if let Some(idx) = self.blocks.windows(2).position(|w| w[1].0 < w[0].1) {
+            return Err(format!(
+                "block {} starts at {}, before block {} ends at {}",
+                idx + 2,
+                self.blocks[idx + 1].0,
+                idx + 1,
+                self.blocks[idx].1
+            ));
+        }
+        if self.thick_start > self.thick_end
+            || self.thick_start < self.start
+            || self.thick_end > self.end
+        {
+            return Err(format!(
+                "thickStart-thickEnd {}-{} is not within {}-{}",
+                self.thick_start, self.thick_end, self.start, self.end
+            ));
+        }
+        Ok(())
+    }
+
+    /// Turns the record back into a gene model, with blocks as child features.
+    pub fn to_gene_pred(&self) -> GenePred {
+        GenePred {
+            chr: self.chrom.clone(),
+            start: self.start,
+            end: self.end,
+            strand: self.strand,
+            exons: self
+                .blocks
+                .iter()
+                .map(|(start, end)| (*start, end - start))
+                .collect(),
+            record_type: RecordType::Parent,
+            attrs: Vec::new(),
+            coding: None,
+            exon_numbers: Vec::new(),
+            duplicates: 0,
+            loci: 0,
+            strands: [0; 2],
+        }
+    }
+
+    /// 1-based exon number of each block, counted from the 5' end; the
+    /// `exon_number` attributes in `numbers` (as `(start, number)`) are
+    /// used instead when every block has one.
+    pub fn exon_ranks(&self, mut numbers: Vec<(u64, u32)>) -> Vec<u32> {
+        numbers.sort_unstable();
+        let given = self
+            .blocks
+            .iter()
+            .map(|(start, _)| {
+                let idx = numbers.binary_search_by_key(start, |(s, _)| *s).ok()?;
+                Some(numbers[idx].1)
+            })
+            .collect::<Option<Vec<_>>>();
+
+        given.unwrap_or_else(|| {
+            let count = self.blocks.len() as u32;
+            (1..=count)
+                .map(|rank| match self.strand {
+                    Strand::Reverse => count + 1 - rank,
+                    _ => rank,
+                })
+                .collect()
+        })
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Block sizes as written in the 11th BED column.
+    pub fn block_sizes(&self) -> Vec<u64> {
+        self.blocks.iter().map(|(start, end)| end - start).collect()
+    }
+
+    /// Block starts relative to `start`, as written in the 12th BED column.
+    pub fn block_starts(&self) -> Vec<u64> {
+        self.blocks
+            .iter()
+            .map(|(start, _)| start - self.start)
+            .collect()
+    }
+}
+
+fn join(values: Vec<u64>) -> String {
+    values
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+        + ","
+}
+
+/// Renders converted records into an output format.
+///
+/// [`BedType`] implements this for the standard BED flavors; downstream
+/// crates can implement it to write their own layouts through
+/// [`crate::convert::run_with_emitter`].
+pub trait BedEmitter: Send + Sync {
+    /// Appends bytes written once before the first record.
+    fn header(&self, _out: &mut Vec<u8>) {}
+
+    /// Appends `record`, including its trailing newline.
+    fn emit(&self, record: &BedRecord, out: &mut Vec<u8>);
+
+    /// Appends bytes written once after the last record.
+    fn footer(&self, _out: &mut Vec<u8>) {}
+}
+
+impl BedEmitter for BedType {
+    fn emit(&self, record: &BedRecord, out: &mut Vec<u8>) {
+        write_record(out, record, *self).expect("ERROR: Could not write to buffer");
+    }
+}
+
+/// [`BedType`] layout with 1-based, inclusive coordinates: `chromStart` and
+/// `thickStart` are shifted by one, block starts stay relative to
+/// `chromStart`. A header line records the convention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OneBased(pub BedType);
+
+impl BedEmitter for OneBased {
+    fn header(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(b"#coordinates: 1-based, inclusive\n");
+    }
+
+    fn emit(&self, record: &BedRecord, out: &mut Vec<u8>) {
+        write_fields(out, record, self.0, 1).expect("ERROR: Could not write to buffer");
+    }
+}
+
+/// A column of a [`Columns`] layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Column {
+    Chrom,
+    Start,
+    End,
+    Name,
+    Score,
+    Strand,
+    ThickStart,
+    ThickEnd,
+    ItemRgb,
+    BlockCount,
+    BlockSizes,
+    BlockStarts,
+    /// Attribute value, taken from the record's extras at this index; `.`
+    /// when the model has none.
+    Attr(usize),
+}
+
+/// User-chosen column layout (`--columns`): standard BED fields by their
+/// BED spec or snake_case name, anything else an attribute, in the order
+/// given. Attribute values are the record's leading extras, one per
+/// attribute column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Columns {
+    pub columns: Vec<Column>,
+    /// Shift starts to 1-based, inclusive, as [`OneBased`] does.
+    pub one_based: bool,
+}
+
+impl Columns {
+    pub fn new(names: &[String], one_based: bool) -> Self {
+        let mut attrs = 0;
+        let columns = names
+            .iter()
+            .map(|name| {
+                Column::field(name).unwrap_or_else(|| {
+                    attrs += 1;
+                    Column::Attr(attrs - 1)
+                })
+            })
+            .collect();
+
+        Self { columns, one_based }
+    }
+
+    /// The attribute columns of `names`, in order.
+    pub fn attrs(names: &[String]) -> Vec<String> {
+        names
+            .iter()
+            .filter(|name| Column::field(name).is_none())
+            .cloned()
+            .collect()
+    }
+}
+
+impl Column {
+    /// Standard field called `name`, if any.
+    fn field(name: &str) -> Option<Self> {
+        Some(match name {
+            "chrom" => Column::Chrom,
+            "start" | "chromStart" | "chrom_start" => Column::Start,
+            "end" | "chromEnd" | "chrom_end" => Column::End,
+            "name" => Column::Name,
+            "score" => Column::Score,
+            "strand" => Column::Strand,
+            "thickStart" | "thick_start" => Column::ThickStart,
+            "thickEnd" | "thick_end" => Column::ThickEnd,
+            "itemRgb" | "item_rgb" => Column::ItemRgb,
+            "blockCount" | "block_count" => Column::BlockCount,
+            "blockSizes" | "block_sizes" => Column::BlockSizes,
+            "blockStarts" | "block_starts" => Column::BlockStarts,
+            _ => return None,
+        })
+    }
+}
+
+impl BedEmitter for Columns {
+    fn header(&self, out: &mut Vec<u8>) {
+        if self.one_based {
+            OneBased(BedType::Bed12).header(out);
+        }
+    }
+
+    fn emit(&self, r: &BedRecord, out: &mut Vec<u8>) {
+        let base = self.one_based as u64;
+        for (i, column) in self.columns.iter().enumerate() {
+            if i > 0 {
+                out.push(b'\t');
+            }
+            match column {
+                Column::Chrom => write!(out, "{}", r.chrom),
+                Column::Start => write!(out, "{}", r.start + base),
+                Column::End => write!(out, "{}", r.end),
+                Column::Name => write!(out, "{}", r.name),
+                Column::Score => write!(out, "{}", r.score),
+                Column::Strand => write!(out, "{}", r.strand),
+                Column::ThickStart => write!(out, "{}", r.thick_start + base),
+                Column::ThickEnd => write!(out, "{}", r.thick_end),
+                Column::ItemRgb => match r.rgb {
+                    Some((red, green, blue)) => write!(out, "{},{},{}", red, green, blue),
+                    None => write!(out, "0"),
+                },
+                Column::BlockCount => write!(out, "{}", r.block_count()),
+                Column::BlockSizes => write!(out, "{}", join(r.block_sizes())),
+                Column::BlockStarts => write!(out, "{}", join(r.block_starts())),
+                Column::Attr(i) => write!(out, "{}", r.extras.get(*i).map_or(".", String::as_str)),
+            }
+            .expect("ERROR: Could not write to buffer");
+        }
+        out.push(b'\n');
+    }
+}
+
+/// Tab-separated table with a header row: position, name, score, strand,
+/// exon count and spliced length, then any extra columns unnamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tsv;
+
+impl BedEmitter for Tsv {
+    fn header(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(b"chrom\tstart\tend\tname\tscore\tstrand\texon_count\tlength\n");
+    }
+
+    fn emit(&self, r: &BedRecord, out: &mut Vec<u8>) {
+        let length = r.blocks.iter().map(|(start, end)| end - start).sum::<u64>();
+        write!(
+            out,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            r.chrom,
+            r.start,
+            r.end,
+            r.name,
+            r.score,
+            r.strand,
+            r.block_count(),
+            length
+        )
+        .and_then(|_| {
+            r.extras
+                .iter()
+                .try_for_each(|extra| write!(out, "\t{}", extra))
+        })
+        .and_then(|_| writeln!(out))
+        .expect("ERROR: Could not write to buffer");
+    }
+}
+
+/// BED6 windows of `window` bp on either side of each splice site, named
+/// `<name>_intron<k>_donor` and `<name>_intron<k>_acceptor` with introns
+/// numbered from the 5' end. The donor is the intron's 5' end on the
+/// record's strand; unstranded records are read as `+`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpliceSites {
+    pub window: u64,
+}
+
+impl BedEmitter for SpliceSites {
+    fn emit(&self, r: &BedRecord, out: &mut Vec<u8>) {
+        let introns = r.blocks.windows(2).map(|pair| (pair[0].1, pair[1].0));
+        let count = r.blocks.len().saturating_sub(1);
+        for (i, (start, end)) in introns.enumerate() {
+            let (rank, donor, acceptor) = match r.strand {
+                Strand::Reverse => (count - i, end, start),
+                _ => (i + 1, start, end),
+            };
+            for (site, pos) in [("donor", donor), ("acceptor", acceptor)] {
+                writeln!(
+                    out,
+                    "{}\t{}\t{}\t{}_intron{}_{}\t{}\t{}",
+                    r.chrom,
+                    pos.saturating_sub(self.window),
+                    pos.saturating_add(self.window),
+                    r.name,
+                    rank,
+                    site,
+                    r.score,
+                    r.strand
+                )
+                .expect("ERROR: Could not write to buffer");
+            }
+        }
+    }
+}
+
+/// Writes each block of a record as its own BED6 line, named
+/// `<name>_exon<k>` with `k` counted from the 5' end; unstranded records
+/// are read as `+`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exons;
+
+impl BedEmitter for Exons {
+    fn emit(&self, r: &BedRecord, out: &mut Vec<u8>) {
+        let count = r.blocks.len();
+        for (i, (start, end)) in r.blocks.iter().enumerate() {
+            let rank = match r.strand {
+                Strand::Reverse => count - i,
+                _ => i + 1,
+            };
+            writeln!(
+                out,
+                "{}\t{}\t{}\t{}_exon{}\t{}\t{}",
+                r.chrom, start, end, r.name, rank, r.score, r.strand
+            )
+            .expect("ERROR: Could not write to buffer");
+        }
+    }
+}
+
+impl From<&BedRecord> for GenePred {
+    fn from(record: &BedRecord) -> Self {
+        record.to_gene_pred()
+    }
+}
+
+/// Writes `record` as a single `bed_type` line, followed by its extras.
+pub fn write_record<W: Write>(
+    writer: &mut W,
+    record: &BedRecord,
+    bed_type: BedType,
+) -> std::io::Result<()> {
+    write_fields(writer, record, bed_type, 0)
+}
+
+/// Writes `record` with `base` added to its start coordinates.
+fn write_fields<W: Write>(
+    writer: &mut W,
+    r: &BedRecord,
+    bed_type: BedType,
+    base: u64,
+) -> std::io::Result<()> {
+    let columns = bed_type.columns();
+
+    write!(writer, "{}\t{}\t{}", r.chrom, r.start + base, r.end)?;
+    if columns >= 4 {
+        write!(writer, "\t{}", r.name)?;
+    }
+    if columns >= 5 {
+        write!(writer, "\t{}", r.score)?;
+    }
+    if columns >= 6 {
+        write!(writer, "\t{}", r.strand)?;
+    }
+    if columns >= 9 {
+        write!(writer, "\t{}\t{}\t", r.thick_start + base, r.thick_end)?;
+        match r.rgb {
+            Some((red, green, blue)) => write!(writer, "{},{},{}", red, green, blue)?,
+            None => write!(writer, "0")?,
+        }
+    }
+    if columns >= 12 {
+        write!(
+            writer,
+            "\t{}\t{}\t{}",
+            r.block_count(),
+            join(r.block_sizes()),
+            join(r.block_starts())
+        )?;
+    }
+    for extra in &r.extras {
+        write!(writer, "\t{}", extra)?;
+    }
+
+    writeln!(writer)
+}
+
+impl std::fmt::Display for BedRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut line = Vec::new();
+        write_record(&mut line, self, BedType::Bed12).map_err(|_| std::fmt::Error)?;
+        line.pop();
+
+        f.write_str(&String::from_utf8_lossy(&line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> BedRecord {
+        BedRecord {
+            chrom: "chr1".to_string(),
+            start: 10,
+            end: 60,
+            name: "tx".to_string(),
+            score: 0,
+            strand: Strand::Reverse,
+            thick_start: 10,
+            thick_end: 60,
+            rgb: None,
+            blocks: vec![(10, 60)],
+            extras: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_from_gene_pred() {
+        let info = GenePred {
+            chr: "chr1".to_string(),
+            start: 11868,
+            end: 12300,
+            strand: Strand::Forward,
+            exons: vec![(11868, 50), (12200, 100)].into_iter().collect(),
+            record_type: RecordType::Parent,
+            attrs: Vec::new(),
+            coding: None,
+            exon_numbers: Vec::new(),
+            duplicates: 0,
+            loci: 0,
+            strands: [0; 2],
+        };
+
+        let record = BedRecord::from_gene_pred("tx".to_string(), info)
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.blocks, vec![(11868, 11918), (12200, 12300)]);
+        assert_eq!(
+            record.to_string(),
+            "chr1\t11868\t12300\ttx\t0\t+\t11868\t12300\t0\t2\t50,100,\t0,332,"
+        );
+    }
+
+    #[test]
+    fn test_write_record_bed_types() {
+        let record = record();
+        let render = |bed_type| {
+            let mut out = Vec::new();
+            write_record(&mut out, &record, bed_type).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!(render(BedType::Bed3), "chr1\t10\t60\n");
+        assert_eq!(render(BedType::Bed6), "chr1\t10\t60\ttx\t0\t-\n");
+        assert_eq!(
+            render(BedType::Bed12),
+            "chr1\t10\t60\ttx\t0\t-\t10\t60\t0\t1\t50,\t0,\n"
+        );
+    }
+
+    #[test]
+    fn test_write_record_rgb_and_extras() {
+        let record = BedRecord {
+            score: 1000,
+            rgb: Some((0, 0, 200)),
+            extras: vec!["protein_coding".to_string()],
+            ..record()
+        };
+
+        assert_eq!(
+            record.to_string(),
+            "chr1\t10\t60\ttx\t1000\t-\t10\t60\t0,0,200\t1\t50,\t0,\tprotein_coding"
+        );
+    }
+
+    #[test]
+    fn test_gene_pred_roundtrip() {
+        let record = BedRecord {
+            end: 100,
+            thick_end: 100,
+            blocks: vec![(10, 20), (50, 100)],
+            ..record()
+        };
+
+        let back = BedRecord::from_gene_pred("tx".to_string(), GenePred::from(&record))
+            .unwrap()
+            .unwrap();
+        assert_eq!(back, record);
+    }
+
+    #[test]
+    fn test_one_based() {
+        let record = BedRecord {
+            end: 100,
+            blocks: vec![(10, 20), (50, 100)],
+            ..record()
+        };
+
+        let mut out = Vec::new();
+        OneBased(BedType::Bed12).header(&mut out);
+        OneBased(BedType::Bed12).emit(&record, &mut out);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "#coordinates: 1-based, inclusive\nchr1\t11\t100\ttx\t0\t-\t11\t60\t0\t2\t10,50,\t0,40,\n"
+        );
+    }
+
+    #[test]
+    fn test_custom_emitter() {
+        struct Tsv;
+
+        impl BedEmitter for Tsv {
+            fn header(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(b"name\tlength\n");
+            }
+
+            fn emit(&self, record: &BedRecord, out: &mut Vec<u8>) {
+                let length = record.block_sizes().iter().sum::<u64>();
+                out.extend_from_slice(format!("{}\t{}\n", record.name, length).as_bytes());
+            }
+        }
+
+        let record = BedRecord {
+            end: 100,
+            blocks: vec![(10, 20), (50, 100)],
+            ..record()
+        };
+
+        let mut out = Vec::new();
+        Tsv.header(&mut out);
+        Tsv.emit(&record, &mut out);
+        assert_eq!(String::from_utf8(out).unwrap(), "name\tlength\ntx\t60\n");
+    }
+
+    #[test]
+    fn test_columns() {
+        let names = [
+            "chrom",
+            "start",
+            "end",
+            "gene_name",
+            "strand",
+            "blockSizes",
+            "gene_biotype",
+        ]
+        .map(String::from);
+        assert_eq!(Columns::attrs(&names), ["gene_name", "gene_biotype"]);
+
+        let record = BedRecord {
+            blocks: vec![(10, 20), (40, 60)],
+            extras: vec!["ACTB".to_string()],
+            ..record()
+        };
+        let mut out = Vec::new();
+        Columns::new(&names, false).emit(&record, &mut out);
+        Columns::new(&names, true).emit(&record, &mut out);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "chr1\t10\t60\tACTB\t-\t10,20,\t.\nchr1\t11\t60\tACTB\t-\t10,20,\t.\n"
+        );
+    }
+
+    #[test]
+    fn test_splice_sites() {
+        let record = BedRecord {
+            start: 0,
+            end: 100,
+            strand: Strand::Forward,
+            blocks: vec![(0, 20), (50, 60), (80, 100)],
+            ..record()
+        };
+
+        let mut out = Vec::new();
+        SpliceSites { window: 5 }.emit(&record, &mut out);
+        SpliceSites { window: 5 }.emit(
+            &BedRecord {
+                strand: Strand::Reverse,
+                ..record
+            },
+            &mut out,
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap().lines().collect::<Vec<_>>(),
+            vec![
+                "chr1\t15\t25\ttx_intron1_donor\t0\t+",
+                "chr1\t45\t55\ttx_intron1_acceptor\t0\t+",
+                "chr1\t55\t65\ttx_intron2_donor\t0\t+",
+                "chr1\t75\t85\ttx_intron2_acceptor\t0\t+",
+                "chr1\t45\t55\ttx_intron2_donor\t0\t-",
+                "chr1\t15\t25\ttx_intron2_acceptor\t0\t-",
+                "chr1\t75\t85\ttx_intron1_donor\t0\t-",
+                "chr1\t55\t65\ttx_intron1_acceptor\t0\t-",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let record = BedRecord {
+            start: 0,
+            end: 100,
+            thick_start: 10,
+            thick_end: 90,
+            blocks: vec![(0, 20), (50, 100)],
+            ..record()
+        };
+        assert_eq!(record.check(), Ok(()));
+
+        let broken = |record: BedRecord| record.check().unwrap_err();
+        assert_eq!(
+            broken(BedRecord {
+                blocks: vec![(5, 20), (50, 100)],
+                ..record.clone()
+            }),
+            "first block starts at 5 instead of chromStart 0"
+        );
+        assert_eq!(
+            broken(BedRecord {
+                blocks: vec![(0, 20), (50, 90)],
+                ..record.clone()
+            }),
+            "last block ends at 90 instead of chromEnd 100"
+        );
+        assert_eq!(
+            broken(BedRecord {
+                blocks: vec![(0, 60), (50, 100)],
+                ..record.clone()
+            }),
+            "block 2 starts at 50, before block 1 ends at 60"
+        );
+        assert_eq!(
+            broken(BedRecord {
+                thick_end: 110,
+                ..record
+            }),
+            "thickStart-thickEnd 10-110 is not within 0-100"
+        );
+    }
+
+    #[test]
+    fn test_exons() {
+        let record = BedRecord {
+            start: 0,
+            end: 100,
+            strand: Strand::Reverse,
+            blocks: vec![(0, 20), (80, 100)],
+            ..record()
+        };
+
+        let mut out = Vec::new();
+        Exons.emit(&record, &mut out);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "chr1\t0\t20\ttx_exon2\t0\t-\nchr1\t80\t100\ttx_exon1\t0\t-\n"
+        );
+    }
+
+    #[test]
+    fn test_from_gene_pred_without_children() {
+        let record = BedRecord::from_gene_pred("tx".to_string(), GenePred::new()).unwrap();
+        assert_eq!(record, None);
+    }
+}
@@ -0,0 +1,97 @@
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Digest computed over the output file while it is written (`--checksum`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Checksum {
+    Md5,
+    Sha256,
+}
+
+impl Checksum {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Checksum::Md5 => "md5",
+            Checksum::Sha256 => "sha256",
+        }
+    }
+}
+
+enum Hasher {
+    Md5(Md5),
+    Sha256(Sha256),
+}
+
+/// Passes writes through to `inner`, hashing the bytes that reach it, so
+/// the digest matches the file on disk (compressed or not).
+pub(crate) struct Hashing<W> {
+    inner: W,
+    hasher: Option<Hasher>,
+}
+
+impl<W> Hashing<W> {
+    /// Hashes with `checksum`; `None` only passes writes through.
+    pub(crate) fn new(inner: W, checksum: Option<Checksum>) -> Self {
+        let hasher = checksum.map(|checksum| match checksum {
+            Checksum::Md5 => Hasher::Md5(Md5::new()),
+            Checksum::Sha256 => Hasher::Sha256(Sha256::new()),
+        });
+
+        Self { inner, hasher }
+    }
+
+    /// Hex digest of everything written so far, if hashing.
+    pub(crate) fn finish(self) -> (W, Option<String>) {
+        let digest = self.hasher.map(|hasher| match hasher {
+            Hasher::Md5(hasher) => format!("{:x}", hasher.finalize()),
+            Hasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+        });
+
+        (self.inner, digest)
+    }
+}
+
+impl<W: Write> Write for Hashing<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        match &mut self.hasher {
+            Some(Hasher::Md5(hasher)) => hasher.update(&buf[..n]),
+            Some(Hasher::Sha256(hasher)) => hasher.update(&buf[..n]),
+            None => (),
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digests() {
+        let digest = |checksum| {
+            let mut writer = Hashing::new(Vec::new(), checksum);
+            writer.write_all(b"chr1\t0\t10\n").unwrap();
+            writer.finish().1
+        };
+
+        assert_eq!(
+            digest(Some(Checksum::Md5)).unwrap(),
+            "585f1d3c58f163cae0f4295452751934"
+        );
+        assert_eq!(
+            digest(Some(Checksum::Sha256)).unwrap(),
+            "15b3f2eb412a054ce887cfd8f2de7727944152649299167ffa4387e5c76f0b1c"
+        );
+        assert_eq!(digest(None), None);
+    }
+}
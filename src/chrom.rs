@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::gxf::GenePred;
+
+/// What to do with a record that does not fit within its chromosome
+/// according to `--chrom-sizes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChromBounds {
+    /// Warn and write the record unchanged.
+    #[default]
+    Report,
+    /// Trim the record to the chromosome end.
+    Clip,
+    /// Skip the record.
+    Drop,
+}
+
+/// Chromosome lengths, as in a UCSC `chrom.sizes` file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChromSizes(HashMap<String, u64>);
+
+impl ChromSizes {
+    /// Reads `<name> <length>` lines; blank and `#` lines are ignored.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| std::io::Error::new(e.kind(), format!("{:?}: {}", path, e)))?;
+        let invalid = |idx: usize, line: &str| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "{:?}: line {}: expected <name> <length>, got {:?}",
+                    path,
+                    idx + 1,
+                    line
+                ),
+            )
+        };
+
+        let mut sizes = HashMap::new();
+        for (idx, line) in contents.lines().enumerate() {
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let (Some(name), Some(Ok(size))) = (fields.next(), fields.next().map(str::parse))
+            else {
+                return Err(invalid(idx, line).into());
+            };
+            sizes.insert(name.to_string(), size);
+        }
+
+        Ok(Self(sizes))
+    }
+
+    pub fn get(&self, chrom: &str) -> Option<u64> {
+        self.0.get(chrom).copied()
+    }
+
+    /// Sum of all chromosome lengths.
+    pub fn total(&self) -> u64 {
+        self.0.values().sum()
+    }
+
+    /// Whether `model` lies on a known chromosome and ends within it.
+    pub fn fits(&self, model: &GenePred) -> bool {
+        self.get(&model.chr).is_some_and(|size| {
+            model.end <= size && model.exons.iter().all(|(start, len)| start + len <= size)
+        })
+    }
+}
+
+impl FromIterator<(String, u64)> for ChromSizes {
+    fn from_iter<I: IntoIterator<Item = (String, u64)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gxf::Strand;
+
+    #[test]
+    fn test_read_and_fit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hg38.chrom.sizes");
+        std::fs::write(&path, "# hg38\nchr1\t1000\nchrM 16569\n").unwrap();
+        let sizes = ChromSizes::from_file(&path).unwrap();
+        assert_eq!(sizes.get("chrM"), Some(16569));
+
+        let mut model = GenePred::new();
+        model.set_parent("chr1", 900, 1000, Strand::Forward);
+        model.add_child("chr1", 900, 1000, Strand::Forward);
+        assert!(sizes.fits(&model));
+        model.add_child("chr1", 950, 1010, Strand::Forward);
+        assert!(!sizes.fits(&model));
+        model.chr = "chr2".to_string();
+        assert!(!sizes.fits(&model));
+
+        std::fs::write(&path, "chr1\tlong\n").unwrap();
+        assert!(ChromSizes::from_file(&path).is_err());
+    }
+}
@@ -1,13 +1,43 @@
-use clap::Parser;
-use std::path::PathBuf;
+use clap::{parser::ValueSource, ArgAction, ArgMatches, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+use crate::bed::{BedType, Emit};
+use crate::checksum::Checksum;
+use crate::chrom::ChromBounds;
+use crate::compat::Compat;
+use crate::compute::Computed;
+use crate::config::{parse_alias, Config, DEFAULT_WINDOW};
+use crate::convert::run;
+#[cfg(feature = "db")]
+use crate::db::{build, query, Lookup, Region};
+use crate::detect::Compression;
+use crate::error::Gxf2BedError;
+use crate::escape::Escape;
+use crate::gxf::{Degenerate, DuplicateIds, InputCoords, MixedLoci, MixedStrand, OutOfBounds};
+use crate::hints::detect;
+use crate::hub::{hub, Hub, BED_TO_BIGBED};
+use crate::input::IoStrategy;
+use crate::isoforms::IsoformRank;
+use crate::logging::{ColorChoice, LogFormat};
+use crate::output::{ChildOutput, OutputSpec};
+use crate::plan::DRY_RUN_LINES;
+use crate::preset::Preset;
+use crate::remote::is_remote;
+use crate::spill::parse_size;
+use crate::stats::{stats, StatsFormat, DEFAULT_STATS_BY};
+
 #[derive(Parser, Debug)]
 #[clap(
     name = "gxf2bed",
     version = env!("CARGO_PKG_VERSION"),
     author = "Alejandro Gonzales-Irribarren <alejandrxgzi@gmail.com>",
-    about = "fastest GTF/GFF-to-BED converter chilling around"
+    about = "fastest GTF/GFF-to-BED converter chilling around",
+    subcommand_negates_reqs = true,
+    args_conflicts_with_subcommands = true
 )]
 pub struct Args {
     /// fastest G{T,F}F-to-BED converter chilling around the world!
@@ -20,27 +50,44 @@ pub struct Args {
         long = "input",
         help = "Path to GTF/GFF file",
         value_name = "GXF",
-        required = true
+        required_unless_present = "config"
     )]
-    pub gxf: PathBuf,
+    pub gxf: Option<PathBuf>,
 
     /// Output filepath; non-required argument.
     ///
     /// The output file will be a BED file with the same name as the input file.
+    /// When a directory is given, the file is named after the input stem
+    /// (foo.gtf.gz -> <dir>/foo.bed).
     #[clap(
         short = 'o',
         long = "output",
-        help = "Path to output BED file",
-        value_name = "BED",
-        required = true
+        help = "Path to output BED file or directory",
+        value_name = "BED"
     )]
-    pub output: PathBuf,
+    pub output: Option<PathBuf>,
+
+    /// Extra outputs from the same parse, e.g. `--out bed6=tx6.bed --out
+    /// tsv=tx.tsv`; parsing dominates the runtime, so this beats running
+    /// once per format. Without --output only these are written.
+    #[clap(
+        long = "out",
+        help = "Extra output as FORMAT=PATH (bed3-bed12, tsv); repeatable",
+        value_name = "FORMAT=PATH"
+    )]
+    pub outs: Vec<OutputSpec>,
+
+    /// Stream the BED to stdout for the next pipeline stage while writing
+    /// the output file too; logs stay on stderr.
+    #[clap(long, help = "Also write the BED output to stdout")]
+    pub tee: bool,
 
-    /// Number of threads to use; default is the number of logical CPUs.
+    /// Number of threads to use; default is the number of logical CPUs, which
+    /// is also what 0 means.
     #[clap(
         short = 't',
         long,
-        help = "Number of threads",
+        help = "Number of threads (0 = all logical CPUs)",
         value_name = "THREADS",
         default_value_t = num_cpus::get()
     )]
@@ -56,28 +103,1303 @@ pub struct Args {
     )]
     pub parent: String,
 
-    /// Child feature; default is "exon".
+    /// Child feature; default is "exon". `CHILD:PATH` writes the blocks of
+    /// CHILD to a BED file of its own instead, so `--child exon:exons.bed
+    /// --child CDS:cds.bed` gets both views from one parse.
     #[clap(
         short = 'c',
         long = "child",
-        help = "Child feature",
-        value_name = "CHILD",
+        help = "Child feature; a comma-separated list (e.g. exon,CDS) unions their blocks, \
+        CHILD:PATH writes them to their own BED file; repeatable",
+        value_name = "CHILD[:PATH]",
         default_value = "exon"
     )]
-    pub child: String,
+    pub child: Vec<String>,
 
-    /// Feature to extract; default is "transcript_id".
+    /// Feature to extract; default is "transcript_id". `KEY:DB` takes the
+    /// `DB:<id>` entry of a cross-reference list, e.g. `Dbxref:GeneID`; a
+    /// comma-separated list such as `transcript_id,ID,Parent` groups each
+    /// line by the first key it has, for files merged from GTF and GFF3.
     #[clap(
         short = 'f',
         long = "feature",
-        help = "Feature to extract",
+        help = "Feature to extract (KEY:DB picks an entry of a Dbxref-style list; \
+        a comma-separated list is tried in order per line)",
         value_name = "FEATURE",
         default_value = "transcript_id"
     )]
     pub feature: String,
+
+    /// Attribute linking child lines to their parent when it is not
+    /// --feature, as with GFF3's `ID`/`Parent` hierarchy.
+    #[clap(
+        long = "child-feature",
+        help = "Attribute of child lines naming their parent (e.g. Parent); default: --feature",
+        value_name = "ATTR"
+    )]
+    pub child_feature: Option<String>,
+
+    /// Attributes tried in order for the BED name before --feature, e.g.
+    /// `reference_id,ref_gene_name` for StringTie.
+    #[clap(
+        long = "name-attr",
+        help = "Attributes tried in order for the record name before --feature",
+        value_name = "ATTR",
+        value_delimiter = ','
+    )]
+    pub name_attrs: Vec<String>,
+
+    /// Attributes tried in order for the BED score, e.g. `TPM,FPKM`; the
+    /// value is rounded and clamped to 0-1000.
+    #[clap(
+        long = "score-attr",
+        help = "Attributes tried in order for the BED score (rounded, clamped to 0-1000)",
+        value_name = "ATTR",
+        value_delimiter = ','
+    )]
+    pub score_attrs: Vec<String>,
+
+    /// Canonical attribute names for provider-specific keys, so the options
+    /// above work across GENCODE, Ensembl and RefSeq files; e.g.
+    /// `gene_type=gene_biotype` reads `gene_biotype` wherever an option
+    /// names `gene_type`.
+    #[clap(
+        long = "attr-alias",
+        help = "Attribute aliases, NAME=KEY: options naming NAME read the input's KEY",
+        value_name = "NAME=KEY",
+        value_delimiter = ',',
+        value_parser = parse_alias
+    )]
+    pub attr_aliases: Vec<(String, String)>,
+
+    /// Key-value separator of the attribute column, for in-house dialects
+    /// such as `key:value` or space-separated GFF attributes.
+    #[clap(
+        long = "attr-sep",
+        help = "Attribute key-value separator (default: space for GTF, '=' for GFF)",
+        value_name = "CHAR"
+    )]
+    pub attr_sep: Option<char>,
+
+    /// Extra line prefixes to skip like `#` comments, e.g. `!` in some
+    /// GAF-adjacent dumps or `track` lines pasted in from a browser session.
+    #[clap(
+        long = "comment-chars",
+        help = "Extra comment line prefixes to skip besides '#' (e.g. '!,track')",
+        value_name = "PREFIX",
+        value_delimiter = ','
+    )]
+    pub comment_chars: Vec<String>,
+
+    /// Copy the leading `#` header lines of the input (genome build,
+    /// provider, version) into the output, preserving provenance.
+    #[clap(
+        long = "keep-comments",
+        help = "Copy the input's leading '#' header lines into the output"
+    )]
+    pub keep_comments: bool,
+
+    /// Settings bundle for a non-GENCODE annotation source; flags given on
+    /// the command line still win.
+    #[clap(
+        long = "preset",
+        help = "Settings for an annotation source (prokaryotic: Prokka/Bakta GFF3, stringtie: StringTie/Cufflinks GTF, augustus: AUGUSTUS/BRAKER GTF, maker: MAKER GFF3)",
+        value_name = "PRESET",
+        value_enum
+    )]
+    pub preset: Option<Preset>,
+
+    /// Keep the GENCODE-style defaults of --parent, --child and --feature
+    /// even when the start of the input uses another layout (e.g. `mRNA`
+    /// linked by `ID`/`Parent`).
+    #[clap(
+        long = "no-detect",
+        help = "Do not adapt unset --parent/--child/--feature to the input"
+    )]
+    pub no_detect: bool,
+
+    /// BED flavor to write; default is "bed12".
+    #[clap(
+        short = 'b',
+        long = "bed-type",
+        help = "BED flavor to write",
+        value_name = "BED_TYPE",
+        value_enum,
+        default_value_t = BedType::Bed12
+    )]
+    pub bed_type: BedType,
+
+    /// Convention of the input's start column; some in-house "GFF-like"
+    /// files are 0-based and would otherwise be shifted by one.
+    #[clap(
+        long = "input-coords",
+        help = "Coordinate convention of the input starts",
+        value_name = "BASE",
+        value_enum,
+        default_value_t = InputCoords::One
+    )]
+    pub input_coords: InputCoords,
+
+    /// What to do when a child extends beyond its parent's span: grow the
+    /// record, clip the block, drop the record or fail.
+    #[clap(
+        long = "out-of-bounds",
+        help = "Policy for child blocks outside their parent's span",
+        value_name = "POLICY",
+        value_enum,
+        default_value_t = OutOfBounds::Expand
+    )]
+    pub out_of_bounds: OutOfBounds,
+
+    /// Merge overlapping or adjacent exons of a transcript (duplicated lines,
+    /// retained-intron artifacts) so the BED12 block set stays valid.
+    #[clap(
+        long = "merge-blocks",
+        help = "Merge overlapping or adjacent blocks of each record"
+    )]
+    pub merge_blocks: bool,
+
+    /// Emit parents that have no child features (gene-only catalogs) as a
+    /// single block spanning the parent instead of skipping them.
+    #[clap(
+        long = "keep-childless",
+        help = "Emit parents without child features as one-block records"
+    )]
+    pub keep_childless: bool,
+
+    /// Build the blocks of models that have no child features from their
+    /// CDS lines instead of skipping them (bacterial, viral or predicted
+    /// annotations without exon lines).
+    #[clap(
+        long = "cds-exons",
+        help = "Use the CDS as blocks of models without child features"
+    )]
+    pub cds_exons: bool,
+
+    /// What to do with features whose end is not past their start: drop the
+    /// record, keep it as a single base, or fail.
+    #[clap(
+        long = "degenerate",
+        help = "Policy for zero- or negative-length features",
+        value_name = "POLICY",
+        value_enum,
+        default_value_t = Degenerate::Error
+    )]
+    pub degenerate: Degenerate,
+
+    /// What to do when several parent lines share an ID (their features
+    /// are merged into one chimeric record): warn, drop the record or fail.
+    #[clap(
+        long = "duplicate-ids",
+        help = "Policy for records whose ID is used by more than one parent line",
+        value_name = "POLICY",
+        value_enum,
+        default_value_t = DuplicateIds::Warn
+    )]
+    pub duplicate_ids: DuplicateIds,
+
+    /// What to do when features sharing an ID sit on different chromosomes
+    /// or strands: write one record per locus, drop them or fail.
+    #[clap(
+        long = "mixed-loci",
+        help = "Policy for IDs whose features are on more than one chromosome or strand",
+        value_name = "POLICY",
+        value_enum,
+        default_value_t = MixedLoci::Split
+    )]
+    pub mixed_loci: MixedLoci,
+
+    /// What to do when features sharing an ID on one chromosome disagree on
+    /// the strand: keep each strand apart, keep the strand of most lines,
+    /// drop the record or fail.
+    #[clap(
+        long = "mixed-strand",
+        help = "Policy for IDs whose features on one chromosome disagree on the strand",
+        value_name = "POLICY",
+        value_enum,
+        default_value_t = MixedStrand::Split
+    )]
+    pub mixed_strand: MixedStrand,
+
+    /// Chromosome lengths (UCSC chrom.sizes) to check every record against,
+    /// catching assembly mismatches before bedToBigBed does.
+    #[clap(
+        long = "chrom-sizes",
+        help = "chrom.sizes file to validate record coordinates against",
+        value_name = "FILE"
+    )]
+    pub chrom_sizes: Option<PathBuf>,
+
+    /// What to do with records that do not fit within their chromosome.
+    #[clap(
+        long = "chrom-bounds",
+        help = "Policy for records past their chromosome end (with --chrom-sizes)",
+        value_name = "POLICY",
+        value_enum,
+        default_value_t = ChromBounds::Report
+    )]
+    pub chrom_bounds: ChromBounds,
+
+    /// Write 1-based, inclusive starts (GFF-style intervals in BED columns)
+    /// for tools that expect them; a header line records the convention.
+    #[clap(
+        long = "one-based",
+        help = "Write 1-based, inclusive coordinates instead of 0-based, half-open"
+    )]
+    pub one_based: bool,
+
+    /// Drop the strand for tools that choke on stranded records or when the
+    /// source's strands cannot be trusted.
+    #[clap(
+        long = "ignore-strand",
+        help = "Write '.' as the strand and treat every record as unstranded"
+    )]
+    pub ignore_strand: bool,
+
+    /// Reproduce another tool's BED output (gffread: BED12 named by the
+    /// grouping key, thick region over CDS and stop codon).
+    #[clap(
+        long,
+        help = "Match another tool's BED conventions exactly",
+        value_name = "TOOL",
+        value_enum
+    )]
+    pub compat: Option<Compat>,
+
+    /// Compose a bespoke BED+/TSV layout from standard fields and
+    /// attributes, e.g. `chrom,start,end,gene_name,score,strand,gene_biotype`.
+    #[clap(
+        long,
+        help = "Column layout: BED fields (chrom, start, end, name, ...) and attributes, in order",
+        value_name = "COLUMN",
+        value_delimiter = ','
+    )]
+    pub columns: Vec<String>,
+
+    /// Derive the score or an extra column per record from its fields and
+    /// attributes, e.g. `--compute 'tx_len=end-start' --compute
+    /// 'score=min(1000, exon_count*50)'`.
+    #[clap(
+        long = "compute",
+        help = "Computed score or extra column as NAME=EXPR; repeatable",
+        value_name = "NAME=EXPR"
+    )]
+    pub compute: Vec<Computed>,
+
+    /// Thin out genes with many isoforms (large GENCODE genes overwhelm
+    /// some genome browsers and plotting tools).
+    #[clap(
+        long = "max-transcripts-per-gene",
+        help = "Keep at most N isoforms per gene_id, the best by --rank-isoforms",
+        value_name = "N"
+    )]
+    pub max_transcripts_per_gene: Option<usize>,
+
+    /// What makes an isoform better for `--max-transcripts-per-gene`.
+    #[clap(
+        long = "rank-isoforms",
+        help = "Rank isoforms by length, tsl (transcript_support_level) or tags",
+        value_name = "BY",
+        value_enum,
+        default_value_t = IsoformRank::Length
+    )]
+    pub rank_isoforms: IsoformRank,
+
+    /// Preview the output of the chosen settings on a huge file without
+    /// reading all of it.
+    #[clap(
+        long,
+        help = "Stop after N records, reading only the input lines of the first N IDs",
+        value_name = "N"
+    )]
+    pub head: Option<usize>,
+
+    /// Keep a reproducible random subset of the records, for small test
+    /// fixtures and quick-look tracks from huge annotations.
+    #[clap(
+        long,
+        help = "Keep this fraction of records (0-1], picked deterministically by ID",
+        value_name = "FRACTION"
+    )]
+    pub sample: Option<f64>,
+
+    /// Seed for `--sample`; another seed picks another subset.
+    #[clap(
+        long,
+        help = "Seed for --sample",
+        value_name = "SEED",
+        default_value_t = 0
+    )]
+    pub seed: u64,
+
+    /// Write donor/acceptor windows instead of the records, for splice
+    /// variant annotation pipelines.
+    #[clap(
+        long,
+        help = "Write the records, BED6 windows around their splice sites or their exons",
+        value_name = "WHAT",
+        value_enum,
+        default_value_t = Emit::Records
+    )]
+    pub emit: Emit,
+
+    /// Bases on either side of each splice site for `--emit splice-sites`.
+    #[clap(
+        long,
+        help = "Bases on either side of each splice site with --emit splice-sites",
+        value_name = "BP",
+        default_value_t = DEFAULT_WINDOW
+    )]
+    pub window: u64,
+
+    /// Append every attribute of the record's line as one JSON column, so
+    /// nothing is lost behind the tab-separated BED columns.
+    #[clap(
+        long = "attrs-json",
+        help = "Append all attributes as a compact JSON column"
+    )]
+    pub attrs_json: bool,
+
+    /// How to write tabs, newlines and spaces found in record names and
+    /// attribute-derived columns: as they are (only counted), replaced
+    /// with `_`, percent-encoded or quoted.
+    #[clap(
+        long = "escape",
+        help = "Policy for whitespace in names and attribute columns",
+        value_name = "POLICY",
+        value_enum,
+        default_value_t = Escape::Keep
+    )]
+    pub escape: Escape,
+
+    /// Append exon numbers and the exon count, for isoform plots that label
+    /// exons without re-deriving their order on the minus strand.
+    #[clap(
+        long = "exon-ranks",
+        help = "Append exon_number (per block, 5' to 3') and exon_count columns"
+    )]
+    pub exon_ranks: bool,
+
+    /// Flag gene models whose CDS does not add up to whole codons, common
+    /// in predicted annotations.
+    #[clap(
+        long = "check-frame",
+        help = "Warn about records whose CDS length is not a multiple of 3"
+    )]
+    pub check_frame: bool,
+
+    /// Flag coding transcripts without start/stop codon features or whose
+    /// codons do not sit at the CDS ends, to judge annotation quality.
+    #[clap(
+        long = "check-codons",
+        help = "Warn about coding records with missing or misplaced start/stop codons"
+    )]
+    pub check_codons: bool,
+
+    /// Genome the annotation was made on, for checks that need sequence.
+    #[clap(
+        long,
+        help = "Genome FASTA (optionally .gz/.zst/.bz2) for --check-orf",
+        value_name = "FILE"
+    )]
+    pub fasta: Option<PathBuf>,
+
+    /// Catch frame and coordinate bugs before the CDS is translated:
+    /// every coding record must read ATG...stop with no stop in between.
+    #[clap(
+        long = "check-orf",
+        help = "Warn about coding records whose --fasta sequence is not a clean ORF"
+    )]
+    pub check_orf: bool,
+
+    /// Guard against block-math bugs: every record must pass the BED12
+    /// invariants before it is written, or the run fails naming it.
+    #[clap(
+        long = "check-output",
+        help = "Fail on the first record breaking the BED12 invariants"
+    )]
+    pub check_output: bool,
+
+    /// List transcripts whose exons leave the transcript span or whose CDS
+    /// leaves their exons, the usual cause of odd block layouts.
+    #[clap(
+        long = "containment-report",
+        help = "Write records with exons outside their transcript or CDS outside their exons to FILE",
+        value_name = "FILE"
+    )]
+    pub containment_report: Option<PathBuf>,
+
+    /// Digest of the written output, computed on the fly and logged, for
+    /// workflow caches and data managers.
+    #[clap(
+        long,
+        help = "Log the md5 or sha256 digest of the output file",
+        value_name = "ALGORITHM",
+        value_enum
+    )]
+    pub checksum: Option<Checksum>,
+
+    /// Compression level for .gz/.zst/.bz2 outputs; higher is smaller but slower.
+    #[clap(
+        long = "compress-level",
+        help = "Compression level for compressed output (gzip/bzip2: 0-9, zstd: 1-22)",
+        value_name = "LEVEL"
+    )]
+    pub compress_level: Option<u32>,
+
+    /// Memory budget for grouped data (e.g. 4G, 512M); larger inputs are
+    /// grouped and sorted through temporary files instead of failing.
+    #[clap(
+        long = "max-memory",
+        help = "Memory budget for grouped data, spilling to disk past it (e.g. 4G)",
+        value_name = "SIZE",
+        value_parser = parse_size
+    )]
+    pub max_memory: Option<u64>,
+
+    /// How the input is read; on network filesystems (Lustre/NFS) a single
+    /// sequential read can beat mmap page faults.
+    #[clap(
+        long = "io-strategy",
+        help = "Input read strategy",
+        value_name = "STRATEGY",
+        value_enum,
+        default_value_t = IoStrategy::Auto
+    )]
+    pub io_strategy: IoStrategy,
+
+    /// Convert a truncated .gz/.zst/.bz2 input (e.g. a cut-off download) up to
+    /// its last complete line, with a warning, instead of failing.
+    #[clap(
+        long = "allow-truncated",
+        help = "Keep the partial output of a truncated compressed input"
+    )]
+    pub allow_truncated: bool,
+
+    /// Replace invalid UTF-8 (e.g. Latin-1 descriptions) with U+FFFD and keep
+    /// going; without it the first bad byte fails with its line and offset.
+    #[clap(
+        long = "lossy-utf8",
+        help = "Replace invalid UTF-8 in the input instead of failing"
+    )]
+    pub lossy_utf8: bool,
+
+    /// Write records without sorting them by position, for pipelines that
+    /// sort downstream anyway; the order is arbitrary and not reproducible.
+    /// Grouping still reads the whole input first: only the sort is saved.
+    #[clap(
+        long = "unordered",
+        help = "Skip the final sort (grouping still reads the whole input first); output order is arbitrary and may differ between runs"
+    )]
+    pub unordered: bool,
+
+    /// Log the time and peak memory spent reading, parsing, rendering and
+    /// writing, to tell which of --io-strategy, --threads or --max-memory
+    /// is worth tuning.
+    #[clap(
+        long = "profile",
+        help = "Report time and peak memory per conversion phase"
+    )]
+    pub profile: bool,
+
+    /// Retry failed requests for http(s):// and s3:// inputs this many
+    /// times, waiting 1s, 2s, 4s... between them; interrupted downloads
+    /// resume with a range request.
+    #[clap(
+        long = "retries",
+        help = "Retries for remote inputs",
+        value_name = "N",
+        default_value_t = 3
+    )]
+    pub retries: u32,
+
+    /// Print the resolved settings and a scan of the first records instead of
+    /// converting; nothing is written.
+    #[clap(
+        long = "dry-run",
+        help = "Show what would be done without writing output"
+    )]
+    pub dry_run: bool,
+
+    /// Exit successfully without converting when the output is newer than the
+    /// input and its settings sidecar (<output>.gxf2bed) matches this run.
+    /// Only for local inputs.
+    #[clap(
+        long = "skip-if-newer",
+        help = "Skip conversion if the output is already up to date"
+    )]
+    pub skip_if_newer: bool,
+
+    /// Only log errors; also hides the banner.
+    #[clap(
+        short = 'q',
+        long = "quiet",
+        help = "Only log errors",
+        conflicts_with = "verbose"
+    )]
+    pub quiet: bool,
+
+    /// Log debug messages with -v and trace messages with -vv.
+    #[clap(
+        short = 'v',
+        long = "verbose",
+        help = "Increase log verbosity (-v, -vv)",
+        action = ArgAction::Count
+    )]
+    pub verbose: u8,
+
+    /// Render log events as text lines or as JSON objects for log scrapers.
+    #[clap(
+        long = "log-format",
+        help = "Log format",
+        value_name = "FORMAT",
+        value_enum,
+        default_value_t = LogFormat::Text
+    )]
+    pub log_format: LogFormat,
+
+    /// Color the banner, log levels and error snippets: auto does so only
+    /// on a terminal and when `NO_COLOR` is not set.
+    #[clap(
+        long = "color",
+        help = "When to color stderr output",
+        value_name = "WHEN",
+        value_enum,
+        default_value_t = ColorChoice::Auto
+    )]
+    pub color: ColorChoice,
+
+    /// Do not print the banner on stderr.
+    #[clap(long = "no-banner", help = "Do not print the banner")]
+    pub no_banner: bool,
+
+    /// TOML/YAML file with conversion settings; environment variables and
+    /// flags given on the command line take precedence over its values.
+    #[clap(
+        long = "config",
+        help = "Path to a TOML/YAML config file",
+        value_name = "CONFIG"
+    )]
+    pub config: Option<PathBuf>,
+
+    /// Print the effective settings, after the config file, preset,
+    /// environment, flags and input detection are applied, as a TOML
+    /// config file and exit; nothing is read past detection or written.
+    #[clap(
+        long = "show-config",
+        help = "Print the resolved configuration as TOML and exit"
+    )]
+    pub show_config: bool,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Helpers that print to stdout instead of converting a file, and shortcuts
+/// for the most common conversions.
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Print feature counts per chromosome, and record counts, length and
+    /// exon-count distributions overall and per biotype
+    Stats {
+        #[clap(
+            short = 'i',
+            long = "input",
+            help = "Path to GTF/GFF file",
+            value_name = "GXF"
+        )]
+        input: PathBuf,
+        /// Attribute the distributions are split by, e.g. `transcript_biotype`
+        /// for Ensembl files.
+        #[clap(
+            long = "by",
+            help = "Attribute to split the distributions by",
+            value_name = "ATTR",
+            default_value = DEFAULT_STATS_BY
+        )]
+        by: String,
+        /// Aligned text to read, or TSV/JSON for QC dashboards.
+        #[clap(
+            long = "format",
+            help = "Output format",
+            value_name = "FORMAT",
+            value_enum,
+            default_value_t = StatsFormat::Text
+        )]
+        format: StatsFormat,
+        /// Chromosome lengths (UCSC chrom.sizes), for the intergenic bases
+        /// and the share of the genome the annotation covers.
+        #[clap(
+            long = "chrom-sizes",
+            help = "chrom.sizes file for intergenic bases and genome coverage",
+            value_name = "FILE"
+        )]
+        chrom_sizes: Option<PathBuf>,
+        /// Grouping settings (parent, child, feature, ...) for non-GENCODE
+        /// inputs; its input and output are ignored.
+        #[clap(
+            long = "config",
+            help = "Path to a TOML/YAML config file",
+            value_name = "CONFIG"
+        )]
+        config: Option<PathBuf>,
+        #[clap(
+            long = "preset",
+            help = "Settings for a non-GENCODE source",
+            value_name = "PRESET",
+            value_enum
+        )]
+        preset: Option<Preset>,
+    },
+    /// Build a UCSC track hub: an indexed bigBed track plus hub.txt,
+    /// genomes.txt and trackDb.txt, ready to load as a hub
+    Hub {
+        #[clap(
+            short = 'i',
+            long = "input",
+            help = "Path to GTF/GFF file",
+            value_name = "GXF"
+        )]
+        input: PathBuf,
+        #[clap(
+            short = 'o',
+            long = "output",
+            help = "Hub directory, created if missing",
+            value_name = "DIR"
+        )]
+        output: PathBuf,
+        /// UCSC assembly name the annotation is on.
+        #[clap(
+            long = "genome",
+            help = "UCSC assembly name, e.g. hg38",
+            value_name = "GENOME"
+        )]
+        genome: String,
+        #[clap(
+            long = "chrom-sizes",
+            help = "chrom.sizes file of the assembly",
+            value_name = "FILE"
+        )]
+        chrom_sizes: PathBuf,
+        /// Hub and track name; the input file name without extensions by
+        /// default.
+        #[clap(long = "name", help = "Hub and track name", value_name = "NAME")]
+        name: Option<String>,
+        #[clap(
+            long = "email",
+            help = "Contact address shown in the browser",
+            value_name = "EMAIL",
+            default_value = ""
+        )]
+        email: String,
+        #[clap(
+            long = "bed-to-bigbed",
+            help = "bedToBigBed executable",
+            value_name = "PATH",
+            default_value = BED_TO_BIGBED
+        )]
+        bed_to_bigbed: PathBuf,
+        /// Grouping settings (parent, child, feature, ...) for non-GENCODE
+        /// inputs; its input and output are ignored.
+        #[clap(
+            long = "config",
+            help = "Path to a TOML/YAML config file",
+            value_name = "CONFIG"
+        )]
+        config: Option<PathBuf>,
+        #[clap(
+            long = "preset",
+            help = "Settings for a non-GENCODE source",
+            value_name = "PRESET",
+            value_enum
+        )]
+        preset: Option<Preset>,
+    },
+    /// Write one BED6 line per gene, spanning its merged exons and named
+    /// by gene_name/Name; genes without exons span the gene line
+    Genes(ExtractArgs),
+    /// Write one BED12 line per transcript, with the grouping detected
+    /// from the input
+    Transcripts(ExtractArgs),
+    /// Write one BED6 line per exon, named `<transcript>_exon<k>` with
+    /// exons numbered 5' to 3'
+    Exons(ExtractArgs),
+    /// Build or query a SQLite annotation database, for repeated lookups
+    /// by region or ID
+    #[cfg(feature = "db")]
+    Db {
+        #[clap(subcommand)]
+        command: DbCommand,
+    },
+    /// Print a shell completion script
+    Completions {
+        #[clap(value_enum, value_name = "SHELL")]
+        shell: Shell,
+    },
+    /// Print the roff man page
+    Manpage,
+}
+
+/// Subcommands of `gxf2bed db`.
+#[cfg(feature = "db")]
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum DbCommand {
+    /// Store every feature, its attributes and its parents in a new
+    /// database
+    Build {
+        #[clap(help = "Path to GTF/GFF file", value_name = "GXF")]
+        input: PathBuf,
+        #[clap(
+            short = 'o',
+            long = "output",
+            help = "Database to write, replaced if present",
+            value_name = "DB"
+        )]
+        output: PathBuf,
+        /// Reading settings (comment_chars, attr_sep, input_coords, ...);
+        /// its input and output are ignored.
+        #[clap(
+            long = "config",
+            help = "Path to a TOML/YAML config file",
+            value_name = "CONFIG"
+        )]
+        config: Option<PathBuf>,
+    },
+    /// Print the input lines of the features in a region or with an ID
+    #[clap(group(clap::ArgGroup::new("lookup").required(true).args(["region", "id"])))]
+    Query {
+        #[clap(help = "Database written by `gxf2bed db build`", value_name = "DB")]
+        db: PathBuf,
+        /// 1-based, inclusive `chrom:start-end`; features overlapping it
+        /// are printed.
+        #[clap(
+            long = "region",
+            help = "Region to print features overlapping, e.g. chr1:1000-2000",
+            value_name = "REGION"
+        )]
+        region: Option<Region>,
+        #[clap(long = "id", help = "ID of the features to print", value_name = "ID")]
+        id: Option<String>,
+        #[clap(
+            long = "children",
+            help = "Also print every feature below --id",
+            requires = "id"
+        )]
+        children: bool,
+    },
+}
+
+/// Arguments of the `genes`, `transcripts` and `exons` shortcuts.
+#[derive(clap::Args, Debug, Clone, PartialEq, Eq)]
+pub struct ExtractArgs {
+    #[clap(
+        short = 'i',
+        long = "input",
+        help = "Path to GTF/GFF file",
+        value_name = "GXF"
+    )]
+    pub input: PathBuf,
+    #[clap(
+        short = 'o',
+        long = "output",
+        help = "Path to BED file",
+        value_name = "BED"
+    )]
+    pub output: PathBuf,
+    /// Other settings (naming, filters, compression, ...); the layout the
+    /// shortcut sets wins over the file's.
+    #[clap(
+        long = "config",
+        help = "Path to a TOML/YAML config file",
+        value_name = "CONFIG"
+    )]
+    pub config: Option<PathBuf>,
+    /// Layout of a non-GENCODE source for `transcripts` and `exons`, used
+    /// instead of detecting it; `genes` keeps only its naming.
+    #[clap(
+        long = "preset",
+        help = "Settings for a non-GENCODE source",
+        value_name = "PRESET",
+        value_enum
+    )]
+    pub preset: Option<Preset>,
+}
+
+impl ExtractArgs {
+    /// Settings of a shortcut writing `emit` lines: the --config file and
+    /// --preset, with the layout detected from the input unless one of
+    /// them gives it.
+    fn config(&self, emit: Emit) -> Result<Config, Gxf2BedError> {
+        let mut config = subcommand_config(&self.input, &self.config, &self.preset)?;
+        config.output.clone_from(&self.output);
+        config.emit = emit;
+        if self.config.is_none() && self.preset.is_none() {
+            // INFO: an unreadable input is reported by the run itself
+            let _ = detect(&mut config, DRY_RUN_LINES, &[]);
+        }
+
+        Ok(config)
+    }
+}
+
+/// Sets the gene layout on `config`: genes grouping the exons that share
+/// their gene_id (GTF) or point at their ID (GFF3), merged into one set of
+/// blocks.
+fn gene_layout(config: &mut Config) {
+    config.parent = "gene".to_string();
+    config.child = "exon".to_string();
+    config.feature = "gene_id,ID".to_string();
+    config.child_feature = Some("gene_id".to_string());
+    config.merge_blocks = true;
+    config.keep_childless = true;
+    config.name_attrs = vec!["gene_name".to_string(), "Name".to_string()];
+    config.bed_type = BedType::Bed6;
+}
+
+impl Command {
+    /// Renders the annotation stats, builds a track hub (printing the path
+    /// of its hub.txt), runs one of the extraction shortcuts, or renders
+    /// the completion script or man page for the `gxf2bed` CLI.
+    pub fn run<W: Write>(&self, writer: &mut W) -> Result<(), Gxf2BedError> {
+        let mut cmd = Args::command_with_env();
+        match self {
+            Command::Stats {
+                input,
+                by,
+                format,
+                chrom_sizes,
+                config,
+                preset,
+            } => {
+                let mut config = subcommand_config(input, config, preset)?;
+                if chrom_sizes.is_some() {
+                    config.chrom_sizes.clone_from(chrom_sizes);
+                }
+                write!(writer, "{}", stats(&config, by)?.render(*format))?;
+            }
+            Command::Hub {
+                input,
+                output,
+                genome,
+                chrom_sizes,
+                name,
+                email,
+                bed_to_bigbed,
+                config,
+                preset,
+            } => {
+                let mut config = subcommand_config(input, config, preset)?;
+                config.chrom_sizes = Some(chrom_sizes.clone());
+                let mut layout = Hub::new(&config, output, genome);
+                if let Some(name) = name {
+                    layout.name.clone_from(name);
+                }
+                layout.email.clone_from(email);
+                layout.bed_to_bigbed.clone_from(bed_to_bigbed);
+                writeln!(writer, "{}", hub(&config, &layout)?.display())?;
+            }
+            Command::Genes(args) => {
+                let mut config = args.config(Emit::Records)?;
+                gene_layout(&mut config);
+                config.validate()?;
+                run(&config)?;
+            }
+            Command::Transcripts(args) => {
+                let mut config = args.config(Emit::Records)?;
+                config.bed_type = BedType::Bed12;
+                config.validate()?;
+                run(&config)?;
+            }
+            Command::Exons(args) => {
+                let config = args.config(Emit::Exons)?;
+                config.validate()?;
+                run(&config)?;
+            }
+            #[cfg(feature = "db")]
+            Command::Db {
+                command:
+                    DbCommand::Build {
+                        input,
+                        output,
+                        config,
+                    },
+            } => {
+                let config = subcommand_config(input, config, &None)?;
+                build(&config, output)?;
+            }
+            #[cfg(feature = "db")]
+            Command::Db {
+                command:
+                    DbCommand::Query {
+                        db,
+                        region,
+                        id,
+                        children,
+                    },
+            } => {
+                let lookup = match (region, id) {
+                    (Some(region), _) => Lookup::Region(region.clone()),
+                    (None, id) => Lookup::Id {
+                        id: id.clone().unwrap_or_default(),
+                        children: *children,
+                    },
+                };
+                for line in query(db, &lookup)? {
+                    writeln!(writer, "{}", line)?;
+                }
+            }
+            Command::Completions { shell } => {
+                // INFO: clap_complete panics on write errors, so render in memory first
+                let mut script = Vec::new();
+                let name = cmd.get_name().to_string();
+                clap_complete::generate(*shell, &mut cmd, name, &mut script);
+                writer.write_all(&script)?;
+            }
+            Command::Manpage => clap_mangen::Man::new(cmd).render(writer)?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Settings of a subcommand reading `input`: the --config file, or the
+/// defaults, with the --preset applied.
+fn subcommand_config(
+    input: &PathBuf,
+    config: &Option<PathBuf>,
+    preset: &Option<Preset>,
+) -> Result<Config, Gxf2BedError> {
+    let mut config = match config {
+        Some(path) => Config::from_file(path)?,
+        None => Config::default(),
+    };
+    config.input.clone_from(input);
+    if let Some(preset) = preset {
+        preset.apply(&mut config);
+    }
+
+    Ok(config)
 }
 
 impl Args {
+    /// The `gxf2bed` command, with the environment variable of every option
+    /// that takes one (see [`env_var`]) listed in its help.
+    pub fn command_with_env() -> clap::Command {
+        Args::command().mut_args(|arg| match env_name(&arg) {
+            Some(var) => {
+                let help = arg.get_help().map(ToString::to_string).unwrap_or_default();
+                arg.help(format!("{} [env: {}]", help, var))
+            }
+            None => arg,
+        })
+    }
+
+    /// Parses `argv` (program name first) with [`Args::command_with_env`],
+    /// reading the options it does not give from `env`, e.g.
+    /// `|var| std::env::var(var).ok()`; they count as given on the command
+    /// line, so they outrank --config and --preset as well.
+    pub fn matches_with_env<I, T>(
+        argv: I,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> clap::error::Result<ArgMatches>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        let mut argv = argv.into_iter().map(Into::into).collect::<Vec<OsString>>();
+        let cmd = Args::command_with_env();
+        // INFO: only to tell which options are given, so errors (e.g. --input set by env) wait
+        let given = cmd
+            .clone()
+            .ignore_errors(true)
+            .try_get_matches_from(&argv)?;
+
+        let mut from_env = Vec::new();
+        for arg in cmd.get_arguments() {
+            let (Some(var), Some(long)) = (env_name(arg), arg.get_long()) else {
+                continue;
+            };
+            if is_explicit(&given, arg.get_id().as_str()) {
+                continue;
+            }
+            let Some(value) = env(&var) else {
+                continue;
+            };
+            match arg.get_action() {
+                // INFO: clap's falsey values, as with its own env support
+                ArgAction::SetTrue => {
+                    if !matches!(
+                        value.to_lowercase().as_str(),
+                        "" | "0" | "n" | "no" | "f" | "false" | "off"
+                    ) {
+                        from_env.push(OsString::from(format!("--{}", long)));
+                    }
+                }
+                _ => from_env.push(OsString::from(format!("--{}={}", long, value))),
+            }
+        }
+        let at = argv.len().min(1);
+        argv.splice(at..at, from_env);
+
+        cmd.try_get_matches_from(argv)
+    }
+
+    /// Log level picked by -q/--quiet and -v/--verbose; `Info` by default.
+    pub fn log_level(&self) -> log::LevelFilter {
+        match (self.quiet, self.verbose) {
+            (true, _) => log::LevelFilter::Error,
+            (false, 0) => log::LevelFilter::Info,
+            (false, 1) => log::LevelFilter::Debug,
+            (false, _) => log::LevelFilter::Trace,
+        }
+    }
+
+    /// Whether the banner should be printed before converting; never in
+    /// JSON mode, where stderr must stay machine-parsable.
+    pub fn show_banner(&self) -> bool {
+        !self.quiet && !self.no_banner && self.log_format == LogFormat::Text
+    }
+
+    /// Fills every option not given on the command line or through its
+    /// environment variable with the value from the --config file, if one
+    /// was provided.
+    pub fn apply_config_file(&mut self, matches: &ArgMatches) -> Result<(), ArgError> {
+        let Some(path) = &self.config else {
+            return Ok(());
+        };
+        let file = Config::from_file(path).map_err(|e| match e {
+            Gxf2BedError::ConfigFile(err) => ArgError::InvalidConfig(err),
+            e => ArgError::InvalidConfig(format!("{:?}: {}", path, e)),
+        })?;
+        let explicit = |id: &str| is_explicit(matches, id);
+
+        if !explicit("gxf") && !file.input.as_os_str().is_empty() {
+            self.gxf = Some(file.input);
+        }
+        if !explicit("output") && !file.output.as_os_str().is_empty() {
+            self.output = Some(file.output);
+        }
+        if !explicit("outs") {
+            self.outs = file.outputs;
+        }
+        if !explicit("tee") {
+            self.tee = file.tee;
+        }
+        if !explicit("threads") {
+            self.threads = file.threads;
+        }
+        if !explicit("parent") {
+            self.parent = file.parent;
+        }
+        if !explicit("child") {
+            self.child = std::iter::once(file.child)
+                .chain(file.child_outputs.iter().map(ToString::to_string))
+                .collect();
+        }
+        if !explicit("feature") {
+            self.feature = file.feature;
+        }
+        if !explicit("child_feature") {
+            self.child_feature = file.child_feature;
+        }
+        if !explicit("name_attrs") {
+            self.name_attrs = file.name_attrs;
+        }
+        if !explicit("score_attrs") {
+            self.score_attrs = file.score_attrs;
+        }
+        if !explicit("attr_aliases") {
+            self.attr_aliases = file.attr_aliases.into_iter().collect();
+        }
+        if !explicit("attr_sep") {
+            self.attr_sep = file.attr_sep;
+        }
+        if !explicit("comment_chars") {
+            self.comment_chars = file.comment_chars;
+        }
+        if !explicit("keep_comments") {
+            self.keep_comments = file.keep_comments;
+        }
+        if !explicit("bed_type") {
+            self.bed_type = file.bed_type;
+        }
+        if !explicit("input_coords") {
+            self.input_coords = file.input_coords;
+        }
+        if !explicit("out_of_bounds") {
+            self.out_of_bounds = file.out_of_bounds;
+        }
+        if !explicit("merge_blocks") {
+            self.merge_blocks = file.merge_blocks;
+        }
+        if !explicit("keep_childless") {
+            self.keep_childless = file.keep_childless;
+        }
+        if !explicit("cds_exons") {
+            self.cds_exons = file.cds_exons;
+        }
+        if !explicit("degenerate") {
+            self.degenerate = file.degenerate;
+        }
+        if !explicit("duplicate_ids") {
+            self.duplicate_ids = file.duplicate_ids;
+        }
+        if !explicit("mixed_loci") {
+            self.mixed_loci = file.mixed_loci;
+        }
+        if !explicit("mixed_strand") {
+            self.mixed_strand = file.mixed_strand;
+        }
+        if !explicit("chrom_sizes") {
+            self.chrom_sizes = file.chrom_sizes;
+        }
+        if !explicit("chrom_bounds") {
+            self.chrom_bounds = file.chrom_bounds;
+        }
+        if !explicit("one_based") {
+            self.one_based = file.one_based;
+        }
+        if !explicit("ignore_strand") {
+            self.ignore_strand = file.ignore_strand;
+        }
+        if !explicit("compat") {
+            self.compat = file.compat;
+        }
+        if !explicit("columns") {
+            self.columns = file.columns;
+        }
+        if !explicit("compute") {
+            self.compute = file.compute;
+        }
+        if !explicit("max_transcripts_per_gene") {
+            self.max_transcripts_per_gene = file.max_transcripts_per_gene;
+        }
+        if !explicit("rank_isoforms") {
+            self.rank_isoforms = file.rank_isoforms;
+        }
+        if !explicit("head") {
+            self.head = file.head;
+        }
+        if !explicit("sample") {
+            self.sample = file.sample;
+        }
+        if !explicit("seed") {
+            self.seed = file.seed;
+        }
+        if !explicit("emit") {
+            self.emit = file.emit;
+        }
+        if !explicit("window") {
+            self.window = file.window;
+        }
+        if !explicit("attrs_json") {
+            self.attrs_json = file.attrs_json;
+        }
+        if !explicit("escape") {
+            self.escape = file.escape;
+        }
+        if !explicit("exon_ranks") {
+            self.exon_ranks = file.exon_ranks;
+        }
+        if !explicit("check_frame") {
+            self.check_frame = file.check_frame;
+        }
+        if !explicit("check_codons") {
+            self.check_codons = file.check_codons;
+        }
+        if !explicit("fasta") {
+            self.fasta = file.fasta;
+        }
+        if !explicit("check_orf") {
+            self.check_orf = file.check_orf;
+        }
+        if !explicit("check_output") {
+            self.check_output = file.check_output;
+        }
+        if !explicit("containment_report") {
+            self.containment_report = file.containment_report;
+        }
+        if !explicit("checksum") {
+            self.checksum = file.checksum;
+        }
+        if !explicit("compress_level") {
+            self.compress_level = file.compress_level;
+        }
+        if !explicit("max_memory") {
+            self.max_memory = file.max_memory;
+        }
+        if !explicit("io_strategy") {
+            self.io_strategy = file.io_strategy;
+        }
+        if !explicit("allow_truncated") {
+            self.allow_truncated = file.allow_truncated;
+        }
+        if !explicit("lossy_utf8") {
+            self.lossy_utf8 = file.lossy_utf8;
+        }
+        if !explicit("unordered") {
+            self.unordered = file.unordered;
+        }
+        if !explicit("profile") {
+            self.profile = file.profile;
+        }
+        if !explicit("retries") {
+            self.retries = file.retries;
+        }
+
+        Ok(())
+    }
+
+    /// Overrides the options set by --preset, except those given on the
+    /// command line or through the environment; a preset takes precedence
+    /// over the --config file.
+    pub fn apply_preset(&mut self, matches: &ArgMatches) {
+        let Some(preset) = self.preset else {
+            return;
+        };
+        let base = Config::default();
+        let mut config = base.clone();
+        preset.apply(&mut config);
+        // INFO: options the preset leaves at their defaults keep their --config value
+        let explicit = |id: &str| is_explicit(matches, id);
+
+        if !explicit("parent") && config.parent != base.parent {
+            self.parent = config.parent;
+        }
+        if !explicit("child") && config.child != base.child {
+            self.child.retain(|child| child.contains(':'));
+            self.child.insert(0, config.child);
+        }
+        if !explicit("feature") && config.feature != base.feature {
+            self.feature = config.feature;
+        }
+        if !explicit("child_feature") && config.child_feature != base.child_feature {
+            self.child_feature = config.child_feature;
+        }
+        if !explicit("name_attrs") && config.name_attrs != base.name_attrs {
+            self.name_attrs = config.name_attrs;
+        }
+        if !explicit("score_attrs") && config.score_attrs != base.score_attrs {
+            self.score_attrs = config.score_attrs;
+        }
+    }
+
+    /// Layout options (as `Config` fields) that input detection must leave
+    /// alone: those given on the command line or through the environment,
+    /// or all of them with
+    /// --no-detect, --config or --preset.
+    pub fn fixed_layout(&self, matches: &ArgMatches) -> Vec<&'static str> {
+        let layout = ["parent", "child", "feature", "child_feature"];
+        if self.no_detect || self.config.is_some() || self.preset.is_some() {
+            return layout.to_vec();
+        }
+
+        layout
+            .into_iter()
+            .filter(|id| is_explicit(matches, id))
+            .collect()
+    }
+
     /// Checks all the arguments for validity using validate_args()
     pub fn check(&self) -> Result<(), ArgError> {
         self.validate_args()
@@ -86,40 +1408,67 @@ impl Args {
     /// Checks the input file for validity. The file must exist and be a GTF or GFF3 file.
     /// If the file does not exist, an error is returned.
     fn check_input(&self) -> Result<(), ArgError> {
-        if !self.gxf.exists() {
-            let err = format!("file {:?} does not exist", self.gxf);
-            Err(ArgError::InvalidInput(err))
-        } else if std::fs::metadata(&self.gxf).unwrap().len() == 0 {
-            let err = format!("file {:?} is empty", self.gxf);
+        let Some(gxf) = &self.gxf else {
+            let err = "no input file given with --input or --config".to_string();
             return Err(ArgError::InvalidInput(err));
+        };
+
+        if is_remote(gxf) && self.skip_if_newer {
+            // INFO: the download is always newer than the output, so it would never skip
+            let err = format!(
+                "--skip-if-newer compares modification times and needs a local input, not {:?}",
+                gxf
+            );
+            Err(ArgError::InvalidInput(err))
+        } else if is_remote(gxf) {
+            Ok(())
+        } else if !gxf.exists() {
+            let err = format!("file {:?} does not exist", gxf);
+            Err(ArgError::UnreadableInput(err))
+        } else if std::fs::metadata(gxf).unwrap().len() == 0 {
+            let err = format!("file {:?} is empty", gxf);
+            Err(ArgError::UnreadableInput(err))
         } else {
             Ok(())
         }
     }
 
-    /// Checks the output file for validity. If the file is not a BED file, an error is returned.
+    /// Checks the output file for validity. If the file is not a BED file or a directory,
+    /// an error is returned.
     fn check_output(&self) -> Result<(), ArgError> {
-        if !self.output.extension().unwrap().eq("bed") & !self.output.extension().unwrap().eq("gz")
-        {
-            let err = format!("file {:?} is not a BED file", self.output);
-            Err(ArgError::InvalidOutput(err))
-        } else {
-            Ok(())
+        let mut child_outputs = false;
+        for spec in self.child.iter().filter(|child| child.contains(':')) {
+            let out = spec
+                .parse::<ChildOutput>()
+                .map_err(ArgError::InvalidOutput)?;
+            check_bed_path(&out.path)?;
+            child_outputs = true;
         }
+
+        let Some(output) = &self.output else {
+            if !self.outs.is_empty() || child_outputs {
+                return Ok(());
+            }
+            let err = "no output file given with --output or --config".to_string();
+            return Err(ArgError::InvalidOutput(err));
+        };
+        if output.is_dir() {
+            return Ok(());
+        }
+
+        check_bed_path(output)
     }
 
-    /// Checks the number of threads for validity. The number of threads must be greater than 0
-    /// and less than or equal to the number of logical CPUs.
-    fn check_threads(&self) -> Result<(), ArgError> {
-        if self.threads == 0 {
-            let err = "number of threads must be greater than 0".to_string();
-            Err(ArgError::InvalidThreads(err))
-        } else if self.threads > num_cpus::get() {
-            let err = "number of threads must be less than or equal to the number of logical CPUs"
-                .to_string();
-            return Err(ArgError::InvalidThreads(err));
-        } else {
-            Ok(())
+    /// Checks the number of threads. 0 means all logical CPUs; more threads than
+    /// CPUs is allowed (container CPU counts are often misreported) but warned about.
+    fn check_threads(&self) {
+        let cpus = num_cpus::get();
+        if self.threads > cpus {
+            log::warn!(
+                "{} threads requested but only {} logical CPUs detected; oversubscribing",
+                self.threads,
+                cpus
+            );
         }
     }
 
@@ -127,22 +1476,224 @@ impl Args {
     fn validate_args(&self) -> Result<(), ArgError> {
         self.check_input()?;
         self.check_output()?;
-        self.check_threads()?;
+        self.check_threads();
+        Ok(())
+    }
+}
+
+/// Prefix of the environment variable of each option, e.g. `GXF2BED_THREADS`
+/// for --threads or `GXF2BED_IO_STRATEGY` for --io-strategy.
+pub const ENV_PREFIX: &str = "GXF2BED_";
+
+/// Environment variable read for option `--long`.
+pub fn env_var(long: &str) -> String {
+    format!("{}{}", ENV_PREFIX, long.to_uppercase().replace('-', "_"))
+}
+
+/// Environment variable of `arg`, if it takes one: options with a long
+/// flag that set a value or a switch.
+fn env_name(arg: &clap::Arg) -> Option<String> {
+    let takes_env = matches!(
+        arg.get_action(),
+        ArgAction::Set | ArgAction::Append | ArgAction::SetTrue
+    );
+    arg.get_long().filter(|_| takes_env).map(env_var)
+}
+
+/// Whether option `id` was given on the command line or through its
+/// environment variable (see [`Args::matches_with_env`]); both outrank
+/// --config and --preset, in that order (defaults < config file < preset <
+/// environment < command line).
+fn is_explicit(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+/// Checks that `path` names a BED file, possibly compressed.
+fn check_bed_path(path: &Path) -> Result<(), ArgError> {
+    let ext = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    if ext != "bed" && Compression::from_extension(ext).is_none() {
+        let err = format!("file {:?} is not a BED file", path);
+        Err(ArgError::InvalidOutput(err))
+    } else {
         Ok(())
     }
 }
 
 #[derive(Debug, Error)]
 pub enum ArgError {
-    /// The input file does not exist or is not a GTF or GFF3 file.
+    /// No input file was given.
     #[error("Invalid input: {0}")]
     InvalidInput(String),
 
+    /// The input file does not exist or is empty.
+    #[error("Invalid input: {0}")]
+    UnreadableInput(String),
+
     /// The output file is not a BED file.
     #[error("Invalid output: {0}")]
     InvalidOutput(String),
 
-    /// The number of threads is invalid.
-    #[error("Invalid number of threads: {0}")]
-    InvalidThreads(String),
+    /// The config file could not be read or parsed.
+    #[error("Invalid config file: {0}")]
+    InvalidConfig(String),
+}
+
+impl ArgError {
+    /// Exit code of this error, as in [`Gxf2BedError::exit_code`]: a missing
+    /// or empty input is an I/O failure, anything else a bad argument.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ArgError::UnreadableInput(_) => 5,
+            _ => 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completions_subcommand() {
+        let args = Args::try_parse_from(["gxf2bed", "completions", "bash"]).unwrap();
+        let command = args.command.unwrap();
+        assert_eq!(command, Command::Completions { shell: Shell::Bash });
+
+        let mut out = Vec::new();
+        command.run(&mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("--compress-level"));
+    }
+
+    #[test]
+    fn test_log_level() {
+        let parse = |flags: &[&str]| {
+            let mut argv = vec!["gxf2bed", "-i", "a.gtf", "-o", "a.bed"];
+            argv.extend_from_slice(flags);
+            Args::try_parse_from(argv)
+        };
+
+        assert_eq!(parse(&[]).unwrap().log_level(), log::LevelFilter::Info);
+        assert_eq!(
+            parse(&["-vv"]).unwrap().log_level(),
+            log::LevelFilter::Trace
+        );
+        assert!(!parse(&["-q"]).unwrap().show_banner());
+        assert!(parse(&["-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn test_layered_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("layers.toml");
+        std::fs::write(&path, "threads = 3\nretries = 5\nwindow = 7\n").unwrap();
+        let env = |var: &str| match var {
+            "GXF2BED_RETRIES" => Some("6".to_string()),
+            "GXF2BED_WINDOW" => Some("8".to_string()),
+            "GXF2BED_UNORDERED" => Some("true".to_string()),
+            "GXF2BED_TEE" => Some("0".to_string()),
+            _ => None,
+        };
+
+        let config = path.to_str().unwrap();
+        let argv = ["gxf2bed", "--config", config, "--window", "9"];
+        let matches = Args::matches_with_env(argv, env).unwrap();
+        let mut args = <Args as clap::FromArgMatches>::from_arg_matches(&matches).unwrap();
+        args.apply_config_file(&matches).unwrap();
+
+        // INFO: defaults < config file < environment < command line
+        assert_eq!((args.threads, args.retries, args.window), (3, 6, 9));
+        assert!(args.unordered && !args.tee);
+        assert_eq!(env_var("io-strategy"), "GXF2BED_IO_STRATEGY");
+        assert!(
+            Args::matches_with_env(["gxf2bed", "--config", config], |var| {
+                (var == "GXF2BED_RETRIES").then(|| "many".to_string())
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_extraction_subcommands() {
+        let dir = tempfile::tempdir().unwrap();
+        let gtf = dir.path().join("a.gtf");
+        std::fs::write(
+            &gtf,
+            "chr1\t.\tgene\t1\t100\t.\t-\t.\tgene_id \"G1\"; gene_name \"ABC\";\n\
+             chr1\t.\ttranscript\t1\t100\t.\t-\t.\tgene_id \"G1\"; transcript_id \"T1\";\n\
+             chr1\t.\texon\t1\t20\t.\t-\t.\tgene_id \"G1\"; transcript_id \"T1\";\n\
+             chr1\t.\texon\t81\t100\t.\t-\t.\tgene_id \"G1\"; transcript_id \"T1\";\n\
+             chr1\t.\ttranscript\t10\t90\t.\t-\t.\tgene_id \"G1\"; transcript_id \"T2\";\n\
+             chr1\t.\texon\t10\t30\t.\t-\t.\tgene_id \"G1\"; transcript_id \"T2\";\n\
+             chr1\t.\texon\t71\t90\t.\t-\t.\tgene_id \"G1\"; transcript_id \"T2\";\n\
+             chr1\t.\tgene\t200\t300\t.\t+\t.\tgene_id \"G2\"; gene_name \"XYZ\";\n",
+        )
+        .unwrap();
+        let extract = |command: &str| {
+            let bed = dir.path().join(format!("{}.bed", command));
+            let args = Args::try_parse_from([
+                "gxf2bed",
+                command,
+                "-i",
+                gtf.to_str().unwrap(),
+                "-o",
+                bed.to_str().unwrap(),
+            ])
+            .unwrap();
+            args.command.unwrap().run(&mut Vec::new()).unwrap();
+            let mut lines = std::fs::read_to_string(bed)
+                .unwrap()
+                .lines()
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+            lines.sort();
+            lines
+        };
+
+        assert_eq!(
+            extract("genes"),
+            vec!["chr1\t0\t100\tABC\t0\t-", "chr1\t199\t300\tXYZ\t0\t+"]
+        );
+        assert_eq!(
+            extract("transcripts"),
+            vec![
+                "chr1\t0\t100\tT1\t0\t-\t0\t100\t0\t2\t20,20,\t0,80,",
+                "chr1\t9\t90\tT2\t0\t-\t9\t90\t0\t2\t21,20,\t0,61,",
+            ]
+        );
+        assert_eq!(
+            extract("exons"),
+            vec![
+                "chr1\t0\t20\tT1_exon2\t0\t-",
+                "chr1\t70\t90\tT2_exon1\t0\t-",
+                "chr1\t80\t100\tT1_exon1\t0\t-",
+                "chr1\t9\t30\tT2_exon2\t0\t-",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_subcommand_rejects_conversion_args() {
+        assert!(Args::try_parse_from(["gxf2bed", "-i", "a.gtf", "manpage"]).is_err());
+    }
+
+    #[test]
+    fn test_skip_if_newer_needs_local_input() {
+        let check = |input: &str| {
+            Args::try_parse_from(["gxf2bed", "-i", input, "-o", "a.bed", "--skip-if-newer"])
+                .unwrap()
+                .check()
+        };
+
+        assert!(matches!(
+            check("https://example.org/a.gtf"),
+            Err(ArgError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            check("s3://bucket/a.gtf.gz"),
+            Err(ArgError::InvalidInput(_))
+        ));
+    }
 }
@@ -0,0 +1,160 @@
+use std::cell::Cell;
+use std::io::{self, Read};
+use std::rc::Rc;
+
+use thiserror::Error;
+
+use crate::detect::Compression;
+
+/// Wraps `reader` with a decoder for `compression`.
+///
+/// Codecs are behind cargo features (`gzip`, `zstd`, `bzip2`); asking for one
+/// that was not compiled in fails with [`io::ErrorKind::Unsupported`]. A
+/// decode error raised after the compressed input ran out is reported as
+/// [`Truncated`] (see [`is_truncated`]).
+#[cfg_attr(
+    not(any(feature = "gzip", feature = "zstd", feature = "bzip2")),
+    allow(unreachable_code, unused_variables)
+)]
+pub(crate) fn decoder<'a, R: Read + 'a>(
+    reader: R,
+    compression: Compression,
+) -> io::Result<Box<dyn Read + 'a>> {
+    let eof = Rc::new(Cell::new(false));
+    let reader = Tail {
+        inner: reader,
+        eof: eof.clone(),
+    };
+
+    let inner: Box<dyn Read + 'a> = match compression {
+        Compression::None => return Ok(Box::new(reader.inner)),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Box::new(zstd::Decoder::new(reader)?),
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+        #[allow(unreachable_patterns)]
+        other => return Err(unavailable(other)),
+    };
+
+    Ok(Box::new(Checked {
+        inner,
+        eof,
+        codec: compression.name(),
+    }))
+}
+
+/// A compressed stream that stopped in the middle, e.g. a cut-off download.
+#[derive(Debug, Error)]
+#[error("{codec} input ends unexpectedly ({source})")]
+pub(crate) struct Truncated {
+    codec: &'static str,
+    source: io::Error,
+}
+
+/// Whether `err` comes from a truncated compressed input.
+pub(crate) fn is_truncated(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|e| e.is::<Truncated>())
+}
+
+/// Compressed side of a decoder; remembers whether the input ran out.
+struct Tail<R> {
+    inner: R,
+    eof: Rc<Cell<bool>>,
+}
+
+impl<R: Read> Read for Tail<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            self.eof.set(true);
+        }
+        Ok(n)
+    }
+}
+
+/// Decoded side of a decoder; errors raised once the compressed input is
+/// exhausted mean it was cut short rather than corrupted.
+struct Checked<'a> {
+    inner: Box<dyn Read + 'a>,
+    eof: Rc<Cell<bool>>,
+    codec: &'static str,
+}
+
+impl Read for Checked<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf).map_err(|source| {
+            if self.eof.get() && !is_truncated(&source) {
+                let codec = self.codec;
+                io::Error::new(io::ErrorKind::UnexpectedEof, Truncated { codec, source })
+            } else {
+                source
+            }
+        })
+    }
+}
+
+/// Error returned when `compression` is detected but its codec is disabled.
+pub(crate) fn unavailable(compression: Compression) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "{} support is not compiled in (enable the `{}` feature)",
+            compression.name(),
+            compression.name()
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_decoder() {
+        let mut out = String::new();
+        decoder(&b"chr1"[..], Compression::None)
+            .unwrap()
+            .read_to_string(&mut out)
+            .unwrap();
+        assert_eq!(out, "chr1");
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_truncated_vs_corrupt() {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder
+            .write_all("chr1\t0\t10\n".repeat(1000).as_bytes())
+            .unwrap();
+        let bytes = encoder.finish().unwrap();
+        let read = |bytes: &[u8]| {
+            let mut out = Vec::new();
+            decoder(bytes, Compression::Gzip)
+                .unwrap()
+                .read_to_end(&mut out)
+                .unwrap_err()
+        };
+
+        assert!(is_truncated(&read(&bytes[..bytes.len() / 2])));
+
+        let mut corrupt = bytes.clone();
+        corrupt[12..20].fill(0xff);
+        assert!(!is_truncated(&read(&corrupt)));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_decoder() {
+        let bytes = zstd::encode_all(&b"chr1\t0\t10\n"[..], 0).unwrap();
+        let mut out = String::new();
+        decoder(&bytes[..], Compression::Zstd)
+            .unwrap()
+            .read_to_string(&mut out)
+            .unwrap();
+        assert_eq!(out, "chr1\t0\t10\n");
+    }
+}
@@ -0,0 +1,99 @@
+use std::io::Write;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::bed::{BedEmitter, BedRecord};
+use crate::gxf::Coding;
+
+/// Other converters whose BED output can be reproduced (`--compat`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compat {
+    /// `gffread --bed`: BED12 named by the grouping key with score 100;
+    /// the thick region spans the CDS and stop codon (empty at `chromEnd`
+    /// for non-coding models), itemRgb carries the phase of the 5'-most CDS
+    /// and block lists have no trailing comma. gffread's extra attribute
+    /// column is not written.
+    Gffread,
+}
+
+impl Compat {
+    /// Sets the fields of `record` that follow this tool's conventions from
+    /// the model's `coding` features.
+    pub fn apply(&self, record: &mut BedRecord, coding: Option<&Coding>) {
+        match self {
+            Compat::Gffread => {
+                let thick = coding
+                    .and_then(Coding::thick)
+                    .map(|(start, end)| (start.max(record.start), end.min(record.end)))
+                    .filter(|(start, end)| start < end);
+                (record.thick_start, record.thick_end) = thick.unwrap_or((record.end, record.end));
+
+                let phase = coding.map_or(0, |coding| coding.phase(record.strand));
+                record.rgb = Some((phase, 0, 0));
+                record.score = 100;
+            }
+        }
+    }
+}
+
+impl BedEmitter for Compat {
+    fn emit(&self, record: &BedRecord, out: &mut Vec<u8>) {
+        match self {
+            Compat::Gffread => write_gffread(out, record),
+        }
+        .expect("ERROR: Could not write to buffer");
+    }
+}
+
+fn write_gffread<W: Write>(writer: &mut W, r: &BedRecord) -> std::io::Result<()> {
+    let join = |values: Vec<u64>| {
+        values
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    let (red, green, blue) = r.rgb.unwrap_or_default();
+
+    writeln!(
+        writer,
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{},{},{}\t{}\t{}\t{}",
+        r.chrom,
+        r.start,
+        r.end,
+        r.name,
+        r.score,
+        r.strand,
+        r.thick_start,
+        r.thick_end,
+        red,
+        green,
+        blue,
+        r.block_count(),
+        join(r.block_sizes()),
+        join(r.block_starts())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::convert::convert_str;
+    use crate::detect::InputFormat;
+
+    #[test]
+    fn test_gffread_golden() {
+        let gtf = include_str!("../tests/golden/gffread.gtf");
+        let bed = include_str!("../tests/golden/gffread.bed");
+
+        let config = Config::builder()
+            .compat(Compat::Gffread)
+            .name_attrs(["gene_name"])
+            .build()
+            .unwrap();
+        assert_eq!(convert_str(gtf, InputFormat::Gtf, &config).unwrap(), bed);
+    }
+}
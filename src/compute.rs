@@ -0,0 +1,655 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bed::BedRecord;
+
+/// Record fields readable in expressions; coordinates are BED's (0-based,
+/// half-open), `exon_len` sums the block sizes and `cds_len` their overlap
+/// with the thick span.
+pub const FIELDS: [&str; 11] = [
+    "chrom",
+    "start",
+    "end",
+    "name",
+    "score",
+    "strand",
+    "thick_start",
+    "thick_end",
+    "exon_count",
+    "exon_len",
+    "cds_len",
+];
+
+/// A derived value (`--compute NAME=EXPR`), e.g. `tx_len=end-start` or
+/// `score=min(1000, exon_count*50)`.
+///
+/// `score` replaces the record score (rounded and clamped to 0-1000); any
+/// other name is appended as a column. Expressions combine numbers,
+/// `"strings"`, the [`FIELDS`], earlier computed names and attributes
+/// (anything else, e.g. `gene_name`; `.` when missing) with `+ - * / %`,
+/// comparisons (`== != < <= > >=`, 1 or 0), `&& || !` and the functions
+/// `min`, `max`, `abs`, `round`, `floor`, `ceil`, `log10`, `len` and
+/// `if(cond, then, else)`. Values that are missing or not numbers where a
+/// number is needed make the result missing, written as `.`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Computed {
+    pub name: String,
+    expr: Expr,
+    source: String,
+}
+
+impl FromStr for Computed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, source) = s
+            .split_once('=')
+            .ok_or_else(|| format!("invalid --compute {:?}: expected NAME=EXPR", s))?;
+        let name = name.trim();
+        if !is_ident(name) || name.contains('.') {
+            return Err(format!("invalid --compute name {:?}", name));
+        }
+        if name != "score" && FIELDS.contains(&name) {
+            return Err(format!(
+                "--compute cannot assign to {:?}; only score and new column names",
+                name
+            ));
+        }
+        let expr = Parser::new(source)
+            .parse()
+            .map_err(|err| format!("invalid --compute {:?}: {}", s, err))?;
+
+        Ok(Self {
+            name: name.to_string(),
+            expr,
+            source: source.trim().to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Computed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.source)
+    }
+}
+
+impl TryFrom<String> for Computed {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Computed> for String {
+    fn from(computed: Computed) -> Self {
+        computed.to_string()
+    }
+}
+
+impl Computed {
+    /// Whether this adds a column rather than replacing the score.
+    pub fn is_column(&self) -> bool {
+        self.name != "score"
+    }
+}
+
+/// Attributes read by `computed`, in order of first use: names that are
+/// neither [`FIELDS`] nor computed by an earlier entry.
+pub fn attrs(computed: &[Computed]) -> Vec<String> {
+    let mut attrs = Vec::new();
+    let mut defined = Vec::new();
+    for entry in computed {
+        entry.expr.vars(&mut |var| {
+            let known =
+                FIELDS.contains(&var) || defined.contains(&var) || attrs.iter().any(|a| a == var);
+            if !known {
+                attrs.push(var.to_string());
+            }
+        });
+        defined.push(entry.name.as_str());
+    }
+    attrs
+}
+
+/// The `--compute` entries of a run with the attributes they read.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Compute {
+    computed: Vec<Computed>,
+    attrs: Vec<String>,
+}
+
+impl Compute {
+    pub(crate) fn new(computed: &[Computed]) -> Self {
+        Self {
+            computed: computed.to_vec(),
+            attrs: attrs(computed),
+        }
+    }
+
+    /// Applies the entries to `record` in order; `values` holds the
+    /// attributes listed by [`attrs`], in that order.
+    pub(crate) fn apply(&self, record: &mut BedRecord, values: &[Option<String>]) {
+        if !self.computed.is_empty() {
+            apply(&self.computed, &self.attrs, record, values);
+        }
+    }
+}
+
+fn apply(
+    computed: &[Computed],
+    attrs: &[String],
+    record: &mut BedRecord,
+    values: &[Option<String>],
+) {
+    let mut scope: Vec<(&str, Value)> = attrs
+        .iter()
+        .zip(values)
+        .map(|(name, value)| {
+            let value = value.clone().map_or(Value::Missing, Value::Str);
+            (name.as_str(), value)
+        })
+        .collect();
+
+    for entry in computed {
+        let value = entry.expr.eval(&|var| {
+            field(record, var).unwrap_or_else(|| {
+                scope
+                    .iter()
+                    .rev()
+                    .find(|(name, _)| *name == var)
+                    .map_or(Value::Missing, |(_, value)| value.clone())
+            })
+        });
+        if entry.is_column() {
+            record.extras.push(value.to_string());
+        } else if let Some(score) = value.num() {
+            record.score = score.round().clamp(0.0, 1000.0) as u32;
+        }
+        scope.push((entry.name.as_str(), value));
+    }
+}
+
+/// Value of record field `name`, if it is one.
+fn field(r: &BedRecord, name: &str) -> Option<Value> {
+    let num = |n: u64| Value::Num(n as f64);
+    Some(match name {
+        "chrom" => Value::Str(r.chrom.clone()),
+        "start" => num(r.start),
+        "end" => num(r.end),
+        "name" => Value::Str(r.name.clone()),
+        "score" => num(r.score as u64),
+        "strand" => Value::Str(r.strand.to_string()),
+        "thick_start" => num(r.thick_start),
+        "thick_end" => num(r.thick_end),
+        "exon_count" => num(r.block_count() as u64),
+        "exon_len" => num(r.blocks.iter().map(|(start, end)| end - start).sum()),
+        "cds_len" => num(r
+            .blocks
+            .iter()
+            .map(|(start, end)| {
+                end.min(&r.thick_end)
+                    .saturating_sub(*start.max(&r.thick_start))
+            })
+            .sum()),
+        _ => return None,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Num(f64),
+    Str(String),
+    Missing,
+}
+
+impl Value {
+    fn num(&self) -> Option<f64> {
+        match self {
+            Value::Num(n) => Some(*n),
+            Value::Str(s) => s.trim().parse().ok(),
+            Value::Missing => None,
+        }
+        .filter(|n: &f64| n.is_finite())
+    }
+
+    fn truthy(&self) -> bool {
+        match self {
+            Value::Num(n) => *n != 0.0,
+            Value::Str(s) => !s.is_empty(),
+            Value::Missing => false,
+        }
+    }
+}
+
+impl From<Option<f64>> for Value {
+    fn from(n: Option<f64>) -> Self {
+        n.filter(|n| n.is_finite())
+            .map_or(Value::Missing, Value::Num)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Num(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Value::Num(n) => write!(f, "{}", n),
+            Value::Str(s) => f.write_str(s),
+            Value::Missing => f.write_str("."),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Num(f64),
+    Str(String),
+    Var(String),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+/// Functions and their argument counts (`None` for one or more).
+const FUNCTIONS: [(&str, Option<usize>); 9] = [
+    ("min", None),
+    ("max", None),
+    ("abs", Some(1)),
+    ("round", Some(1)),
+    ("floor", Some(1)),
+    ("ceil", Some(1)),
+    ("log10", Some(1)),
+    ("len", Some(1)),
+    ("if", Some(3)),
+];
+
+impl Expr {
+    /// Calls `visit` with every variable name, in order.
+    fn vars<'a>(&'a self, visit: &mut impl FnMut(&'a str)) {
+        match self {
+            Expr::Num(_) | Expr::Str(_) => {}
+            Expr::Var(name) => visit(name),
+            Expr::Neg(expr) | Expr::Not(expr) => expr.vars(visit),
+            Expr::Binary(_, lhs, rhs) => {
+                lhs.vars(visit);
+                rhs.vars(visit);
+            }
+            Expr::Call(_, args) => args.iter().for_each(|arg| arg.vars(visit)),
+        }
+    }
+
+    fn eval(&self, lookup: &dyn Fn(&str) -> Value) -> Value {
+        match self {
+            Expr::Num(n) => Value::Num(*n),
+            Expr::Str(s) => Value::Str(s.clone()),
+            Expr::Var(name) => lookup(name),
+            Expr::Neg(expr) => expr.eval(lookup).num().map(|n| -n).into(),
+            Expr::Not(expr) => bool_value(!expr.eval(lookup).truthy()),
+            Expr::Binary(Op::And, lhs, rhs) => {
+                bool_value(lhs.eval(lookup).truthy() && rhs.eval(lookup).truthy())
+            }
+            Expr::Binary(Op::Or, lhs, rhs) => {
+                bool_value(lhs.eval(lookup).truthy() || rhs.eval(lookup).truthy())
+            }
+            Expr::Binary(op, lhs, rhs) => binary(*op, lhs.eval(lookup), rhs.eval(lookup)),
+            Expr::Call(name, args) => {
+                let mut args = args.iter().map(|arg| arg.eval(lookup));
+                match name.as_str() {
+                    "if" => {
+                        let cond = args.next().is_some_and(|cond| cond.truthy());
+                        let mut branches = args.skip(usize::from(!cond));
+                        branches.next().unwrap_or(Value::Missing)
+                    }
+                    "len" => match args.next() {
+                        Some(Value::Str(s)) => Value::Num(s.chars().count() as f64),
+                        Some(value @ Value::Num(_)) => Value::Num(value.to_string().len() as f64),
+                        _ => Value::Missing,
+                    },
+                    "min" | "max" => {
+                        let nums = args.map(|arg| arg.num()).collect::<Option<Vec<_>>>();
+                        let pick = if name == "min" { f64::min } else { f64::max };
+                        nums.and_then(|nums| nums.into_iter().reduce(pick)).into()
+                    }
+                    _ => {
+                        let n = args.next().and_then(|arg| arg.num());
+                        n.map(|n| match name.as_str() {
+                            "abs" => n.abs(),
+                            "round" => n.round(),
+                            "floor" => n.floor(),
+                            "ceil" => n.ceil(),
+                            _ => n.log10(),
+                        })
+                        .into()
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn bool_value(b: bool) -> Value {
+    Value::Num(if b { 1.0 } else { 0.0 })
+}
+
+fn binary(op: Op, lhs: Value, rhs: Value) -> Value {
+    if lhs == Value::Missing || rhs == Value::Missing {
+        return Value::Missing;
+    }
+    if matches!(op, Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge) {
+        // INFO: numbers compare as numbers, so "10" > 9 holds for attribute values
+        let ordering = match (lhs.num(), rhs.num()) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ => Some(lhs.to_string().cmp(&rhs.to_string())),
+        };
+        let Some(ordering) = ordering else {
+            return Value::Missing;
+        };
+        return bool_value(match op {
+            Op::Eq => ordering.is_eq(),
+            Op::Ne => ordering.is_ne(),
+            Op::Lt => ordering.is_lt(),
+            Op::Le => ordering.is_le(),
+            Op::Gt => ordering.is_gt(),
+            _ => ordering.is_ge(),
+        });
+    }
+
+    let (Some(a), Some(b)) = (lhs.num(), rhs.num()) else {
+        return Value::Missing;
+    };
+    Some(match op {
+        Op::Add => a + b,
+        Op::Sub => a - b,
+        Op::Mul => a * b,
+        Op::Div => a / b,
+        _ => a % b,
+    })
+    .into()
+}
+
+/// Whether `s` can name a variable: letters, digits, `_` and `.`, not
+/// starting with a digit or `.`.
+fn is_ident(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Recursive-descent parser; precedence from loosest: `||`, `&&`,
+/// comparisons, `+ -`, `* / %`, unary `- !`.
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Self { src, pos: 0 }
+    }
+
+    fn parse(mut self) -> Result<Expr, String> {
+        let expr = self.or()?;
+        self.skip_space();
+        match self.rest().chars().next() {
+            None => Ok(expr),
+            Some(c) => Err(format!("unexpected {:?} at column {}", c, self.pos + 1)),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn skip_space(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consumes `token` if it comes next.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_space();
+        let found = self.rest().starts_with(token);
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.and()?;
+        while self.eat("||") {
+            lhs = Expr::Binary(Op::Or, Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.comparison()?;
+        while self.eat("&&") {
+            lhs = Expr::Binary(Op::And, Box::new(lhs), Box::new(self.comparison()?));
+        }
+        Ok(lhs)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let lhs = self.sum()?;
+        let ops = [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ];
+        for (token, op) in ops {
+            if self.eat(token) {
+                return Ok(Expr::Binary(op, Box::new(lhs), Box::new(self.sum()?)));
+            }
+        }
+        Ok(lhs)
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.product()?;
+        loop {
+            let op = match () {
+                _ if self.eat("+") => Op::Add,
+                _ if self.eat("-") => Op::Sub,
+                _ => return Ok(lhs),
+            };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match () {
+                _ if self.eat("*") => Op::Mul,
+                _ if self.eat("/") => Op::Div,
+                _ if self.eat("%") => Op::Rem,
+                _ => return Ok(lhs),
+            };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat("-") {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        self.skip_space();
+        let rest = self.rest();
+        let column = self.pos + 1;
+        let Some(first) = rest.chars().next() else {
+            return Err("unexpected end of expression".to_string());
+        };
+
+        if self.eat("(") {
+            let expr = self.or()?;
+            return match self.eat(")") {
+                true => Ok(expr),
+                false => Err(format!("unclosed '(' at column {}", column)),
+            };
+        }
+        if first == '"' {
+            let Some(len) = rest[1..].find('"') else {
+                return Err(format!("unclosed string at column {}", column));
+            };
+            self.pos += len + 2;
+            return Ok(Expr::Str(rest[1..=len].to_string()));
+        }
+        if first.is_ascii_digit() || first == '.' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            self.pos += len;
+            return rest[..len]
+                .parse()
+                .map(Expr::Num)
+                .map_err(|_| format!("invalid number {:?} at column {}", &rest[..len], column));
+        }
+
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+            .unwrap_or(rest.len());
+        let name = &rest[..len];
+        if !is_ident(name) {
+            return Err(format!("unexpected {:?} at column {}", first, column));
+        }
+        self.pos += len;
+        if !self.eat("(") {
+            return Ok(Expr::Var(name.to_string()));
+        }
+
+        let Some((_, arity)) = FUNCTIONS.iter().find(|(function, _)| *function == name) else {
+            return Err(format!("unknown function {:?} at column {}", name, column));
+        };
+        let mut args = Vec::new();
+        if !self.eat(")") {
+            loop {
+                args.push(self.or()?);
+                if self.eat(")") {
+                    break;
+                }
+                if !self.eat(",") {
+                    return Err(format!("expected ',' or ')' at column {}", self.pos + 1));
+                }
+            }
+        }
+        match arity {
+            Some(n) if args.len() != *n => Err(format!("{} takes {} arguments", name, n)),
+            None if args.is_empty() => Err(format!("{} takes at least one argument", name)),
+            _ => Ok(Expr::Call(name.to_string(), args)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gxf::Strand;
+
+    fn record() -> BedRecord {
+        BedRecord {
+            chrom: "chr1".to_string(),
+            start: 100,
+            end: 1000,
+            name: "T1".to_string(),
+            score: 0,
+            strand: Strand::Forward,
+            thick_start: 150,
+            thick_end: 900,
+            rgb: None,
+            blocks: vec![(100, 200), (800, 1000)],
+            extras: Vec::new(),
+        }
+    }
+
+    fn compute(specs: &[&str], values: &[Option<&str>]) -> BedRecord {
+        let computed = specs
+            .iter()
+            .map(|spec| spec.parse::<Computed>().unwrap())
+            .collect::<Vec<_>>();
+        let values = values
+            .iter()
+            .map(|value| value.map(str::to_string))
+            .collect::<Vec<_>>();
+        let mut record = record();
+        Compute::new(&computed).apply(&mut record, &values);
+        record
+    }
+
+    #[test]
+    fn test_compute_columns() {
+        let record = compute(
+            &[
+                "tx_len = end - start",
+                "score=min(1000, exon_count*50)",
+                "half=tx_len / 2 + 0.25",
+                "coding=if(cds_len > 0 && strand == \"+\", \"yes\", \"no\")",
+                "tsl=transcript_support_level * 10",
+                "gene=gene_name",
+            ],
+            &[Some("2"), None],
+        );
+
+        assert_eq!(record.score, 100);
+        assert_eq!(record.extras, ["900", "450.25", "yes", "20", "."]);
+    }
+
+    #[test]
+    fn test_compute_attrs() {
+        let computed = ["a=gene_name", "b=a + level", "score=b * level"]
+            .iter()
+            .map(|spec| spec.parse::<Computed>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(attrs(&computed), ["gene_name", "level"]);
+    }
+
+    #[test]
+    fn test_compute_parse_errors() {
+        let err = |spec: &str| spec.parse::<Computed>().unwrap_err();
+        assert!(err("x").contains("expected NAME=EXPR"));
+        assert!(err("start=1").contains("cannot assign"));
+        assert!(err("x=1 +").contains("unexpected end"));
+        assert!(err("x=(1").contains("unclosed '('"));
+        assert!(err("x=foo(1)").contains("unknown function"));
+        assert!(err("x=if(1, 2)").contains("takes 3 arguments"));
+        assert!(err("x=1 2").contains("column 3"));
+
+        let computed = "score = round(exon_len / 3)".parse::<Computed>().unwrap();
+        assert_eq!(computed.to_string(), "score=round(exon_len / 3)");
+        assert!(!computed.is_column());
+    }
+}
@@ -0,0 +1,1235 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use thiserror::Error;
+
+use crate::bed::{BedType, Columns, Emit};
+use crate::checksum::Checksum;
+use crate::chrom::ChromBounds;
+use crate::cli::Args;
+use crate::compat::Compat;
+use crate::compute::{self, Computed};
+use crate::detect::InputFormat;
+use crate::error::{Gxf2BedError, Result};
+use crate::escape::Escape;
+use crate::gxf::{Degenerate, DuplicateIds, InputCoords, MixedLoci, MixedStrand, OutOfBounds};
+use crate::input::IoStrategy;
+use crate::isoforms::{self, IsoformRank};
+use crate::output::{
+    derive_output_name, output_compression, ChildOutput, OutputFormat, OutputSpec,
+};
+use crate::preset::Preset;
+use crate::spill::parse_size;
+
+pub const DEFAULT_PARENT: &str = "transcript";
+pub const DEFAULT_CHILD: &str = "exon";
+pub const DEFAULT_FEATURE: &str = "transcript_id";
+/// Bases on either side of a splice site in `--emit splice-sites` windows.
+pub const DEFAULT_WINDOW: u64 = 10;
+
+/// Settings for a single GTF/GFF-to-BED conversion.
+///
+/// Missing keys fall back to [`Config::default`] when deserializing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Path to the GTF/GFF input, optionally gzip/zstd/bzip2 compressed.
+    pub input: PathBuf,
+    /// Path to the BED output; a `.gz`, `.zst` or `.bz2` extension enables
+    /// compression. An existing directory receives `<input stem>.bed`.
+    pub output: PathBuf,
+    /// Extra outputs rendered from the same parse; with an empty `output`,
+    /// only these are written.
+    pub outputs: Vec<OutputSpec>,
+    /// Also stream the main output, uncompressed, to stdout.
+    pub tee: bool,
+    /// Number of threads used to parse the input; 0 means all logical CPUs.
+    pub threads: usize,
+    /// Feature type whose span defines each record (third column).
+    pub parent: String,
+    /// Feature type turned into BED blocks (third column); a
+    /// comma-separated list, e.g. `exon,CDS`, unions the blocks of every
+    /// listed type, merging overlapping and adjacent ones.
+    pub child: String,
+    /// BED outputs of other child types from the same parse, e.g. exons and
+    /// CDS side by side; with an empty `output`, only these are written.
+    /// Read by [`crate::run`] only.
+    pub child_outputs: Vec<ChildOutput>,
+    /// Attribute used to group parents and children and to name records; a
+    /// comma-separated list is tried in order on each line.
+    pub feature: String,
+    /// Attribute of child lines holding their model's `feature` value when
+    /// it differs, e.g. `Parent` in GFF3 (which may list several models).
+    pub child_feature: Option<String>,
+    /// Attributes tried in order for the record name; the `feature` value is
+    /// used when none is present.
+    pub name_attrs: Vec<String>,
+    /// Attributes tried in order for the BED score (e.g. `TPM`, `FPKM`,
+    /// `cov`); values are rounded and clamped to 0-1000.
+    pub score_attrs: Vec<String>,
+    /// Attribute renames: options naming a key here read the mapped input
+    /// key instead, e.g. `gene_type = "gene_biotype"` for Ensembl files.
+    pub attr_aliases: BTreeMap<String, String>,
+    /// Key-value separator of the attribute column for in-house dialects
+    /// (e.g. `:`); GTF uses a space and GFF3 `=` when unset.
+    pub attr_sep: Option<char>,
+    /// Line prefixes skipped like `#` comments, e.g. `!` or `track`.
+    pub comment_chars: Vec<String>,
+    /// Copy the input's leading `#` header lines (genome build, provider,
+    /// version) to the top of the output.
+    pub keep_comments: bool,
+    /// Convention of the input's start column; GTF/GFF are 1-based.
+    pub input_coords: InputCoords,
+    /// What to do with child blocks outside their parent's span.
+    pub out_of_bounds: OutOfBounds,
+    /// Merge overlapping or adjacent blocks of a record into one.
+    pub merge_blocks: bool,
+    /// Emit parents without child features as one block spanning the parent,
+    /// for gene-only annotations.
+    pub keep_childless: bool,
+    /// Build the blocks of models without child features from their CDS
+    /// (and stop codon), for annotations with CDS but no exon lines;
+    /// applied before `keep_childless`.
+    pub cds_exons: bool,
+    /// What to do with zero- or negative-length features.
+    pub degenerate: Degenerate,
+    /// What to do with records whose key is used by several parent lines.
+    pub duplicate_ids: DuplicateIds,
+    /// What to do with IDs shared by features on different chromosomes or
+    /// strands.
+    pub mixed_loci: MixedLoci,
+    /// What to do with IDs whose features on one chromosome disagree on
+    /// the strand.
+    pub mixed_strand: MixedStrand,
+    /// UCSC-style `chrom.sizes` file; records are checked against it.
+    pub chrom_sizes: Option<PathBuf>,
+    /// What to do with records that do not fit within their chromosome.
+    pub chrom_bounds: ChromBounds,
+    /// Number of BED columns written per record.
+    pub bed_type: BedType,
+    /// Write 1-based, inclusive starts instead of BED's 0-based, half-open.
+    pub one_based: bool,
+    /// Write every record unstranded (`.`), exon ranks and the 5' phase
+    /// counted from the left as on `+`.
+    pub ignore_strand: bool,
+    /// Reproduce another tool's BED output; overrides `bed_type`,
+    /// `one_based`, `name_attrs` and `score_attrs`.
+    pub compat: Option<Compat>,
+    /// Column layout replacing `bed_type`: standard BED field names and
+    /// attributes in any order (see [`crate::bed::Columns`]); empty for
+    /// the standard layout.
+    pub columns: Vec<String>,
+    /// Derived score and extra columns, evaluated per record in order (see
+    /// [`crate::compute::Computed`]); columns go after all others.
+    pub compute: Vec<Computed>,
+    /// Keep at most this many isoforms per gene, the best by
+    /// `rank_isoforms`.
+    pub max_transcripts_per_gene: Option<usize>,
+    /// How isoforms are ranked by `max_transcripts_per_gene`.
+    pub rank_isoforms: IsoformRank,
+    /// Stop after writing this many records, reading the input only up to
+    /// the lines of the first that many IDs.
+    pub head: Option<usize>,
+    /// Keep this fraction (0 to 1) of the records, picked by hashing each
+    /// grouping key with `seed`; the same settings always pick the same
+    /// records.
+    pub sample: Option<f64>,
+    /// Seed of the `sample` hash.
+    pub seed: u64,
+    /// Write each record itself, the windows around its splice sites or
+    /// its exons.
+    pub emit: Emit,
+    /// Bases on either side of each splice site with
+    /// [`Emit::SpliceSites`].
+    pub window: u64,
+    /// Append the whole attribute column of each record's parent line (or
+    /// first child line) as one compact JSON column, after every other one.
+    pub attrs_json: bool,
+    /// How whitespace in record names and attribute-derived columns is
+    /// written.
+    pub escape: Escape,
+    /// Append each record's exon numbers (5' to 3', in block order) and
+    /// exon count as two extra columns; `exon_number` attributes are used
+    /// when every block has one.
+    pub exon_ranks: bool,
+    /// Count records whose CDS length is not a multiple of 3 (see
+    /// [`crate::RunStats::out_of_frame`]).
+    pub check_frame: bool,
+    /// Count coding records without start/stop codons or with codons off
+    /// the CDS ends (see [`crate::RunStats::missing_codons`]).
+    pub check_codons: bool,
+    /// Genome FASTA file the annotation was made on, for `check_orf`.
+    pub fasta: Option<PathBuf>,
+    /// Count coding records whose CDS, read from `fasta`, does not start
+    /// with ATG, end with a stop codon or is interrupted by one (see
+    /// [`crate::RunStats::bad_orfs`]).
+    pub check_orf: bool,
+    /// Fail on the first record that breaks the BED12 invariants (see
+    /// [`crate::BedRecord::check`]) instead of writing it.
+    pub check_output: bool,
+    /// Tab-separated report of records with exons outside their transcript
+    /// or CDS outside their exons, checked before `out_of_bounds` applies.
+    pub containment_report: Option<PathBuf>,
+    /// Digest of the output file computed while writing it, reported in
+    /// [`crate::RunStats::checksum`].
+    pub checksum: Option<Checksum>,
+    /// Codec level for compressed outputs; `None` favors speed.
+    pub compress_level: Option<u32>,
+    /// Budget in bytes for grouped data; past it, partial groups are spilled
+    /// to temporary files. Config files accept bytes or sizes like `"4G"`.
+    #[serde(deserialize_with = "deserialize_size")]
+    pub max_memory: Option<u64>,
+    /// How the input is read: buffered, in one read, or memory-mapped.
+    pub io_strategy: IoStrategy,
+    /// Keep converting a truncated compressed input up to its last complete
+    /// line instead of failing.
+    pub allow_truncated: bool,
+    /// Replace invalid UTF-8 in the input with U+FFFD instead of failing.
+    pub lossy_utf8: bool,
+    /// Skip sorting and write records in whatever order grouping yields them;
+    /// the order may change from run to run. Models are only complete once
+    /// the whole input is grouped, so nothing is written earlier than
+    /// without it; the final sort (or, with `max_memory`, its spilled runs)
+    /// is what is saved.
+    pub unordered: bool,
+    /// Report time and peak memory per phase (read, parse, render, write)
+    /// in `RunStats::profile`.
+    pub profile: bool,
+    /// Retries of a failed request for an `http(s)://` or `s3://` input,
+    /// with exponential backoff; transfers resume where they stopped.
+    pub retries: u32,
+}
+
+impl Config {
+    /// Creates a config with default parent/child/feature settings.
+    pub fn new<I: Into<PathBuf>, O: Into<PathBuf>>(input: I, output: O) -> Self {
+        Self {
+            input: input.into(),
+            output: output.into(),
+            outputs: Vec::new(),
+            tee: false,
+            threads: num_cpus::get(),
+            parent: DEFAULT_PARENT.to_string(),
+            child: DEFAULT_CHILD.to_string(),
+            child_outputs: Vec::new(),
+            feature: DEFAULT_FEATURE.to_string(),
+            child_feature: None,
+            name_attrs: Vec::new(),
+            score_attrs: Vec::new(),
+            attr_aliases: BTreeMap::new(),
+            attr_sep: None,
+            comment_chars: Vec::new(),
+            keep_comments: false,
+            input_coords: InputCoords::default(),
+            out_of_bounds: OutOfBounds::default(),
+            merge_blocks: false,
+            keep_childless: false,
+            cds_exons: false,
+            degenerate: Degenerate::default(),
+            duplicate_ids: DuplicateIds::default(),
+            mixed_loci: MixedLoci::default(),
+            mixed_strand: MixedStrand::default(),
+            chrom_sizes: None,
+            chrom_bounds: ChromBounds::default(),
+            bed_type: BedType::default(),
+            one_based: false,
+            ignore_strand: false,
+            compat: None,
+            columns: Vec::new(),
+            compute: Vec::new(),
+            max_transcripts_per_gene: None,
+            rank_isoforms: IsoformRank::default(),
+            head: None,
+            sample: None,
+            seed: 0,
+            emit: Emit::default(),
+            window: DEFAULT_WINDOW,
+            attrs_json: false,
+            escape: Escape::default(),
+            exon_ranks: false,
+            check_frame: false,
+            check_codons: false,
+            fasta: None,
+            check_orf: false,
+            check_output: false,
+            containment_report: None,
+            checksum: None,
+            compress_level: None,
+            max_memory: None,
+            io_strategy: IoStrategy::default(),
+            allow_truncated: false,
+            lossy_utf8: false,
+            unordered: false,
+            profile: false,
+            retries: 3,
+        }
+    }
+
+    /// Reads a TOML (`.toml`) or YAML (`.yaml`/`.yml`) config file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        let invalid = |e: String| Gxf2BedError::ConfigFile(format!("{:?}: {}", path, e));
+
+        match path.extension().and_then(|s| s.to_str()) {
+            Some("toml") => toml::from_str(&contents).map_err(|e| invalid(e.to_string())),
+            Some("yaml") | Some("yml") => {
+                serde_yaml::from_str(&contents).map_err(|e| invalid(e.to_string()))
+            }
+            _ => Err(invalid("expected a .toml, .yaml or .yml file".to_string())),
+        }
+    }
+
+    /// Renders the settings as a TOML config file that [`Config::from_file`]
+    /// reads back unchanged.
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("ERROR: Config is always serializable")
+    }
+
+    /// Attributes captured per record while grouping: `name_attrs`, then
+    /// `score_attrs`, then those ranking isoforms with
+    /// `max_transcripts_per_gene`, then those read by `compute`, then the
+    /// attribute `columns` (see [`crate::gxf::GenePred::attrs`]).
+    pub(crate) fn captured_attrs(&self) -> Vec<String> {
+        let mut attrs = self
+            .name_attrs
+            .iter()
+            .chain(&self.score_attrs)
+            .map(|name| self.attr_key(name))
+            .collect::<Vec<_>>();
+        if self.max_transcripts_per_gene.is_some() {
+            attrs.extend(isoforms::attrs(self));
+        }
+        attrs.extend(
+            compute::attrs(&self.compute)
+                .iter()
+                .map(|name| self.attr_key(name)),
+        );
+        attrs.extend(
+            Columns::attrs(&self.columns)
+                .iter()
+                .map(|name| self.attr_key(name)),
+        );
+        attrs
+    }
+
+    /// Feature types listed in `child`.
+    pub fn child_types(&self) -> impl Iterator<Item = &str> {
+        self.child.split(',')
+    }
+
+    /// Whether `kind` is one of the `child` feature types.
+    pub fn is_child(&self, kind: &str) -> bool {
+        self.child_types().any(|child| child == kind)
+    }
+
+    /// Attribute key-value separator for `format` input, after `attr_sep`.
+    pub fn separator(&self, format: InputFormat) -> u8 {
+        match self.attr_sep {
+            Some(sep) => sep as u8,
+            None => format.separator(),
+        }
+    }
+
+    /// Input key read for attribute `name` after `attr_aliases`; a `KEY:DB`
+    /// selector keeps its `:DB` part.
+    pub fn attr_key(&self, name: &str) -> String {
+        let (key, db) = match name.split_once(':') {
+            Some((key, db)) => (key, Some(db)),
+            None => (name, None),
+        };
+        let key = self.attr_aliases.get(key).map_or(key, String::as_str);
+
+        match db {
+            Some(db) => format!("{}:{}", key, db),
+            None => key.to_string(),
+        }
+    }
+
+    /// Grouping attribute with aliases resolved per entry of the `feature`
+    /// fallback list, as matched by [`crate::gxf::KeyMatcher::fallback`].
+    pub fn feature_key(&self) -> String {
+        self.feature
+            .split(',')
+            .map(|key| self.attr_key(key))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// File the BED output goes to: `output` itself, or the name derived from
+    /// `input` inside it when `output` is an existing directory.
+    pub fn output_path(&self) -> PathBuf {
+        if self.output.is_dir() {
+            self.output.join(derive_output_name(&self.input))
+        } else {
+            self.output.clone()
+        }
+    }
+
+    /// Whether the main `output` is written, i.e. it is set or there are no
+    /// extra `outputs` or `child_outputs` to write instead.
+    pub fn writes_output(&self) -> bool {
+        !self.output.as_os_str().is_empty()
+            || (self.outputs.is_empty() && self.child_outputs.is_empty())
+    }
+
+    /// Worker threads to run with, resolving 0 to the number of logical CPUs.
+    pub fn effective_threads(&self) -> usize {
+        match self.threads {
+            0 => num_cpus::get(),
+            n => n,
+        }
+    }
+
+    /// Starts a [`ConfigBuilder`] with default settings.
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
+    /// Checks option combinations, collecting every violation instead of
+    /// stopping at the first one.
+    pub fn validate(&self) -> std::result::Result<(), ConfigError> {
+        let mut violations = Vec::new();
+
+        let child_outputs = self.child_outputs.iter().map(|out| out.child.as_str());
+        for (name, empty) in [
+            ("parent", self.parent.is_empty()),
+            ("child", self.child_types().any(str::is_empty)),
+            (
+                "child outputs",
+                child_outputs
+                    .clone()
+                    .any(|child| child.split(',').any(str::is_empty)),
+            ),
+            ("feature", self.feature.split(',').any(str::is_empty)),
+        ] {
+            if empty {
+                violations.push(ConfigViolation::Empty(name));
+            }
+        }
+        if !self.parent.is_empty()
+            && (self.is_child(&self.parent)
+                || child_outputs
+                    .flat_map(|child| child.split(','))
+                    .any(|c| c == self.parent))
+        {
+            violations.push(ConfigViolation::SameParentChild(self.parent.clone()));
+        }
+        if let Some(sep) = self.attr_sep.filter(|c| !valid_attr_sep(*c)) {
+            violations.push(ConfigViolation::AttrSep(sep));
+        }
+        let mut paths = std::collections::HashSet::new();
+        if self.writes_output() {
+            paths.insert(self.output_path());
+        }
+        let extra = self.outputs.iter().map(|out| &out.path);
+        for path in extra.chain(self.child_outputs.iter().map(|out| &out.path)) {
+            if !paths.insert(path.clone()) {
+                violations.push(ConfigViolation::DuplicateOutput(path.clone()));
+            }
+        }
+        if !self.child_outputs.is_empty() && self.max_memory.is_some() {
+            violations.push(ConfigViolation::Conflict("child outputs", "max_memory"));
+        }
+        if self.max_transcripts_per_gene.is_some() && self.max_memory.is_some() {
+            violations.push(ConfigViolation::Conflict(
+                "max_transcripts_per_gene",
+                "max_memory",
+            ));
+        }
+        if let Some(fraction) = self.sample.filter(|f| !(*f > 0.0 && *f <= 1.0)) {
+            violations.push(ConfigViolation::Sample(fraction.to_string()));
+        }
+        if self.check_orf && self.fasta.is_none() {
+            violations.push(ConfigViolation::Requires("check_orf", "fasta"));
+        }
+        // INFO: reading frames and codons are only defined on a strand
+        if self.ignore_strand {
+            if self.check_frame {
+                violations.push(ConfigViolation::Conflict("ignore_strand", "check_frame"));
+            }
+            if self.check_codons {
+                violations.push(ConfigViolation::Conflict("ignore_strand", "check_codons"));
+            }
+            if self.check_orf {
+                violations.push(ConfigViolation::Conflict("ignore_strand", "check_orf"));
+            }
+        }
+        if !self.columns.is_empty() && self.compat.is_some() {
+            violations.push(ConfigViolation::Conflict("columns", "compat"));
+        }
+        // INFO: computed columns are appended to the standard layout only
+        if self.compute.iter().any(Computed::is_column) {
+            if !self.columns.is_empty() {
+                violations.push(ConfigViolation::Conflict("compute", "columns"));
+            }
+            if self.compat.is_some() {
+                violations.push(ConfigViolation::Conflict("compute", "compat"));
+            }
+        }
+        let emit = match self.emit {
+            Emit::Records => None,
+            Emit::SpliceSites => Some("emit splice-sites"),
+            Emit::Exons => Some("emit exons"),
+        };
+        if self.attrs_json {
+            if !self.columns.is_empty() {
+                violations.push(ConfigViolation::Conflict("attrs_json", "columns"));
+            }
+            if self.compat.is_some() {
+                violations.push(ConfigViolation::Conflict("attrs_json", "compat"));
+            }
+        }
+        // INFO: ranks describe the blocks and follow them as extra columns, which only BED12 writes
+        if self.exon_ranks && emit.is_none() {
+            if !self.columns.is_empty() {
+                violations.push(ConfigViolation::Conflict("exon_ranks", "columns"));
+            }
+            if self.compat.is_some() {
+                violations.push(ConfigViolation::Conflict("exon_ranks", "compat"));
+            }
+            if self.columns.is_empty() && self.compat.is_none() && self.bed_type != BedType::Bed12 {
+                violations.push(ConfigViolation::Conflict(
+                    "exon_ranks",
+                    "bed_type below bed12",
+                ));
+            }
+        }
+        if let Some(emit) = emit {
+            if self.exon_ranks {
+                violations.push(ConfigViolation::Conflict(emit, "exon_ranks"));
+            }
+            if self.attrs_json {
+                violations.push(ConfigViolation::Conflict(emit, "attrs_json"));
+            }
+            if self.compute.iter().any(Computed::is_column) {
+                violations.push(ConfigViolation::Conflict(emit, "compute"));
+            }
+            if !self.columns.is_empty() {
+                violations.push(ConfigViolation::Conflict(emit, "columns"));
+            }
+            if self.compat.is_some() {
+                violations.push(ConfigViolation::Conflict(emit, "compat"));
+            }
+            if self.one_based {
+                violations.push(ConfigViolation::Conflict(emit, "one_based"));
+            }
+        }
+        if let Some(level) = self.compress_level {
+            let compression = output_compression(self.output_path());
+            if let Some(levels) = compression.levels().filter(|l| !l.contains(&level)) {
+                violations.push(ConfigViolation::CompressLevel {
+                    level,
+                    codec: compression.name(),
+                    max: *levels.end(),
+                });
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError { violations })
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new(PathBuf::new(), PathBuf::new())
+    }
+}
+
+impl From<Args> for Config {
+    fn from(args: Args) -> Self {
+        // INFO: `--child TYPE:PATH` adds an output; plain values set the main child types
+        let (child_outputs, plain): (Vec<_>, Vec<_>) =
+            args.child.iter().partition(|child| child.contains(':'));
+        let child_outputs = child_outputs
+            .into_iter()
+            .filter_map(|spec| spec.parse::<ChildOutput>().ok())
+            .collect::<Vec<_>>();
+        let child = match plain.is_empty() {
+            false => plain.into_iter().cloned().collect::<Vec<_>>().join(","),
+            true if child_outputs.is_empty() => DEFAULT_CHILD.to_string(),
+            true => child_outputs
+                .iter()
+                .map(|out| out.child.as_str())
+                .collect::<Vec<_>>()
+                .join(","),
+        };
+
+        Self {
+            input: args.gxf.unwrap_or_default(),
+            output: args.output.unwrap_or_default(),
+            outputs: args.outs,
+            tee: args.tee,
+            threads: args.threads,
+            parent: args.parent,
+            child,
+            child_outputs,
+            feature: args.feature,
+            child_feature: args.child_feature,
+            name_attrs: args.name_attrs,
+            score_attrs: args.score_attrs,
+            attr_aliases: args.attr_aliases.into_iter().collect(),
+            attr_sep: args.attr_sep,
+            comment_chars: args.comment_chars,
+            keep_comments: args.keep_comments,
+            input_coords: args.input_coords,
+            out_of_bounds: args.out_of_bounds,
+            merge_blocks: args.merge_blocks,
+            keep_childless: args.keep_childless,
+            cds_exons: args.cds_exons,
+            degenerate: args.degenerate,
+            duplicate_ids: args.duplicate_ids,
+            mixed_loci: args.mixed_loci,
+            mixed_strand: args.mixed_strand,
+            chrom_sizes: args.chrom_sizes,
+            chrom_bounds: args.chrom_bounds,
+            bed_type: args.bed_type,
+            one_based: args.one_based,
+            ignore_strand: args.ignore_strand,
+            compat: args.compat,
+            columns: args.columns,
+            compute: args.compute,
+            max_transcripts_per_gene: args.max_transcripts_per_gene,
+            rank_isoforms: args.rank_isoforms,
+            head: args.head,
+            sample: args.sample,
+            seed: args.seed,
+            emit: args.emit,
+            window: args.window,
+            attrs_json: args.attrs_json,
+            escape: args.escape,
+            exon_ranks: args.exon_ranks,
+            check_frame: args.check_frame,
+            check_codons: args.check_codons,
+            fasta: args.fasta,
+            check_orf: args.check_orf,
+            check_output: args.check_output,
+            containment_report: args.containment_report,
+            checksum: args.checksum,
+            compress_level: args.compress_level,
+            max_memory: args.max_memory,
+            io_strategy: args.io_strategy,
+            allow_truncated: args.allow_truncated,
+            lossy_utf8: args.lossy_utf8,
+            unordered: args.unordered,
+            profile: args.profile,
+            retries: args.retries,
+        }
+    }
+}
+
+/// Whether `sep` can split attribute keys from values: one byte that is
+/// not part of the column's own syntax.
+fn valid_attr_sep(sep: char) -> bool {
+    sep.is_ascii() && !matches!(sep, ';' | '"' | '\t' | '\n' | '\r')
+}
+
+/// Parses a `NAME=KEY` attribute alias.
+pub fn parse_alias(alias: &str) -> std::result::Result<(String, String), String> {
+    match alias.split_once('=') {
+        Some((name, key)) if !name.trim().is_empty() && !key.trim().is_empty() => {
+            Ok((name.trim().to_string(), key.trim().to_string()))
+        }
+        _ => Err(format!("invalid alias {:?}, expected NAME=KEY", alias)),
+    }
+}
+
+/// Reads `max_memory` as either a byte count or a size string like `"4G"`.
+fn deserialize_size<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<u64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(u64),
+        Text(String),
+    }
+
+    match Option::<Size>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Size::Bytes(bytes)) => Ok(Some(bytes)),
+        Some(Size::Text(text)) => parse_size(&text).map(Some).map_err(D::Error::custom),
+    }
+}
+
+/// Builds a [`Config`] step by step and validates it in [`ConfigBuilder::build`].
+#[derive(Debug, Clone, Default)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    pub fn input<P: Into<PathBuf>>(mut self, input: P) -> Self {
+        self.config.input = input.into();
+        self
+    }
+
+    pub fn output<P: Into<PathBuf>>(mut self, output: P) -> Self {
+        self.config.output = output.into();
+        self
+    }
+
+    /// Adds an extra `format` output at `path`.
+    pub fn also_output<P: Into<PathBuf>>(mut self, format: OutputFormat, path: P) -> Self {
+        self.config.outputs.push(OutputSpec {
+            format,
+            path: path.into(),
+        });
+        self
+    }
+
+    pub fn tee(mut self, tee: bool) -> Self {
+        self.config.tee = tee;
+        self
+    }
+
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.config.threads = threads;
+        self
+    }
+
+    pub fn parent<S: Into<String>>(mut self, parent: S) -> Self {
+        self.config.parent = parent.into();
+        self
+    }
+
+    pub fn child<S: Into<String>>(mut self, child: S) -> Self {
+        self.config.child = child.into();
+        self
+    }
+
+    /// Adds a BED output at `path` whose blocks come from the `child` types.
+    pub fn child_output<S: Into<String>, P: Into<PathBuf>>(mut self, child: S, path: P) -> Self {
+        self.config.child_outputs.push(ChildOutput {
+            child: child.into(),
+            path: path.into(),
+        });
+        self
+    }
+
+    pub fn feature<S: Into<String>>(mut self, feature: S) -> Self {
+        self.config.feature = feature.into();
+        self
+    }
+
+    pub fn child_feature<S: Into<String>>(mut self, attr: S) -> Self {
+        self.config.child_feature = Some(attr.into());
+        self
+    }
+
+    pub fn name_attrs<I: IntoIterator<Item = S>, S: Into<String>>(mut self, attrs: I) -> Self {
+        self.config.name_attrs = attrs.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn score_attrs<I: IntoIterator<Item = S>, S: Into<String>>(mut self, attrs: I) -> Self {
+        self.config.score_attrs = attrs.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Reads input key `key` wherever an option names attribute `name`.
+    pub fn attr_alias<N: Into<String>, K: Into<String>>(mut self, name: N, key: K) -> Self {
+        self.config.attr_aliases.insert(name.into(), key.into());
+        self
+    }
+
+    pub fn attr_sep(mut self, sep: char) -> Self {
+        self.config.attr_sep = Some(sep);
+        self
+    }
+
+    pub fn comment_chars<I: IntoIterator<Item = S>, S: Into<String>>(
+        mut self,
+        prefixes: I,
+    ) -> Self {
+        self.config.comment_chars = prefixes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn keep_comments(mut self, keep: bool) -> Self {
+        self.config.keep_comments = keep;
+        self
+    }
+
+    pub fn input_coords(mut self, coords: InputCoords) -> Self {
+        self.config.input_coords = coords;
+        self
+    }
+
+    pub fn out_of_bounds(mut self, policy: OutOfBounds) -> Self {
+        self.config.out_of_bounds = policy;
+        self
+    }
+
+    pub fn merge_blocks(mut self, merge: bool) -> Self {
+        self.config.merge_blocks = merge;
+        self
+    }
+
+    pub fn keep_childless(mut self, keep: bool) -> Self {
+        self.config.keep_childless = keep;
+        self
+    }
+
+    pub fn cds_exons(mut self, cds_exons: bool) -> Self {
+        self.config.cds_exons = cds_exons;
+        self
+    }
+
+    pub fn degenerate(mut self, policy: Degenerate) -> Self {
+        self.config.degenerate = policy;
+        self
+    }
+
+    pub fn duplicate_ids(mut self, policy: DuplicateIds) -> Self {
+        self.config.duplicate_ids = policy;
+        self
+    }
+
+    pub fn mixed_loci(mut self, policy: MixedLoci) -> Self {
+        self.config.mixed_loci = policy;
+        self
+    }
+
+    pub fn mixed_strand(mut self, policy: MixedStrand) -> Self {
+        self.config.mixed_strand = policy;
+        self
+    }
+
+    pub fn chrom_sizes<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.config.chrom_sizes = Some(path.into());
+        self
+    }
+
+    pub fn chrom_bounds(mut self, policy: ChromBounds) -> Self {
+        self.config.chrom_bounds = policy;
+        self
+    }
+
+    pub fn bed_type(mut self, bed_type: BedType) -> Self {
+        self.config.bed_type = bed_type;
+        self
+    }
+
+    pub fn one_based(mut self, one_based: bool) -> Self {
+        self.config.one_based = one_based;
+        self
+    }
+
+    pub fn ignore_strand(mut self, ignore: bool) -> Self {
+        self.config.ignore_strand = ignore;
+        self
+    }
+
+    pub fn compat(mut self, compat: Compat) -> Self {
+        self.config.compat = Some(compat);
+        self
+    }
+
+    pub fn columns<I: IntoIterator<Item = S>, S: Into<String>>(mut self, columns: I) -> Self {
+        self.config.columns = columns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Adds a derived score or column, e.g. `"tx_len=end-start"`.
+    pub fn compute(mut self, computed: Computed) -> Self {
+        self.config.compute.push(computed);
+        self
+    }
+
+    pub fn max_transcripts_per_gene(mut self, max: usize) -> Self {
+        self.config.max_transcripts_per_gene = Some(max);
+        self
+    }
+
+    pub fn rank_isoforms(mut self, rank: IsoformRank) -> Self {
+        self.config.rank_isoforms = rank;
+        self
+    }
+
+    pub fn head(mut self, n: usize) -> Self {
+        self.config.head = Some(n);
+        self
+    }
+
+    pub fn sample(mut self, fraction: f64) -> Self {
+        self.config.sample = Some(fraction);
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = seed;
+        self
+    }
+
+    pub fn emit(mut self, emit: Emit) -> Self {
+        self.config.emit = emit;
+        self
+    }
+
+    pub fn window(mut self, window: u64) -> Self {
+        self.config.window = window;
+        self
+    }
+
+    pub fn attrs_json(mut self, json: bool) -> Self {
+        self.config.attrs_json = json;
+        self
+    }
+
+    pub fn escape(mut self, policy: Escape) -> Self {
+        self.config.escape = policy;
+        self
+    }
+
+    pub fn exon_ranks(mut self, ranks: bool) -> Self {
+        self.config.exon_ranks = ranks;
+        self
+    }
+
+    pub fn check_frame(mut self, check: bool) -> Self {
+        self.config.check_frame = check;
+        self
+    }
+
+    pub fn check_codons(mut self, check: bool) -> Self {
+        self.config.check_codons = check;
+        self
+    }
+
+    pub fn fasta<P: Into<PathBuf>>(mut self, fasta: P) -> Self {
+        self.config.fasta = Some(fasta.into());
+        self
+    }
+
+    pub fn check_orf(mut self, check: bool) -> Self {
+        self.config.check_orf = check;
+        self
+    }
+
+    pub fn check_output(mut self, check: bool) -> Self {
+        self.config.check_output = check;
+        self
+    }
+
+    pub fn containment_report<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.config.containment_report = Some(path.into());
+        self
+    }
+
+    pub fn checksum(mut self, checksum: Checksum) -> Self {
+        self.config.checksum = Some(checksum);
+        self
+    }
+
+    pub fn compress_level(mut self, level: u32) -> Self {
+        self.config.compress_level = Some(level);
+        self
+    }
+
+    pub fn max_memory(mut self, bytes: u64) -> Self {
+        self.config.max_memory = Some(bytes);
+        self
+    }
+
+    pub fn io_strategy(mut self, strategy: IoStrategy) -> Self {
+        self.config.io_strategy = strategy;
+        self
+    }
+
+    pub fn allow_truncated(mut self, allow: bool) -> Self {
+        self.config.allow_truncated = allow;
+        self
+    }
+
+    pub fn lossy_utf8(mut self, lossy: bool) -> Self {
+        self.config.lossy_utf8 = lossy;
+        self
+    }
+
+    pub fn unordered(mut self, unordered: bool) -> Self {
+        self.config.unordered = unordered;
+        self
+    }
+
+    pub fn profile(mut self, profile: bool) -> Self {
+        self.config.profile = profile;
+        self
+    }
+
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.config.retries = retries;
+        self
+    }
+
+    /// Applies `preset` to the settings so far; later calls override it.
+    pub fn preset(mut self, preset: Preset) -> Self {
+        preset.apply(&mut self.config);
+        self
+    }
+
+    /// Validates the accumulated settings and returns the finished config.
+    pub fn build(self) -> std::result::Result<Config, ConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ConfigViolation {
+    /// A required option was set to an empty string.
+    #[error("{0} must not be empty")]
+    Empty(&'static str),
+
+    /// Parent and child select the same feature type.
+    #[error("parent and child are both {0:?}")]
+    SameParentChild(String),
+
+    /// The attribute separator is not a usable single-byte character.
+    #[error(
+        "attribute separator {0:?} must be an ASCII character other than ';', '\"', tab or newline"
+    )]
+    AttrSep(char),
+
+    /// Two outputs would be written to the same file.
+    #[error("{0:?} is given as more than one output")]
+    DuplicateOutput(PathBuf),
+
+    /// The sampled fraction is not within (0, 1].
+    #[error("sample fraction {0} must be greater than 0 and at most 1")]
+    Sample(String),
+
+    /// An option that only works together with another one.
+    #[error("{0} needs {1}")]
+    Requires(&'static str, &'static str),
+
+    /// Two options that cannot be used together.
+    #[error("{0} cannot be combined with {1}")]
+    Conflict(&'static str, &'static str),
+
+    /// The compression level is out of range for the output codec.
+    #[error("compression level {level} is out of range for {codec} (max {max})")]
+    CompressLevel {
+        level: u32,
+        codec: &'static str,
+        max: u32,
+    },
+}
+
+/// Every violation found while validating a [`Config`].
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("{}", .violations.iter().map(|v| v.to_string()).collect::<Vec<String>>().join("; "))]
+pub struct ConfigError {
+    pub violations: Vec<ConfigViolation>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_defaults() {
+        let config = Config::builder()
+            .input("a.gtf")
+            .output("a.bed")
+            .bed_type(BedType::Bed6)
+            .build()
+            .unwrap();
+
+        assert_eq!(config.parent, DEFAULT_PARENT);
+        assert_eq!(config.bed_type, BedType::Bed6);
+    }
+
+    #[test]
+    fn test_compress_level_range() {
+        let config = |output: &str, level| {
+            Config::builder()
+                .output(output)
+                .compress_level(level)
+                .build()
+        };
+
+        assert!(config("a.bed.gz", 9).is_ok());
+        assert!(config("a.bed.zst", 19).is_ok());
+        assert!(config("a.bed", 19).is_ok());
+        assert_eq!(
+            config("a.bed.gz", 19).unwrap_err().violations,
+            vec![ConfigViolation::CompressLevel {
+                level: 19,
+                codec: "gzip",
+                max: 9
+            }]
+        );
+    }
+
+    #[test]
+    fn test_output_path_directory() {
+        let config = Config::new("data/foo.gtf.gz", std::env::temp_dir());
+        assert_eq!(config.output_path(), std::env::temp_dir().join("foo.bed"));
+
+        let config = Config::new("data/foo.gtf.gz", "out.bed.gz");
+        assert_eq!(config.output_path(), PathBuf::from("out.bed.gz"));
+    }
+
+    #[test]
+    fn test_zero_threads_is_auto() {
+        let config = Config::builder().threads(0).build().unwrap();
+        assert_eq!(config.effective_threads(), num_cpus::get());
+    }
+
+    #[test]
+    fn test_deserialize_partial_toml() {
+        let config: Config = toml::from_str(
+            r#"
+            input = "a.gff3"
+            parent = "mRNA"
+            bed_type = "bed6"
+            max_memory = "2G"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.input, PathBuf::from("a.gff3"));
+        assert_eq!(config.parent, "mRNA");
+        assert_eq!(config.child, DEFAULT_CHILD);
+        assert_eq!(config.bed_type, BedType::Bed6);
+        assert_eq!(config.max_memory, Some(2 << 30));
+    }
+
+    #[test]
+    fn test_to_toml_round_trip() {
+        let config = Config::builder()
+            .input("a.gff3")
+            .parent("mRNA")
+            .child_output("CDS", "cds.bed")
+            .sample(0.5)
+            .head(10)
+            .attr_alias("gene", "Name")
+            .build()
+            .unwrap();
+
+        assert_eq!(toml::from_str::<Config>(&config.to_toml()).unwrap(), config);
+    }
+
+    #[test]
+    fn test_deserialize_yaml_rejects_unknown_keys() {
+        assert!(serde_yaml::from_str::<Config>("parnet: mRNA\n").is_err());
+    }
+
+    #[test]
+    fn test_builder_collects_all_violations() {
+        let err = Config::builder()
+            .feature("")
+            .parent("exon")
+            .attr_sep(';')
+            .emit(Emit::SpliceSites)
+            .one_based(true)
+            .build()
+            .unwrap_err();
+
+        assert_eq!(
+            err.violations,
+            vec![
+                ConfigViolation::Empty("feature"),
+                ConfigViolation::SameParentChild("exon".to_string()),
+                ConfigViolation::AttrSep(';'),
+                ConfigViolation::Conflict("emit splice-sites", "one_based"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_emit_compute_conflict() {
+        let config = |emit, spec: &str| {
+            Config::builder()
+                .emit(emit)
+                .compute(spec.parse().unwrap())
+                .build()
+        };
+
+        for (emit, name) in [
+            (Emit::Exons, "emit exons"),
+            (Emit::SpliceSites, "emit splice-sites"),
+        ] {
+            assert_eq!(
+                config(emit, "len=end-start").unwrap_err().violations,
+                vec![ConfigViolation::Conflict(name, "compute")]
+            );
+            // INFO: record fields are still written per exon or splice site
+            assert!(config(emit, "score=exon_count").is_ok());
+        }
+    }
+
+    #[test]
+    fn test_exon_ranks_conflicts() {
+        let violations = |builder: ConfigBuilder| builder.exon_ranks(true).build().err();
+
+        assert_eq!(violations(Config::builder()), None);
+        assert_eq!(
+            violations(Config::builder().bed_type(BedType::Bed6))
+                .unwrap()
+                .violations,
+            vec![ConfigViolation::Conflict(
+                "exon_ranks",
+                "bed_type below bed12"
+            )]
+        );
+        assert_eq!(
+            violations(Config::builder().columns(["chrom", "start", "end"]))
+                .unwrap()
+                .violations,
+            vec![ConfigViolation::Conflict("exon_ranks", "columns")]
+        );
+        assert_eq!(
+            violations(Config::builder().emit(Emit::Exons))
+                .unwrap()
+                .violations,
+            vec![ConfigViolation::Conflict("emit exons", "exon_ranks")]
+        );
+    }
+
+    #[test]
+    fn test_ignore_strand_conflicts() {
+        let err = Config::builder()
+            .ignore_strand(true)
+            .check_frame(true)
+            .check_codons(true)
+            .build()
+            .unwrap_err();
+
+        assert_eq!(
+            err.violations,
+            vec![
+                ConfigViolation::Conflict("ignore_strand", "check_frame"),
+                ConfigViolation::Conflict("ignore_strand", "check_codons"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_attr_aliases() {
+        let config: Config = toml::from_str(
+            r#"
+            feature = "gene_type"
+            [attr_aliases]
+            gene_type = "gene_biotype"
+            Name = "gene_name"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.attr_key(&config.feature), "gene_biotype");
+        assert_eq!(config.attr_key("Name:HGNC"), "gene_name:HGNC");
+        assert_eq!(config.attr_key("gene_id"), "gene_id");
+        assert_eq!(
+            parse_alias("Name = gene_name"),
+            Ok(("Name".to_string(), "gene_name".to_string()))
+        );
+        assert!(parse_alias("gene_type").is_err());
+    }
+}
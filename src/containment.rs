@@ -0,0 +1,93 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::error::Result;
+use crate::gxf::{GenePred, RecordType};
+
+/// Tab-separated list of models whose exons leave their transcript, whose
+/// CDS leaves their exons or whose ID is shared with other chromosomes or
+/// strands (`--containment-report`), one line per problem; these are
+/// behind most odd-looking block layouts.
+pub(crate) struct Containment {
+    writer: BufWriter<File>,
+    /// Models with at least one problem so far.
+    pub(crate) found: usize,
+}
+
+impl Containment {
+    pub(crate) fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .map_err(|e| std::io::Error::new(e.kind(), format!("{:?}: {}", path, e)))?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "#name\tchrom\tstart\tend\tproblem")?;
+
+        Ok(Self { writer, found: 0 })
+    }
+
+    /// Reports the problems of model `name`, as grouped from the input.
+    pub(crate) fn check(&mut self, name: &str, info: &GenePred) -> Result<()> {
+        let mut problems = Vec::new();
+        // INFO: without a parent line the span comes from the blocks themselves
+        if info.record_type == RecordType::Parent && info.has_blocks_outside() {
+            problems.push("exon_outside_transcript");
+        }
+        if info.has_cds_outside_exons() {
+            problems.push("cds_outside_exons");
+        }
+        if info.loci > 1 {
+            problems.push("id_on_several_loci");
+        }
+
+        for problem in &problems {
+            writeln!(
+                self.writer,
+                "{}\t{}\t{}\t{}\t{}",
+                name, info.chr, info.start, info.end, problem
+            )?;
+        }
+        if !problems.is_empty() {
+            self.found += 1;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn finish(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gxf::Strand;
+
+    #[test]
+    fn test_containment_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("containment.tsv");
+        let mut report = Containment::create(&path).unwrap();
+
+        let mut model = GenePred::new();
+        model.set_parent("chr1", 100, 200, Strand::Forward);
+        model.add_child("chr1", 100, 150, Strand::Forward);
+        model.add_child("chr1", 180, 200, Strand::Forward);
+        model.coding_mut().add_cds(120, 150, "0");
+        report.check("ok", &model).unwrap();
+
+        model.coding_mut().add_cds(160, 190, "0");
+        model.add_child("chr1", 190, 210, Strand::Forward);
+        report.check("bad", &model).unwrap();
+        report.finish().unwrap();
+
+        assert_eq!(report.found, 1);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "#name\tchrom\tstart\tend\tproblem\n\
+             bad\tchr1\t100\t200\texon_outside_transcript\n\
+             bad\tchr1\t100\t200\tcds_outside_exons\n"
+        );
+    }
+}
@@ -0,0 +1,2375 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::time::{Duration, Instant};
+
+use hashbrown::HashMap;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::bed::{BedEmitter, BedRecord, BedType, Columns, Emit, Exons, OneBased, SpliceSites};
+use crate::checksum::Hashing;
+use crate::chrom::{ChromBounds, ChromSizes};
+use crate::codec::{decoder, is_truncated};
+use crate::compat::Compat;
+use crate::compute::{self, Compute};
+use crate::config::Config;
+use crate::containment::Containment;
+use crate::detect::{detect_input_kind, Compression, InputFormat};
+use crate::error::{Gxf2BedError, Result};
+use crate::escape::Escape;
+use crate::fasta::{check_orf, Genome};
+use crate::gxf::{
+    header_comments, Coding, Degenerate, DuplicateIds, GenePred, MixedLoci, MixedStrand,
+    OutOfBounds, RecordType, Strand,
+};
+use crate::input::{read_head, read_input, read_input_head, Input};
+use crate::isoforms;
+use crate::output::{create_output, finish_output, wrap_output, OutputWriter, Tee};
+use crate::par_y;
+use crate::profile::{timed as profiled, Phase, Profile};
+use crate::progress::{NoProgress, ProgressHook, ProgressReader};
+use crate::source::{group_source, AnnotationSource};
+use crate::spill::group_with_budget;
+use crate::utf8::into_text;
+use crate::utils::{to_bed, to_bed_many, Grouping};
+
+/// Summary of a finished conversion.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RunStats {
+    /// Records written to the output.
+    pub written: usize,
+    /// Groups skipped because they had no child features.
+    pub skipped: usize,
+    /// Groups without a parent line, whose span was derived from their
+    /// child features.
+    pub derived_spans: usize,
+    /// Records with blocks outside their parent's span, handled according
+    /// to `Config::out_of_bounds`.
+    pub out_of_bounds: usize,
+    /// Records whose overlapping or adjacent blocks were merged
+    /// (`Config::merge_blocks`).
+    pub merged: usize,
+    /// Records whose blocks were built from their CDS
+    /// (`Config::cds_exons`).
+    pub cds_exons: usize,
+    /// Records with whitespace in their name or attribute-derived columns,
+    /// written according to `Config::escape`.
+    pub escaped: usize,
+    /// Records with a zero- or negative-length feature, handled according
+    /// to `Config::degenerate`.
+    pub degenerate: usize,
+    /// Records that do not fit within their chromosome according to
+    /// `Config::chrom_sizes`, handled according to `Config::chrom_bounds`.
+    pub off_chrom: usize,
+    /// Records whose ID is used by more than one parent line, handled
+    /// according to `Config::duplicate_ids`.
+    pub duplicate_ids: usize,
+    /// Records whose ID is shared by features on other chromosomes or
+    /// strands, handled according to `Config::mixed_loci`.
+    pub mixed_loci: usize,
+    /// Records whose features on one chromosome disagree on the strand,
+    /// handled according to `Config::mixed_strand`.
+    pub mixed_strand: usize,
+    /// Records whose CDS length, stop codon included and 5' phase skipped,
+    /// is not a multiple of 3 (`Config::check_frame`).
+    pub out_of_frame: usize,
+    /// Coding records lacking a `start_codon` or `stop_codon` feature
+    /// (`Config::check_codons`).
+    pub missing_codons: usize,
+    /// Coding records whose start or stop codon is not at its CDS end
+    /// (`Config::check_codons`).
+    pub misplaced_codons: usize,
+    /// Coding records whose CDS sequence is not a clean open reading
+    /// frame (`Config::check_orf`).
+    pub bad_orfs: usize,
+    /// Records listed in `Config::containment_report`.
+    pub uncontained: usize,
+    /// Hex digest of the output file, with `Config::checksum`.
+    pub checksum: Option<String>,
+    /// Size of `config.input` as stored (compressed or not), for runs that
+    /// read it; 0 otherwise.
+    pub input_bytes: u64,
+    /// BED bytes rendered, before compression, summed over the outputs.
+    pub output_bytes: u64,
+    /// Wall time from reading the input to finishing the outputs, for runs
+    /// that read `config.input`; zero otherwise.
+    pub elapsed: Duration,
+    /// Time and peak memory per phase, with `Config::profile`.
+    pub profile: Option<Profile>,
+}
+
+impl RunStats {
+    /// Adds the counts of `other`; the checksum, being per file, is dropped.
+    fn add(&mut self, other: RunStats) {
+        let RunStats {
+            written,
+            skipped,
+            derived_spans,
+            out_of_bounds,
+            merged,
+            cds_exons,
+            escaped,
+            degenerate,
+            off_chrom,
+            duplicate_ids,
+            mixed_loci,
+            mixed_strand,
+            out_of_frame,
+            missing_codons,
+            misplaced_codons,
+            bad_orfs,
+            uncontained,
+            checksum: _,
+            input_bytes: _,
+            output_bytes,
+            elapsed: _,
+            profile,
+        } = other;
+        self.written += written;
+        self.skipped += skipped;
+        self.derived_spans += derived_spans;
+        self.out_of_bounds += out_of_bounds;
+        self.merged += merged;
+        self.cds_exons += cds_exons;
+        self.escaped += escaped;
+        self.degenerate += degenerate;
+        self.off_chrom += off_chrom;
+        self.duplicate_ids += duplicate_ids;
+        self.mixed_loci += mixed_loci;
+        self.mixed_strand += mixed_strand;
+        self.out_of_frame += out_of_frame;
+        self.missing_codons += missing_codons;
+        self.misplaced_codons += misplaced_codons;
+        self.bad_orfs += bad_orfs;
+        self.uncontained += uncontained;
+        self.output_bytes += output_bytes;
+        if let Some(profile) = profile {
+            self.profile
+                .get_or_insert_with(Profile::default)
+                .merge(&profile);
+        }
+        self.checksum = None;
+    }
+
+    /// Records written per second of `elapsed`; 0 for untimed runs.
+    pub fn records_per_sec(&self) -> f64 {
+        per_sec(self.written as f64, self.elapsed)
+    }
+
+    /// Input megabytes (10^6 bytes) read per second of `elapsed`.
+    pub fn input_mb_per_sec(&self) -> f64 {
+        per_sec(self.input_bytes as f64 / 1e6, self.elapsed)
+    }
+
+    /// Output megabytes (10^6 bytes) rendered per second of `elapsed`.
+    pub fn output_mb_per_sec(&self) -> f64 {
+        per_sec(self.output_bytes as f64 / 1e6, self.elapsed)
+    }
+}
+
+fn per_sec(amount: f64, elapsed: Duration) -> f64 {
+    match elapsed.as_secs_f64() {
+        secs if secs > 0.0 => amount / secs,
+        _ => 0.0,
+    }
+}
+
+/// Grouped models in output order; fallible when they are read back from disk.
+pub(crate) type Groups = Box<dyn Iterator<Item = Result<(String, GenePred)>> + Send>;
+
+/// Rendered bytes collected before each write to the output.
+const WRITE_BATCH: usize = 1 << 20;
+
+/// Converted records, yielded sorted by chromosome, start, end and name
+/// (in arbitrary order with `Config::unordered`).
+///
+/// Grouping needs the whole input, so it happens up front; each
+/// [`BedRecord`] is only built when the iterator is advanced.
+pub struct Records {
+    error: Option<Gxf2BedError>,
+    groups: Groups,
+    skipped: usize,
+    derived: usize,
+    parents: usize,
+    /// Parent and child feature names, taken once the spans are reported.
+    features: Option<(String, String)>,
+    fixes: Fixes,
+    labels: Labels,
+    sample: Option<Sample>,
+    /// Records still to write with `Config::head`.
+    remaining: Option<usize>,
+    compat: Option<Compat>,
+    exon_ranks: bool,
+    check_frame: bool,
+    out_of_frame: usize,
+    check_codons: bool,
+    missing_codons: usize,
+    misplaced_codons: usize,
+    /// Sequences for `Config::check_orf`.
+    genome: Option<Genome>,
+    bad_orfs: usize,
+    containment: Option<Containment>,
+    header: Vec<String>,
+    /// Read and parse times with `Config::profile`, completed by
+    /// [`write_outputs`].
+    profile: Option<Profile>,
+    compute: Compute,
+    escape: Escape,
+    escaped: usize,
+    check_output: bool,
+    ignore_strand: bool,
+}
+
+/// Picks record names, scores and attribute columns from the attribute
+/// values captured while grouping (`Config::name_attrs`, then
+/// `Config::score_attrs`, with those of `Config::compute` and then
+/// `Config::columns` last).
+#[derive(Default)]
+struct Labels {
+    names: usize,
+    scores: usize,
+    computed: usize,
+    columns: usize,
+    /// Whether the last value is the attribute column as JSON
+    /// (`Config::attrs_json`).
+    json: bool,
+}
+
+impl Labels {
+    fn new(config: &Config) -> Self {
+        Self {
+            names: config.name_attrs.len(),
+            scores: config.score_attrs.len(),
+            computed: compute::attrs(&config.compute).len(),
+            columns: Columns::attrs(&config.columns).len(),
+            json: config.attrs_json,
+        }
+    }
+
+    /// Captured values before the JSON column, if any.
+    fn values<'a>(&self, attrs: &'a [Option<String>]) -> &'a [Option<String>] {
+        &attrs[..attrs.len().saturating_sub(self.json as usize)]
+    }
+
+    /// Values of the attributes read by `Config::compute`.
+    fn computed<'a>(&self, attrs: &'a [Option<String>]) -> &'a [Option<String>] {
+        let attrs = self.values(attrs);
+        let end = attrs.len().saturating_sub(self.columns);
+        &attrs[end.saturating_sub(self.computed)..end]
+    }
+
+    /// Values of the attribute columns, `.` when missing.
+    fn columns(&self, attrs: &[Option<String>]) -> Vec<String> {
+        let attrs = self.values(attrs);
+        let first = attrs.len().saturating_sub(self.columns);
+        attrs[first..]
+            .iter()
+            .map(|value| value.clone().unwrap_or_else(|| ".".to_string()))
+            .collect()
+    }
+
+    /// The attribute column as JSON; `{}` for models whose lines had none
+    /// captured.
+    fn json(&self, attrs: &[Option<String>]) -> String {
+        match attrs.last() {
+            Some(Some(json)) if self.json => json.clone(),
+            _ => "{}".to_string(),
+        }
+    }
+
+    /// First name attribute present, or the grouping key.
+    fn name(&self, key: String, attrs: &[Option<String>]) -> String {
+        attrs
+            .iter()
+            .take(self.names)
+            .find_map(|value| value.clone())
+            .unwrap_or(key)
+    }
+
+    /// First numeric score attribute, rounded and clamped to BED's 0-1000.
+    fn score(&self, attrs: &[Option<String>]) -> u32 {
+        attrs
+            .iter()
+            .skip(self.names)
+            .take(self.scores)
+            .flatten()
+            .find_map(|value| value.parse::<f64>().ok())
+            .map_or(0, |score| score.round().clamp(0.0, 1000.0) as u32)
+    }
+}
+
+/// Deterministic subset of the records (`Config::sample`), picked by a
+/// seeded hash of the grouping key so it does not depend on input order,
+/// threads or spilling.
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    fraction: f64,
+    seed: u64,
+}
+
+impl Sample {
+    fn new(config: &Config) -> Option<Self> {
+        config.sample.map(|fraction| Self {
+            fraction,
+            seed: config.seed,
+        })
+    }
+
+    /// Whether the model keyed `name` is in the sample.
+    fn keeps(&self, name: &str) -> bool {
+        // INFO: FNV-1a, then the splitmix64 finalizer; stable across builds, unlike std's hasher
+        let mut hash = 0xcbf2_9ce4_8422_2325 ^ self.seed;
+        for byte in name.bytes() {
+            hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        hash ^= hash >> 31;
+
+        ((hash >> 11) as f64 / (1u64 << 53) as f64) < self.fraction
+    }
+}
+
+/// Repairs applied to each grouped model before it becomes a [`BedRecord`],
+/// with counts for the run summary.
+#[derive(Default)]
+struct Fixes {
+    out_of_bounds: OutOfBounds,
+    merge_blocks: bool,
+    /// Several child types are unioned, so their blocks always merge.
+    union: bool,
+    keep_childless: bool,
+    cds_exons: bool,
+    degenerate: Degenerate,
+    duplicate_ids: DuplicateIds,
+    mixed_loci: MixedLoci,
+    mixed_strand: MixedStrand,
+    chrom_sizes: Option<ChromSizes>,
+    chrom_bounds: ChromBounds,
+    outside: usize,
+    merged: usize,
+    from_cds: usize,
+    empty: usize,
+    duplicated: usize,
+    mixed: usize,
+    stranded: usize,
+    off_chrom: usize,
+}
+
+impl Fixes {
+    fn new(config: &Config) -> Result<Self> {
+        Ok(Self {
+            out_of_bounds: config.out_of_bounds,
+            merge_blocks: config.merge_blocks,
+            union: config.child_types().count() > 1,
+            keep_childless: config.keep_childless,
+            cds_exons: config.cds_exons,
+            degenerate: config.degenerate,
+            duplicate_ids: config.duplicate_ids,
+            mixed_loci: config.mixed_loci,
+            mixed_strand: config.mixed_strand,
+            chrom_sizes: config
+                .chrom_sizes
+                .as_ref()
+                .map(ChromSizes::from_file)
+                .transpose()?,
+            chrom_bounds: config.chrom_bounds,
+            outside: 0,
+            merged: 0,
+            from_cds: 0,
+            empty: 0,
+            duplicated: 0,
+            mixed: 0,
+            stranded: 0,
+            off_chrom: 0,
+        })
+    }
+
+    /// Applies the duplicate-ID, mixed-loci and mixed-strand policies, builds the blocks
+    /// of childless models from their CDS or parent span and merges blocks
+    /// if asked to, then applies the degenerate,
+    /// out-of-bounds and chromosome-size policies; `None` drops the model.
+    fn apply(&mut self, name: &str, mut info: GenePred) -> Result<Option<GenePred>> {
+        if info.duplicates > 0 {
+            self.duplicated += 1;
+            match self.duplicate_ids {
+                DuplicateIds::Warn if self.duplicated == 1 => log::warn!(
+                    "{} is the ID of {} parent lines; their features were merged into one record",
+                    name,
+                    info.duplicates + 1
+                ),
+                DuplicateIds::Warn => (),
+                DuplicateIds::Drop => return Ok(None),
+                DuplicateIds::Error => {
+                    return Err(Gxf2BedError::InvalidRecord(format!(
+                        "{} is the ID of {} parent lines (see --duplicate-ids)",
+                        name,
+                        info.duplicates + 1
+                    )))
+                }
+            }
+        }
+        if info.loci > 1 {
+            self.mixed += 1;
+            match self.mixed_loci {
+                MixedLoci::Split if self.mixed == 1 => log::warn!(
+                    "{} is one of {} records split off an ID found on several chromosomes or strands",
+                    name,
+                    info.loci
+                ),
+                MixedLoci::Split => (),
+                MixedLoci::Drop => return Ok(None),
+                MixedLoci::Error => {
+                    return Err(Gxf2BedError::InvalidRecord(format!(
+                        "the ID of {} has features on {} chromosomes or strands (see --mixed-loci)",
+                        name, info.loci
+                    )))
+                }
+            }
+        }
+        if info.has_mixed_strands() {
+            self.stranded += 1;
+            match self.mixed_strand {
+                MixedStrand::Split => (),
+                MixedStrand::Majority => {
+                    info.take_majority_strand();
+                    if self.stranded == 1 {
+                        log::warn!(
+                            "{} has {} lines on the forward strand and {} on the reverse; written on {}",
+                            name,
+                            info.strands[0],
+                            info.strands[1],
+                            info.strand
+                        );
+                    }
+                }
+                MixedStrand::Drop => return Ok(None),
+                MixedStrand::Error => {
+                    return Err(Gxf2BedError::InvalidRecord(format!(
+                        "{} has {} lines on the forward strand and {} on the reverse (see --mixed-strand)",
+                        name, info.strands[0], info.strands[1]
+                    )))
+                }
+            }
+        }
+        if self.cds_exons && info.exons_from_cds() {
+            self.from_cds += 1;
+        }
+        if self.keep_childless && info.exons.is_empty() && info.record_type == RecordType::Parent {
+            info.exons
+                .insert((info.start, info.end.saturating_sub(info.start)));
+        }
+        if self.union || self.merge_blocks {
+            let merged = info.exons.merge_overlapping();
+            if self.merge_blocks && merged > 0 {
+                self.merged += 1;
+            }
+        }
+        // INFO: models without children are skipped later on, whatever their span
+        if !info.exons.is_empty() && info.is_degenerate() {
+            self.empty += 1;
+            match self.degenerate {
+                Degenerate::KeepPoint => info.widen_points(),
+                Degenerate::Drop => return Ok(None),
+                Degenerate::Error => {
+                    return Err(Gxf2BedError::InvalidRecord(format!(
+                        "zero- or negative-length feature in {} (see --degenerate)",
+                        name
+                    )))
+                }
+            }
+        }
+
+        if info.has_blocks_outside() {
+            self.outside += 1;
+            match self.out_of_bounds {
+                OutOfBounds::Expand => info.expand_to_blocks(),
+                OutOfBounds::Clip => info.clip_to_span(),
+                OutOfBounds::Drop => return Ok(None),
+                OutOfBounds::Error => {
+                    return Err(Gxf2BedError::InvalidRecord(format!(
+                        "blocks outside the parent span in {} (see --out-of-bounds)",
+                        name
+                    )))
+                }
+            }
+        }
+
+        match &self.chrom_sizes {
+            Some(sizes) if !info.exons.is_empty() && !sizes.fits(&info) => {
+                self.off_chrom += 1;
+                Ok(self.fit_chrom(name, info))
+            }
+            _ => Ok(Some(info)),
+        }
+    }
+
+    /// Handles a model that does not fit within its chromosome.
+    fn fit_chrom(&self, name: &str, mut info: GenePred) -> Option<GenePred> {
+        let size = self.chrom_sizes.as_ref().and_then(|s| s.get(&info.chr));
+        if self.off_chrom == 1 {
+            match size {
+                Some(size) => log::warn!(
+                    "{} ends past {} ({} bp); check that --chrom-sizes matches the annotation assembly",
+                    name, info.chr, size
+                ),
+                None => log::warn!(
+                    "{} is on {}, which is not in --chrom-sizes; check that it matches the annotation assembly",
+                    name, info.chr
+                ),
+            }
+        }
+
+        match (self.chrom_bounds, size) {
+            (ChromBounds::Report, _) | (ChromBounds::Clip, None) => Some(info),
+            (ChromBounds::Clip, Some(size)) => {
+                info.end = info.end.min(size);
+                info.start = info.start.min(info.end);
+                info.clip_to_span();
+                (!info.exons.is_empty()).then_some(info)
+            }
+            (ChromBounds::Drop, _) => None,
+        }
+    }
+}
+
+impl Records {
+    fn load(config: &Config, hook: &dyn ProgressHook) -> Self {
+        let mut profile = config.profile.then(Profile::default);
+        let mut records = Self::from_groups(config, |header| {
+            detect_input_kind(&config.input).and_then(|kind| {
+                if let Some(n) = config.head {
+                    let contents = profiled(&mut profile, Phase::Read, || {
+                        read_input_head(config, kind, hook, n)
+                    })?;
+                    return profiled(&mut profile, Phase::Parse, || {
+                        group(config, &contents, kind.format, header).map(in_order)
+                    });
+                }
+                let Some(budget) = config.max_memory else {
+                    let contents =
+                        profiled(&mut profile, Phase::Read, || read_input(config, kind, hook))?;
+                    return profiled(&mut profile, Phase::Parse, || {
+                        group(config, contents.as_str(), kind.format, header).map(in_order)
+                    });
+                };
+
+                profiled(&mut profile, Phase::Parse, || {
+                    let file = File::open(&config.input)?;
+                    hook.start(file.metadata().ok().map(|m| m.len()));
+                    let reader = decoder(ProgressReader::new(file, hook), kind.compression)?;
+                    let reader = BufReader::new(reader);
+                    let groups = group_with_budget(config, reader, kind.format, budget, header);
+                    hook.finish();
+
+                    groups
+                })
+            })
+        });
+        records.profile = profile;
+        records
+    }
+
+    fn from_reader<R: Read>(config: &Config, mut reader: R, format: InputFormat) -> Self {
+        if let Some(n) = config.head {
+            return Self::new(config, |header| {
+                let contents = read_head(config, BufReader::new(reader), format, n)?;
+                group(config, &contents, format, header)
+            });
+        }
+        if let Some(budget) = config.max_memory {
+            return Self::from_groups(config, |header| {
+                group_with_budget(config, BufReader::new(reader), format, budget, header)
+            });
+        }
+
+        Self::new(config, |header| {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes)?;
+            group(
+                config,
+                &into_text(bytes, config.lossy_utf8)?,
+                format,
+                header,
+            )
+        })
+    }
+
+    fn new(
+        config: &Config,
+        groups: impl FnOnce(&mut Vec<String>) -> Result<Vec<(String, GenePred)>>,
+    ) -> Self {
+        Self::from_groups(config, |header| groups(header).map(in_order))
+    }
+
+    /// Sets up the per-record fixes, then runs `groups`, which also collects
+    /// the input's header comments; the fixes come first so a bad
+    /// `--chrom-sizes` fails before grouping a large input.
+    fn from_groups(
+        config: &Config,
+        groups: impl FnOnce(&mut Vec<String>) -> Result<Groups>,
+    ) -> Self {
+        let mut header = Vec::new();
+        let setup = || {
+            let fixes = Fixes::new(config)?;
+            let containment = config
+                .containment_report
+                .as_ref()
+                .map(Containment::create)
+                .transpose()?;
+            let genome = match config.check_orf {
+                true => config.fasta.as_ref().map(Genome::from_file).transpose()?,
+                false => None,
+            };
+            Ok((fixes, containment, genome, groups(&mut header)?))
+        };
+        match setup() {
+            Ok((fixes, containment, genome, groups)) => Self {
+                error: None,
+                groups,
+                skipped: 0,
+                derived: 0,
+                parents: 0,
+                features: Some((config.parent.clone(), config.child.clone())),
+                fixes,
+                labels: Labels::new(config),
+                sample: Sample::new(config),
+                remaining: config.head,
+                compat: config.compat,
+                exon_ranks: config.exon_ranks,
+                check_frame: config.check_frame,
+                out_of_frame: 0,
+                check_codons: config.check_codons,
+                missing_codons: 0,
+                misplaced_codons: 0,
+                genome,
+                bad_orfs: 0,
+                containment,
+                header,
+                profile: config.profile.then(Profile::default),
+                compute: Compute::new(&config.compute),
+                escape: config.escape,
+                escaped: 0,
+                check_output: config.check_output,
+                ignore_strand: config.ignore_strand,
+            },
+            Err(err) => Self {
+                error: Some(err),
+                groups: Box::new(std::iter::empty()),
+                skipped: 0,
+                derived: 0,
+                parents: 0,
+                features: None,
+                fixes: Fixes::default(),
+                labels: Labels::default(),
+                sample: None,
+                remaining: None,
+                compat: None,
+                exon_ranks: false,
+                check_frame: false,
+                out_of_frame: 0,
+                check_codons: false,
+                missing_codons: 0,
+                misplaced_codons: 0,
+                genome: None,
+                bad_orfs: 0,
+                containment: None,
+                header: Vec::new(),
+                profile: None,
+                compute: Compute::default(),
+                escape: Escape::default(),
+                escaped: 0,
+                check_output: false,
+                ignore_strand: false,
+            },
+        }
+    }
+
+    /// Leading `#` lines of the input, collected with
+    /// `Config::keep_comments`.
+    pub fn header(&self) -> &[String] {
+        &self.header
+    }
+
+    /// Number of groups skipped so far because they had no child features.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Number of groups so far without a parent line, spanning their
+    /// child features instead.
+    pub fn derived_spans(&self) -> usize {
+        self.derived
+    }
+
+    /// Number of records so far with blocks outside their parent's span.
+    pub fn out_of_bounds(&self) -> usize {
+        self.fixes.outside
+    }
+
+    /// Number of records so far whose blocks were merged.
+    pub fn merged(&self) -> usize {
+        self.fixes.merged
+    }
+
+    /// Number of records so far whose blocks were built from their CDS.
+    pub fn cds_exons(&self) -> usize {
+        self.fixes.from_cds
+    }
+
+    /// Number of records so far with whitespace in their name or
+    /// attribute-derived columns.
+    pub fn escaped(&self) -> usize {
+        self.escaped
+    }
+
+    /// Number of records so far with a zero- or negative-length feature.
+    pub fn degenerate(&self) -> usize {
+        self.fixes.empty
+    }
+
+    /// Number of records so far whose ID is used by several parent lines.
+    pub fn duplicate_ids(&self) -> usize {
+        self.fixes.duplicated
+    }
+
+    /// Number of records so far whose ID is shared with other loci.
+    pub fn mixed_loci(&self) -> usize {
+        self.fixes.mixed
+    }
+
+    /// Number of records so far whose features disagree on the strand.
+    pub fn mixed_strand(&self) -> usize {
+        self.fixes.stranded
+    }
+
+    /// Number of records so far that do not fit within their chromosome.
+    pub fn off_chrom(&self) -> usize {
+        self.fixes.off_chrom
+    }
+
+    /// Number of records so far listed in the containment report.
+    pub fn uncontained(&self) -> usize {
+        self.containment.as_ref().map_or(0, |report| report.found)
+    }
+
+    /// Number of records so far whose CDS is not a whole number of codons.
+    pub fn out_of_frame(&self) -> usize {
+        self.out_of_frame
+    }
+
+    /// Number of coding records so far without a start or stop codon.
+    pub fn missing_codons(&self) -> usize {
+        self.missing_codons
+    }
+
+    /// Number of coding records so far whose codons are off the CDS ends.
+    pub fn misplaced_codons(&self) -> usize {
+        self.misplaced_codons
+    }
+
+    /// Reports models spanning their children for lack of a parent line;
+    /// a warning when the parent feature never appears (e.g. exon-only
+    /// GTFs from quantification tools).
+    fn report_derived(&self, (parent, child): (String, String)) {
+        match self.parents {
+            0 => log::warn!(
+                "No {:?} features found; record spans were derived from their {:?} features",
+                parent,
+                child
+            ),
+            _ => log::info!(
+                "{} records have no {:?} line; their spans were derived from their {:?} features",
+                self.derived,
+                parent,
+                child
+            ),
+        }
+    }
+
+    /// Counts `record` if its `coding` features lack a start or stop codon,
+    /// or have one away from the CDS ends; only non-coding records pass
+    /// unchecked.
+    fn check_codons(&mut self, record: &BedRecord, coding: Option<&Coding>) {
+        let Some(coding) = coding.filter(|coding| !coding.cds.is_empty()) else {
+            return;
+        };
+
+        if coding.start_codon.is_none() || coding.stop_codon.is_none() {
+            self.missing_codons += 1;
+            log::debug!("{} lacks a start or stop codon", record.name);
+        } else if !coding.codons_at_cds_ends(record.strand) {
+            self.misplaced_codons += 1;
+            log::debug!(
+                "{} has a start or stop codon away from its CDS ends",
+                record.name
+            );
+        }
+    }
+
+    /// Number of coding records so far whose CDS sequence is not a clean
+    /// open reading frame.
+    pub fn bad_orfs(&self) -> usize {
+        self.bad_orfs
+    }
+
+    /// Counts `record` if its CDS read from the genome is not a clean ORF;
+    /// warns for the first one only, with the reason.
+    fn check_orf(&mut self, record: &BedRecord, coding: Option<&Coding>) {
+        let (Some(genome), Some(coding)) = (&self.genome, coding) else {
+            return;
+        };
+        if coding.cds.is_empty() {
+            return;
+        }
+        let Err(err) = check_orf(genome, &record.chrom, record.strand, coding) else {
+            return;
+        };
+
+        self.bad_orfs += 1;
+        match self.bad_orfs {
+            1 => log::warn!("{}: {} (see --check-orf)", record.name, err),
+            _ => log::debug!("{}: {}", record.name, err),
+        }
+    }
+
+    /// Counts `record` if its `coding` features are out of frame; warns
+    /// for the first one only, predictions can have thousands.
+    fn check_frame(&mut self, record: &BedRecord, coding: Option<&Coding>) {
+        if coding.is_none_or(|coding| coding.cds.is_empty() || coding.in_frame(record.strand)) {
+            return;
+        }
+
+        self.out_of_frame += 1;
+        match self.out_of_frame {
+            1 => log::warn!(
+                "{} has a CDS length that is not a multiple of 3 (broken or partial model)",
+                record.name
+            ),
+            _ => log::debug!(
+                "{} has a CDS length that is not a multiple of 3",
+                record.name
+            ),
+        }
+    }
+}
+
+impl Iterator for Records {
+    type Item = Result<BedRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.error.take() {
+            return Some(Err(err));
+        }
+
+        for group in self.groups.by_ref() {
+            let (name, info) = match group {
+                Ok(group) => group,
+                Err(err) => return Some(Err(err)),
+            };
+            if self.remaining == Some(0) {
+                break;
+            }
+            if self.sample.is_some_and(|sample| !sample.keeps(&name)) {
+                continue;
+            }
+            if let Some(report) = &mut self.containment {
+                if let Err(err) = report.check(&name, &info) {
+                    return Some(Err(err));
+                }
+            }
+            match info.record_type {
+                RecordType::Parent => self.parents += 1,
+                RecordType::Child => self.derived += 1,
+                RecordType::Unknown => (),
+            }
+            let mut info = match self.fixes.apply(&name, info) {
+                Ok(Some(info)) => info,
+                Ok(None) => continue,
+                Err(err) => return Some(Err(err)),
+            };
+            // INFO: before building, so ranks and phases are counted from the left as well
+            if self.ignore_strand {
+                info.strand = Strand::Unknown;
+            }
+            let attrs = std::mem::take(&mut info.attrs);
+            let coding = info.coding.take();
+            let numbers = std::mem::take(&mut info.exon_numbers);
+            // INFO: compat modes name records by the grouping key, like the tool they mimic
+            let name = match self.compat {
+                Some(_) => name,
+                None => self.labels.name(name, &attrs),
+            };
+            match BedRecord::from_gene_pred(name, info) {
+                Ok(Some(mut record)) => {
+                    match self.compat {
+                        Some(compat) => compat.apply(&mut record, coding.as_deref()),
+                        None => record.score = self.labels.score(&attrs),
+                    }
+                    if self.labels.columns > 0 {
+                        record.extras.splice(0..0, self.labels.columns(&attrs));
+                    }
+                    if self.exon_ranks {
+                        push_exon_ranks(&mut record, numbers);
+                    }
+                    self.compute
+                        .apply(&mut record, self.labels.computed(&attrs));
+                    // INFO: before the JSON column, which escapes its own values
+                    if self.escape.apply(&mut record) {
+                        self.escaped += 1;
+                    }
+                    if self.labels.json {
+                        record.extras.push(self.labels.json(&attrs));
+                    }
+                    if self.check_frame {
+                        self.check_frame(&record, coding.as_deref());
+                    }
+                    if self.check_codons {
+                        self.check_codons(&record, coding.as_deref());
+                    }
+                    self.check_orf(&record, coding.as_deref());
+                    if self.check_output {
+                        if let Err(err) = check_output(&record) {
+                            return Some(Err(err));
+                        }
+                    }
+                    if let Some(remaining) = &mut self.remaining {
+                        *remaining -= 1;
+                    }
+                    return Some(Ok(record));
+                }
+                Ok(None) => self.skipped += 1,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+
+        if let Some(report) = &mut self.containment {
+            if let Err(err) = report.finish() {
+                return Some(Err(err));
+            }
+        }
+        if let Some(features) = self.features.take().filter(|_| self.derived > 0) {
+            self.report_derived(features);
+        }
+        None
+    }
+}
+
+/// Fails when `record` breaks the BED12 invariants, showing it as BED12.
+fn check_output(record: &BedRecord) -> Result<()> {
+    record.check().map_err(|reason| {
+        let mut line = Vec::new();
+        BedType::Bed12.emit(record, &mut line);
+        Gxf2BedError::InvalidRecord(format!(
+            "{} fails --check-output: {}\n{}",
+            record.name,
+            reason,
+            String::from_utf8_lossy(&line).trim_end()
+        ))
+    })
+}
+
+/// Appends the exon numbers of `record`'s blocks and its exon count, which
+/// is higher than the block count when the blocks are e.g. CDS parts of
+/// some exons only.
+fn push_exon_ranks(record: &mut BedRecord, numbers: Vec<(u64, u32)>) {
+    let ranks = record.exon_ranks(numbers);
+    let count = ranks
+        .iter()
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .max(ranks.len() as u32);
+
+    record
+        .extras
+        .push(ranks.iter().map(|rank| format!("{},", rank)).collect());
+    record.extras.push(count.to_string());
+}
+
+/// Converts `config.input` and returns the records instead of writing them.
+///
+/// Input errors are yielded as the first item.
+pub fn records(config: &Config) -> Records {
+    Records::load(config, &NoProgress)
+}
+
+/// Same as [`records`], reading uncompressed `format` data from `reader`
+/// instead of `config.input`.
+pub fn records_from_reader<R: Read>(config: &Config, reader: R, format: InputFormat) -> Records {
+    Records::from_reader(config, reader, format)
+}
+
+/// Same as [`records`], grouping features from a custom `source` instead of
+/// parsing `config.input`.
+pub fn records_from_source<S: AnnotationSource>(config: &Config, source: S) -> Records {
+    Records::new(config, |_| {
+        group_source(config, source).map(|data| sort_groups(data, config.unordered))
+    })
+}
+
+/// Same as [`records`], parsing already-decoded `contents`.
+pub(crate) fn records_from_str(config: &Config, contents: &str, format: InputFormat) -> Records {
+    Records::new(config, |header| group(config, contents, format, header))
+}
+
+/// Converts features from a custom `source` into `config.output`.
+pub fn run_from_source<S: AnnotationSource>(config: &Config, source: S) -> Result<RunStats> {
+    let records = records_from_source(config, source);
+    write_to_path(config, records, &*emitter(config))
+}
+
+/// Converts `config.input` into `config.output`.
+///
+/// Safe to call from several threads at once: a run keeps no global state
+/// and its only temporary files (`max_memory` spills) are private to it.
+/// Each holds its own input in memory, so bound concurrent runs with
+/// [`crate::Runner::max_running`] or `max_memory`.
+pub fn run(config: &Config) -> Result<RunStats> {
+    run_with_progress(config, &NoProgress)
+}
+
+/// Same as [`run`], reporting input consumption to `hook`.
+pub fn run_with_progress(config: &Config, hook: &dyn ProgressHook) -> Result<RunStats> {
+    timed(config, || {
+        if !config.child_outputs.is_empty() {
+            return run_children(config, hook);
+        }
+
+        let records = Records::load(config, hook);
+        if config.writes_output() {
+            log::info!("Writing to {:?}", config.output_path());
+        }
+        for out in &config.outputs {
+            log::info!("Writing {:?} to {:?}", out.format, out.path);
+        }
+
+        write_to_path(config, records, &*emitter(config))
+    })
+}
+
+/// Parses `config.input` once for the main output (if any) and every
+/// `config.child_outputs` file, each grouped with its own child types;
+/// returns their stats summed.
+pub(crate) fn run_children(config: &Config, hook: &dyn ProgressHook) -> Result<RunStats> {
+    let main = (config.writes_output() || !config.outputs.is_empty()).then(|| {
+        let mut main = config.clone();
+        main.child_outputs.clear();
+        main
+    });
+    let configs = main
+        .into_iter()
+        .chain(config.child_outputs.iter().map(|out| {
+            let mut child = config.clone();
+            child.child.clone_from(&out.child);
+            child.output.clone_from(&out.path);
+            child.outputs.clear();
+            child.child_outputs.clear();
+            child
+        }))
+        .collect::<Vec<_>>();
+
+    let kind = detect_input_kind(&config.input)?;
+    let mut profile = config.profile.then(Profile::default);
+    let contents = profiled(&mut profile, Phase::Read, || match config.head {
+        Some(n) => read_input_head(config, kind, hook, n).map(Input::Text),
+        None => read_input(config, kind, hook),
+    })?;
+    let header = match config.keep_comments {
+        true => header_comments(contents.as_str(), &config.comment_chars),
+        false => Vec::new(),
+    };
+    let groupings = configs.iter().map(Grouping::from).collect::<Vec<_>>();
+    let groups = profiled(&mut profile, Phase::Parse, || {
+        in_pool(config, || {
+            to_bed_many(contents.as_str(), config.separator(kind.format), &groupings)
+        })
+    })?;
+    drop(contents);
+
+    let mut total = RunStats {
+        profile,
+        ..Default::default()
+    };
+    for (config, data) in configs.iter().zip(groups) {
+        let records = Records::from_groups(config, |h| {
+            h.clone_from(&header);
+            profiled(&mut total.profile, Phase::Parse, || {
+                in_pool(config, || Ok(finish_groups(config, data)))
+            })
+            .map(in_order)
+        });
+        if config.writes_output() {
+            log::info!(
+                "Writing {:?} blocks to {:?}",
+                config.child,
+                config.output_path()
+            );
+        }
+        for out in &config.outputs {
+            log::info!("Writing {:?} to {:?}", out.format, out.path);
+        }
+        let stats = write_to_path(config, records, &*emitter(config))?;
+        log::info!(
+            written = stats.written;
+            "{} records written to {:?}", stats.written, config.output_path()
+        );
+        if let (Some(algorithm), Some(checksum)) = (config.checksum, &stats.checksum) {
+            log::info!(
+                "{} {:?}: {}",
+                algorithm.name(),
+                config.output_path(),
+                checksum
+            );
+        }
+        total.add(stats);
+    }
+
+    Ok(total)
+}
+
+/// Same as [`run`], rendering records with a custom `emitter` instead of
+/// `config.bed_type`.
+pub fn run_with_emitter(config: &Config, emitter: &dyn BedEmitter) -> Result<RunStats> {
+    timed(config, || {
+        let records = Records::load(config, &NoProgress);
+        write_to_path(config, records, emitter)
+    })
+}
+
+/// Converts uncompressed `format` data read from `reader` into `config.output`,
+/// ignoring `config.input`.
+pub fn run_from_reader<R: Read>(
+    config: &Config,
+    reader: R,
+    format: InputFormat,
+) -> Result<RunStats> {
+    let records = Records::from_reader(config, reader, format);
+    write_to_path(config, records, &*emitter(config))
+}
+
+/// Converts `config.input` and streams the BED output into `writer`,
+/// ignoring `config.output`.
+pub fn run_to_writer<W: Write>(config: &Config, writer: W) -> Result<RunStats> {
+    timed(config, || {
+        let mut writer = BufWriter::new(writer);
+        write_output(records(config), &mut writer, &*emitter(config))
+    })
+}
+
+/// Runs `op`, a conversion of `config.input`, adding its wall time and the
+/// input size to its stats.
+fn timed(config: &Config, op: impl FnOnce() -> Result<RunStats>) -> Result<RunStats> {
+    let started = Instant::now();
+    let mut stats = op()?;
+    stats.elapsed = started.elapsed();
+    stats.input_bytes = std::fs::metadata(&config.input).map_or(0, |m| m.len());
+    Ok(stats)
+}
+
+/// Converts an in-memory GTF/GFF string and returns the BED output as a string.
+///
+/// Records are built and rendered as by [`run`]: grouping, the fix and
+/// policy settings, `head`, `bed_type`, `columns`, `compute`, `compat` and
+/// `emit` all apply, and `chrom_sizes`, `fasta` and `containment_report`
+/// are still read or written. Settings about the input and output files are
+/// ignored (`input`, `output`, `outputs`, `child_outputs`, `tee`,
+/// `checksum`, `compress_level`, `io_strategy`, `max_memory`); meant for
+/// small inputs, tests and notebooks.
+pub fn convert_str(input: &str, format: InputFormat, config: &Config) -> Result<String> {
+    let mut out = Vec::new();
+    write_output(
+        records_from_str(config, input, format),
+        &mut out,
+        &*emitter(config),
+    )?;
+
+    Ok(String::from_utf8(out).expect("ERROR: BED output is not valid UTF-8"))
+}
+
+/// Emitter for `config.bed_type` in the coordinate convention of `config`,
+/// or for `config.compat`, `config.columns` or `config.emit`.
+pub(crate) fn emitter(config: &Config) -> Box<dyn BedEmitter> {
+    match config.emit {
+        Emit::SpliceSites => {
+            return Box::new(SpliceSites {
+                window: config.window,
+            })
+        }
+        Emit::Exons => return Box::new(Exons),
+        Emit::Records => {}
+    }
+    if let Some(compat) = config.compat {
+        return Box::new(compat);
+    }
+    if !config.columns.is_empty() {
+        return Box::new(Columns::new(&config.columns, config.one_based));
+    }
+    match config.one_based {
+        true => Box::new(OneBased(config.bed_type)),
+        false => Box::new(config.bed_type),
+    }
+}
+
+pub(crate) fn write_to_path(
+    config: &Config,
+    mut records: Records,
+    emitter: &dyn BedEmitter,
+) -> Result<RunStats> {
+    // INFO: fail before creating the output, so an older one is not clobbered
+    if let Some(err) = records.error.take() {
+        return Err(err);
+    }
+    let stats = write_files(config, records, emitter);
+    if stats.is_err() {
+        discard_outputs(config);
+    }
+    stats
+}
+
+/// Writes `records` into `config.output` and every `config.outputs` file.
+fn write_files(config: &Config, records: Records, emitter: &dyn BedEmitter) -> Result<RunStats> {
+    let (level, threads) = (config.compress_level, config.effective_threads());
+    let extras = config
+        .outputs
+        .iter()
+        .map(|out| {
+            let writer = create_output(&out.path, level, threads)?;
+            Ok((writer, out.format.emitter(config.one_based)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if !config.writes_output() {
+        return write_and_finish(
+            records,
+            None::<OutputWriter<Hashing<File>>>,
+            emitter,
+            extras,
+            config.tee,
+        );
+    }
+    let path = config.output_path();
+
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    if let Some(file) = crate::uring::UringWriter::create(&path)? {
+        let writer = wrap_output(&path, Hashing::new(file, config.checksum), level, threads)?;
+        return write_and_finish(records, Some(writer), emitter, extras, config.tee);
+    }
+
+    let file = Hashing::new(File::create(&path)?, config.checksum);
+    let writer = wrap_output(&path, file, level, threads)?;
+    write_and_finish(records, Some(writer), emitter, extras, config.tee)
+}
+
+/// Removes the output files of a failed run, so a truncated one is not
+/// taken for a result (e.g. by --skip-if-newer or a workflow engine);
+/// anything but a regular file (e.g. `/dev/stdout`) is left alone.
+fn discard_outputs(config: &Config) {
+    let main = config.writes_output().then(|| config.output_path());
+    let extras = config.outputs.iter().map(|out| out.path.clone());
+    for path in main.into_iter().chain(extras) {
+        if !std::fs::metadata(&path).is_ok_and(|meta| meta.is_file()) {
+            continue;
+        }
+        if let Err(err) = std::fs::remove_file(&path) {
+            log::warn!("could not remove the partial output {:?}: {}", path, err);
+        }
+    }
+}
+
+/// Extra outputs (`Config::outputs`) with their emitters.
+type Extras = Vec<(OutputWriter<File>, Box<dyn BedEmitter>)>;
+
+fn write_and_finish<W: Write>(
+    records: Records,
+    mut writer: Option<OutputWriter<Hashing<W>>>,
+    emitter: &dyn BedEmitter,
+    mut extras: Extras,
+    tee: bool,
+) -> Result<RunStats> {
+    let mut stdout = tee.then(|| Tee::new(BufWriter::new(std::io::stdout().lock())));
+    let mut sinks = Vec::with_capacity(extras.len() + 2);
+    if let Some(writer) = &mut writer {
+        sinks.push((writer as &mut dyn Write, emitter));
+    }
+    if let Some(stdout) = &mut stdout {
+        sinks.push((stdout as &mut dyn Write, emitter));
+    }
+    for (writer, emitter) in &mut extras {
+        sinks.push((writer as &mut dyn Write, &**emitter));
+    }
+    let mut stats = write_outputs(records, sinks)?;
+
+    // INFO: compressed outputs write their last frames when finished
+    let mut profile = stats.profile.take();
+    if let Some(writer) = writer {
+        let (mut file, checksum) = profiled(&mut profile, Phase::Write, || {
+            finish_output(writer).map(Hashing::finish)
+        })?;
+        profiled(&mut profile, Phase::Write, || file.flush())?;
+        stats.checksum = checksum;
+    }
+    for (writer, _) in extras {
+        profiled(&mut profile, Phase::Write, || {
+            finish_output(writer)?.flush()
+        })?;
+    }
+    stats.profile = profile;
+
+    Ok(stats)
+}
+
+/// Renders `records` into `writer`. Plain bytes are written as-is: callers
+/// writing to a path go through [`write_to_path`], which wraps the file in
+/// the encoder implied by its extension.
+pub(crate) fn write_output<W: Write>(
+    records: Records,
+    writer: &mut W,
+    emitter: &dyn BedEmitter,
+) -> Result<RunStats> {
+    write_outputs(records, vec![(writer, emitter)])
+}
+
+/// Renders `records` once per `(writer, emitter)` sink.
+///
+/// Records are rendered straight into a [`WRITE_BATCH`]-sized buffer per
+/// sink that is handed over in one `write_all`, which a `BufWriter` passes
+/// through without copying it again.
+fn write_outputs(
+    mut records: Records,
+    mut sinks: Vec<(&mut dyn Write, &dyn BedEmitter)>,
+) -> Result<RunStats> {
+    let mut batches = (0..sinks.len())
+        .map(|_| Vec::with_capacity(WRITE_BATCH))
+        .collect::<Vec<_>>();
+    for ((_, emitter), batch) in sinks.iter().zip(&mut batches) {
+        for line in records.header() {
+            batch.extend_from_slice(line.as_bytes());
+            batch.push(b'\n');
+        }
+        emitter.header(batch);
+    }
+
+    let mut stats = RunStats::default();
+    let mut profile = records.profile.take();
+    // INFO: the clock is only read when profiling; convert_str runs without one on wasm
+    let started = profile.is_some().then(Instant::now);
+    for record in records.by_ref() {
+        let record = record?;
+        for ((writer, emitter), batch) in sinks.iter_mut().zip(&mut batches) {
+            emitter.emit(&record, batch);
+            if batch.len() >= WRITE_BATCH {
+                profiled(&mut profile, Phase::Write, || writer.write_all(batch))?;
+                stats.output_bytes += batch.len() as u64;
+                batch.clear();
+            }
+        }
+        stats.written += 1;
+    }
+
+    for ((writer, emitter), batch) in sinks.iter_mut().zip(&mut batches) {
+        emitter.footer(batch);
+        profiled(&mut profile, Phase::Write, || {
+            writer.write_all(batch)?;
+            writer.flush()
+        })?;
+        stats.output_bytes += batch.len() as u64;
+    }
+    if let (Some(profile), Some(started)) = (&mut profile, started) {
+        // INFO: whatever was not spent writing went into building and formatting records
+        let render = started.elapsed().saturating_sub(profile.time(Phase::Write));
+        profile.add(Phase::Render, render);
+    }
+    stats.profile = profile;
+
+    stats.skipped = records.skipped();
+    stats.derived_spans = records.derived_spans();
+    stats.out_of_bounds = records.out_of_bounds();
+    stats.merged = records.merged();
+    stats.cds_exons = records.cds_exons();
+    stats.escaped = records.escaped();
+    stats.degenerate = records.degenerate();
+    stats.off_chrom = records.off_chrom();
+    stats.duplicate_ids = records.duplicate_ids();
+    stats.mixed_loci = records.mixed_loci();
+    stats.mixed_strand = records.mixed_strand();
+    stats.out_of_frame = records.out_of_frame();
+    stats.missing_codons = records.missing_codons();
+    stats.misplaced_codons = records.misplaced_codons();
+    stats.bad_orfs = records.bad_orfs();
+    stats.uncontained = records.uncontained();
+    Ok(stats)
+}
+
+/// Groups `contents`; with `Config::keep_comments` its leading `#` lines
+/// go to `header`.
+fn group(
+    config: &Config,
+    contents: &str,
+    format: InputFormat,
+    header: &mut Vec<String>,
+) -> Result<Vec<(String, GenePred)>> {
+    if config.keep_comments {
+        *header = header_comments(contents, &config.comment_chars);
+    }
+
+    in_pool(config, || {
+        let data = to_bed(contents, config.separator(format), &Grouping::from(config))?;
+        Ok(finish_groups(config, data))
+    })
+}
+
+/// Caps isoforms per gene and sorts freshly parsed groups.
+fn finish_groups(config: &Config, mut data: HashMap<String, GenePred>) -> Vec<(String, GenePred)> {
+    log::info!(parsed = data.len(); "{} records parsed", data.len());
+    if let Some(limit) = config.max_transcripts_per_gene {
+        let offset = config.name_attrs.len() + config.score_attrs.len();
+        let dropped = isoforms::cap(&mut data, limit, config.rank_isoforms, offset);
+        log::info!(capped = dropped; "{} isoforms over --max-transcripts-per-gene dropped", dropped);
+    }
+
+    sort_groups(data, config.unordered)
+}
+
+/// Runs `op` on a pool with `config.threads` workers (0 = all logical CPUs),
+/// reusing the current pool when it already has that many (see [`crate::Runner`]).
+#[cfg(feature = "parallel")]
+fn in_pool<T: Send>(config: &Config, op: impl FnOnce() -> Result<T> + Send) -> Result<T> {
+    if in_sized_pool(config.effective_threads()) {
+        return op();
+    }
+
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(config.effective_threads())
+        .build()
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .install(op)
+}
+
+/// Whether the caller runs on a rayon pool with exactly `threads` workers.
+#[cfg(feature = "parallel")]
+pub(crate) fn in_sized_pool(threads: usize) -> bool {
+    rayon::current_thread_index().is_some() && rayon::current_num_threads() == threads
+}
+
+#[cfg(not(feature = "parallel"))]
+fn in_pool<T>(_config: &Config, op: impl FnOnce() -> Result<T>) -> Result<T> {
+    op()
+}
+
+/// Output order of grouped models: chromosome, start, end, then name.
+pub(crate) fn by_position(
+    (a_name, a): &(String, GenePred),
+    (b_name, b): &(String, GenePred),
+) -> std::cmp::Ordering {
+    (&a.chr, a.start, a.end, a_name).cmp(&(&b.chr, b.start, b.end, b_name))
+}
+
+fn in_order(groups: Vec<(String, GenePred)>) -> Groups {
+    Box::new(groups.into_iter().map(Ok))
+}
+
+/// Puts grouped models in output order, or leaves them in hash order when
+/// `unordered` is set.
+fn sort_groups(mut data: HashMap<String, GenePred>, unordered: bool) -> Vec<(String, GenePred)> {
+    par_y::resolve(&mut data);
+    let mut groups = data.into_iter().collect::<Vec<_>>();
+    if unordered {
+        return groups;
+    }
+
+    #[cfg(feature = "parallel")]
+    groups.par_sort_unstable_by(by_position);
+    #[cfg(not(feature = "parallel"))]
+    groups.sort_unstable_by(by_position);
+
+    groups
+}
+
+/// Reads `reader` to the end, decompressing it if needed.
+#[cfg(test)]
+pub(crate) fn decode<R: Read>(reader: R, compression: Compression) -> Result<String> {
+    decode_input(reader, compression, false, false)
+}
+
+/// Same as [`decode`]; a truncated compressed input either fails with
+/// [`Gxf2BedError::Truncated`] or, with `allow_truncated`, is cut back to
+/// its last complete line with a warning. Invalid UTF-8 is handled by
+/// [`into_text`] according to `lossy_utf8`.
+pub(crate) fn decode_input<R: Read>(
+    reader: R,
+    compression: Compression,
+    allow_truncated: bool,
+    lossy_utf8: bool,
+) -> Result<String> {
+    let mut bytes = Vec::new();
+    if let Err(err) = decoder(reader, compression)?.read_to_end(&mut bytes) {
+        if !is_truncated(&err) {
+            return Err(err.into());
+        }
+
+        let complete = bytes
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |idx| idx + 1);
+        bytes.truncate(complete);
+        let records = bytes[..complete]
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.starts_with(b"#") && !line.trim_ascii().is_empty())
+            .count();
+        truncated(err, records, allow_truncated)?;
+    }
+
+    into_text(bytes, lossy_utf8)
+}
+
+/// Fails on a truncated input, or warns and lets the caller keep the
+/// `records` read so far when `allow` is set.
+pub(crate) fn truncated(err: std::io::Error, records: usize, allow: bool) -> Result<()> {
+    let message = err.to_string();
+    if !allow {
+        return Err(Gxf2BedError::Truncated { records, message });
+    }
+
+    log::warn!(
+        records = records;
+        "TRUNCATED INPUT: {}; converting only the {} records read before the cut, the output is incomplete",
+        message,
+        records
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigBuilder;
+    use crate::isoforms::IsoformRank;
+    use crate::source::GxfReader;
+
+    const GTF: &str = "chr2\tHAVANA\ttranscript\t101\t400\t.\t-\t.\ttranscript_id \"B\";
+chr2\tHAVANA\texon\t101\t150\t.\t-\t.\ttranscript_id \"B\";
+chr2\tHAVANA\texon\t301\t400\t.\t-\t.\ttranscript_id \"B\";
+chr1\tHAVANA\ttranscript\t11\t60\t.\t+\t.\ttranscript_id \"A\";
+chr1\tHAVANA\texon\t11\t60\t.\t+\t.\ttranscript_id \"A\";
+chr1\tHAVANA\ttranscript\t1\t9\t.\t+\t.\ttranscript_id \"C\";
+";
+
+    /// [`GTF`] as BED12; C has no exons, so it is skipped.
+    const BED: [&str; 2] = [
+        "chr1\t10\t60\tA\t0\t+\t10\t60\t0\t1\t50,\t0,",
+        "chr2\t100\t400\tB\t0\t-\t100\t400\t0\t2\t50,100,\t0,200,",
+    ];
+
+    /// BED lines of `gtf` converted with `config` (through spills when it
+    /// sets `max_memory`), and the drained records for their counters; the
+    /// first failing record ends the lines with its error.
+    fn records(config: ConfigBuilder, gtf: &str) -> (Result<Vec<String>>, Records) {
+        let config = config.build().unwrap();
+        let emitter = emitter(&config);
+        let mut records = Records::from_reader(&config, gtf.as_bytes(), InputFormat::Gtf);
+        let lines = records
+            .by_ref()
+            .map(|record| {
+                let mut line = Vec::new();
+                emitter.emit(&record?, &mut line);
+                Ok(String::from_utf8(line).unwrap().trim_end().to_string())
+            })
+            .collect();
+        (lines, records)
+    }
+
+    /// Writes [`GTF`] into `dir`, which is removed when the test ends.
+    fn write_input(dir: &tempfile::TempDir) -> std::path::PathBuf {
+        let path = dir.path().join("input.gtf");
+        std::fs::write(&path, GTF).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_records_sorted() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = write_input(&dir);
+        let config = Config::new(&input, "unused.bed");
+
+        let mut records = super::records(&config);
+        let names = records
+            .by_ref()
+            .map(|r| r.unwrap().name)
+            .collect::<Vec<String>>();
+
+        assert_eq!(names, vec!["A", "B"]);
+        assert_eq!(records.skipped(), 1);
+    }
+
+    #[test]
+    fn test_out_of_bounds_policies() {
+        let gtf = GTF.replace("exon\t301\t400", "exon\t301\t420");
+        let convert = |policy| records(Config::builder().out_of_bounds(policy), &gtf);
+
+        let (lines, counts) = convert(OutOfBounds::Expand);
+        assert_eq!(
+            lines.unwrap()[1],
+            "chr2\t100\t420\tB\t0\t-\t100\t420\t0\t2\t50,120,\t0,200,"
+        );
+        assert_eq!(counts.out_of_bounds(), 1);
+        assert_eq!(convert(OutOfBounds::Clip).0.unwrap(), BED);
+        assert_eq!(convert(OutOfBounds::Drop).0.unwrap(), BED[..1]);
+        assert!(convert(OutOfBounds::Error).0.is_err());
+    }
+
+    #[test]
+    fn test_merge_blocks() {
+        let gtf = format!("{GTF}chr2\tHAVANA\texon\t131\t200\t.\t-\t.\ttranscript_id \"B\";\n");
+        let (lines, counts) = records(Config::builder().merge_blocks(true), &gtf);
+
+        assert_eq!(
+            lines.unwrap()[1],
+            "chr2\t100\t400\tB\t0\t-\t100\t400\t0\t2\t100,100,\t0,200,"
+        );
+        assert_eq!(counts.merged(), 1);
+    }
+
+    #[test]
+    fn test_derived_spans() {
+        let exons = GTF
+            .lines()
+            .filter(|line| line.contains("\texon\t"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let config = Config::default();
+        let mut records = records_from_str(&config, &exons, InputFormat::Gtf);
+        let spans = records
+            .by_ref()
+            .map(|r| r.map(|r| (r.name, r.start, r.end)))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(spans, vec![("A".into(), 10, 60), ("B".into(), 100, 400)]);
+        assert_eq!(records.derived_spans(), 2);
+        let mut records = records_from_str(&config, GTF, InputFormat::Gtf);
+        assert_eq!(records.by_ref().count(), 2);
+        assert_eq!(records.derived_spans(), 0);
+    }
+
+    #[test]
+    fn test_keep_childless() {
+        let gtf = "chr1\tHAVANA\tgene\t11\t50\t.\t+\t.\tgene_id \"G\";\n";
+        let convert = |keep| {
+            let config = Config::builder().parent("gene").feature("gene_id");
+            records(config.keep_childless(keep), gtf)
+        };
+
+        let (lines, counts) = convert(false);
+        assert_eq!((lines.unwrap().len(), counts.skipped()), (0, 1));
+        let (lines, counts) = convert(true);
+        assert_eq!(
+            lines.unwrap(),
+            ["chr1\t10\t50\tG\t0\t+\t10\t50\t0\t1\t40,\t0,"]
+        );
+        assert_eq!(counts.skipped(), 0);
+    }
+
+    #[test]
+    fn test_cds_exons() {
+        let gtf = "chr1\tHAVANA\ttranscript\t11\t100\t.\t-\t.\ttranscript_id \"A\";\n\
+                   chr1\tHAVANA\tCDS\t11\t30\t.\t-\t0\ttranscript_id \"A\";\n\
+                   chr1\tHAVANA\tCDS\t71\t100\t.\t-\t0\ttranscript_id \"A\";\n\
+                   chr1\tHAVANA\tstop_codon\t8\t10\t.\t-\t0\ttranscript_id \"A\";\n\
+                   chr2\tprodigal\tCDS\t201\t290\t.\t+\t0\ttranscript_id \"B\";\n";
+
+        let (lines, counts) = records(Config::builder(), gtf);
+        assert_eq!((lines.unwrap().len(), counts.skipped()), (0, 1));
+        let (lines, counts) = records(Config::builder().cds_exons(true), gtf);
+        assert_eq!(
+            lines.unwrap(),
+            [
+                "chr1\t7\t100\tA\t0\t-\t7\t100\t0\t2\t23,30,\t0,63,",
+                "chr2\t200\t290\tB\t0\t+\t200\t290\t0\t1\t90,\t0,",
+            ]
+        );
+        assert_eq!((counts.cds_exons(), counts.skipped()), (2, 0));
+    }
+
+    #[test]
+    fn test_cds_exons_parent_span() {
+        let gtf = "chr1\tHAVANA\ttranscript\t11\t400\t.\t+\t.\ttranscript_id \"A\";\n\
+                   chr1\tHAVANA\tCDS\t31\t100\t.\t+\t0\ttranscript_id \"A\";\n\
+                   chr1\tHAVANA\tCDS\t201\t300\t.\t+\t2\ttranscript_id \"A\";\n";
+        let config = Config::builder().cds_exons(true).check_output(true);
+
+        // INFO: the transcript's UTRs are not blocks, so the span shrinks to the CDS
+        assert_eq!(
+            records(config, gtf).0.unwrap(),
+            ["chr1\t30\t300\tA\t0\t+\t30\t300\t0\t2\t70,100,\t0,170,"]
+        );
+    }
+
+    #[test]
+    fn test_attrs_json() {
+        let gtf = "chr1\tHAVANA\ttranscript\t11\t60\t.\t+\t.\ttranscript_id \"A\"; gene_name \"X\"; tag \"basic\"; tag \"CCDS\";\n\
+                   chr1\tHAVANA\texon\t11\t60\t.\t+\t.\ttranscript_id \"A\"; exon_number \"1\";\n\
+                   chr2\tHAVANA\texon\t11\t60\t.\t+\t.\ttranscript_id \"B\"; note \"a\\\"b\";\n";
+        let config = Config::builder()
+            .name_attrs(["gene_name"])
+            .attrs_json(true)
+            .exon_ranks(true)
+            .build()
+            .unwrap();
+        let records = records_from_str(&config, gtf, InputFormat::Gtf)
+            .map(|r| r.map(|r| (r.name, r.extras)))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(
+            records,
+            vec![
+                (
+                    "X".to_string(),
+                    vec![
+                        "1,".to_string(),
+                        "1".to_string(),
+                        r#"{"gene_name":"X","tag":["basic","CCDS"],"transcript_id":"A"}"#
+                            .to_string()
+                    ]
+                ),
+                (
+                    "B".to_string(),
+                    vec![
+                        "1,".to_string(),
+                        "1".to_string(),
+                        r#"{"note":"a\\\"b","transcript_id":"B"}"#.to_string()
+                    ]
+                ),
+            ]
+        );
+        assert!(Config::builder()
+            .attrs_json(true)
+            .emit(Emit::Exons)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_escape() {
+        let gff = "chr1\t.\tmRNA\t11\t60\t.\t+\t.\tID=A;Name=my gene;note=x\n\
+                   chr1\t.\texon\t11\t60\t.\t+\t.\tParent=A\n\
+                   chr1\t.\tmRNA\t71\t90\t.\t+\t.\tID=B;Name=B1\n\
+                   chr1\t.\texon\t71\t90\t.\t+\t.\tParent=B\n";
+        let convert = |escape| {
+            let config = Config::builder()
+                .parent("mRNA")
+                .feature("ID")
+                .child_feature("Parent")
+                .attr_sep('=')
+                .name_attrs(["Name"]);
+            records(config.escape(escape), gff)
+        };
+
+        let (lines, counts) = convert(Escape::Keep);
+        assert_eq!(
+            lines.unwrap()[0],
+            "chr1\t10\t60\tmy gene\t0\t+\t10\t60\t0\t1\t50,\t0,"
+        );
+        assert_eq!(counts.escaped(), 1);
+        let (lines, counts) = convert(Escape::Replace);
+        assert_eq!(
+            lines.unwrap(),
+            [
+                "chr1\t10\t60\tmy_gene\t0\t+\t10\t60\t0\t1\t50,\t0,",
+                "chr1\t70\t90\tB1\t0\t+\t70\t90\t0\t1\t20,\t0,",
+            ]
+        );
+        assert_eq!(counts.escaped(), 1);
+        assert!(convert(Escape::Url).0.unwrap()[0].contains("\tmy%20gene\t"));
+    }
+
+    #[test]
+    fn test_check_output() {
+        let config = Config::builder().check_output(true).build().unwrap();
+        assert_eq!(
+            records_from_str(&config, GTF, InputFormat::Gtf)
+                .collect::<Result<Vec<_>>>()
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // INFO: the exon does not reach the transcript ends, which BED12 requires
+        let gtf = "chr1\tHAVANA\ttranscript\t1\t100\t.\t+\t.\ttranscript_id \"A\";\n\
+                   chr1\tHAVANA\texon\t11\t30\t.\t+\t.\ttranscript_id \"A\";\n";
+        let err = records_from_str(&config, gtf, InputFormat::Gtf)
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid record: A fails --check-output: first block starts at 10 instead of \
+             chromStart 0\nchr1\t0\t100\tA\t0\t+\t10\t30\t0\t1\t20,\t10,"
+        );
+    }
+
+    #[test]
+    fn test_check_output_removes_partial_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = write_input(&dir);
+        let gtf = format!(
+            "{GTF}chr3\tHAVANA\ttranscript\t1\t100\t.\t+\t.\ttranscript_id \"D\";\n\
+                           chr3\tHAVANA\texon\t11\t30\t.\t+\t.\ttranscript_id \"D\";\n"
+        );
+        std::fs::write(&input, gtf).unwrap();
+        let (output, bed6) = (input.with_extension("bed"), input.with_extension("6.bed"));
+        let mut config = Config::builder()
+            .input(&input)
+            .output(&output)
+            .check_output(true)
+            .build()
+            .unwrap();
+        config.outputs = vec![format!("bed6={}", bed6.display()).parse().unwrap()];
+
+        assert!(run(&config).is_err());
+        assert!(!output.exists() && !bed6.exists());
+    }
+
+    #[test]
+    fn test_exon_ranks() {
+        let extras = |gtf: &str, child: &str| {
+            let config = Config::builder()
+                .child(child)
+                .exon_ranks(true)
+                .build()
+                .unwrap();
+            records_from_str(&config, gtf, InputFormat::Gtf)
+                .map(|r| r.unwrap().extras)
+                .collect::<Vec<_>>()
+        };
+
+        // INFO: computed from the strand, A is on + and B on -
+        assert_eq!(extras(GTF, "exon"), [["1,", "1"], ["2,1,", "2"]]);
+
+        let gtf = format!(
+            "{GTF}chr2\tHAVANA\tCDS\t301\t330\t.\t-\t0\ttranscript_id \"B\"; exon_number \"3\";\n"
+        );
+        assert_eq!(extras(&gtf, "CDS"), [["3,", "3"]]);
+    }
+
+    #[test]
+    fn test_ignore_strand() {
+        let config = Config::builder()
+            .ignore_strand(true)
+            .exon_ranks(true)
+            .build()
+            .unwrap();
+        let records = records_from_str(&config, GTF, InputFormat::Gtf)
+            .map(|r| r.map(|r| (r.name, r.strand, r.extras)))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        // INFO: B is on -, yet its blocks are ranked from the left
+        assert_eq!(
+            records,
+            [
+                (
+                    "A".to_string(),
+                    Strand::Unknown,
+                    vec!["1,".to_string(), "1".into()]
+                ),
+                ("B".into(), Strand::Unknown, vec!["1,2,".into(), "2".into()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_columns() {
+        let gtf = "chr1\t.\texon\t11\t20\t.\t+\t.\tgene_id \"G\"; transcript_id \"A\"; gene_name \"ACTB\"; gene_biotype \"protein_coding\";\n\
+                   chr1\t.\texon\t31\t40\t.\t-\t.\tgene_id \"H\"; transcript_id \"B\";\n";
+        let config = Config::builder()
+            .columns([
+                "chrom",
+                "start",
+                "end",
+                "gene_name",
+                "strand",
+                "gene_biotype",
+            ])
+            .build()
+            .unwrap();
+        let emitter = emitter(&config);
+        let mut out = Vec::new();
+        for record in records_from_str(&config, gtf, InputFormat::Gtf) {
+            emitter.emit(&record.unwrap(), &mut out);
+        }
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "chr1\t10\t20\tACTB\t+\tprotein_coding\nchr1\t30\t40\t.\t-\t.\n"
+        );
+    }
+
+    #[test]
+    fn test_compute() {
+        let gtf = "chr1\t.\texon\t11\t20\t.\t+\t.\ttranscript_id \"A\"; gene_name \"ACTB\"; level \"2\";\n\
+                   chr1\t.\texon\t31\t40\t.\t+\t.\ttranscript_id \"A\";\n\
+                   chr1\t.\texon\t51\t60\t.\t-\t.\ttranscript_id \"B\";\n";
+        let compute = |spec: &str| spec.parse().unwrap();
+        let config = Config::builder()
+            .bed_type(BedType::Bed6)
+            .name_attrs(["gene_name"])
+            .compute(compute("tx_len=end-start"))
+            .compute(compute("score=min(1000, exon_count*50 + level)"))
+            .compute(compute("label=gene_name + 1"))
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        write_output(
+            records_from_str(&config, gtf, InputFormat::Gtf),
+            &mut out,
+            &*emitter(&config),
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "chr1\t10\t40\tACTB\t102\t+\t30\t.\nchr1\t50\t60\tB\t0\t-\t10\t.\n"
+        );
+        assert!(Config::builder()
+            .columns(["chrom"])
+            .compute(compute("x=1"))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_check_orf() {
+        let dir = tempfile::tempdir().unwrap();
+        let fasta = dir.path().join("genome.fa");
+        std::fs::write(&fasta, ">chr1\nccATGAAAGCCTAAccATGTGAAAATAGcc\n").unwrap();
+        let gtf = "chr1\t.\texon\t1\t14\t.\t+\t.\ttranscript_id \"A\";\n\
+                   chr1\t.\tCDS\t3\t11\t.\t+\t0\ttranscript_id \"A\";\n\
+                   chr1\t.\tstop_codon\t12\t14\t.\t+\t0\ttranscript_id \"A\";\n\
+                   chr1\t.\texon\t15\t30\t.\t+\t.\ttranscript_id \"B\";\n\
+                   chr1\t.\tCDS\t17\t28\t.\t+\t0\ttranscript_id \"B\";\n";
+        let config = Config::builder()
+            .fasta(&fasta)
+            .check_orf(true)
+            .build()
+            .unwrap();
+        let mut records = records_from_str(&config, gtf, InputFormat::Gtf);
+        assert_eq!(records.by_ref().count(), 2);
+        assert_eq!(records.bad_orfs(), 1);
+
+        assert!(Config::builder().check_orf(true).build().is_err());
+    }
+
+    #[test]
+    fn test_child_union() {
+        let gff = "chr1\t.\tmRNA\t1\t100\t.\t+\t.\tID=A\n\
+                   chr1\t.\tfive_prime_UTR\t1\t10\t.\t+\t.\tParent=A\n\
+                   chr1\t.\tCDS\t11\t30\t.\t+\t0\tParent=A\n\
+                   chr1\t.\tCDS\t61\t80\t.\t+\t1\tParent=A\n\
+                   chr1\t.\texon\t61\t90\t.\t+\t.\tParent=A\n";
+        let blocks = |child: &str| {
+            let config = Config::builder()
+                .parent("mRNA")
+                .child(child)
+                .feature("ID")
+                .child_feature("Parent")
+                .build()
+                .unwrap();
+            records_from_str(&config, gff, InputFormat::Gff)
+                .map(|r| r.unwrap().blocks)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(blocks("CDS"), [vec![(10, 30), (60, 80)]]);
+        assert_eq!(blocks("exon,CDS,five_prime_UTR"), [vec![(0, 30), (60, 90)]]);
+        assert!(Config::builder().child("exon,").build().is_err());
+        assert!(Config::builder().child("exon,transcript").build().is_err());
+    }
+
+    #[test]
+    fn test_feature_fallback() {
+        let gff = "chr1\t.\ttranscript\t1\t100\t.\t+\t.\ttranscript_id=A;gene_id=G\n\
+                   chr1\t.\texon\t1\t100\t.\t+\t.\ttranscript_id=A\n\
+                   chr1\t.\ttranscript\t201\t300\t.\t-\t.\tID=B\n\
+                   chr1\t.\texon\t201\t250\t.\t-\t.\tParent=B\n";
+        let config = Config::builder()
+            .feature("transcript_id,ID,Parent")
+            .build()
+            .unwrap();
+        let names = records_from_str(&config, gff, InputFormat::Gff)
+            .map(|r| r.unwrap().name)
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, ["A", "B"]);
+        assert!(Config::builder().feature("ID,").build().is_err());
+    }
+
+    #[test]
+    fn test_max_transcripts_per_gene() {
+        let gtf = "chr1\t.\texon\t1\t100\t.\t+\t.\tgene_id \"G\"; transcript_id \"A\"; tag \"basic\";\n\
+                   chr1\t.\texon\t1\t50\t.\t+\t.\tgene_id \"G\"; transcript_id \"B\"; tag \"basic\"; tag \"MANE_Select\";\n\
+                   chr1\t.\texon\t1\t80\t.\t+\t.\tgene_id \"G\"; transcript_id \"C\";\n";
+        let names = |rank| {
+            let config = Config::builder()
+                .max_transcripts_per_gene(1)
+                .rank_isoforms(rank)
+                .score_attrs(["transcript_support_level"])
+                .build()
+                .unwrap();
+            records_from_str(&config, gtf, InputFormat::Gtf)
+                .map(|r| r.unwrap().name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(IsoformRank::Length), ["A"]);
+        assert_eq!(names(IsoformRank::Tags), ["B"]);
+    }
+
+    #[test]
+    fn test_sample() {
+        let lines = (0..1000)
+            .map(|i| {
+                format!(
+                    "chr1\t.\texon\t{}\t{}\t.\t+\t.\ttranscript_id \"T{}\";\n",
+                    i + 1,
+                    i + 10,
+                    i
+                )
+            })
+            .collect::<Vec<_>>();
+        let names = |lines: &[String], seed| {
+            let config = Config::builder().sample(0.1).seed(seed).build().unwrap();
+            let mut names = records_from_str(&config, &lines.concat(), InputFormat::Gtf)
+                .map(|r| r.unwrap().name)
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+
+        let sample = names(&lines, 42);
+        assert!((50..150).contains(&sample.len()));
+        let reversed = lines.iter().rev().cloned().collect::<Vec<_>>();
+        assert_eq!(names(&reversed, 42), sample);
+        assert_ne!(names(&lines, 7), sample);
+        assert!(Config::builder().sample(0.0).build().is_err());
+    }
+
+    #[test]
+    fn test_degenerate_policies() {
+        let gtf = GTF.replace("exon\t301\t400", "exon\t301\t300");
+        let convert = |policy| records(Config::builder().degenerate(policy), &gtf);
+
+        let (lines, counts) = convert(Degenerate::KeepPoint);
+        assert_eq!(
+            lines.unwrap()[1],
+            "chr2\t100\t400\tB\t0\t-\t100\t301\t0\t2\t50,1,\t0,200,"
+        );
+        assert_eq!(counts.degenerate(), 1);
+        assert_eq!(convert(Degenerate::Drop).0.unwrap(), BED[..1]);
+        assert!(convert(Degenerate::Error).0.is_err());
+    }
+
+    #[test]
+    fn test_duplicate_ids_policies() {
+        let gtf =
+            format!("{GTF}chr1\tHAVANA\ttranscript\t501\t600\t.\t+\t.\ttranscript_id \"A\";\n");
+        let convert = |policy| records(Config::builder().duplicate_ids(policy), &gtf);
+
+        let (lines, counts) = convert(DuplicateIds::Warn);
+        assert_eq!(lines.unwrap().len(), 2);
+        assert_eq!(counts.duplicate_ids(), 1);
+        assert_eq!(convert(DuplicateIds::Drop).0.unwrap(), BED[1..]);
+        assert!(convert(DuplicateIds::Error).0.is_err());
+    }
+
+    #[test]
+    fn test_mixed_loci_policies() {
+        let gtf = format!("{GTF}chr3\tHAVANA\texon\t501\t600\t.\t+\t.\ttranscript_id \"B\";\n");
+        let split = [
+            BED[0],
+            "chr2\t100\t400\tB_chr2_-\t0\t-\t100\t400\t0\t2\t50,100,\t0,200,",
+            "chr3\t500\t600\tB_chr3_+\t0\t+\t500\t600\t0\t1\t100,\t0,",
+        ];
+
+        for config in [Config::builder(), Config::builder().max_memory(1)] {
+            let (lines, counts) = records(config.mixed_loci(MixedLoci::Split), &gtf);
+            assert_eq!(lines.unwrap(), split);
+            assert_eq!(counts.mixed_loci(), 2);
+        }
+        let drop = Config::builder().mixed_loci(MixedLoci::Drop);
+        assert_eq!(records(drop, &gtf).0.unwrap(), split[..1]);
+        let error = Config::builder().mixed_loci(MixedLoci::Error);
+        assert!(records(error, &gtf).0.is_err());
+    }
+
+    #[test]
+    fn test_mixed_strand_policies() {
+        let gtf = "\
+chr1\tHAVANA\ttranscript\t101\t400\t.\t+\t.\ttranscript_id \"A\";
+chr1\tHAVANA\texon\t101\t150\t.\t-\t.\ttranscript_id \"A\";
+chr1\tHAVANA\texon\t201\t250\t.\t-\t.\ttranscript_id \"A\";
+chr1\tHAVANA\texon\t301\t320\t.\t+\t.\ttranscript_id \"A\";
+chr1\tHAVANA\texon\t351\t400\t.\t-\t.\ttranscript_id \"A\";
+";
+        let convert = |policy| Config::builder().mixed_strand(policy);
+
+        let (lines, counts) = records(convert(MixedStrand::Split), gtf);
+        assert_eq!(lines.unwrap().len(), 2);
+        assert_eq!((counts.mixed_strand(), counts.mixed_loci()), (0, 2));
+        for config in [
+            convert(MixedStrand::Majority),
+            convert(MixedStrand::Majority).max_memory(1),
+        ] {
+            let (lines, counts) = records(config, gtf);
+            assert_eq!(
+                lines.unwrap(),
+                ["chr1\t100\t400\tA\t0\t-\t100\t400\t0\t4\t50,50,20,50,\t0,100,200,250,"]
+            );
+            assert_eq!((counts.mixed_strand(), counts.mixed_loci()), (1, 0));
+        }
+        assert!(records(convert(MixedStrand::Drop), gtf)
+            .0
+            .unwrap()
+            .is_empty());
+        assert!(records(convert(MixedStrand::Error), gtf).0.is_err());
+    }
+
+    #[test]
+    fn test_records_unordered() {
+        let config = Config::builder().unordered(true).build().unwrap();
+
+        let mut names = records_from_str(&config, GTF, InputFormat::Gtf)
+            .map(|r| r.unwrap().name)
+            .collect::<Vec<String>>();
+        names.sort();
+
+        assert_eq!(names, vec!["A", "B"]);
+    }
+
+    #[test]
+    fn test_run_to_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = write_input(&dir);
+        let config = Config::new(&input, "unused.bed");
+
+        let mut out = Vec::new();
+        let stats = run_to_writer(&config, &mut out).unwrap();
+
+        assert_eq!(stats.written, 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "chr1\t10\t60\tA\t0\t+\t10\t60\t0\t1\t50,\t0,\n\
+             chr2\t100\t400\tB\t0\t-\t100\t400\t0\t2\t50,100,\t0,200,\n"
+        );
+    }
+
+    #[test]
+    fn test_write_output_batches_records() {
+        struct CountingWriter(Vec<usize>);
+        impl Write for CountingWriter {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.push(buf.len());
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let config = Config::new("unused.gtf", "unused.bed");
+        let records = records_from_str(&config, GTF, InputFormat::Gtf);
+        let mut writer = CountingWriter(Vec::new());
+        write_output(records, &mut writer, &BedType::Bed12).unwrap();
+
+        assert_eq!(writer.0.len(), 1);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_run_compressed_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = write_input(&dir);
+        let output = input.with_extension("bed.gz");
+        let mut config = Config::new(&input, &output);
+        config.checksum = Some(crate::Checksum::Sha256);
+
+        let stats = run(&config).unwrap();
+        assert_eq!(stats.written, 2);
+
+        let bytes = std::fs::read(&output).unwrap();
+        assert!(bytes.starts_with(&[0x1f, 0x8b]));
+        let mut hashing = Hashing::new(Vec::new(), config.checksum);
+        hashing.write_all(&bytes).unwrap();
+        assert_eq!(stats.checksum, hashing.finish().1);
+        let bed = decode(&bytes[..], Compression::Gzip).unwrap();
+        assert!(bed.starts_with("chr1\t10\t60\tA\t"));
+    }
+
+    #[test]
+    fn test_run_multiple_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = write_input(&dir);
+        let (bed6, tsv) = (input.with_extension("6.bed"), input.with_extension("tsv"));
+        let mut config = Config::new(&input, "");
+        config.outputs = vec![
+            format!("bed6={}", bed6.display()).parse().unwrap(),
+            format!("TSV={}", tsv.display()).parse().unwrap(),
+        ];
+
+        assert_eq!(run(&config).unwrap().written, 2);
+        assert_eq!(
+            std::fs::read_to_string(&bed6).unwrap(),
+            "chr1\t10\t60\tA\t0\t+\nchr2\t100\t400\tB\t0\t-\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&tsv).unwrap().lines().nth(2),
+            Some("chr2\t100\t400\tB\t0\t-\t2\t150")
+        );
+        assert!("bed7=x.bed".parse::<crate::output::OutputSpec>().is_err());
+    }
+
+    #[test]
+    fn test_run_throughput() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = write_input(&dir);
+        let output = input.with_extension("bed");
+        let stats = run(&Config::new(&input, &output)).unwrap();
+
+        assert_eq!(stats.input_bytes, GTF.len() as u64);
+        assert_eq!(
+            stats.output_bytes,
+            std::fs::metadata(&output).unwrap().len()
+        );
+        assert!(stats.elapsed > Duration::ZERO);
+        assert!(stats.records_per_sec() > 0.0 && stats.output_mb_per_sec() > 0.0);
+        assert_eq!(RunStats::default().records_per_sec(), 0.0);
+    }
+
+    #[test]
+    fn test_run_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = write_input(&dir);
+        // INFO: compressed when gzip is compiled in, so the write phase covers compression
+        let output = input.with_extension(match cfg!(feature = "gzip") {
+            true => "bed.gz",
+            false => "bed",
+        });
+        assert_eq!(run(&Config::new(&input, &output)).unwrap().profile, None);
+
+        let mut config = Config::new(&input, &output);
+        config.profile = true;
+        let started = Instant::now();
+        let profile = run(&config).unwrap().profile.unwrap();
+        let elapsed = started.elapsed();
+
+        // INFO: a phase of a tiny input can take less than a clock tick, so only the total is checked
+        let total = profile.times.iter().sum::<Duration>();
+        assert!(total > Duration::ZERO && total <= elapsed, "{total:?}");
+        let text = profile.to_string();
+        for phase in Phase::ALL {
+            assert!(text.contains(phase.name()), "{text}");
+            // INFO: the peak is noted whenever a phase is timed
+            assert_eq!(
+                profile.peak_mb(phase).is_some(),
+                cfg!(feature = "rusage"),
+                "{:?}",
+                phase
+            );
+        }
+    }
+
+    #[test]
+    fn test_run_child_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = write_input(&dir);
+        let (exons, cds) = (
+            input.with_extension("exon.bed"),
+            input.with_extension("cds.bed"),
+        );
+        let config = Config::builder()
+            .input(&input)
+            .bed_type(BedType::Bed6)
+            .child_output("exon", &exons)
+            .child_output("CDS", &cds)
+            .build()
+            .unwrap();
+        assert!(!config.writes_output());
+
+        let stats = run(&config).unwrap();
+        assert_eq!((stats.written, stats.skipped), (2, 4));
+        assert_eq!(
+            std::fs::read_to_string(&exons).unwrap(),
+            "chr1\t10\t60\tA\t0\t+\nchr2\t100\t400\tB\t0\t-\n"
+        );
+        assert_eq!(std::fs::read_to_string(&cds).unwrap(), "");
+        assert!(Config::builder()
+            .child_output("exon", &exons)
+            .child_output("CDS", &exons)
+            .build()
+            .is_err());
+        assert!("exon".parse::<crate::output::ChildOutput>().is_err());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_decode_truncated_input() {
+        let gtf = GTF.repeat(200);
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(gtf.as_bytes()).unwrap();
+        let bytes = encoder.finish().unwrap();
+        let cut = &bytes[..bytes.len() * 3 / 4];
+
+        let err = decode_input(cut, Compression::Gzip, false, false).unwrap_err();
+        assert!(matches!(err, Gxf2BedError::Truncated { records, .. } if records > 0));
+
+        let partial = decode_input(cut, Compression::Gzip, true, false).unwrap();
+        assert!(partial.ends_with('\n') && gtf.starts_with(&partial));
+        assert!(partial.len() < gtf.len());
+    }
+
+    #[test]
+    fn test_records_from_reader() {
+        let config = Config::new("unused.gtf", "unused.bed");
+        let records = records_from_reader(&config, GTF.as_bytes(), InputFormat::Gtf)
+            .collect::<Result<Vec<BedRecord>>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].blocks, vec![(100, 150), (300, 400)]);
+    }
+
+    #[test]
+    fn test_convert_str() {
+        let config = Config::new("unused.gtf", "unused.bed");
+        let bed = convert_str(GTF, InputFormat::Gtf, &config).unwrap();
+
+        assert_eq!(bed.lines().count(), 2);
+        assert!(bed.starts_with("chr1\t10\t60\tA\t"));
+    }
+
+    #[test]
+    fn test_records_from_source() {
+        let config = Config::new("unused.gtf", "unused.bed");
+        let reader = GxfReader::new(GTF.as_bytes(), InputFormat::Gtf, "transcript_id");
+        let from_source = records_from_source(&config, reader)
+            .collect::<Result<Vec<BedRecord>>>()
+            .unwrap();
+        let from_str = records_from_reader(&config, GTF.as_bytes(), InputFormat::Gtf)
+            .collect::<Result<Vec<BedRecord>>>()
+            .unwrap();
+
+        assert_eq!(from_source, from_str);
+    }
+
+    #[test]
+    fn test_records_unsupported_input() {
+        let config = Config::new("missing.txt", "unused.bed");
+        let mut records = super::records(&config);
+
+        assert!(matches!(
+            records.next(),
+            Some(Err(Gxf2BedError::UnsupportedFormat(_)))
+        ));
+        assert!(records.next().is_none());
+    }
+}
@@ -0,0 +1,365 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use hashbrown::HashMap;
+use rusqlite::{params, Connection, OpenFlags};
+
+use crate::config::Config;
+use crate::detect::detect_input_kind;
+use crate::diagnostic::Span;
+use crate::error::{Gxf2BedError, Result};
+use crate::gxf::{is_comment, lines, until_fasta, GxfRecord, KeyMatcher};
+use crate::input::read_input;
+use crate::progress::NoProgress;
+
+/// Tables of an annotation database, gffutils-style: one `features` row per
+/// input line (1-based, inclusive coordinates, the line kept verbatim), its
+/// attributes one value per row, and parent-child links by feature ID.
+const SCHEMA: &str = r#"
+CREATE TABLE features (
+    id TEXT NOT NULL,
+    seqid TEXT NOT NULL,
+    source TEXT NOT NULL,
+    featuretype TEXT NOT NULL,
+    start INTEGER NOT NULL,
+    "end" INTEGER NOT NULL,
+    score TEXT NOT NULL,
+    strand TEXT NOT NULL,
+    frame TEXT NOT NULL,
+    line TEXT NOT NULL
+);
+CREATE TABLE attributes (feature INTEGER NOT NULL, key TEXT NOT NULL, value TEXT NOT NULL);
+CREATE TABLE relations (parent TEXT NOT NULL, child TEXT NOT NULL);
+CREATE TABLE meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+"#;
+
+/// Built after the rows are in, which is much faster than updating them on
+/// every insert.
+const INDEXES: &str = r#"
+CREATE INDEX features_id ON features (id);
+CREATE INDEX features_region ON features (seqid, start, "end");
+CREATE INDEX features_type ON features (featuretype);
+CREATE INDEX attributes_feature ON attributes (feature);
+CREATE INDEX attributes_key_value ON attributes (key, value);
+CREATE INDEX relations_parent ON relations (parent);
+CREATE INDEX relations_child ON relations (child);
+"#;
+
+impl From<rusqlite::Error> for Gxf2BedError {
+    fn from(err: rusqlite::Error) -> Self {
+        Gxf2BedError::Database(err.to_string())
+    }
+}
+
+/// Writes every feature line of `config.input` into a new SQLite database
+/// at `path`, replacing any file there, and returns the number of features
+/// stored.
+///
+/// A feature's ID is its `ID` attribute, the `gene_id`/`transcript_id` of a
+/// GTF `gene`/`transcript` line, or `<featuretype>_<n>` otherwise. Its
+/// parents are its `Parent` values, or for GTF lines the `gene_id` of a
+/// transcript and the `transcript_id` (else `gene_id`) of anything else.
+pub fn build(config: &Config, path: &Path) -> Result<usize> {
+    let kind = detect_input_kind(&config.input)?;
+    let contents = read_input(config, kind, &NoProgress)?;
+    let sep = config.separator(kind.format);
+    let keys = KeyMatcher::new(&["ID"]);
+
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let mut conn = Connection::open(path)?;
+    conn.execute_batch(SCHEMA)?;
+
+    let tx = conn.transaction()?;
+    let mut count = 0;
+    {
+        let mut features =
+            tx.prepare("INSERT INTO features VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
+        let mut attributes = tx.prepare("INSERT INTO attributes VALUES (?, ?, ?)")?;
+        let mut relations = tx.prepare("INSERT INTO relations VALUES (?, ?)")?;
+        let mut numbers = HashMap::new();
+
+        for (idx, line) in lines(until_fasta(contents.as_str())).enumerate() {
+            if is_comment(line, &config.comment_chars) || line.trim().is_empty() {
+                continue;
+            }
+            let record =
+                GxfRecord::parse_with(line, &keys, sep, config.input_coords).map_err(|e| {
+                    Gxf2BedError::Parse {
+                        line: idx + 1,
+                        message: e.to_string(),
+                        span: Some(Box::new(Span::record(idx + 1, line, e))),
+                    }
+                })?;
+            let attrs = record
+                .attr
+                .pairs()
+                .flat_map(|(key, value)| values(value, sep).map(move |value| (key, value)))
+                .collect::<Vec<_>>();
+            let id = feature_id(record.feature, &attrs, &mut numbers);
+            let column = |idx: usize| line.split('\t').nth(idx).unwrap_or(".");
+
+            features.execute(params![
+                id,
+                record.chr,
+                column(1),
+                record.feature,
+                record.start + 1,
+                record.end,
+                column(5),
+                record.strand.to_string(),
+                record.frame,
+                line,
+            ])?;
+            let rowid = tx.last_insert_rowid();
+            for (key, value) in &attrs {
+                attributes.execute(params![rowid, key, value])?;
+            }
+            for parent in parents(record.feature, &attrs) {
+                if parent != id {
+                    relations.execute(params![parent, id])?;
+                }
+            }
+            count += 1;
+        }
+    }
+    tx.execute_batch(INDEXES)?;
+    tx.execute(
+        "INSERT INTO meta VALUES ('input', ?), ('version', ?)",
+        params![
+            config.input.display().to_string(),
+            env!("CARGO_PKG_VERSION")
+        ],
+    )?;
+    tx.commit()?;
+
+    Ok(count)
+}
+
+/// Values of one attribute; GFF3 lists several separated by commas.
+fn values(value: &str, sep: u8) -> impl Iterator<Item = &str> {
+    let list = match sep {
+        b'=' => Some(','),
+        _ => None,
+    };
+    value
+        .split(move |c| Some(c) == list)
+        .filter(|value| !value.is_empty())
+}
+
+fn attr<'a>(attrs: &[(&str, &'a str)], key: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, value)| *value)
+}
+
+/// ID of a feature (see [`build`]); `numbers` counts the numbered ones per
+/// feature type.
+fn feature_id(
+    featuretype: &str,
+    attrs: &[(&str, &str)],
+    numbers: &mut HashMap<String, usize>,
+) -> String {
+    let id = attr(attrs, "ID").or_else(|| match featuretype {
+        "gene" => attr(attrs, "gene_id"),
+        "transcript" => attr(attrs, "transcript_id"),
+        _ => None,
+    });
+
+    match id {
+        Some(id) => id.to_string(),
+        None => {
+            let n = numbers.entry(featuretype.to_string()).or_insert(0);
+            *n += 1;
+            format!("{}_{}", featuretype, n)
+        }
+    }
+}
+
+/// IDs of the parents of a feature (see [`build`]).
+fn parents<'a>(featuretype: &str, attrs: &[(&'a str, &'a str)]) -> Vec<&'a str> {
+    let parents = attrs
+        .iter()
+        .filter(|(key, _)| *key == "Parent")
+        .map(|(_, value)| *value)
+        .collect::<Vec<_>>();
+    if !parents.is_empty() {
+        return parents;
+    }
+
+    let parent = match featuretype {
+        "gene" => None,
+        "transcript" => attr(attrs, "gene_id"),
+        _ => attr(attrs, "transcript_id").or_else(|| attr(attrs, "gene_id")),
+    };
+    parent.into_iter().collect()
+}
+
+/// A `chrom`, `chrom:start` or `chrom:start-end` region, 1-based and
+/// inclusive as in samtools; thousands separators are allowed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub chrom: String,
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let number = |n: &str| n.replace(',', "").parse::<u64>().ok();
+        let bounds = s.rsplit_once(':').and_then(|(chrom, range)| {
+            let (start, end) = match range.split_once('-') {
+                Some((start, end)) => (number(start)?, Some(number(end)?)),
+                None => (number(range)?, None),
+            };
+            Some((chrom, start, end))
+        });
+
+        let region = match bounds {
+            Some((chrom, start, end)) => Region {
+                chrom: chrom.to_string(),
+                start,
+                end,
+            },
+            None => Region {
+                chrom: s.to_string(),
+                start: 1,
+                end: None,
+            },
+        };
+        match region.end {
+            _ if region.chrom.is_empty() => Err(format!("no chromosome in region {:?}", s)),
+            Some(end) if end < region.start => Err(format!("region {:?} ends before it starts", s)),
+            _ => Ok(region),
+        }
+    }
+}
+
+/// What `gxf2bed db query` looks up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lookup {
+    /// Features overlapping a region.
+    Region(Region),
+    /// Features with an ID, and with `children` every feature below them.
+    Id { id: String, children: bool },
+}
+
+/// The input lines of the features of database `path` matching `lookup`,
+/// in input order.
+pub fn query(path: &Path, lookup: &Lookup) -> Result<Vec<String>> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let lines = match lookup {
+        Lookup::Region(region) => {
+            let end = region.end.map_or(i64::MAX, |end| end as i64);
+            let mut stmt = conn.prepare(
+                r#"SELECT line FROM features
+                   WHERE seqid = ? AND start <= ? AND "end" >= ?
+                   ORDER BY rowid"#,
+            )?;
+            let rows =
+                stmt.query_map(params![region.chrom, end, region.start], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<Vec<String>>>()?
+        }
+        Lookup::Id { id, children } => {
+            // INFO: UNION drops repeated IDs, so cyclic Parent links still end
+            let mut stmt = conn.prepare(
+                r#"WITH RECURSIVE tree(id) AS (
+                       SELECT ?1
+                       UNION
+                       SELECT relations.child FROM relations JOIN tree
+                       ON ?2 AND relations.parent = tree.id
+                   )
+                   SELECT line FROM features WHERE id IN tree ORDER BY rowid"#,
+            )?;
+            let rows = stmt.query_map(params![id, children], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<Vec<String>>>()?
+        }
+    };
+
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GFF: &str = "##gff-version 3\n\
+        chr1\thavana\tgene\t1\t100\t.\t+\t.\tID=g1;Name=ABC\n\
+        chr1\thavana\tmRNA\t1\t100\t.\t+\t.\tID=t1;Parent=g1\n\
+        chr1\thavana\texon\t1\t20\t.\t+\t.\tParent=t1\n\
+        chr1\thavana\texon\t81\t100\t.\t+\t.\tParent=t1\n\
+        chr2\thavana\tgene\t500\t900\t7\t-\t.\tID=g2;Note=a,b\n";
+
+    #[test]
+    fn test_build_and_query() {
+        let dir = tempfile::tempdir().unwrap();
+        let gff = dir.path().join("a.gff3");
+        std::fs::write(&gff, GFF).unwrap();
+        let path = dir.path().join("a.db");
+        let config = Config {
+            input: gff,
+            ..Config::default()
+        };
+
+        assert_eq!(build(&config, &path).unwrap(), 5);
+        let lines = |lookup: Lookup| query(&path, &lookup).unwrap();
+        let gff_lines = GFF.lines().skip(1).collect::<Vec<_>>();
+
+        let region = |s: &str| Lookup::Region(s.parse().unwrap());
+        assert_eq!(lines(region("chr1:50-70")), gff_lines[..2].to_vec());
+        assert_eq!(
+            lines(region("chr1:81")),
+            vec![gff_lines[0], gff_lines[1], gff_lines[3]]
+        );
+        assert_eq!(lines(region("chr2")), vec![gff_lines[4]]);
+        assert!(lines(region("chr3")).is_empty());
+
+        let id = |id: &str, children| Lookup::Id {
+            id: id.to_string(),
+            children,
+        };
+        assert_eq!(lines(id("g1", false)), vec![gff_lines[0]]);
+        assert_eq!(lines(id("g1", true)), gff_lines[..4].to_vec());
+        assert_eq!(lines(id("exon_2", false)), vec![gff_lines[3]]);
+
+        let conn = Connection::open(&path).unwrap();
+        let notes: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM attributes WHERE key = 'Note'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(notes, 2);
+    }
+
+    #[test]
+    fn test_gtf_ids() {
+        let attrs = [("gene_id", "G1"), ("transcript_id", "T1")];
+        let mut numbers = HashMap::new();
+        assert_eq!(feature_id("transcript", &attrs, &mut numbers), "T1");
+        assert_eq!(feature_id("exon", &attrs, &mut numbers), "exon_1");
+        assert_eq!(feature_id("exon", &attrs, &mut numbers), "exon_2");
+        assert_eq!(parents("transcript", &attrs), vec!["G1"]);
+        assert_eq!(parents("exon", &attrs), vec!["T1"]);
+        assert!(parents("gene", &attrs).is_empty());
+    }
+
+    #[test]
+    fn test_region() {
+        assert_eq!(
+            "chr1:1,000-2,000".parse::<Region>(),
+            Ok(Region {
+                chrom: "chr1".to_string(),
+                start: 1000,
+                end: Some(2000),
+            })
+        );
+        assert_eq!("chrM".parse::<Region>().unwrap().end, None);
+        assert!("chr1:20-10".parse::<Region>().is_err());
+    }
+}
@@ -0,0 +1,285 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use crate::codec::decoder;
+use crate::error::{Gxf2BedError, Result};
+
+/// Annotation dialect of the input file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    Gtf,
+    Gff,
+}
+
+impl InputFormat {
+    /// Byte separating keys from values in the attribute column.
+    pub fn separator(&self) -> u8 {
+        match self {
+            InputFormat::Gtf => b' ',
+            InputFormat::Gff => b'=',
+        }
+    }
+
+    fn from_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "gtf" => Some(InputFormat::Gtf),
+            "gff" | "gff3" => Some(InputFormat::Gff),
+            _ => None,
+        }
+    }
+}
+
+/// Compression applied on top of the annotation file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    /// Codec name, matching the cargo feature that enables it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Bzip2 => "bzip2",
+        }
+    }
+
+    /// Valid `--compress-level` values for this codec.
+    pub fn levels(&self) -> Option<RangeInclusive<u32>> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip => Some(0..=9),
+            Compression::Zstd => Some(1..=22),
+            Compression::Bzip2 => Some(1..=9),
+        }
+    }
+
+    /// Maps a file extension (`gz`, `zst`, `bz2`) to its codec.
+    pub(crate) fn from_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "gz" => Some(Compression::Gzip),
+            "zst" => Some(Compression::Zstd),
+            "bz2" => Some(Compression::Bzip2),
+            _ => None,
+        }
+    }
+
+    fn from_magic(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&GZIP_MAGIC) {
+            Compression::Gzip
+        } else if bytes.starts_with(&ZSTD_MAGIC) {
+            Compression::Zstd
+        } else if bytes.starts_with(BZIP2_MAGIC) {
+            Compression::Bzip2
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Format and compression of an input file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputKind {
+    pub format: InputFormat,
+    pub compression: Compression,
+}
+
+/// Number of leading bytes inspected when sniffing content.
+const SNIFF_BYTES: usize = 64 * 1024;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const BZIP2_MAGIC: &[u8] = b"BZh";
+
+/// Detects the input kind of a file, from its extension (e.g. `.gtf`,
+/// `.gff3.gz`) or, when the extension is not recognized, from its content.
+pub fn detect_input_kind<P: AsRef<Path>>(path: P) -> Result<InputKind> {
+    let path = path.as_ref();
+    if let Some(kind) = kind_from_extension(path) {
+        return Ok(kind);
+    }
+
+    let unsupported =
+        || Gxf2BedError::UnsupportedFormat(format!("{:?} is not a GTF/GFF file", path));
+    let file = File::open(path).map_err(|_| unsupported())?;
+
+    detect_from_reader(&mut BufReader::with_capacity(SNIFF_BYTES, file)).map_err(|_| unsupported())
+}
+
+/// Sniffs format and compression from the start of `reader` without
+/// consuming any bytes, so the same reader can be handed to the converter.
+pub fn detect_from_reader<R: BufRead>(reader: &mut R) -> Result<InputKind> {
+    detect_from_bytes(reader.fill_buf()?)
+}
+
+/// Sniffs format and compression from the leading bytes of a file.
+pub fn detect_from_bytes(bytes: &[u8]) -> Result<InputKind> {
+    let compression = Compression::from_magic(bytes);
+    let text = decompress_prefix(bytes, compression)?;
+
+    sniff_format(&String::from_utf8_lossy(&text))
+        .map(|format| InputKind {
+            format,
+            compression,
+        })
+        .ok_or_else(|| {
+            Gxf2BedError::UnsupportedFormat("content does not look like GTF/GFF".to_string())
+        })
+}
+
+fn kind_from_extension(path: &Path) -> Option<InputKind> {
+    let (stem, compression) = match path
+        .extension()
+        .and_then(|s| s.to_str())
+        .and_then(Compression::from_extension)
+    {
+        Some(compression) => (Path::new(path.file_stem().unwrap_or_default()), compression),
+        None => (path, Compression::None),
+    };
+
+    stem.extension()
+        .and_then(|s| s.to_str())
+        .and_then(InputFormat::from_extension)
+        .map(|format| InputKind {
+            format,
+            compression,
+        })
+}
+
+/// Decompresses as much of a (possibly truncated) prefix as possible.
+fn decompress_prefix(bytes: &[u8], compression: Compression) -> Result<Vec<u8>> {
+    let mut out = vec![0; SNIFF_BYTES];
+    let mut decoder = decoder(bytes, compression)?;
+    let mut n = 0;
+
+    while n < out.len() {
+        match decoder.read(&mut out[n..]) {
+            Ok(0) | Err(_) => break,
+            Ok(k) => n += k,
+        }
+    }
+
+    out.truncate(n);
+    Ok(out)
+}
+
+/// Guesses the dialect from a `##gff-version` directive or from how the
+/// first attribute of each record separates its key from its value.
+fn sniff_format(text: &str) -> Option<InputFormat> {
+    for line in text.lines() {
+        if line.starts_with("##gff-version") {
+            return Some(InputFormat::Gff);
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+
+        // INFO: skips blank and truncated lines (the prefix may end mid-line)
+        let Some(attr) = line.split('\t').nth(8) else {
+            continue;
+        };
+        let first = attr.split(';').next().unwrap_or_default().trim();
+        match (first.find('='), first.find(' ')) {
+            (Some(eq), Some(space)) if eq < space => return Some(InputFormat::Gff),
+            (Some(_), None) => return Some(InputFormat::Gff),
+            (_, Some(_)) => return Some(InputFormat::Gtf),
+            _ => continue,
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_plain_and_gz() {
+        assert_eq!(
+            detect_input_kind("a.gtf").unwrap(),
+            InputKind {
+                format: InputFormat::Gtf,
+                compression: Compression::None
+            }
+        );
+        assert_eq!(
+            detect_input_kind("dir/a.gff3.gz").unwrap(),
+            InputKind {
+                format: InputFormat::Gff,
+                compression: Compression::Gzip
+            }
+        );
+    }
+
+    #[test]
+    fn test_detect_unsupported() {
+        assert!(detect_input_kind("a.bed").is_err());
+        assert!(detect_input_kind("a.gz").is_err());
+    }
+
+    #[test]
+    fn test_detect_from_bytes() {
+        let gtf = b"#!genome-build GRCh38\nchr1\tHAVANA\texon\t1\t10\t.\t+\t.\tgene_id \"A\"; transcript_id \"B\";\n";
+        let gff = b"chr1\t.\tmRNA\t1\t10\t.\t+\t.\tID=tx1;Name=A B\n";
+
+        assert_eq!(
+            detect_from_bytes(gtf).unwrap(),
+            InputKind {
+                format: InputFormat::Gtf,
+                compression: Compression::None
+            }
+        );
+        assert_eq!(detect_from_bytes(gff).unwrap().format, InputFormat::Gff);
+        assert_eq!(
+            detect_from_bytes(b"##gff-version 3\n").unwrap().format,
+            InputFormat::Gff
+        );
+        assert!(detect_from_bytes(b"chr1\t0\t10\n").is_err());
+    }
+
+    #[test]
+    fn test_detect_compressed_extensions() {
+        assert_eq!(
+            detect_input_kind("a.gtf.zst").unwrap().compression,
+            Compression::Zstd
+        );
+        assert_eq!(
+            detect_input_kind("a.gff.bz2").unwrap(),
+            InputKind {
+                format: InputFormat::Gff,
+                compression: Compression::Bzip2
+            }
+        );
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_detect_from_reader_gzip_without_consuming() {
+        use flate2::{write::GzEncoder, Compression as Level};
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Level::fast());
+        encoder
+            .write_all(b"chr1\t.\tgene\t1\t10\t.\t+\t.\tID=g1\n")
+            .unwrap();
+        let bytes = encoder.finish().unwrap();
+
+        let mut reader = BufReader::new(&bytes[..]);
+        assert_eq!(
+            detect_from_reader(&mut reader).unwrap(),
+            InputKind {
+                format: InputFormat::Gff,
+                compression: Compression::Gzip
+            }
+        );
+        assert_eq!(reader.fill_buf().unwrap().len(), bytes.len());
+    }
+}
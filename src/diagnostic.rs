@@ -0,0 +1,171 @@
+use std::fmt;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use colored::Colorize;
+
+/// How a tab is drawn, so underlines stay under their column.
+const TAB: &str = "    ";
+
+/// Where in the input an error lies, with the offending line for context;
+/// displayed as a compiler-style snippet:
+///
+/// ```text
+///  --> a.gtf:3:13
+///   |
+/// 3 | chr1    .    exon    one    10    .    +    .    transcript_id "A";
+///   |                      ^^^
+///   |
+///   = help: start and end must be whole numbers
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    /// Input file, when the reader knows it (see
+    /// [`crate::Gxf2BedError::in_file`]).
+    pub file: Option<PathBuf>,
+    /// 1-based line number.
+    pub line: usize,
+    /// The offending line, without its terminator.
+    pub text: String,
+    /// Bytes of `text` at fault; empty to point past its end.
+    pub range: Range<usize>,
+    /// How to fix it.
+    pub hint: Option<String>,
+}
+
+impl Span {
+    /// Span of feature line `line` that failed to parse with `message`
+    /// (as returned by [`crate::gxf::GxfRecord::parse`]): the column it
+    /// names, or the line end when columns are missing.
+    pub fn record(line: usize, text: &str, message: &str) -> Self {
+        let text = text.trim_end_matches(['\n', '\r']);
+        let (column, hint) = match message {
+            "Invalid start" | "Invalid end" => (
+                Some(if message == "Invalid start" { 3 } else { 4 }),
+                "start and end must be whole numbers",
+            ),
+            "Start before the first base" => (
+                Some(3),
+                "starts are 1-based; use --input-coords zero for 0-based input",
+            ),
+            _ => (
+                None,
+                "feature lines have 9 tab-separated columns; are some separated by spaces?",
+            ),
+        };
+        let range = column
+            .and_then(|column| field(text, column))
+            .unwrap_or(text.len()..text.len());
+
+        Self {
+            file: None,
+            line,
+            text: text.to_string(),
+            range,
+            hint: Some(hint.to_string()),
+        }
+    }
+}
+
+/// Byte range of tab-separated field `column` (0-based) of `text`.
+fn field(text: &str, column: usize) -> Option<Range<usize>> {
+    let mut start = 0;
+    for (idx, value) in text.split('\t').enumerate() {
+        if idx == column {
+            return Some(start..start + value.len());
+        }
+        start += value.len() + 1;
+    }
+    None
+}
+
+/// Display width of `text` with tabs drawn as [`TAB`].
+fn width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if c == '\t' { TAB.len() } else { 1 })
+        .sum()
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let start = self.range.start.min(self.text.len());
+        let end = self.range.end.clamp(start, self.text.len());
+        let (Some(before), Some(marked)) = (self.text.get(..start), self.text.get(start..end))
+        else {
+            return Ok(());
+        };
+
+        let number = self.line.to_string();
+        let pad = " ".repeat(number.len());
+        let bar = "|".blue().bold();
+        let file = self
+            .file
+            .as_ref()
+            .map_or_else(|| "<input>".to_string(), |file| file.display().to_string());
+
+        writeln!(
+            f,
+            "{}{} {}:{}:{}",
+            pad,
+            "-->".blue().bold(),
+            file,
+            self.line,
+            before.chars().count() + 1
+        )?;
+        writeln!(f, "{} {}", pad, bar)?;
+        writeln!(
+            f,
+            "{} {} {}",
+            number.blue().bold(),
+            bar,
+            self.text.replace('\t', TAB)
+        )?;
+        write!(
+            f,
+            "{} {} {}{}",
+            pad,
+            bar,
+            " ".repeat(width(before)),
+            "^".repeat(width(marked).max(1)).red().bold()
+        )?;
+        if let Some(hint) = &self.hint {
+            write!(
+                f,
+                "\n{} {}\n{} {} {}",
+                pad,
+                bar,
+                pad,
+                "= help:".bold(),
+                hint
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_record() {
+        colored::control::set_override(false);
+        let mut span = Span::record(3, "chr1\t.\texon\tone\t10\n", "Invalid start");
+        span.file = Some(PathBuf::from("a.gtf"));
+        assert_eq!(span.range, 12..15);
+        assert_eq!(
+            span.to_string(),
+            " --> a.gtf:3:13\n  \
+             |\n\
+             3 | chr1    .    exon    one    10\n  \
+             |                      ^^^\n  \
+             |\n  \
+             = help: start and end must be whole numbers"
+        );
+
+        let span = Span::record(1, "chr1 . exon", "Missing source");
+        assert_eq!(span.range, 11..11);
+        assert!(span.to_string().contains("<input>:1:12"));
+    }
+}
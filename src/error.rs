@@ -0,0 +1,129 @@
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::cli::ArgError;
+use crate::config::ConfigError;
+use crate::diagnostic::Span;
+
+pub type Result<T> = std::result::Result<T, Gxf2BedError>;
+
+#[derive(Debug, Error)]
+pub enum Gxf2BedError {
+    /// Reading the input or writing the output failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The configuration has invalid option combinations.
+    #[error("Invalid configuration: {0}")]
+    Config(#[from] ConfigError),
+
+    /// A command-line argument is invalid or names an unusable file.
+    #[error(transparent)]
+    Args(#[from] ArgError),
+
+    /// A config file could not be parsed.
+    #[error("Invalid config file: {0}")]
+    ConfigFile(String),
+
+    /// The input is not a GTF/GFF file.
+    #[error("Unsupported format: {0}")]
+    UnsupportedFormat(String),
+
+    /// An input line could not be parsed; `span` shows it in context when
+    /// the reader kept it.
+    #[error("Parse error: line {line}: {message}")]
+    Parse {
+        line: usize,
+        message: String,
+        span: Option<Box<Span>>,
+    },
+
+    /// A compressed input ends early; `records` complete lines precede the cut.
+    #[error("Truncated input: {message}; {records} records were read before the cut (use --allow-truncated to keep them)")]
+    Truncated { records: usize, message: String },
+
+    /// A grouped record cannot be represented as BED.
+    #[error("Invalid record: {0}")]
+    InvalidRecord(String),
+
+    /// An annotation database could not be written or queried.
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+impl Gxf2BedError {
+    /// Source context of a parse error, if known.
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            Gxf2BedError::Parse { span, .. } => span.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Parse error of feature line `line`, `text`, that failed with
+    /// `message`, pointing at the offending column (see [`Span::record`]).
+    pub(crate) fn record(line: usize, text: &str, message: &str) -> Self {
+        Gxf2BedError::Parse {
+            line,
+            message: message.to_string(),
+            span: Some(Box::new(Span::record(line, text, message))),
+        }
+    }
+
+    /// Names `path` as the file of a parse error whose reader did not know it.
+    pub fn in_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        if let Gxf2BedError::Parse {
+            span: Some(span), ..
+        } = &mut self
+        {
+            span.file.get_or_insert_with(|| path.as_ref().to_path_buf());
+        }
+        self
+    }
+
+    /// Stable process exit code for this error class, so wrapper scripts can
+    /// branch on the failure type:
+    ///
+    /// | code | class                              |
+    /// |------|------------------------------------|
+    /// | 2    | bad arguments or configuration     |
+    /// | 3    | unsupported input format           |
+    /// | 4    | unparsable line or invalid record  |
+    /// | 5    | I/O or database failure            |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Gxf2BedError::Args(err) => err.exit_code(),
+            Gxf2BedError::Config(_) | Gxf2BedError::ConfigFile(_) => 2,
+            Gxf2BedError::UnsupportedFormat(_) => 3,
+            Gxf2BedError::Parse { .. } | Gxf2BedError::InvalidRecord(_) => 4,
+            Gxf2BedError::Io(_) | Gxf2BedError::Truncated { .. } | Gxf2BedError::Database(_) => 5,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        let io = Gxf2BedError::from(std::io::Error::other("disk full"));
+        let parse = Gxf2BedError::Parse {
+            line: 3,
+            message: "bad".to_string(),
+            span: None,
+        };
+
+        assert_eq!(io.exit_code(), 5);
+        assert_eq!(parse.exit_code(), 4);
+        assert_eq!(
+            Gxf2BedError::UnsupportedFormat(String::new()).exit_code(),
+            3
+        );
+        let missing = ArgError::UnreadableInput("file \"a.gtf\" does not exist".to_string());
+        assert_eq!(Gxf2BedError::from(missing).exit_code(), 5);
+        let unnamed = ArgError::InvalidInput("no input file given".to_string());
+        assert_eq!(Gxf2BedError::from(unnamed).exit_code(), 2);
+    }
+}
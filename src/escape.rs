@@ -0,0 +1,106 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::bed::BedRecord;
+
+/// How whitespace in attribute-derived values (record names, attribute and
+/// computed columns) is written, since tabs and newlines break the TSV and
+/// spaces break many BED consumers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Escape {
+    /// Write values as they are; affected records are only counted.
+    #[default]
+    Keep,
+    /// Replace each tab, newline, carriage return or space with `_`.
+    Replace,
+    /// Percent-encode them (`%09`, `%0A`, `%0D`, `%20`), `%` itself included.
+    Url,
+    /// Wrap the value in double quotes, backslash-escaping quotes,
+    /// backslashes, tabs (`\t`), newlines (`\n`) and carriage returns (`\r`).
+    Quote,
+}
+
+/// Characters that need escaping.
+fn is_special(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | ' ')
+}
+
+impl Escape {
+    /// `value` escaped, or `None` when it holds nothing to escape.
+    pub fn escape(&self, value: &str) -> Option<String> {
+        if !value.contains(is_special) {
+            return None;
+        }
+
+        let mut out = String::with_capacity(value.len() + 8);
+        match self {
+            Escape::Keep => out.push_str(value),
+            Escape::Replace => out.extend(value.chars().map(|c| match is_special(c) {
+                true => '_',
+                false => c,
+            })),
+            Escape::Url => {
+                for c in value.chars() {
+                    match c {
+                        '%' => out.push_str("%25"),
+                        c if is_special(c) => out.push_str(&format!("%{:02X}", c as u8)),
+                        c => out.push(c),
+                    }
+                }
+            }
+            Escape::Quote => {
+                out.push('"');
+                for c in value.chars() {
+                    match c {
+                        '"' => out.push_str("\\\""),
+                        '\\' => out.push_str("\\\\"),
+                        '\t' => out.push_str("\\t"),
+                        '\n' => out.push_str("\\n"),
+                        '\r' => out.push_str("\\r"),
+                        c => out.push(c),
+                    }
+                }
+                out.push('"');
+            }
+        }
+        Some(out)
+    }
+
+    /// Escapes the name and extra columns of `record`; returns whether any
+    /// value needed it.
+    pub(crate) fn apply(&self, record: &mut BedRecord) -> bool {
+        let mut found = false;
+        for value in std::iter::once(&mut record.name).chain(&mut record.extras) {
+            if let Some(escaped) = self.escape(value) {
+                found = true;
+                *value = escaped;
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        let value = "50% of\tX \"a\"";
+        assert_eq!(Escape::Keep.escape(value).as_deref(), Some(value));
+        assert_eq!(
+            Escape::Replace.escape(value).as_deref(),
+            Some("50%_of_X_\"a\"")
+        );
+        assert_eq!(
+            Escape::Url.escape(value).as_deref(),
+            Some("50%25%20of%09X%20\"a\"")
+        );
+        assert_eq!(
+            Escape::Quote.escape(value).as_deref(),
+            Some(r#""50% of\tX \"a\"""#)
+        );
+        assert_eq!(Escape::Quote.escape("ENST0001.2"), None);
+    }
+}
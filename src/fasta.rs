@@ -0,0 +1,184 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use hashbrown::HashMap;
+
+use crate::codec::decoder;
+use crate::detect::Compression;
+use crate::error::Result;
+use crate::gxf::{Coding, Strand};
+
+const START: &[u8] = b"ATG";
+const STOPS: [&[u8]; 3] = [b"TAA", b"TAG", b"TGA"];
+
+/// Genome sequences by name, as read from a FASTA file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Genome(HashMap<String, Vec<u8>>);
+
+impl Genome {
+    /// Reads a FASTA file, gzip/zstd/bzip2-compressed by extension.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let compression = path
+            .extension()
+            .and_then(|ext| Compression::from_extension(&ext.to_string_lossy()))
+            .unwrap_or(Compression::None);
+        let reader = File::open(path)
+            .and_then(|file| decoder(file, compression))
+            .map_err(|e| std::io::Error::new(e.kind(), format!("{:?}: {}", path, e)))?;
+
+        Self::from_reader(BufReader::new(reader))
+    }
+
+    /// Reads FASTA records, named by the first word of their `>` line;
+    /// bases are upper-cased so soft-masked sequence compares the same.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self> {
+        let mut seqs = HashMap::new();
+        let mut current: Option<(String, Vec<u8>)> = None;
+        for line in reader.split(b'\n') {
+            let line = line?;
+            let line = line.strip_suffix(b"\r").unwrap_or(&line);
+            match line.strip_prefix(b">") {
+                Some(header) => {
+                    seqs.extend(current.take());
+                    let header = String::from_utf8_lossy(header);
+                    let name = header.split_whitespace().next().unwrap_or_default();
+                    current = Some((name.to_string(), Vec::new()));
+                }
+                None => {
+                    if let Some((_, seq)) = &mut current {
+                        seq.extend(line.iter().map(u8::to_ascii_uppercase));
+                    }
+                }
+            }
+        }
+        seqs.extend(current);
+
+        Ok(Self(seqs))
+    }
+
+    /// Bases `start..end` (0-based, half-open) of `chrom`.
+    pub fn get(&self, chrom: &str, start: u64, end: u64) -> Option<&[u8]> {
+        self.0.get(chrom)?.get(start as usize..end as usize)
+    }
+}
+
+/// Why a coding model's CDS is not a clean open reading frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrfError {
+    /// The CDS reaches past the sequence, or its chromosome is not in the
+    /// FASTA file.
+    NoSequence,
+    /// The first codon is not ATG.
+    NoStart,
+    /// The last codon is not a stop codon.
+    NoStop,
+    /// A stop codon before the last one, by codon number from 1.
+    InternalStop(usize),
+}
+
+impl fmt::Display for OrfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OrfError::NoSequence => write!(f, "CDS not covered by the FASTA sequences"),
+            OrfError::NoStart => write!(f, "CDS does not start with ATG"),
+            OrfError::NoStop => write!(f, "CDS does not end with a stop codon"),
+            OrfError::InternalStop(codon) => write!(f, "internal stop codon at codon {}", codon),
+        }
+    }
+}
+
+/// Checks that the CDS of `coding` on `chrom` (its stop codon included
+/// when it lies outside, as in GTF, and its 5' phase skipped) starts with
+/// ATG, ends with a stop codon and has no stop codon before that.
+pub fn check_orf(
+    genome: &Genome,
+    chrom: &str,
+    strand: Strand,
+    coding: &Coding,
+) -> std::result::Result<(), OrfError> {
+    let mut spans = coding
+        .cds
+        .iter()
+        .map(|(start, size)| (*start, start + size))
+        .collect::<Vec<_>>();
+    if let Some((start, end)) = coding.stop_codon {
+        if !spans.iter().any(|(s, e)| start < *e && *s < end) {
+            spans.push((start, end));
+            spans.sort_unstable();
+        }
+    }
+
+    let mut seq = Vec::new();
+    for (start, end) in spans {
+        seq.extend_from_slice(genome.get(chrom, start, end).ok_or(OrfError::NoSequence)?);
+    }
+    if strand == Strand::Reverse {
+        seq = reverse_complement(&seq);
+    }
+
+    let seq = seq.get(coding.phase(strand) as usize..).unwrap_or_default();
+    let codons = seq.chunks_exact(3).collect::<Vec<_>>();
+    if codons.first() != Some(&START) {
+        return Err(OrfError::NoStart);
+    }
+    let (last, inner) = codons.split_last().ok_or(OrfError::NoStop)?;
+    if let Some(codon) = inner.iter().position(|codon| STOPS.contains(codon)) {
+        return Err(OrfError::InternalStop(codon + 1));
+    }
+    if seq.len() % 3 != 0 || !STOPS.contains(last) {
+        return Err(OrfError::NoStop);
+    }
+
+    Ok(())
+}
+
+fn reverse_complement(seq: &[u8]) -> Vec<u8> {
+    seq.iter()
+        .rev()
+        .map(|base| match base {
+            b'A' => b'T',
+            b'C' => b'G',
+            b'G' => b'C',
+            b'T' => b'A',
+            other => *other,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_orf() {
+        // INFO: 0-based; ATG at 2, exon break after "ATGAA", TAA at 15
+        let fasta = ">chr1 test\nccATGAAggggGCCTTAAcc\n>chrM\nACGT\n";
+        let genome = Genome::from_reader(fasta.as_bytes()).unwrap();
+        assert_eq!(genome.get("chr1", 2, 5), Some(&b"ATG"[..]));
+
+        let mut coding = Coding::default();
+        coding.add_cds(2, 7, "0");
+        coding.add_cds(11, 15, "1");
+        coding.add_stop_codon(15, 18);
+        assert_eq!(check_orf(&genome, "chr1", Strand::Forward, &coding), Ok(()));
+        assert_eq!(
+            check_orf(&genome, "chr2", Strand::Forward, &coding),
+            Err(OrfError::NoSequence)
+        );
+        assert_eq!(
+            check_orf(&genome, "chr1", Strand::Reverse, &coding),
+            Err(OrfError::NoStart)
+        );
+
+        let mut coding = Coding::default();
+        coding.add_cds(2, 14, "0");
+        let genome = Genome::from_reader(&b">chr1\nccATGTAAGCCTAAcc\n"[..]).unwrap();
+        assert_eq!(
+            check_orf(&genome, "chr1", Strand::Forward, &coding),
+            Err(OrfError::InternalStop(2))
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::error::Result;
+
+/// Extension appended to the output path for the settings sidecar.
+const SIDECAR_EXT: &str = "gxf2bed";
+
+/// Path of the sidecar recording the settings an output was produced with
+/// (`out.bed` -> `out.bed.gxf2bed`).
+pub fn sidecar_path(config: &Config) -> PathBuf {
+    let mut path = config.output_path().into_os_string();
+    path.push(".");
+    path.push(SIDECAR_EXT);
+    path.into()
+}
+
+/// Whether `config.output` can be reused: it must be newer than the input
+/// and, if a sidecar exists, have been produced with the same settings.
+pub fn is_up_to_date(config: &Config) -> bool {
+    let modified = |path: PathBuf| fs::metadata(path).and_then(|m| m.modified()).ok();
+
+    let (Some(input), Some(output)) = (
+        modified(config.input.clone()),
+        modified(config.output_path()),
+    ) else {
+        return false;
+    };
+    if output < input {
+        return false;
+    }
+
+    match fs::read_to_string(sidecar_path(config)) {
+        Ok(recorded) => recorded.trim() == fingerprint(config),
+        Err(_) => true,
+    }
+}
+
+/// Records the settings used to produce `config.output` next to it.
+pub fn write_sidecar(config: &Config) -> Result<()> {
+    fs::write(sidecar_path(config), format!("{}\n", fingerprint(config)))?;
+    Ok(())
+}
+
+/// Stable hash of every setting that affects the output bytes.
+fn fingerprint(config: &Config) -> String {
+    let mut settings = config.clone();
+    // INFO: thread count, I/O strategy, profiling, retries and paths do not change what is written
+    settings.threads = 0;
+    settings.io_strategy = Default::default();
+    settings.profile = false;
+    settings.retries = 0;
+    settings.input = PathBuf::new();
+    settings.output = PathBuf::new();
+    let json = serde_json::to_string(&settings).expect("ERROR: Config is always serializable");
+
+    // INFO: FNV-1a, so the hash does not change across Rust releases
+    let hash = json.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_up_to_date() {
+        let dir = tempfile::tempdir().unwrap();
+        let (input, output) = (dir.path().join("fresh.gtf"), dir.path().join("fresh.bed"));
+        let mut config = Config::new(&input, &output);
+
+        fs::write(&input, "").unwrap();
+        assert!(!is_up_to_date(&config));
+
+        fs::write(&output, "").unwrap();
+        assert!(is_up_to_date(&config));
+
+        write_sidecar(&config).unwrap();
+        config.threads += 1;
+        assert!(is_up_to_date(&config));
+        config.parent = "mRNA".to_string();
+        assert!(!is_up_to_date(&config));
+    }
+}
@@ -1,7 +1,15 @@
 mod attr;
+mod exons;
+mod keys;
+mod scan;
 pub use attr::*;
+pub use exons::Exons;
+pub use keys::KeyMatcher;
+pub(crate) use scan::{header_comments, is_comment, until_fasta, FASTA_DIRECTIVE};
+pub use scan::{line_chunks, lines, Lines};
 
-use std::collections::BTreeSet;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, PartialEq)]
 pub struct GxfRecord<'a> {
@@ -14,6 +22,103 @@ pub struct GxfRecord<'a> {
     pub attr: Attribute<'a>,
 }
 
+/// Coordinate convention of the input's start column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputCoords {
+    /// 0-based starts, as written by some in-house "GFF-like" tools.
+    Zero,
+    /// 1-based starts, as the GTF/GFF specifications require.
+    #[default]
+    One,
+}
+
+impl InputCoords {
+    /// Amount subtracted from input starts to make them 0-based.
+    pub fn offset(&self) -> u64 {
+        match self {
+            InputCoords::Zero => 0,
+            InputCoords::One => 1,
+        }
+    }
+}
+
+/// What to do with a model whose child blocks reach outside its parent's
+/// span (common in RefSeq).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutOfBounds {
+    /// Grow the record span to cover every block.
+    #[default]
+    Expand,
+    /// Trim blocks to the span; blocks entirely outside it are removed.
+    Clip,
+    /// Skip the record.
+    Drop,
+    /// Fail the conversion.
+    Error,
+}
+
+/// What to do with a model that has a zero- or negative-length feature
+/// (`end <= start` once converted to 0-based).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Degenerate {
+    /// Skip the record.
+    Drop,
+    /// Widen each empty feature to the single base at its start.
+    KeepPoint,
+    /// Fail the conversion.
+    #[default]
+    Error,
+}
+
+/// What to do with models whose ID is shared by features on different
+/// chromosomes or strands, which would otherwise merge into one record
+/// spanning both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MixedLoci {
+    /// Write one record per chromosome and strand, named
+    /// `<id>_<chrom>_<strand>`.
+    #[default]
+    Split,
+    /// Skip every record of the ID.
+    Drop,
+    /// Fail the conversion.
+    Error,
+}
+
+/// What to do with the features of an ID on one chromosome that disagree
+/// on the strand (annotation errors, trans-spliced genes).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MixedStrand {
+    /// Keep each strand apart, as another locus (see [`MixedLoci`]).
+    #[default]
+    Split,
+    /// Write one record on the strand of most lines, the parent's on a tie.
+    Majority,
+    /// Skip the record.
+    Drop,
+    /// Fail the conversion.
+    Error,
+}
+
+/// What to do with a model whose grouping key is used by more than one
+/// parent line (e.g. a reused GFF3 `ID=`), which merges unrelated features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateIds {
+    /// Keep the record, counted and logged.
+    #[default]
+    Warn,
+    /// Skip the record.
+    Drop,
+    /// Fail the conversion.
+    Error,
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Strand {
     Forward,
@@ -44,8 +149,152 @@ pub struct GenePred {
     pub start: u64,
     pub end: u64,
     pub strand: Strand,
-    pub exons: BTreeSet<(u64, u64)>,
+    pub exons: Exons,
     pub record_type: RecordType,
+    /// Values of the attributes listed by `Config::captured_attrs`, taken
+    /// from the parent line (or the first child without one); empty when
+    /// none are asked for.
+    pub attrs: Vec<Option<String>>,
+    /// CDS and stop codon features, when tracked (`Config::compat`).
+    pub coding: Option<Box<Coding>>,
+    /// `exon_number` attributes of child features as `(start, number)`,
+    /// when tracked (`Config::exon_ranks`).
+    pub exon_numbers: Vec<(u64, u32)>,
+    /// Parent lines seen for this key after the first; reused IDs.
+    pub duplicates: u32,
+    /// Chromosome/strand loci sharing this model's ID, this one included,
+    /// when there is more than one; 0 otherwise.
+    pub loci: u32,
+    /// Parent and child lines seen on the forward and reverse strand; both
+    /// are non-zero only when strands share a key (`Config::mixed_strand`).
+    pub strands: [u32; 2],
+}
+
+/// Coding features of a model: CDS blocks, the start and stop codons and
+/// the frames needed to tell where the reading frame starts.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coding {
+    /// CDS blocks as `(start, size)`.
+    pub cds: Exons,
+    /// Span covered by `stop_codon` features (outside the CDS in GTF).
+    pub stop_codon: Option<(u64, u64)>,
+    /// Span covered by `start_codon` features.
+    pub start_codon: Option<(u64, u64)>,
+    /// Frames of the lowest and highest CDS blocks, with their start/end.
+    pub(crate) first_frame: Option<(u64, u8)>,
+    pub(crate) last_frame: Option<(u64, u8)>,
+}
+
+impl Coding {
+    /// Adds a CDS block with its frame column (`.` reads as 0).
+    pub fn add_cds(&mut self, start: u64, end: u64, frame: &str) {
+        let frame = frame.parse().unwrap_or(0);
+        self.cds.insert((start, end.saturating_sub(start)));
+        self.add_frames(Some((start, frame)), Some((end, frame)));
+    }
+
+    /// Keeps the frames of the lowest-starting and highest-ending blocks.
+    fn add_frames(&mut self, first: Option<(u64, u8)>, last: Option<(u64, u8)>) {
+        if let Some((start, frame)) = first {
+            if self.first_frame.is_none_or(|(own, _)| start < own) {
+                self.first_frame = Some((start, frame));
+            }
+        }
+        if let Some((end, frame)) = last {
+            if self.last_frame.is_none_or(|(own, _)| end > own) {
+                self.last_frame = Some((end, frame));
+            }
+        }
+    }
+
+    pub fn add_stop_codon(&mut self, start: u64, end: u64) {
+        self.stop_codon = Some(match self.stop_codon {
+            Some((s, e)) => (s.min(start), e.max(end)),
+            None => (start, end),
+        });
+    }
+
+    pub fn add_start_codon(&mut self, start: u64, end: u64) {
+        self.start_codon = Some(match self.start_codon {
+            Some((s, e)) => (s.min(start), e.max(end)),
+            None => (start, end),
+        });
+    }
+
+    pub fn merge(&mut self, other: Coding) {
+        self.cds.extend(other.cds);
+        if let Some((start, end)) = other.stop_codon {
+            self.add_stop_codon(start, end);
+        }
+        if let Some((start, end)) = other.start_codon {
+            self.add_start_codon(start, end);
+        }
+        self.add_frames(other.first_frame, other.last_frame);
+    }
+
+    /// CDS span, extended over the stop codon; `None` for non-coding models.
+    pub fn thick(&self) -> Option<(u64, u64)> {
+        let (first, last) = (self.cds.first()?, self.cds.last()?);
+        let (mut start, mut end) = (first.0, last.0 + last.1);
+        if let Some((stop_start, stop_end)) = self.stop_codon {
+            start = start.min(stop_start);
+            end = end.max(stop_end);
+        }
+        Some((start, end))
+    }
+
+    /// Whether the CDS, with the stop codon when it lies outside it (GTF) and
+    /// without the 5' phase, is a whole number of codons.
+    pub fn in_frame(&self, strand: Strand) -> bool {
+        let mut len = self
+            .cds
+            .iter()
+            .fold(0u64, |len, (_, size)| len.saturating_add(*size));
+        if let Some((start, end)) = self.stop_codon {
+            if !self
+                .cds
+                .iter()
+                .any(|(s, size)| start < s + size && *s < end)
+            {
+                len = len.saturating_add(end.saturating_sub(start));
+            }
+        }
+
+        len.saturating_sub(self.phase(strand) as u64) % 3 == 0
+    }
+
+    /// Whether the start codon begins the CDS and the stop codon ends it
+    /// (right past it in GTF, within it in GFF3) on `strand`; `false` when
+    /// either is missing.
+    pub fn codons_at_cds_ends(&self, strand: Strand) -> bool {
+        let (Some(first), Some(last)) = (self.cds.first(), self.cds.last()) else {
+            return false;
+        };
+        let (Some(start), Some(stop)) = (self.start_codon, self.stop_codon) else {
+            return false;
+        };
+
+        let (cds_start, cds_end) = (first.0, last.0 + last.1);
+        match strand {
+            Strand::Reverse => start.1 == cds_end && (stop.1 == cds_start || stop.0 == cds_start),
+            _ => start.0 == cds_start && (stop.0 == cds_end || stop.1 == cds_end),
+        }
+    }
+
+    /// Frame of the 5'-most CDS block on `strand`.
+    pub fn phase(&self, strand: Strand) -> u8 {
+        let frame = match strand {
+            Strand::Reverse => self.last_frame,
+            _ => self.first_frame,
+        };
+        frame.map_or(0, |(_, frame)| frame)
+    }
+}
+
+impl Default for GenePred {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GenePred {
@@ -55,56 +304,234 @@ impl GenePred {
             start: 0,
             end: 0,
             strand: Strand::Unknown,
-            exons: BTreeSet::new(),
+            exons: Exons::new(),
             record_type: RecordType::Unknown,
+            attrs: Vec::new(),
+            coding: None,
+            exon_numbers: Vec::new(),
+            duplicates: 0,
+            loci: 0,
+            strands: [0; 2],
+        }
+    }
+
+    /// Coding features, created on first use.
+    pub fn coding_mut(&mut self) -> &mut Coding {
+        self.coding.get_or_insert_with(Default::default)
+    }
+
+    /// Records a parent feature, which defines the span of the model.
+    pub fn set_parent(&mut self, chr: &str, start: u64, end: u64, strand: Strand) {
+        if self.record_type == RecordType::Parent {
+            self.duplicates += 1;
+        }
+        self.chr = chr.to_owned();
+        self.start = start;
+        self.end = end;
+        self.strand = strand;
+        self.record_type = RecordType::Parent;
+        self.vote(strand);
+    }
+
+    /// Adds a child feature as a block of the model.
+    pub fn add_child(&mut self, chr: &str, start: u64, end: u64, strand: Strand) {
+        self.chr = chr.to_owned();
+        // INFO: the parent's strand breaks ties under --mixed-strand majority
+        if self.record_type != RecordType::Parent {
+            self.strand = strand;
         }
+        self.vote(strand);
+        self.cover(start, end);
+        // INFO: end < start is kept as an empty block, see `is_degenerate`
+        self.exons.insert((start, end.saturating_sub(start)));
     }
 
-    pub fn merge(&mut self, query: GenePred) {
+    pub fn merge(&mut self, mut query: GenePred) {
+        if let Some(coding) = query.coding.take() {
+            match &mut self.coding {
+                Some(own) => own.merge(*coding),
+                None => self.coding = Some(coding),
+            }
+        }
+        self.exon_numbers.append(&mut query.exon_numbers);
+        self.duplicates += query.duplicates;
+        self.strands[0] += query.strands[0];
+        self.strands[1] += query.strands[1];
+
         match query.record_type {
             RecordType::Parent => {
+                if self.record_type == RecordType::Parent {
+                    self.duplicates += 1;
+                }
                 self.chr = query.chr;
                 self.start = query.start;
                 self.end = query.end;
                 self.strand = query.strand;
                 self.record_type = RecordType::Parent;
                 self.exons.extend(query.exons);
+                if !query.attrs.is_empty() {
+                    self.attrs = query.attrs;
+                }
             }
             RecordType::Child => {
                 // INFO: covers empty cases in reducing step!
                 if self.chr.is_empty() {
                     self.chr = query.chr;
                     self.strand = query.strand;
-                    self.end = self.end.max(query.end);
-                }
-
-                if self.start < 1 && query.start > 0 {
-                    self.start = query.start;
-                } else if self.start > 0 && query.start > 0 {
-                    self.start = self.start.min(query.start);
                 }
+                self.cover(query.start, query.end);
 
                 self.exons.extend(query.exons);
-                if self.record_type != RecordType::Parent {
-                    self.record_type = RecordType::Child;
+                if self.attrs.is_empty() {
+                    self.attrs = query.attrs;
                 }
             }
+            // INFO: no span, only coding features (merged above) and where they lie
             RecordType::Unknown => {
-                if self.chr.is_empty() && !query.chr.is_empty() {
+                if self.chr.is_empty() {
                     self.chr = query.chr;
                     self.strand = query.strand;
-                    self.end = self.end.max(query.end);
                 }
+            }
+        }
+    }
 
-                if self.start < 1 && query.start > 0 {
-                    self.start = query.start;
-                } else if self.start > 0 && query.start > 0 {
-                    self.start = self.start.min(query.start);
-                }
+    /// Uses the CDS blocks, extended over a stop codon outside them (GTF),
+    /// as the blocks of a model without children, for CDS-only annotations,
+    /// and their extent as its span; returns whether any were added.
+    pub fn exons_from_cds(&mut self) -> bool {
+        let Some(coding) = self.coding.as_ref().filter(|_| self.exons.is_empty()) else {
+            return false;
+        };
+        let mut blocks = coding
+            .cds
+            .iter()
+            .map(|(start, size)| (*start, start + size))
+            .collect::<Vec<_>>();
+        if blocks.is_empty() {
+            return false;
+        }
+        // INFO: a stop codon split by an intron would bridge it, so only whole codons count
+        blocks.extend(coding.stop_codon.filter(|(start, end)| end - start <= 3));
+
+        // INFO: the span is the blocks' extent, a parent's included, or BED12 would break
+        self.start = blocks
+            .iter()
+            .map(|(start, _)| *start)
+            .min()
+            .unwrap_or_default();
+        self.end = blocks.iter().map(|(_, end)| *end).max().unwrap_or_default();
+        if self.record_type == RecordType::Unknown {
+            self.record_type = RecordType::Child;
+        }
+        for (start, end) in blocks {
+            self.exons.insert((start, end - start));
+        }
+        self.exons.merge_overlapping();
+        true
+    }
+
+    /// Counts a line on `strand`.
+    fn vote(&mut self, strand: Strand) {
+        match strand {
+            Strand::Forward => self.strands[0] += 1,
+            Strand::Reverse => self.strands[1] += 1,
+            Strand::Unknown => (),
+        }
+    }
+
+    /// Whether lines of the model were seen on both strands.
+    pub fn has_mixed_strands(&self) -> bool {
+        self.strands.iter().all(|count| *count > 0)
+    }
+
+    /// Moves the model to the strand of most lines, keeping its own on a
+    /// tie.
+    pub fn take_majority_strand(&mut self) {
+        let [forward, reverse] = self.strands;
+        if forward > reverse {
+            self.strand = Strand::Forward;
+        } else if reverse > forward {
+            self.strand = Strand::Reverse;
+        }
+    }
+
+    /// Grows a child-built span over `start..end`; a parent's span is kept
+    /// as given, so blocks outside it can be told apart.
+    fn cover(&mut self, start: u64, end: u64) {
+        match self.record_type {
+            RecordType::Parent => (),
+            RecordType::Child => {
+                self.start = self.start.min(start);
+                self.end = self.end.max(end);
+            }
+            RecordType::Unknown => {
+                self.start = start;
+                self.end = end;
+                self.record_type = RecordType::Child;
             }
         }
     }
 
+    /// Whether the span or a block is empty (or was given with end < start).
+    pub fn is_degenerate(&self) -> bool {
+        self.start >= self.end || self.exons.iter().any(|(_, size)| *size == 0)
+    }
+
+    /// Widens an empty span and empty blocks to the single base at their start.
+    pub fn widen_points(&mut self) {
+        if self.start >= self.end {
+            self.end = self.start.saturating_add(1);
+        }
+        self.exons = self
+            .exons
+            .iter()
+            .map(|(start, size)| (*start, (*size).max(1)))
+            .collect();
+    }
+
+    /// Whether some block starts before or ends after the model span.
+    pub fn has_blocks_outside(&self) -> bool {
+        self.exons
+            .iter()
+            .any(|(start, size)| *start < self.start || start + size > self.end)
+    }
+
+    /// Whether some CDS block is not within a single block of the model.
+    pub fn has_cds_outside_exons(&self) -> bool {
+        let Some(coding) = &self.coding else {
+            return false;
+        };
+
+        coding.cds.iter().any(|(start, size)| {
+            !self
+                .exons
+                .iter()
+                .any(|(s, len)| s <= start && start + size <= s + len)
+        })
+    }
+
+    /// Grows the span to cover every block.
+    pub fn expand_to_blocks(&mut self) {
+        for (start, size) in &self.exons {
+            self.start = self.start.min(*start);
+            self.end = self.end.max(start + size);
+        }
+    }
+
+    /// Trims blocks to the span, removing those left empty.
+    pub fn clip_to_span(&mut self) {
+        let (lo, hi) = (self.start, self.end);
+        self.exons = self
+            .exons
+            .iter()
+            .filter_map(|(start, size)| {
+                let (start, end) = ((*start).max(lo), (start + size).min(hi));
+                (start < end).then(|| (start, end - start))
+            })
+            .collect();
+    }
+
     pub fn get_exon_count(&self) -> usize {
         self.exons.len()
     }
@@ -163,12 +590,28 @@ impl GenePred {
 }
 
 impl<'a> GxfRecord<'a> {
-    pub fn parse<const SEP: u8>(line: &'a str, attribute: &String) -> Result<Self, &'static str> {
+    /// Parses a feature line, converting its start from `coords` to 0-based.
+    pub fn parse<const SEP: u8>(
+        line: &'a str,
+        keys: &KeyMatcher,
+        coords: InputCoords,
+    ) -> Result<Self, &'static str> {
+        Self::parse_with(line, keys, SEP, coords)
+    }
+
+    /// Same as [`GxfRecord::parse`] with the attribute key-value separator
+    /// picked at runtime.
+    pub fn parse_with(
+        line: &'a str,
+        keys: &KeyMatcher,
+        sep: u8,
+        coords: InputCoords,
+    ) -> Result<Self, &'static str> {
         if line.is_empty() {
             return Err("Empty line");
         }
 
-        let mut fields = line.split('\t');
+        let mut fields = scan::tab_fields(line);
 
         let (chr, _, feature, start, end, _, strand, frame, attr) = (
             fields.next().ok_or("Missing chrom")?,
@@ -182,21 +625,25 @@ impl<'a> GxfRecord<'a> {
             fields.next().ok_or("Missing attributes")?,
         );
 
-        let strand = match strand.chars().next().expect("ERROR: Strand is empty") {
-            '+' => Strand::Forward,
-            '-' => Strand::Reverse,
+        let strand = match strand.as_bytes().first() {
+            Some(b'+') => Strand::Forward,
+            Some(b'-') => Strand::Reverse,
             _ => Strand::Unknown,
         };
 
-        let attr = Attribute::parse::<SEP>(attr, attribute)
-            .map_err(|e| format!("Error parsing attributes: {e}"))
-            .unwrap();
+        let attr = Attribute::parse_with(attr, keys, sep).map_err(|_| "Missing attributes")?;
+        let start = start
+            .parse::<u64>()
+            .map_err(|_| "Invalid start")?
+            .checked_sub(coords.offset())
+            .ok_or("Start before the first base")?;
 
         Ok(Self {
-            chr: chr.to_string(),
+            // INFO: indented lines (pasted or heredoc input) still name the same chromosome
+            chr: chr.trim_start().to_string(),
             feature,
-            start: start.parse::<u64>().unwrap() - 1,
-            end: end.parse().unwrap(),
+            start,
+            end: end.parse().map_err(|_| "Invalid end")?,
             strand,
             frame: frame.to_string(),
             attr,
@@ -216,8 +663,8 @@ mod tests {
     fn test_record_gtf() {
         let line = "chr1\tunknown\texon\t11869\t12227\t.\t+\t.\tgene_id \"DDX11L1\"; gene_name \"DDX11L1\"; gene_source \"ensembl_havana\";
         gene_biotype \"transcribed_unprocessed_pseudogene\";".to_string();
-        let feature = "gene_id".to_string();
-        let record = GxfRecord::parse::<b' '>(&line, &feature).unwrap();
+        let keys = KeyMatcher::new(&["gene_id"]);
+        let record = GxfRecord::parse::<b' '>(&line, &keys, InputCoords::One).unwrap();
         assert_eq!(record.chr, "chr1");
         assert_eq!(record.feature, "exon");
         assert_eq!(record.start, 11868);
@@ -230,8 +677,8 @@ mod tests {
     #[test]
     fn test_record_gff() {
         let line = "chr1\tunknown\texon\t11869\t12227\t.\t+\t.\tID=ENSG00000223972;Name=DDX11L1;biotype=transcribed_unprocessed_pseudogene";
-        let feature = "ID".to_string();
-        let record = GxfRecord::parse::<b'='>(line, &feature).unwrap();
+        let keys = KeyMatcher::new(&["ID"]);
+        let record = GxfRecord::parse::<b'='>(line, &keys, InputCoords::One).unwrap();
         assert_eq!(record.chr, "chr1");
         assert_eq!(record.feature, "exon");
         assert_eq!(record.start, 11868);
@@ -239,24 +686,164 @@ mod tests {
         assert_eq!(record.strand, Strand::Forward);
         assert_eq!(record.frame, ".");
         assert_eq!(record.attr.feature(), "ENSG00000223972");
+
+        let record = GxfRecord::parse::<b'='>(line, &keys, InputCoords::Zero).unwrap();
+        assert_eq!(record.start, 11869);
+    }
+
+    #[test]
+    fn test_feature_fallback() {
+        let keys = KeyMatcher::fallback("transcript_id,ID,Parent");
+        let feature = |attrs: &str| {
+            let line = format!("chr1\t.\texon\t1\t10\t.\t+\t.\t{}", attrs);
+            let record = GxfRecord::parse::<b'='>(&line, &keys, InputCoords::One).unwrap();
+            record.attr.feature().to_string()
+        };
+
+        assert_eq!(feature("Parent=P;ID=I;transcript_id=T"), "T");
+        assert_eq!(feature("Parent=P;ID=I"), "I");
+        assert_eq!(feature("gene_id=G;Parent=P"), "P");
+        assert_eq!(feature("gene_id=G"), "");
     }
 
     #[test]
     fn test_empty_line() {
         let line = "";
-        let feature = "ID".to_string();
-        let record = GxfRecord::parse::<b' '>(line, &feature);
+        let keys = KeyMatcher::new(&["ID"]);
+        let record = GxfRecord::parse::<b' '>(line, &keys, InputCoords::One);
         assert_eq!(record, Err("Empty line"));
     }
 
     #[test]
     fn test_empty_strand() {
         let line = "chr1\tunknown\texon\t11869\t12227\t.\t+\t.\tID=ENSG00000223972;Name=DDX11L1;biotype=transcribed_unprocessed_pseudogene";
-        let feature = "ID".to_string();
-        let record = GxfRecord::parse::<b'='>(line, &feature).unwrap();
+        let keys = KeyMatcher::new(&["ID"]);
+        let record = GxfRecord::parse::<b'='>(line, &keys, InputCoords::One).unwrap();
         assert_eq!(record.strand, Strand::Forward);
     }
 
+    #[test]
+    fn test_parse_garbage() {
+        let keys = KeyMatcher::new(&["transcript_id"]);
+        for line in [
+            "chr1\t.\texon\t1\t10\t.\t\t.\t",
+            "chr1\t.\texon\tone\t10\t.\t+\t.\ttranscript_id \"A\";",
+            "chr1\t.\texon\t1\t-10\t.\t+\t.\ttranscript_id \"A\";",
+            "chr1\t.\texon\t0\t10\t.\t+\t.\ttranscript_id \"A\";",
+            "\t\t\t\t",
+        ] {
+            assert!(GxfRecord::parse_with(line, &keys, b' ', InputCoords::One).is_err());
+        }
+
+        // INFO: a non-ASCII separator byte must not split a character unchecked
+        let line = "chr1\t.\texon\t1\t10\t.\t.\t.\ttranscript_id\u{e9}A;transcript_id\u{e9}";
+        let record = GxfRecord::parse_with(line, &keys, 0xc3, InputCoords::One).unwrap();
+        assert_eq!(record.strand, Strand::Unknown);
+        assert_eq!(record.attr.get("transcript_id"), None);
+    }
+
+    #[test]
+    fn test_blocks_outside_span() {
+        let model = GenePred {
+            chr: "chr1".to_string(),
+            start: 100,
+            end: 200,
+            strand: Strand::Forward,
+            exons: vec![(90, 30), (150, 10), (190, 20), (250, 10)]
+                .into_iter()
+                .collect(),
+            record_type: RecordType::Parent,
+            attrs: Vec::new(),
+            coding: None,
+            exon_numbers: Vec::new(),
+            duplicates: 0,
+            loci: 0,
+            strands: [0; 2],
+        };
+        assert!(model.has_blocks_outside());
+
+        let mut expanded = model;
+        expanded.expand_to_blocks();
+        assert_eq!((expanded.start, expanded.end), (90, 260));
+        assert!(!expanded.has_blocks_outside());
+
+        let mut clipped = GenePred {
+            start: 100,
+            end: 200,
+            ..expanded
+        };
+        clipped.clip_to_span();
+        assert_eq!(&*clipped.exons, &[(100, 20), (150, 10), (190, 10)]);
+        assert!(!clipped.has_blocks_outside());
+    }
+
+    #[test]
+    fn test_widen_points() {
+        let mut model = GenePred::new();
+        model.set_parent("chr1", 100, 100, Strand::Forward);
+        model.add_child("chr1", 100, 100, Strand::Forward);
+        model.add_child("chr1", 100, 101, Strand::Forward);
+        model.add_child("chr1", 120, 110, Strand::Forward);
+        assert!(model.is_degenerate());
+
+        model.widen_points();
+        assert_eq!((model.start, model.end), (100, 101));
+        assert_eq!(&*model.exons, &[(100, 1), (120, 1)]);
+        assert!(!model.is_degenerate());
+    }
+
+    #[test]
+    fn test_codons_at_cds_ends() {
+        let mut coding = Coding::default();
+        coding.add_cds(100, 130, "0");
+        coding.add_cds(200, 244, "0");
+        assert!(!coding.codons_at_cds_ends(Strand::Forward));
+
+        // INFO: GTF-style, stop codon past the CDS; minus strand mirrors it
+        coding.add_start_codon(100, 103);
+        coding.add_stop_codon(244, 247);
+        assert!(coding.codons_at_cds_ends(Strand::Forward));
+        assert!(!coding.codons_at_cds_ends(Strand::Reverse));
+
+        let mut reverse = Coding::default();
+        reverse.add_cds(300, 330, "0");
+        reverse.add_start_codon(327, 330);
+        reverse.add_stop_codon(300, 303);
+        assert!(reverse.codons_at_cds_ends(Strand::Reverse));
+    }
+
+    #[test]
+    fn test_duplicate_parents() {
+        let mut model = GenePred::new();
+        model.set_parent("chr1", 100, 200, Strand::Forward);
+        model.add_child("chr1", 100, 150, Strand::Forward);
+        assert_eq!(model.duplicates, 0);
+
+        let mut other = GenePred::new();
+        other.set_parent("chr2", 500, 600, Strand::Reverse);
+        model.merge(other);
+        model.set_parent("chr3", 10, 20, Strand::Forward);
+        assert_eq!(model.duplicates, 2);
+    }
+
+    #[test]
+    fn test_coding_in_frame() {
+        // INFO: GTF-style, 5' partial on the minus strand: 2 bases to skip
+        let mut coding = Coding::default();
+        coding.add_cds(100, 130, "0");
+        coding.add_cds(200, 244, "2");
+        coding.add_stop_codon(97, 100);
+        assert!(coding.in_frame(Strand::Reverse));
+        assert!(!coding.in_frame(Strand::Forward));
+
+        // INFO: GFF3-style, stop codon already within the CDS
+        let mut other = Coding::default();
+        other.add_cds(300, 330, ".");
+        other.add_stop_codon(327, 330);
+        assert!(other.in_frame(Strand::Forward));
+        assert_eq!(coding.thick(), Some((97, 244)));
+    }
+
     #[test]
     fn test_gene_pred() {
         let mut gene_pred = GenePred::new();
@@ -267,6 +854,12 @@ mod tests {
             strand: Strand::Forward,
             exons: vec![(11868, 50), (12200, 100)].into_iter().collect(),
             record_type: RecordType::Parent,
+            attrs: Vec::new(),
+            coding: None,
+            exon_numbers: Vec::new(),
+            duplicates: 0,
+            loci: 0,
+            strands: [0; 2],
         };
 
         gene_pred.merge(query);
@@ -1,14 +1,17 @@
 // use hashbrown::HashMap;
+use serde_json::{Map, Value};
 use std::fmt::Debug;
 use thiserror::Error;
 
+use super::keys::KeyMatcher;
+use super::scan::split;
+
 macro_rules! extract_field {
     ($bytes:ident split by $sep:ident to $( $field_name:expr => $output_field:expr; )+) => {
         $(
             if let Some(without_key) = $bytes.strip_prefix($field_name) {
-                if let Some(without_eq) = without_key.strip_prefix(&[$sep]) {
-                    let value = unsafe { std::str::from_utf8_unchecked(without_eq) };
-                    *$output_field = Some(value.trim_matches(|c| c == '"'));
+                if let Some(Ok(value)) = without_key.strip_prefix(&[$sep]).map(std::str::from_utf8) {
+                    *$output_field = Some(trim_value(value));
                 }
             }
         )+
@@ -16,9 +19,8 @@ macro_rules! extract_field {
     ($bytes:ident split by $sep:literal to $( $field_name:literal => $output_field:expr; )+) => {
         $(
             if let Some(without_key) = $bytes.strip_prefix($field_name) {
-                if let Some(without_eq) = without_key.strip_prefix(&[$sep]) {
-                    let value = unsafe { std::str::from_utf8_unchecked(without_eq) };
-                    *$output_field = Some(value.trim_matches(|c| c == '"'));
+                if let Some(Ok(value)) = without_key.strip_prefix(&[$sep]).map(std::str::from_utf8) {
+                    *$output_field = Some(trim_value(value));
                 }
             }
         )+
@@ -27,7 +29,7 @@ macro_rules! extract_field {
 
 #[inline(always)]
 fn split_and_trim_bytes<const BY: u8, const TRIM: u8>(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
-    bytes.split(|b| *b == BY).map(|b| {
+    split(bytes, BY).map(|b| {
         let mut idx = 0;
         while idx < b.len() && b[idx] == TRIM {
             idx += 1;
@@ -36,30 +38,43 @@ fn split_and_trim_bytes<const BY: u8, const TRIM: u8>(bytes: &[u8]) -> impl Iter
     })
 }
 
+/// Attribute column of a record.
+///
+/// Only the grouping key (the earliest of the run's [`KeyMatcher`] keys
+/// present) is extracted up front, and scanning stops at the first
+/// occurrence of the first key; other keys are looked up lazily with
+/// [`Attribute::get`].
 #[derive(Debug, PartialEq)]
 pub struct Attribute<'a> {
     feature: &'a str,
+    raw: &'a str,
+    sep: u8,
 }
 
 impl<'a> Attribute<'a> {
     pub fn parse<const SEP: u8>(
         line: &'a str,
-        feature: &String,
+        keys: &KeyMatcher,
     ) -> Result<Attribute<'a>, ParseError> {
-        if !line.is_empty() {
-            let field_bytes = split_and_trim_bytes::<b';', b' '>(line.trim_end().as_bytes());
-
-            let mut feat = None;
+        Self::parse_with(line, keys, SEP)
+    }
 
-            for field in field_bytes {
-                extract_field!(
-                    field split by SEP to
-                    feature.as_bytes() => &mut (feat);
-                )
-            }
+    /// Same as [`Attribute::parse`] with the key-value separator picked at
+    /// runtime, e.g. `:` for in-house `key:value` dialects.
+    pub fn parse_with(
+        line: &'a str,
+        keys: &KeyMatcher,
+        sep: u8,
+    ) -> Result<Attribute<'a>, ParseError> {
+        if !line.is_empty() {
+            let raw = line.trim_end();
 
             Ok(Attribute {
-                feature: feat.unwrap_or(""),
+                feature: find_matched(raw, keys, sep)
+                    .or_else(|| bare(raw, sep))
+                    .unwrap_or(""),
+                raw,
+                sep,
             })
         } else {
             Err(ParseError::Empty)
@@ -70,6 +85,145 @@ impl<'a> Attribute<'a> {
     pub fn feature(&self) -> &'a str {
         self.feature
     }
+
+    /// Value of the first `key` in the column, scanning only up to it.
+    /// `KEY:DB` selects the `DB:<id>` entry of `KEY`, as in `Dbxref:GeneID`.
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        let (key, db) = match key.split_once(':') {
+            Some((key, db)) => (key, Some(db)),
+            None => (key, None),
+        };
+        let value = find(self.raw, key, self.sep)?;
+
+        match db {
+            Some(db) => xref(value, db),
+            None => Some(value),
+        }
+    }
+
+    /// Every value of `key` in the column, for keys repeated on one line
+    /// such as GENCODE's `tag`.
+    pub fn get_all(&self, key: &str) -> Vec<&'a str> {
+        let sep = self.sep;
+        split_and_trim_bytes::<b';', b' '>(self.raw.as_bytes())
+            .filter_map(|field| {
+                let mut value = None;
+                extract_field!(
+                    field split by sep to
+                    key.as_bytes() => &mut (value);
+                );
+                value
+            })
+            .collect()
+    }
+
+    /// The column as a compact JSON object of [`Attribute::pairs`]; a key
+    /// repeated on the line (GENCODE `tag`) maps to an array of its values.
+    pub fn to_json(&self) -> String {
+        let mut map = Map::new();
+        for (key, value) in self.pairs() {
+            match map.get_mut(key) {
+                Some(Value::Array(values)) => values.push(value.into()),
+                Some(first) => *first = Value::Array(vec![first.take(), value.into()]),
+                None => {
+                    map.insert(key.to_string(), value.into());
+                }
+            }
+        }
+        Value::Object(map).to_string()
+    }
+
+    /// Every `key SEP value` pair of the column in order, values unquoted;
+    /// fields without a separator are skipped.
+    pub fn pairs(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        let sep = self.sep as char;
+        self.raw.split(';').filter_map(move |field| {
+            let (key, value) = field.trim_start().split_once(sep)?;
+            Some((key, trim_value(value)))
+        })
+    }
+}
+
+/// The id of the `db:<id>` entry in a comma-separated cross-reference list
+/// such as `GeneID:672,HGNC:HGNC:1100`.
+#[inline]
+fn xref<'a>(value: &'a str, db: &str) -> Option<&'a str> {
+    value
+        .split(',')
+        .find_map(|entry| entry.trim().strip_prefix(db)?.strip_prefix(':'))
+}
+
+/// Scans `key SEP value;` pairs and returns the first value of the earliest
+/// grouping key present, splitting each key off at `sep` and looking it up
+/// in `keys`.
+#[inline(always)]
+fn find_matched<'a>(raw: &'a str, keys: &KeyMatcher, sep: u8) -> Option<&'a str> {
+    let mut best: Option<(usize, &str)> = None;
+    for field in split_and_trim_bytes::<b';', b' '>(raw.as_bytes()) {
+        let Some(idx) = memchr::memchr(sep, field) else {
+            continue;
+        };
+        let Some(key) = keys.find(&field[..idx]) else {
+            continue;
+        };
+        if best.is_some_and(|(best, _)| best <= key) {
+            continue;
+        }
+        // INFO: checked, `sep` may split a multi-byte character when it is not ASCII
+        let Ok(value) = std::str::from_utf8(&field[idx + 1..]) else {
+            continue;
+        };
+        let value = trim_value(value);
+        let value = match keys.db(key) {
+            None => value,
+            Some(db) => match xref(value, db) {
+                Some(id) => id,
+                None => continue,
+            },
+        };
+        if key == 0 {
+            return Some(value);
+        }
+        best = Some((key, value));
+    }
+
+    best.map(|(_, value)| value)
+}
+
+/// Strips the quotes and any stray spaces around a value, so `key "v"`,
+/// `key v` and `key  "v" ;` all read as `v`.
+#[inline(always)]
+fn trim_value(value: &str) -> &str {
+    value.trim_matches(|c| c == '"' || c == ' ')
+}
+
+/// The whole column when it is a single bare value, as on AUGUSTUS `gene`
+/// and `transcript` lines (`g1.t1` instead of `transcript_id "g1.t1";`).
+#[inline]
+fn bare(raw: &str, sep: u8) -> Option<&str> {
+    let bare = !raw.is_empty()
+        && raw != "."
+        && !raw.contains([';', '=', ' ', '"'])
+        && !raw.as_bytes().contains(&sep);
+    bare.then_some(raw)
+}
+
+/// Scans `key SEP value;` pairs and returns the first value for `key`.
+#[inline(always)]
+fn find<'a>(raw: &'a str, key: &str, sep: u8) -> Option<&'a str> {
+    let mut value = None;
+
+    for field in split_and_trim_bytes::<b';', b' '>(raw.as_bytes()) {
+        extract_field!(
+            field split by sep to
+            key.as_bytes() => &mut (value);
+        );
+        if value.is_some() {
+            break;
+        }
+    }
+
+    value
 }
 
 #[derive(Debug, Error, PartialEq, Eq)]
@@ -90,16 +244,77 @@ mod tests {
     #[test]
     fn test_parse_gtf() {
         let line = "gene_id \"ENSG00000223972\"; gene_type \"transcribed_unprocessed_pseudogene\"; gene_name \"DDX11L1\"; level 2; havana_gene OTTHUMG00000000961.1;";
-        let feature = "gene_id".to_string();
-        let attr = Attribute::parse::<b' '>(&line, &feature).unwrap();
+        let keys = KeyMatcher::new(&["gene_id"]);
+        let attr = Attribute::parse::<b' '>(line, &keys).unwrap();
         assert_eq!(attr.feature, "ENSG00000223972");
     }
 
+    #[test]
+    fn test_parse_first_occurrence_and_lazy_get() {
+        let line = "gene_id \"G1\"; transcript_id \"T1\"; tag \"basic\"; tag \"CCDS\";";
+        let keys = KeyMatcher::new(&["transcript_id", "gene_id"]);
+        let attr = Attribute::parse::<b' '>(line, &keys).unwrap();
+
+        assert_eq!(attr.feature(), "T1");
+        assert_eq!(attr.get("gene_id"), Some("G1"));
+        assert_eq!(attr.get("tag"), Some("basic"));
+        assert_eq!(attr.get("gene_name"), None);
+        assert_eq!(
+            attr.pairs().collect::<Vec<_>>(),
+            vec![
+                ("gene_id", "G1"),
+                ("transcript_id", "T1"),
+                ("tag", "basic"),
+                ("tag", "CCDS")
+            ]
+        );
+        assert_eq!(
+            attr.to_json(),
+            r#"{"gene_id":"G1","tag":["basic","CCDS"],"transcript_id":"T1"}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_dbxref() {
+        let line = "ID=gene-A1BG;Dbxref=GeneID:1,HGNC:HGNC:5,MIM:138670;Name=A1BG";
+        let keys = KeyMatcher::new(&["Dbxref:GeneID"]);
+        let attr = Attribute::parse::<b'='>(line, &keys).unwrap();
+
+        assert_eq!(attr.feature(), "1");
+        assert_eq!(attr.get("Dbxref:HGNC"), Some("HGNC:5"));
+        assert_eq!(attr.get("Dbxref:Ensembl"), None);
+        assert_eq!(attr.get("Dbxref"), Some("GeneID:1,HGNC:HGNC:5,MIM:138670"));
+    }
+
+    #[test]
+    fn test_parse_prediction_quirks() {
+        let keys = KeyMatcher::new(&["transcript_id"]);
+        let bare = Attribute::parse::<b' '>("g1.t1", &keys).unwrap();
+        assert_eq!(bare.feature(), "g1.t1");
+        assert_eq!(Attribute::parse::<b' '>(".", &keys).unwrap().feature(), "");
+
+        let line = "transcript_id  g2.t1 ; gene_id \"g2\" ;";
+        let attr = Attribute::parse::<b' '>(line, &keys).unwrap();
+        assert_eq!(attr.feature(), "g2.t1");
+        assert_eq!(attr.get("gene_id"), Some("g2"));
+    }
+
+    #[test]
+    fn test_parse_runtime_separator() {
+        let line = "ID:tx1; Parent:gene1; Note:a=b";
+        let keys = KeyMatcher::new(&["Parent"]);
+        let attr = Attribute::parse_with(line, &keys, b':').unwrap();
+
+        assert_eq!(attr.feature(), "gene1");
+        assert_eq!(attr.get("ID"), Some("tx1"));
+        assert_eq!(attr.get("Note"), Some("a=b"));
+    }
+
     #[test]
     fn test_parse_gff() {
         let line = "ID=ENSG00000223972;Name=DDX11L1;biotype=transcribed_unprocessed_pseudogene";
-        let feature = "ID".to_string();
-        let attr = Attribute::parse::<b'='>(&line, &feature).unwrap();
+        let keys = KeyMatcher::new(&["ID"]);
+        let attr = Attribute::parse::<b'='>(line, &keys).unwrap();
         assert_eq!(attr.feature, "ENSG00000223972");
     }
 }
@@ -0,0 +1,144 @@
+use std::ops::Deref;
+
+use smallvec::SmallVec;
+
+/// Blocks stored inline before spilling to the heap; most transcripts have
+/// fewer exons than this.
+const INLINE: usize = 8;
+
+/// Blocks of a gene model as `(start, size)`, sorted and without duplicates.
+///
+/// GTF/GFF list a model's children 5' to 3', ascending on `+` and
+/// descending on `-`, so inserts are usually an append or a front insert
+/// into an inline buffer: a short memmove rather than a tree node
+/// allocation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Exons(SmallVec<[(u64, u64); INLINE]>);
+
+impl Exons {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `block`, keeping the order; returns `false` if it was present.
+    #[inline]
+    pub fn insert(&mut self, block: (u64, u64)) -> bool {
+        match self.0.last() {
+            None => self.0.push(block),
+            Some(last) if *last < block => self.0.push(block),
+            _ if block < self.0[0] => self.0.insert(0, block),
+            _ => match self.0.binary_search(&block) {
+                Ok(_) => return false,
+                Err(idx) => self.0.insert(idx, block),
+            },
+        }
+        true
+    }
+
+    /// Merges overlapping or adjacent blocks into one; returns how many
+    /// blocks were merged away.
+    pub fn merge_overlapping(&mut self) -> usize {
+        if self.0.windows(2).all(|w| w[0].0 + w[0].1 < w[1].0) {
+            return 0;
+        }
+
+        let before = self.0.len();
+        let mut merged: SmallVec<[(u64, u64); INLINE]> = SmallVec::with_capacity(before);
+
+        for &(start, size) in &self.0 {
+            match merged.last_mut() {
+                Some((last, last_size)) if start <= *last + *last_size => {
+                    *last_size = (*last_size).max(start + size - *last);
+                }
+                _ => merged.push((start, size)),
+            }
+        }
+
+        self.0 = merged;
+        before - self.0.len()
+    }
+}
+
+impl Deref for Exons {
+    type Target = [(u64, u64)];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Extend<(u64, u64)> for Exons {
+    fn extend<I: IntoIterator<Item = (u64, u64)>>(&mut self, iter: I) {
+        for block in iter {
+            self.insert(block);
+        }
+    }
+}
+
+impl FromIterator<(u64, u64)> for Exons {
+    fn from_iter<I: IntoIterator<Item = (u64, u64)>>(iter: I) -> Self {
+        let mut exons = Self::new();
+        exons.extend(iter);
+        exons
+    }
+}
+
+impl IntoIterator for Exons {
+    type Item = (u64, u64);
+    type IntoIter = smallvec::IntoIter<[(u64, u64); INLINE]>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Exons {
+    type Item = &'a (u64, u64);
+    type IntoIter = std::slice::Iter<'a, (u64, u64)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_sorts_and_dedups() {
+        let blocks = [(300, 100), (100, 50), (300, 100), (200, 10), (500, 5)];
+        let exons = blocks.into_iter().collect::<Exons>();
+
+        assert_eq!(&*exons, &[(100, 50), (200, 10), (300, 100), (500, 5)]);
+        assert_eq!(
+            exons,
+            blocks
+                .into_iter()
+                .collect::<std::collections::BTreeSet<_>>()
+                .into_iter()
+                .collect()
+        );
+    }
+
+    #[test]
+    fn test_insert_descending() {
+        let mut exons = [(500, 5), (300, 100), (100, 50)]
+            .into_iter()
+            .collect::<Exons>();
+
+        assert!(!exons.insert((100, 50)));
+        assert_eq!(&*exons, &[(100, 50), (300, 100), (500, 5)]);
+    }
+
+    #[test]
+    fn test_merge_overlapping() {
+        let mut exons = [(100, 50), (120, 10), (150, 20), (200, 10), (205, 20)]
+            .into_iter()
+            .collect::<Exons>();
+
+        assert_eq!(exons.merge_overlapping(), 3);
+        assert_eq!(&*exons, &[(100, 70), (200, 25)]);
+        assert_eq!(exons.merge_overlapping(), 0);
+    }
+}
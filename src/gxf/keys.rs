@@ -0,0 +1,137 @@
+/// Slots in the perfect-hash table; a power of two larger than any
+/// realistic set of requested keys.
+const SLOTS: usize = 64;
+
+/// Matcher over the attribute keys requested for a run, built once and
+/// shared by every line parse.
+///
+/// Keys are placed in a small perfect-hash table keyed on length and
+/// first/last byte, so matching a field is one hash and at most one
+/// comparison instead of a prefix test per requested key.
+///
+/// A key written `KEY:DB` (e.g. `Dbxref:GeneID`) matches attribute `KEY`
+/// and selects the `DB:<id>` entry of its comma-separated value; see
+/// [`KeyMatcher::db`].
+#[derive(Debug, Clone)]
+pub struct KeyMatcher {
+    keys: Vec<Box<[u8]>>,
+    dbs: Vec<Option<Box<str>>>,
+    slots: [u8; SLOTS],
+    seed: usize,
+}
+
+impl KeyMatcher {
+    /// Builds the matcher; indices returned by [`KeyMatcher::find`] follow
+    /// the order of `keys`, duplicate attributes keeping their first index.
+    pub fn new(keys: &[&str]) -> Self {
+        let mut unique: Vec<Box<[u8]>> = Vec::with_capacity(keys.len());
+        let mut dbs = Vec::with_capacity(keys.len());
+        for key in keys {
+            let (name, db) = match key.split_once(':') {
+                Some((name, db)) => (name, Some(db.into())),
+                None => (*key, None),
+            };
+            if !unique.iter().any(|k| **k == *name.as_bytes()) {
+                unique.push(name.as_bytes().into());
+                dbs.push(db);
+            }
+        }
+        assert!(
+            unique.len() < SLOTS,
+            "ERROR: Too many attribute keys requested"
+        );
+
+        // INFO: few keys means a collision-free seed is found almost immediately
+        (0..)
+            .find_map(|seed| Self::build(&unique, &dbs, seed))
+            .expect("ERROR: Could not build attribute key table")
+    }
+
+    fn build(keys: &[Box<[u8]>], dbs: &[Option<Box<str>>], seed: usize) -> Option<Self> {
+        let mut slots = [0; SLOTS];
+        for (idx, key) in keys.iter().enumerate() {
+            let slot = &mut slots[hash(key, seed)];
+            if *slot != 0 {
+                return None;
+            }
+            *slot = idx as u8 + 1;
+        }
+
+        Some(Self {
+            keys: keys.to_vec(),
+            dbs: dbs.to_vec(),
+            slots,
+            seed,
+        })
+    }
+
+    /// Index of `key` among the requested keys, if it is one of them.
+    #[inline(always)]
+    pub fn find(&self, key: &[u8]) -> Option<usize> {
+        match self.slots[hash(key, self.seed)] {
+            0 => None,
+            slot => {
+                let idx = slot as usize - 1;
+                (*self.keys[idx] == *key).then_some(idx)
+            }
+        }
+    }
+
+    /// Builds the matcher for a grouping attribute given as a comma-separated
+    /// fallback list, e.g. `transcript_id,ID,Parent`: each line is grouped by
+    /// the first listed key it has.
+    pub fn fallback(list: &str) -> Self {
+        Self::new(&list.split(',').collect::<Vec<_>>())
+    }
+
+    /// Cross-reference database requested for key `idx` (`GeneID` for
+    /// `Dbxref:GeneID`).
+    #[inline(always)]
+    pub fn db(&self, idx: usize) -> Option<&str> {
+        self.dbs[idx].as_deref()
+    }
+
+    /// Number of distinct requested keys.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+#[inline(always)]
+fn hash(key: &[u8], seed: usize) -> usize {
+    let (first, last) = match key {
+        [] => (0, 0),
+        [first, .., last] => (*first as usize, *last as usize),
+        [only] => (*only as usize, *only as usize),
+    };
+    (key.len().wrapping_mul(seed * 2 + 1) ^ first.wrapping_mul(31) ^ last.wrapping_add(seed))
+        % SLOTS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_requested_keys_only() {
+        let keys = ["transcript_id", "gene_id", "gene_name", "gene_id"];
+        let matcher = KeyMatcher::new(&keys);
+
+        assert_eq!(matcher.len(), 3);
+        assert_eq!(matcher.find(b"transcript_id"), Some(0));
+        assert_eq!(matcher.find(b"gene_id"), Some(1));
+        assert_eq!(matcher.find(b"gene_name"), Some(2));
+        for other in [&b"gene_type"[..], b"exon_id", b"", b"g", b"transcript_ID"] {
+            assert_eq!(matcher.find(other), None);
+        }
+        assert_eq!(matcher.db(0), None);
+
+        let matcher = KeyMatcher::new(&["Dbxref:GeneID"]);
+        assert_eq!(matcher.find(b"Dbxref"), Some(0));
+        assert_eq!(matcher.db(0), Some("GeneID"));
+    }
+}
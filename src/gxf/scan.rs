@@ -0,0 +1,194 @@
+use memchr::memchr;
+
+/// Splits `bytes` on every `sep` like `<[u8]>::split`, jumping between
+/// separators with memchr instead of testing byte by byte.
+#[inline]
+pub(crate) fn split(bytes: &[u8], sep: u8) -> Split<'_> {
+    Split {
+        rest: Some(bytes),
+        sep,
+    }
+}
+
+pub(crate) struct Split<'a> {
+    rest: Option<&'a [u8]>,
+    sep: u8,
+}
+
+impl<'a> Iterator for Split<'a> {
+    type Item = &'a [u8];
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest?;
+        match memchr(self.sep, rest) {
+            Some(idx) => {
+                self.rest = Some(&rest[idx + 1..]);
+                Some(&rest[..idx])
+            }
+            None => {
+                self.rest = None;
+                Some(rest)
+            }
+        }
+    }
+}
+
+/// Splits `line` on tabs.
+#[inline]
+pub(crate) fn tab_fields(line: &str) -> impl Iterator<Item = &str> {
+    line.split('\t')
+}
+
+/// GFF3 directive after which the file holds sequences, not features.
+pub(crate) const FASTA_DIRECTIVE: &str = "##FASTA";
+
+/// `text` up to its `##FASTA` section (Prokka/Bakta embed the genome).
+pub(crate) fn until_fasta(text: &str) -> &str {
+    if text.starts_with(FASTA_DIRECTIVE) {
+        return "";
+    }
+    match memchr::memmem::find(text.as_bytes(), b"\n##FASTA") {
+        Some(idx) => &text[..idx + 1],
+        None => text,
+    }
+}
+
+/// Whether `line` is a `#` comment or directive, or starts with one of the
+/// `extra` prefixes (`!`, pasted-in UCSC `track` lines and the like).
+#[inline]
+pub(crate) fn is_comment(line: &str, extra: &[String]) -> bool {
+    line.starts_with('#') || extra.iter().any(|prefix| line.starts_with(prefix.as_str()))
+}
+
+/// Leading `#` lines of `text`, up to its first record; blank lines and
+/// `extra`-prefixed comments in between are skipped.
+pub(crate) fn header_comments(text: &str, extra: &[String]) -> Vec<String> {
+    let mut header = Vec::new();
+    for line in lines(text) {
+        if line.starts_with(FASTA_DIRECTIVE) {
+            break;
+        }
+        if line.starts_with('#') {
+            header.push(line.to_string());
+        } else if !is_comment(line, extra) && !line.trim().is_empty() {
+            break;
+        }
+    }
+    header
+}
+
+/// Lines of `text` with the same semantics as `str::lines` (a trailing `\r`
+/// is dropped, a final newline does not yield an empty line).
+#[inline]
+pub fn lines(text: &str) -> Lines<'_> {
+    Lines { rest: text }
+}
+
+pub struct Lines<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = &'a str;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+
+        let line = match memchr(b'\n', self.rest.as_bytes()) {
+            Some(idx) => {
+                let line = &self.rest[..idx];
+                self.rest = &self.rest[idx + 1..];
+                line
+            }
+            None => std::mem::take(&mut self.rest),
+        };
+        Some(line.strip_suffix('\r').unwrap_or(line))
+    }
+}
+
+/// Cuts `text` into about `n` pieces that end on line boundaries, so each
+/// can be scanned with [`lines`] on its own thread.
+pub fn line_chunks(text: &str, n: usize) -> Vec<&str> {
+    let size = (text.len() / n.max(1)).max(1);
+    let mut chunks = Vec::with_capacity(n);
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let end = match rest
+            .as_bytes()
+            .get(size..)
+            .and_then(|tail| memchr(b'\n', tail))
+        {
+            Some(idx) => size + idx + 1,
+            None => rest.len(),
+        };
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_until_fasta() {
+        let gff = "##gff-version 3\nchr1\t.\tgene\t1\t9\t.\t+\t.\tID=a\n##FASTA\n>chr1\nACGT\n";
+        assert_eq!(until_fasta(gff), &gff[..gff.find("##FASTA").unwrap()]);
+        assert_eq!(until_fasta("##FASTA\n>chr1\n"), "");
+        assert_eq!(until_fasta("chr1\t#\n"), "chr1\t#\n");
+    }
+
+    #[test]
+    fn test_is_comment() {
+        let extra = ["!".to_string(), "track".to_string()];
+        assert!(is_comment("##gff-version 3", &[]));
+        assert!(is_comment("!gaf-version: 2.2", &extra));
+        assert!(is_comment("track name=genes", &extra));
+        assert!(!is_comment("track name=genes", &[]));
+        assert!(!is_comment("chr1\t.\texon", &extra));
+
+        let gtf = "#!genome-build GRCh38.p14\n\ntrack name=x\n#!genebuild-last-updated 2023-03\nchr1\t.\n#late\n";
+        assert_eq!(
+            header_comments(gtf, &extra),
+            [
+                "#!genome-build GRCh38.p14",
+                "#!genebuild-last-updated 2023-03"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_matches_std() {
+        for bytes in [&b""[..], b";", b"a;b;;c", b"a;b;"] {
+            assert_eq!(
+                split(bytes, b';').collect::<Vec<_>>(),
+                bytes.split(|b| *b == b';').collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_lines_and_chunks_match_std() {
+        let text = "a\tb\r\n\nc\nlong line here\nd";
+        assert_eq!(
+            lines(text).collect::<Vec<_>>(),
+            text.lines().collect::<Vec<_>>()
+        );
+
+        for n in 1..8 {
+            let chunked = line_chunks(text, n)
+                .into_iter()
+                .flat_map(lines)
+                .collect::<Vec<_>>();
+            assert_eq!(chunked, text.lines().collect::<Vec<_>>());
+        }
+    }
+}
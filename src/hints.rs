@@ -0,0 +1,396 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::codec::decoder;
+use crate::config::Config;
+use crate::detect::detect_input_kind;
+use crate::error::Result;
+use crate::gxf::{is_comment, FASTA_DIRECTIVE};
+
+/// Feature types that usually define a record, in order of preference.
+const PARENTS: &[&str] = &[
+    "transcript",
+    "mRNA",
+    "lnc_RNA",
+    "ncRNA",
+    "primary_transcript",
+    "gene",
+];
+/// Feature types that usually become blocks, in order of preference.
+const CHILDREN: &[&str] = &["exon", "CDS"];
+/// Attributes that usually name a record, in order of preference.
+const ID_KEYS: &[&str] = &["transcript_id", "ID", "gene_id", "Name"];
+
+/// Feature types and attribute keys found at the start of an input, used
+/// to explain a conversion that wrote nothing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Seen {
+    /// Key-value separator the attributes were split with.
+    pub sep: char,
+    /// Feature lines inspected.
+    pub lines: usize,
+    /// Feature lines by type (third column).
+    pub features: BTreeMap<String, usize>,
+    /// Attribute keys of lines not of the configured child type.
+    pub parent_keys: BTreeSet<String>,
+    /// Attribute keys of lines of the configured child type.
+    pub child_keys: BTreeSet<String>,
+}
+
+/// Scans up to `lines` feature lines of `config.input` and returns what
+/// in `config` does not match them, as hints naming the flag to change.
+pub fn hints(config: &Config, lines: usize) -> Result<Vec<String>> {
+    Ok(scan(config, lines)?.hints(config))
+}
+
+/// Adapts the layout options of `config` (`parent`, `child`, `feature`,
+/// `child_feature`) that are not in `fixed` to the first `lines` feature
+/// lines of `config.input`, when the configured ones are not found there:
+/// e.g. `mRNA` models linked by `ID`/`Parent` in GFF3. Returns the flags
+/// that were changed.
+pub fn detect(config: &mut Config, lines: usize, fixed: &[&str]) -> Result<Vec<String>> {
+    let free = |name: &str| !fixed.contains(&name);
+    let mut changes = Vec::new();
+    let mut seen = scan(config, lines)?;
+
+    if free("parent") && !seen.features.contains_key(config.parent.as_str()) {
+        if let Some(parent) = pick_feature(&seen, PARENTS) {
+            config.parent = parent.to_string();
+            changes.push(format!("--parent {}", parent));
+        }
+    }
+    if free("child") && !config.child_types().any(|c| seen.features.contains_key(c)) {
+        if let Some(child) = pick_feature(&seen, CHILDREN).filter(|c| *c != config.parent) {
+            config.child = child.to_string();
+            changes.push(format!("--child {}", child));
+            // INFO: keys are split by child type, so they change with it
+            seen = scan(config, lines)?;
+        }
+    }
+
+    let feature = config.feature_key();
+    if free("feature")
+        && free("child_feature")
+        && config.child_feature.is_none()
+        && !seen.child_keys.is_empty()
+        && !has_key(&seen.child_keys, &feature)
+        && has_key(&seen.child_keys, "Parent")
+        && has_key(&seen.parent_keys, "ID")
+    {
+        config.feature = "ID".to_string();
+        config.child_feature = Some("Parent".to_string());
+        changes.push("--feature ID --child-feature Parent".to_string());
+    }
+
+    Ok(changes)
+}
+
+/// First of `known` among the scanned feature types.
+fn pick_feature<'a>(seen: &Seen, known: &[&'a str]) -> Option<&'a str> {
+    known
+        .iter()
+        .copied()
+        .find(|kind| seen.features.contains_key(*kind))
+}
+
+/// Collects the feature types and attribute keys of up to `lines` feature
+/// lines of `config.input`.
+pub fn scan(config: &Config, lines: usize) -> Result<Seen> {
+    let kind = detect_input_kind(&config.input)?;
+    let reader = BufReader::new(decoder(File::open(&config.input)?, kind.compression)?);
+    let mut seen = Seen::new(config.separator(kind.format) as char);
+    for line in reader.lines().map_while(std::result::Result::ok) {
+        if line.starts_with(FASTA_DIRECTIVE) || seen.lines == lines {
+            break;
+        }
+        if line.trim().is_empty() || is_comment(&line, &config.comment_chars) {
+            continue;
+        }
+        seen.add(&line, config);
+    }
+
+    Ok(seen)
+}
+
+impl Seen {
+    fn new(sep: char) -> Self {
+        Self {
+            sep,
+            lines: 0,
+            features: BTreeMap::new(),
+            parent_keys: BTreeSet::new(),
+            child_keys: BTreeSet::new(),
+        }
+    }
+
+    /// Counts one feature line, keeping its attribute keys apart by
+    /// whether it is a child line.
+    fn add(&mut self, line: &str, config: &Config) {
+        self.lines += 1;
+        let mut fields = line.split('\t');
+        let (Some(kind), Some(attrs)) = (fields.nth(2), fields.nth(5)) else {
+            return;
+        };
+        *self.features.entry(kind.to_string()).or_default() += 1;
+
+        let keys = match config.is_child(kind) {
+            true => &mut self.child_keys,
+            false => &mut self.parent_keys,
+        };
+        let pairs = attrs.split(';').map(str::trim);
+        keys.extend(pairs.filter_map(|pair| Some(pair.split_once(self.sep)?.0.to_string())));
+    }
+
+    /// What in `config` does not match the scanned lines; empty when
+    /// everything is found.
+    pub fn hints(&self, config: &Config) -> Vec<String> {
+        if self.lines == 0 {
+            return vec![format!(
+                "no feature lines found in {:?}; is it empty or only comments?",
+                config.input
+            )];
+        }
+
+        let mut hints = Vec::new();
+        let children = config
+            .child_types()
+            .any(|child| self.features.contains_key(child));
+        // INFO: children alone still make records, so a missing parent only matters with a fix
+        if !self.features.contains_key(config.parent.as_str()) {
+            hints.extend(self.missing_feature("--parent", &config.parent, PARENTS, !children));
+        }
+        if !children {
+            hints.extend(self.missing_feature("--child", &config.child, CHILDREN, true));
+        }
+
+        let feature = config.feature_key();
+        let child_key = config
+            .child_feature
+            .as_deref()
+            .map_or_else(|| feature.clone(), |key| config.attr_key(key));
+        if !self.child_keys.is_empty() && !has_key(&self.child_keys, &child_key) {
+            let flag = match config.child_feature {
+                Some(_) => "--child-feature",
+                None => "--feature",
+            };
+            hints.push(self.missing_child_key(flag, &child_key));
+        } else if !self.parent_keys.is_empty()
+            && self.child_keys.is_empty()
+            && !has_key(&self.parent_keys, &feature)
+        {
+            let other = closest(&self.parent_keys, &feature).or(pick(&self.parent_keys, ID_KEYS));
+            hints.push(missing_key(
+                "feature lines",
+                &feature,
+                other.map(|other| format!("--feature {}", other)),
+                &self.parent_keys,
+            ));
+        }
+        if children && self.child_keys.is_empty() {
+            hints.push(format!(
+                "no {:?}-separated attributes found on {:?} lines; check --attr-sep and the input format",
+                self.sep,
+                config.child
+            ));
+        }
+
+        hints
+    }
+
+    /// Names a `known` type present in the input; lists the types seen
+    /// instead with `always`.
+    fn missing_feature(
+        &self,
+        flag: &str,
+        kind: &str,
+        known: &[&str],
+        always: bool,
+    ) -> Option<String> {
+        let types = self.features.keys().map(String::as_str).collect::<Vec<_>>();
+        match known.iter().find(|known| types.contains(known)) {
+            Some(other) => Some(format!(
+                "no {:?} features found; did you mean {} {}?",
+                kind, flag, other
+            )),
+            None => always.then(|| {
+                format!(
+                    "no {:?} features found; pick one with {} (types seen: {})",
+                    kind,
+                    flag,
+                    types.join(", ")
+                )
+            }),
+        }
+    }
+
+    /// Suggests a near spelling of `key` first, then plain GFF3's
+    /// `Parent=` links (a single `--feature` cannot match both parents and
+    /// children there), then a usual ID attribute.
+    fn missing_child_key(&self, flag: &str, key: &str) -> String {
+        let gff3 = has_key(&self.parent_keys, "ID") && has_key(&self.child_keys, "Parent");
+        let fix = match closest(&self.child_keys, key) {
+            Some(other) => Some(format!("{} {}", flag, other)),
+            None if gff3 => Some("--feature ID --child-feature Parent".to_string()),
+            None => pick(&self.child_keys, ID_KEYS).map(|other| format!("{} {}", flag, other)),
+        };
+        missing_key("child lines", key, fix, &self.child_keys)
+    }
+}
+
+fn missing_key(lines: &str, key: &str, fix: Option<String>, keys: &BTreeSet<String>) -> String {
+    match fix {
+        Some(fix) => format!(
+            "{} have no {:?} attribute; did you mean {}?",
+            lines, key, fix
+        ),
+        None => format!(
+            "{} have no {:?} attribute (keys seen: {})",
+            lines,
+            key,
+            list(keys)
+        ),
+    }
+}
+
+/// Key of `keys` nearest to `key` by case-insensitive edit distance, if
+/// close enough to be a typo (a third of its length, at least one edit).
+fn closest<'a>(keys: &'a BTreeSet<String>, key: &str) -> Option<&'a str> {
+    let key = key.split([',', ':']).next().unwrap_or(key).to_lowercase();
+    let limit = (key.chars().count() / 3).max(1);
+    keys.iter()
+        .map(|other| (edit_distance(&key, &other.to_lowercase()), other))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, other)| other.as_str())
+}
+
+/// Levenshtein distance between `a` and `b`, by characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Whether `keys` holds `key`, ignoring a `:DB` selector.
+/// Whether `keys` has `key`, or any key of a `--feature` fallback list.
+fn has_key(keys: &BTreeSet<String>, key: &str) -> bool {
+    key.split(',')
+        .any(|key| keys.contains(key.split(':').next().unwrap_or(key)))
+}
+
+fn pick<'a>(keys: &BTreeSet<String>, known: &[&'a str]) -> Option<&'a str> {
+    known.iter().copied().find(|key| keys.contains(*key))
+}
+
+fn list(keys: &BTreeSet<String>) -> String {
+    keys.iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seen(config: &Config, lines: &str, sep: char) -> Seen {
+        let mut seen = Seen::new(sep);
+        for line in lines.lines() {
+            seen.add(line, config);
+        }
+        seen
+    }
+
+    #[test]
+    fn test_hints() {
+        let gff = "chr1\t.\tmRNA\t1\t60\t.\t+\t.\tID=A\n\
+                   chr1\t.\texon\t1\t20\t.\t+\t.\tParent=A\n";
+        let config = Config::default();
+        assert_eq!(
+            seen(&config, gff, '=').hints(&config),
+            vec![
+                "no \"transcript\" features found; did you mean --parent mRNA?",
+                "child lines have no \"transcript_id\" attribute; did you mean --feature ID --child-feature Parent?",
+            ]
+        );
+
+        let config = Config::builder()
+            .parent("mRNA")
+            .feature("ID")
+            .child_feature("Parent")
+            .build()
+            .unwrap();
+        assert!(seen(&config, gff, '=').hints(&config).is_empty());
+
+        let gtf = "chr1\t.\ttranscript\t1\t60\t.\t+\t.\tgene_id \"G\"; transcript_id \"A\";\n\
+                   chr1\t.\texon\t1\t20\t.\t+\t.\tgene_id \"G\"; transcript_id \"A\";\n";
+        let config = Config::builder().child("CDS").build().unwrap();
+        assert_eq!(
+            seen(&config, gtf, ' ').hints(&config),
+            vec!["no \"CDS\" features found; did you mean --child exon?"]
+        );
+
+        let config = Config::builder().feature("transcrip_id").build().unwrap();
+        assert_eq!(
+            seen(&config, gtf, ' ').hints(&config),
+            vec!["child lines have no \"transcrip_id\" attribute; did you mean --feature transcript_id?"]
+        );
+        let config = Config::builder().feature("Parent").build().unwrap();
+        assert_eq!(
+            seen(&config, gtf, ' ').hints(&config),
+            vec!["child lines have no \"Parent\" attribute; did you mean --feature transcript_id?"]
+        );
+    }
+
+    #[test]
+    fn test_detect() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("detect.gff3");
+        std::fs::write(
+            &input,
+            "chr1\t.\tmRNA\t1\t60\t.\t+\t.\tID=A\n\
+             chr1\t.\tCDS\t1\t20\t.\t+\t0\tParent=A\n",
+        )
+        .unwrap();
+
+        let mut config = Config::new(&input, "");
+        let changes = detect(&mut config, 10, &[]).unwrap();
+        assert_eq!(
+            changes,
+            [
+                "--parent mRNA",
+                "--child CDS",
+                "--feature ID --child-feature Parent"
+            ]
+        );
+        assert_eq!(config.child_feature.as_deref(), Some("Parent"));
+
+        let mut config = Config::new(&input, "");
+        let changes = detect(&mut config, 10, &["child", "feature"]).unwrap();
+        assert_eq!(changes, ["--parent mRNA"]);
+        assert_eq!(
+            (config.child.as_str(), config.feature.as_str()),
+            ("exon", "transcript_id")
+        );
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("transcript_id", "transcript_id"), 0);
+        assert_eq!(edit_distance("transcrip_id", "transcript_id"), 1);
+        assert_eq!(edit_distance("gene_name", "gene_id"), 4);
+        assert_eq!(edit_distance("", "ID"), 2);
+    }
+}
@@ -0,0 +1,180 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::bed::{BedType, Emit};
+use crate::chrom::ChromBounds;
+use crate::config::Config;
+use crate::convert::run;
+use crate::error::Result;
+use crate::output::derive_output_name;
+
+/// UCSC converter run on the sorted BED12, looked up on `PATH` by default.
+pub const BED_TO_BIGBED: &str = "bedToBigBed";
+
+/// Layout of a UCSC track hub holding one bigBed annotation track.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hub {
+    /// Directory the hub is written to; created if missing.
+    pub dir: PathBuf,
+    /// UCSC assembly name, e.g. `hg38`.
+    pub genome: String,
+    /// Hub and track name; also the bigBed file stem.
+    pub name: String,
+    /// Contact address shown by the browser.
+    pub email: String,
+    /// `bedToBigBed` executable.
+    pub bed_to_bigbed: PathBuf,
+}
+
+impl Hub {
+    /// Hub for `config.input` in `dir`, named after the input file.
+    pub fn new<P: Into<PathBuf>, S: Into<String>>(config: &Config, dir: P, genome: S) -> Self {
+        let name = derive_output_name(&config.input)
+            .with_extension("")
+            .to_string_lossy()
+            .into_owned();
+
+        Self {
+            dir: dir.into(),
+            genome: genome.into(),
+            name,
+            email: String::new(),
+            bed_to_bigbed: PathBuf::from(BED_TO_BIGBED),
+        }
+    }
+
+    /// Path of the bigBed track.
+    pub fn bigbed(&self) -> PathBuf {
+        self.dir
+            .join(&self.genome)
+            .join(format!("{}.bb", self.name))
+    }
+
+    /// `hub.txt`, `genomes.txt` and `trackDb.txt`, as paths relative to the
+    /// hub directory and their contents.
+    pub fn stubs(&self) -> [(PathBuf, String); 3] {
+        let label = format!("{} annotation (gxf2bed)", self.name);
+        let hub = format!(
+            "hub {}\nshortLabel {}\nlongLabel {}\ngenomesFile genomes.txt\nemail {}\n",
+            self.name, self.name, label, self.email
+        );
+        let genomes = format!(
+            "genome {}\ntrackDb {}/trackDb.txt\n",
+            self.genome, self.genome
+        );
+        let track_db = format!(
+            "track {}\nbigDataUrl {}.bb\nshortLabel {}\nlongLabel {}\ntype bigBed 12 +\nsearchIndex name\nvisibility pack\n",
+            self.name, self.name, self.name, label
+        );
+
+        [
+            (PathBuf::from("hub.txt"), hub),
+            (PathBuf::from("genomes.txt"), genomes),
+            (Path::new(&self.genome).join("trackDb.txt"), track_db),
+        ]
+    }
+}
+
+/// Converts `config.input` to a sorted BED12, turns it into an indexed
+/// bigBed with `bedToBigBed` and writes the hub stubs next to it; returns
+/// the path of `hub.txt`.
+///
+/// `config.chrom_sizes` is required. Records past their chromosome end
+/// would make `bedToBigBed` fail, so they are dropped unless
+/// `config.chrom_bounds` asks to clip them.
+pub fn hub(config: &Config, hub: &Hub) -> Result<PathBuf> {
+    let Some(sizes) = config.chrom_sizes.clone() else {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "a track hub needs --chrom-sizes for the target assembly",
+        )
+        .into());
+    };
+
+    let bigbed = hub.bigbed();
+    let track_dir = bigbed.parent().unwrap_or(&hub.dir);
+    fs::create_dir_all(track_dir)?;
+    let bed = tempfile::Builder::new()
+        .prefix("gxf2bed-hub-")
+        .suffix(".bed")
+        .tempfile_in(track_dir)?;
+
+    let mut config = config.clone();
+    config.output = bed.path().to_path_buf();
+    config.outputs.clear();
+    config.child_outputs.clear();
+    config.tee = false;
+    config.bed_type = BedType::Bed12;
+    config.one_based = false;
+    config.unordered = false;
+    config.compat = None;
+    config.columns.clear();
+    config.compute.retain(|computed| !computed.is_column());
+    config.emit = Emit::Records;
+    config.exon_ranks = false;
+    config.attrs_json = false;
+    if config.chrom_bounds == ChromBounds::Report {
+        config.chrom_bounds = ChromBounds::Drop;
+    }
+    let stats = run(&config)?;
+    log::info!(written = stats.written; "{} records written to the track", stats.written);
+
+    let status = Command::new(&hub.bed_to_bigbed)
+        .arg("-type=bed12")
+        .arg("-extraIndex=name")
+        .arg(bed.path())
+        .arg(&sizes)
+        .arg(&bigbed)
+        .status()
+        .map_err(|e| {
+            Error::new(
+                e.kind(),
+                format!(
+                    "could not run {:?} ({}); install it from https://hgdownload.soe.ucsc.edu/admin/exe/",
+                    hub.bed_to_bigbed, e
+                ),
+            )
+        })?;
+    if !status.success() {
+        return Err(Error::other(format!("{:?} failed ({})", hub.bed_to_bigbed, status)).into());
+    }
+
+    for (path, contents) in hub.stubs() {
+        fs::write(hub.dir.join(path), contents)?;
+    }
+
+    Ok(hub.dir.join("hub.txt"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hub_layout() {
+        let config = Config::new("gencode.v44.annotation.gtf.gz", "unused.bed");
+        let mut hub = Hub::new(&config, "hub", "hg38");
+        hub.email = "me@example.org".to_string();
+
+        assert_eq!(hub.name, "gencode.v44.annotation");
+        assert_eq!(
+            hub.bigbed(),
+            Path::new("hub/hg38/gencode.v44.annotation.bb")
+        );
+        let [(_, hub_txt), (_, genomes), (track_db, contents)] = hub.stubs();
+        assert!(hub_txt.contains("genomesFile genomes.txt\nemail me@example.org\n"));
+        assert_eq!(genomes, "genome hg38\ntrackDb hg38/trackDb.txt\n");
+        assert_eq!(track_db, Path::new("hg38/trackDb.txt"));
+        assert!(contents.contains("bigDataUrl gencode.v44.annotation.bb\n"));
+        assert!(contents.contains("type bigBed 12 +\nsearchIndex name\n"));
+    }
+
+    #[test]
+    fn test_hub_needs_chrom_sizes() {
+        let config = Config::new("in.gtf", "unused.bed");
+        let hub = Hub::new(&config, std::env::temp_dir(), "hg38");
+        assert!(super::hub(&config, &hub).is_err());
+    }
+}
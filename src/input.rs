@@ -0,0 +1,324 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Read};
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::codec::{decoder, is_truncated};
+use crate::config::Config;
+use crate::convert::{decode_input, truncated};
+#[cfg(feature = "mmap")]
+use crate::detect::Compression;
+use crate::detect::{InputFormat, InputKind};
+use crate::error::Result;
+use crate::gxf::{is_comment, KeyMatcher, FASTA_DIRECTIVE};
+use crate::progress::{ProgressHook, ProgressReader};
+#[cfg(feature = "mmap")]
+use crate::utf8::into_text;
+use crate::utf8::Lines;
+use crate::utils::{child_ids, parse_row, Grouping};
+
+/// How the input file is read before parsing.
+///
+/// Only affects in-memory conversions; with `max_memory` set the input is
+/// always streamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IoStrategy {
+    /// io_uring when compiled in and allowed, buffered reads otherwise.
+    #[default]
+    Auto,
+    /// Streams the file through a buffered reader.
+    Buffered,
+    /// Reads the whole file with one sequential read, then decodes it;
+    /// often the fastest choice on Lustre/NFS.
+    ReadAll,
+    /// Maps the file into memory and parses uncompressed input in place.
+    Mmap,
+}
+
+/// Decoded input text, either owned or mapped from the file.
+pub(crate) enum Input {
+    Text(String),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl Input {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            Input::Text(text) => text,
+            // SAFETY: validated as UTF-8 when mapped
+            #[cfg(feature = "mmap")]
+            Input::Mapped(map) => unsafe { std::str::from_utf8_unchecked(map) },
+        }
+    }
+}
+
+/// Reads and decodes `config.input` with `config.io_strategy`, reporting
+/// progress to `hook`.
+pub(crate) fn read_input(
+    config: &Config,
+    kind: InputKind,
+    hook: &dyn ProgressHook,
+) -> Result<Input> {
+    #[cfg(feature = "mmap")]
+    if config.io_strategy == IoStrategy::Mmap && kind.compression == Compression::None {
+        return read_mapped(config, hook);
+    }
+
+    let contents = decode_input(
+        open_input(config, hook)?,
+        kind.compression,
+        config.allow_truncated,
+        config.lossy_utf8,
+    )?;
+    hook.finish();
+
+    Ok(Input::Text(contents))
+}
+
+/// Same as [`read_input`] for `Config::head`: decodes only the lines
+/// [`read_head`] keeps.
+pub(crate) fn read_input_head(
+    config: &Config,
+    kind: InputKind,
+    hook: &dyn ProgressHook,
+    n: usize,
+) -> Result<String> {
+    let reader = BufReader::new(decoder(open_input(config, hook)?, kind.compression)?);
+    let head = read_head(config, reader, kind.format, n)?;
+    hook.finish();
+
+    Ok(head)
+}
+
+/// Opens `config.input` with `config.io_strategy`, reporting progress to
+/// `hook`; the reader yields the raw, possibly compressed, bytes.
+fn open_input<'a>(config: &Config, hook: &'a dyn ProgressHook) -> Result<Box<dyn Read + 'a>> {
+    let path = config.input.as_path();
+    let buffered = || -> Result<Box<dyn Read + 'a>> {
+        let file = File::open(path)?;
+        hook.start(file.metadata().ok().map(|m| m.len()));
+        Ok(Box::new(ProgressReader::new(file, hook)))
+    };
+
+    match config.io_strategy {
+        IoStrategy::Auto => {
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            if let Some(bytes) = crate::uring::read_file(path, hook)? {
+                return Ok(Box::new(Cursor::new(bytes)));
+            }
+
+            buffered()
+        }
+        IoStrategy::Buffered => buffered(),
+        IoStrategy::ReadAll => {
+            let mut file = File::open(path)?;
+            let len = file.metadata()?.len();
+            hook.start(Some(len));
+
+            let mut bytes = Vec::with_capacity(len as usize);
+            file.read_to_end(&mut bytes)?;
+            hook.advance(bytes.len() as u64);
+
+            Ok(Box::new(Cursor::new(bytes)))
+        }
+        IoStrategy::Mmap => open_mapped(path, hook),
+    }
+}
+
+/// Reads lines from `reader` up to the first line of the `n + 1`th
+/// grouping key, for `Config::head`; the rest of the input is never read.
+///
+/// Annotations list each model's lines together, so this covers the first
+/// `n` models whole; a model with lines further down is cut short.
+pub(crate) fn read_head<R: BufRead>(
+    config: &Config,
+    mut reader: R,
+    format: InputFormat,
+    n: usize,
+) -> Result<String> {
+    let grouping = Grouping::from(config);
+    let keys = KeyMatcher::fallback(&grouping.feature);
+    let sep = config.separator(format);
+    let mut seen = HashSet::new();
+    let (mut head, mut buf, mut lines, mut records) =
+        (String::new(), Vec::new(), Lines::default(), 0);
+
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(err) if is_truncated(&err) => {
+                truncated(err, records, config.allow_truncated)?;
+                break;
+            }
+            Err(err) => return Err(err.into()),
+        }
+        let line = lines.next(&buf, config.lossy_utf8)?;
+        let row = line.trim_end_matches(['\n', '\r']);
+        if row.starts_with(FASTA_DIRECTIVE) {
+            break;
+        }
+        if !is_comment(row, &config.comment_chars) {
+            // INFO: bad lines are reported with their line number when the head is parsed
+            if let Ok(record) = parse_row(row, &keys, sep, config.input_coords) {
+                let key = match record.feature == grouping.parent {
+                    true => Some(record.attr.feature()).filter(|id| !id.is_empty()),
+                    false => child_ids(&record, &grouping).next(),
+                };
+                if let Some(key) = key.filter(|key| !seen.contains(*key)) {
+                    if seen.len() == n {
+                        break;
+                    }
+                    seen.insert(key.to_string());
+                }
+            }
+            records += usize::from(!row.trim().is_empty());
+        }
+        head.push_str(&line);
+    }
+
+    Ok(head)
+}
+
+#[cfg(feature = "mmap")]
+fn open_mapped<'a>(path: &Path, hook: &dyn ProgressHook) -> Result<Box<dyn Read + 'a>> {
+    let map = map_file(path)?;
+    hook.start(Some(map.len() as u64));
+    hook.advance(map.len() as u64);
+
+    Ok(Box::new(Cursor::new(map)))
+}
+
+#[cfg(not(feature = "mmap"))]
+fn open_mapped<'a>(_path: &Path, _hook: &dyn ProgressHook) -> Result<Box<dyn Read + 'a>> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "mmap support is not compiled in (enable the `mmap` feature)",
+    )
+    .into())
+}
+
+#[cfg(feature = "mmap")]
+fn map_file(path: &Path) -> Result<memmap2::Mmap> {
+    let file = File::open(path)?;
+    // SAFETY: the map is read-only; like every mmap reader, we assume the
+    // file is not truncated while it is being converted
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    #[cfg(unix)]
+    map.advise(memmap2::Advice::Sequential)?;
+
+    Ok(map)
+}
+
+/// Maps uncompressed input and parses it in place.
+#[cfg(feature = "mmap")]
+fn read_mapped(config: &Config, hook: &dyn ProgressHook) -> Result<Input> {
+    let map = map_file(&config.input)?;
+    let len = map.len() as u64;
+    hook.start(Some(len));
+
+    let input = match std::str::from_utf8(&map) {
+        Ok(_) => Input::Mapped(map),
+        // INFO: only invalid input pays for a copy, to report or replace the bad bytes
+        Err(_) => Input::Text(into_text(map.to_vec(), config.lossy_utf8)?),
+    };
+    hook.advance(len);
+    hook.finish();
+
+    Ok(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::Compression;
+    use crate::error::Gxf2BedError;
+    use crate::progress::NoProgress;
+
+    #[test]
+    fn test_strategies_read_the_same_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("io.gtf");
+        let gtf = "chr1\tHAVANA\texon\t11\t60\t.\t+\t.\ttranscript_id \"A\";\n";
+        std::fs::write(&path, gtf).unwrap();
+        let kind = InputKind {
+            format: InputFormat::Gtf,
+            compression: Compression::None,
+        };
+
+        for strategy in IoStrategy::value_variants() {
+            let config = Config::builder()
+                .input(&path)
+                .io_strategy(*strategy)
+                .build()
+                .unwrap();
+            match read_input(&config, kind, &NoProgress) {
+                Ok(input) => assert_eq!(input.as_str(), gtf),
+                Err(err) => assert!(
+                    cfg!(not(feature = "mmap")) && *strategy == IoStrategy::Mmap,
+                    "{err}"
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_head() {
+        let gtf = "#!genome-build GRCh38\n\
+                   chr1\t.\tgene\t1\t90\t.\t+\t.\tgene_id \"G\";\n\
+                   chr1\t.\texon\t1\t20\t.\t+\t.\tgene_id \"G\"; transcript_id \"A\";\n\
+                   chr1\t.\texon\t31\t40\t.\t+\t.\tgene_id \"G\"; transcript_id \"A\";\n\
+                   chr1\t.\texon\t51\t90\t.\t+\t.\tgene_id \"G\"; transcript_id \"B\";\n\
+                   chr1\t.\texon\t91\t99\t.\t+\t.\tgene_id \"G\"; transcript_id \"C\";\n";
+        let config = Config::default();
+        let head = |n| read_head(&config, gtf.as_bytes(), InputFormat::Gtf, n).unwrap();
+
+        assert_eq!(head(1).lines().count(), 4);
+        assert_eq!(head(2).lines().count(), 5);
+        assert_eq!(head(5), gtf);
+    }
+
+    #[test]
+    fn test_read_input_head_checks_utf8() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.gtf");
+        std::fs::write(
+            &path,
+            b"chr1\t.\texon\t1\t20\t.\t+\t.\ttranscript_id \"A\";\n\
+              chr1\t.\texon\t31\t40\t.\t+\t.\ttranscript_id \"A\"; gene_name \"Caf\xe9\";\n",
+        )
+        .unwrap();
+        let kind = InputKind {
+            format: InputFormat::Gtf,
+            compression: Compression::None,
+        };
+
+        for strategy in IoStrategy::value_variants() {
+            if cfg!(not(feature = "mmap")) && *strategy == IoStrategy::Mmap {
+                continue;
+            }
+            let config = |lossy| {
+                Config::builder()
+                    .input(&path)
+                    .io_strategy(*strategy)
+                    .lossy_utf8(lossy)
+                    .build()
+                    .unwrap()
+            };
+
+            let err = read_input_head(&config(false), kind, &NoProgress, 1).unwrap_err();
+            assert!(
+                matches!(err, Gxf2BedError::Parse { line: 2, .. }),
+                "{strategy:?}: {err}"
+            );
+            let head = read_input_head(&config(true), kind, &NoProgress, 1).unwrap();
+            assert!(head.contains("Caf\u{FFFD}"), "{strategy:?}");
+        }
+    }
+}
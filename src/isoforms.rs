@@ -0,0 +1,145 @@
+use std::cmp::Reverse;
+
+use clap::ValueEnum;
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::gxf::GenePred;
+
+/// How the isoforms of a gene are ranked by `--max-transcripts-per-gene`;
+/// ties go to the longer, then the first-named model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IsoformRank {
+    /// Longest spliced length first.
+    #[default]
+    Length,
+    /// Best `transcript_support_level` first (1 to 5, then NA or none).
+    Tsl,
+    /// Best tag first, in the order of [`TAGS`].
+    Tags,
+}
+
+/// Tags of preferred isoforms in GENCODE and Ensembl, best first.
+pub const TAGS: &[&str] = &[
+    "MANE_Select",
+    "Ensembl_canonical",
+    "basic",
+    "CCDS",
+    "appris_principal_1",
+];
+
+/// Sort key of an isoform: rank attribute priority, then longest first.
+type Rank = (u32, Reverse<u64>);
+
+/// Attributes captured to cap isoforms: the gene (`gene_id`, or the GFF3
+/// `Parent` of a transcript line), then the one ranked by `rank`.
+pub(crate) fn attrs(config: &Config) -> Vec<String> {
+    let mut attrs = vec![config.attr_key("gene_id"), config.attr_key("Parent")];
+    match config.rank_isoforms {
+        IsoformRank::Length => (),
+        IsoformRank::Tsl => attrs.push(config.attr_key("transcript_support_level")),
+        IsoformRank::Tags => attrs.push(config.attr_key("tag")),
+    }
+    attrs
+}
+
+/// Keeps the `limit` best models of each gene in `groups`, reading the
+/// attributes of [`attrs`] from `GenePred::attrs` at `offset`; returns how
+/// many were dropped. Models without a gene are kept.
+pub(crate) fn cap(
+    groups: &mut HashMap<String, GenePred>,
+    limit: usize,
+    rank: IsoformRank,
+    offset: usize,
+) -> usize {
+    let mut genes: HashMap<(&str, &str), Vec<(Rank, &str)>> = HashMap::new();
+    for (key, info) in groups.iter() {
+        let attrs = info.attrs.get(offset..).unwrap_or_default();
+        let Some(gene) = attrs.iter().take(2).flatten().next() else {
+            continue;
+        };
+        let value = attrs.get(2).and_then(Option::as_deref);
+        let length = info.get_exon_sizes().iter().sum::<u64>();
+        genes
+            .entry((gene.as_str(), info.chr.as_str()))
+            .or_default()
+            .push(((priority(rank, value), Reverse(length)), key.as_str()));
+    }
+
+    let dropped = genes
+        .into_values()
+        .filter(|models| models.len() > limit)
+        .flat_map(|mut models| {
+            models.sort_unstable();
+            models.split_off(limit)
+        })
+        .map(|(_, key)| key.to_string())
+        .collect::<Vec<_>>();
+    for key in &dropped {
+        groups.remove(key);
+    }
+
+    dropped.len()
+}
+
+/// Rank of a model by its `rank` attribute `value`; lower is better.
+fn priority(rank: IsoformRank, value: Option<&str>) -> u32 {
+    match rank {
+        IsoformRank::Length => 0,
+        // INFO: Ensembl appends notes, e.g. "1 (assigned to previous version 5)"
+        IsoformRank::Tsl => value
+            .and_then(|tsl| tsl.split_whitespace().next()?.parse().ok())
+            .unwrap_or(u32::MAX),
+        IsoformRank::Tags => value
+            .and_then(|tags| {
+                let tags = tags.split(',').collect::<Vec<_>>();
+                TAGS.iter().position(|tag| tags.contains(tag))
+            })
+            .map_or(u32::MAX, |index| index as u32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(gene: &str, exons: &[(u64, u64)], value: Option<&str>) -> GenePred {
+        let mut info = GenePred::new();
+        for (start, end) in exons {
+            info.add_child("chr1", *start, *end, crate::gxf::Strand::Forward);
+        }
+        info.attrs = vec![Some(gene.to_string()), None, value.map(str::to_string)];
+        info
+    }
+
+    fn kept(rank: IsoformRank, limit: usize) -> Vec<String> {
+        let mut groups = HashMap::new();
+        groups.insert("A".to_string(), model("G1", &[(0, 100)], Some("3")));
+        groups.insert(
+            "B".to_string(),
+            model("G1", &[(0, 50)], Some("basic,Ensembl_canonical")),
+        );
+        groups.insert(
+            "C".to_string(),
+            model("G1", &[(0, 80)], Some("1 (assigned)")),
+        );
+        groups.insert("D".to_string(), model("G2", &[(0, 10)], None));
+
+        let dropped = cap(&mut groups, limit, rank, 0);
+        assert_eq!(dropped + groups.len(), 4);
+        let mut names = groups.into_keys().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_cap_isoforms() {
+        assert_eq!(kept(IsoformRank::Length, 1), ["A", "D"]);
+        assert_eq!(kept(IsoformRank::Length, 2), ["A", "C", "D"]);
+        assert_eq!(kept(IsoformRank::Tsl, 1), ["C", "D"]);
+        assert_eq!(kept(IsoformRank::Tags, 1), ["B", "D"]);
+        assert_eq!(kept(IsoformRank::Tags, 3), ["A", "B", "C", "D"]);
+    }
+}
@@ -1,3 +1,70 @@
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod bed;
+pub mod checksum;
+pub mod chrom;
 pub mod cli;
+mod codec;
+pub mod compat;
+pub mod compute;
+pub mod config;
+mod containment;
+pub mod convert;
+#[cfg(feature = "db")]
+pub mod db;
+pub mod detect;
+pub mod diagnostic;
+pub mod error;
+pub mod escape;
+pub mod fasta;
+pub mod freshness;
 pub mod gxf;
+pub mod hints;
+pub mod hub;
+pub mod input;
+pub mod isoforms;
+mod loci;
+pub mod logging;
+pub mod output;
+mod par_y;
+pub mod plan;
+pub mod preset;
+pub mod profile;
+pub mod progress;
+pub mod remote;
+pub mod runner;
+pub mod source;
+pub mod spill;
+pub mod stats;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
+mod utf8;
 pub mod utils;
+
+#[cfg(feature = "async")]
+pub use asynchronous::{run_async, run_async_with_io};
+pub use bed::{
+    BedEmitter, BedRecord, BedType, Column, Columns, Emit, Exons, OneBased, SpliceSites,
+};
+pub use checksum::Checksum;
+pub use chrom::{ChromBounds, ChromSizes};
+pub use compat::Compat;
+pub use config::{Config, ConfigBuilder, ConfigError};
+pub use convert::{
+    convert_str, records, records_from_reader, records_from_source, run, run_from_reader,
+    run_from_source, run_to_writer, run_with_emitter, run_with_progress, Records, RunStats,
+};
+pub use detect::{detect_from_reader, detect_input_kind, InputFormat};
+pub use error::{Gxf2BedError, Result};
+pub use escape::Escape;
+pub use gxf::{Degenerate, DuplicateIds, InputCoords, MixedLoci, MixedStrand, OutOfBounds};
+pub use hints::hints;
+pub use hub::{hub, Hub};
+pub use input::IoStrategy;
+pub use isoforms::IsoformRank;
+pub use plan::{plan, Plan};
+pub use preset::Preset;
+pub use profile::{Phase, Profile};
+pub use runner::Runner;
+pub use source::{AnnotationSource, Feature, GxfReader};
+pub use stats::{stats, Stats, StatsFormat};
@@ -0,0 +1,168 @@
+use std::collections::VecDeque;
+
+use hashbrown::HashMap;
+
+use crate::error::Result;
+use crate::gxf::{GenePred, MixedStrand, Strand};
+use crate::par_y;
+
+/// Separates the feature ID (with its PAR mark) from the locus part of a
+/// grouping key. It sorts before any ID character, so all loci of an ID
+/// are adjacent in a name-ordered stream, and before the PAR mark, so the
+/// chrX copy still comes first.
+const LOCUS: &str = "\0\0";
+
+/// Grouping key for feature `id` on `chrom` and `strand`.
+///
+/// Features sharing an ID on different chromosomes or strands (reused IDs,
+/// broken liftovers) are keyed apart instead of merging into one chimeric
+/// model; [`resolve`] then names the parts and tags them with
+/// `GenePred::loci`.
+#[inline]
+pub(crate) fn key(id: &str, chrom: &str, strand: Strand) -> String {
+    let mut key = par_y::key(id, chrom);
+    key.reserve(LOCUS.len() + chrom.len() + 1);
+    key.push_str(LOCUS);
+    key.push_str(chrom);
+    key.push(match strand {
+        Strand::Forward => '+',
+        Strand::Reverse => '-',
+        Strand::Unknown => '.',
+    });
+    key
+}
+
+/// Strand part of the grouping key of a feature on `strand`: its own when
+/// strands are split apart, none when `policy` groups them together.
+#[inline]
+pub(crate) fn key_strand(policy: MixedStrand, strand: Strand) -> Strand {
+    match policy {
+        MixedStrand::Split => strand,
+        _ => Strand::Unknown,
+    }
+}
+
+/// The ID part of `key` and its `<chrom><strand>` locus.
+fn split(key: &str) -> (&str, &str) {
+    key.split_once(LOCUS).unwrap_or((key, ""))
+}
+
+/// Output key of a group: the bare ID (still PAR-marked) when it is on one
+/// locus, `<id>_<chrom>_<strand>` when its ID is shared by `loci` loci.
+fn name(mut key: String, info: &mut GenePred, loci: u32) -> String {
+    let (id, locus) = split(&key);
+    if loci < 2 {
+        let len = id.len();
+        key.truncate(len);
+        return key;
+    }
+
+    info.loci = loci;
+    let id = par_y::bare(id);
+    let (chrom, strand) = locus.split_at(locus.len().saturating_sub(1));
+    format!("{}_{}_{}", id, chrom, strand)
+}
+
+/// Turns the grouping keys of `groups` back into IDs, naming the models of
+/// IDs found on several loci apart.
+pub(crate) fn resolve(groups: &mut HashMap<String, GenePred>) {
+    let mut loci: HashMap<&str, u32> = HashMap::new();
+    for key in groups.keys() {
+        *loci.entry(split(key).0).or_default() += 1;
+    }
+    let shared = loci
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(id, count)| (id.to_string(), count))
+        .collect::<HashMap<_, _>>();
+
+    *groups = std::mem::take(groups)
+        .into_iter()
+        .map(|(key, mut info)| {
+            let loci = shared.get(split(&key).0).copied().unwrap_or(1);
+            (name(key, &mut info, loci), info)
+        })
+        .collect();
+}
+
+/// Same as [`resolve`] for groups streamed in name order.
+pub(crate) struct Resolve<I: Iterator> {
+    inner: std::iter::Peekable<I>,
+    ready: VecDeque<(String, GenePred)>,
+}
+
+impl<I: Iterator<Item = Result<(String, GenePred)>>> Resolve<I> {
+    pub(crate) fn new(inner: I) -> Self {
+        Self {
+            inner: inner.peekable(),
+            ready: VecDeque::new(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<(String, GenePred)>>> Iterator for Resolve<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(group) = self.ready.pop_front() {
+            return Some(Ok(group));
+        }
+
+        let first = match self.inner.next()? {
+            Ok(group) => group,
+            Err(err) => return Some(Err(err)),
+        };
+        let mut run = vec![first];
+        while let Some(Ok((key, _))) = self.inner.peek() {
+            if split(key).0 != split(&run[0].0).0 {
+                break;
+            }
+            run.extend(self.inner.next().and_then(|group| group.ok()));
+        }
+
+        let loci = run.len() as u32;
+        self.ready = run
+            .into_iter()
+            .map(|(key, mut info)| (name(key, &mut info, loci), info))
+            .collect();
+        self.ready.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_ids_split_by_locus() {
+        let keys = [
+            key("T1", "chr1", Strand::Forward),
+            key("T1", "chr2", Strand::Forward),
+            key("T2", "chr1", Strand::Reverse),
+            key("T3", "chrX", Strand::Forward),
+            key("T3", "chrY", Strand::Forward),
+        ];
+        let expected = ["T1_chr1_+", "T1_chr2_+", "T2", "T3", "T3_PAR_Y"];
+
+        let mut groups = keys
+            .iter()
+            .map(|k| (k.clone(), GenePred::new()))
+            .collect::<HashMap<_, _>>();
+        resolve(&mut groups);
+        assert_eq!(groups["T1_chr2_+"].loci, 2);
+        assert_eq!(groups["T2"].loci, 0);
+        par_y::resolve(&mut groups);
+        let mut names = groups.into_keys().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, expected);
+
+        let mut sorted = keys.to_vec();
+        sorted.sort();
+        let streamed = par_y::Resolve::new(Resolve::new(
+            sorted.into_iter().map(|k| Ok((k, GenePred::new()))),
+        ))
+        .map(|group| group.unwrap().0)
+        .collect::<Vec<_>>();
+        assert_eq!(streamed, expected);
+    }
+}
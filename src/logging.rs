@@ -0,0 +1,178 @@
+use std::ffi::OsString;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::ValueEnum;
+use log::kv::{Error as KvError, Key, Value, VisitSource};
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+use serde_json::{Map, Value as Json};
+use simple_logger::SimpleLogger;
+
+/// How log events are rendered on stderr.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines with timestamp, level and module.
+    #[default]
+    Text,
+    /// One JSON object per line, with key-value fields flattened in.
+    Json,
+}
+
+/// When stderr output (banner, log levels, error snippets) is colored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Only when stderr is a terminal and `NO_COLOR` is unset or empty.
+    #[default]
+    Auto,
+    /// Always, even when stderr is captured or `NO_COLOR` is set.
+    Always,
+    /// Never.
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to color stderr, given whether it is a terminal and the
+    /// value of `NO_COLOR` (<https://no-color.org>).
+    pub fn enabled(self, is_terminal: bool, no_color: Option<OsString>) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => is_terminal && no_color.is_none_or(|value| value.is_empty()),
+        }
+    }
+}
+
+/// Whether [`init`] installed the JSON logger.
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Installs the global logger for `format`, filtered at `level`, and turns
+/// colors on or off for everything written to stderr as `color` says.
+pub fn init(
+    format: LogFormat,
+    level: LevelFilter,
+    color: ColorChoice,
+) -> Result<(), SetLoggerError> {
+    let colored = color.enabled(
+        std::io::stderr().is_terminal(),
+        std::env::var_os("NO_COLOR"),
+    );
+    colored::control::set_override(colored);
+    JSON.store(format == LogFormat::Json, Ordering::Relaxed);
+    match format {
+        LogFormat::Text => SimpleLogger::new()
+            .with_level(level)
+            .with_colors(colored)
+            .init(),
+        LogFormat::Json => {
+            log::set_boxed_logger(Box::new(JsonLogger { level }))?;
+            log::set_max_level(level);
+            Ok(())
+        }
+    }
+}
+
+/// Whether log events go to stderr as text, so multi-line output such as an
+/// error's source snippet can follow them.
+pub fn is_text() -> bool {
+    !JSON.load(Ordering::Relaxed)
+}
+
+/// Writes each event as a JSON line to stderr, e.g.
+/// `{"ts":1712345678901,"level":"WARN","target":"gxf2bed","message":"...","line":12}`.
+pub struct JsonLogger {
+    level: LevelFilter,
+}
+
+impl JsonLogger {
+    fn render(record: &Record) -> Json {
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+
+        let mut event = Map::new();
+        event.insert("ts".into(), ts.into());
+        event.insert("level".into(), record.level().as_str().into());
+        event.insert("target".into(), record.target().into());
+        event.insert("message".into(), record.args().to_string().into());
+
+        let mut fields = Fields(&mut event);
+        let _ = record.key_values().visit(&mut fields);
+
+        Json::Object(event)
+    }
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = Self::render(record).to_string();
+        let _ = writeln!(std::io::stderr().lock(), "{}", line);
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
+
+/// Copies key-value pairs into the event, keeping numbers and booleans typed.
+struct Fields<'a>(&'a mut Map<String, Json>);
+
+impl<'kvs> VisitSource<'kvs> for Fields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), KvError> {
+        let value = if let Some(n) = value.to_u64() {
+            n.into()
+        } else if let Some(n) = value.to_i64() {
+            n.into()
+        } else if let Some(n) = value.to_f64() {
+            n.into()
+        } else if let Some(b) = value.to_bool() {
+            b.into()
+        } else {
+            value.to_string().into()
+        };
+
+        self.0.insert(key.as_str().to_string(), value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_choice() {
+        assert!(ColorChoice::Auto.enabled(true, None));
+        assert!(ColorChoice::Auto.enabled(true, Some(OsString::new())));
+        assert!(!ColorChoice::Auto.enabled(true, Some("1".into())));
+        assert!(!ColorChoice::Auto.enabled(false, None));
+        assert!(ColorChoice::Always.enabled(false, Some("1".into())));
+        assert!(!ColorChoice::Never.enabled(true, None));
+    }
+
+    #[test]
+    fn test_render_json_with_fields() {
+        let fields: [(&str, Value); 2] = [("line", 12u64.into()), ("stage", "parse".into())];
+        let event = JsonLogger::render(
+            &Record::builder()
+                .level(log::Level::Warn)
+                .target("gxf2bed")
+                .args(format_args!("bad \"row\""))
+                .key_values(&fields)
+                .build(),
+        );
+
+        assert_eq!(event["level"], "WARN");
+        assert_eq!(event["message"], "bad \"row\"");
+        assert_eq!(event["line"], 12);
+        assert_eq!(event["stage"], "parse");
+    }
+}
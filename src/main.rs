@@ -1,29 +1,235 @@
-use clap::Parser;
-use log::Level;
+use clap::FromArgMatches;
 
 use gxf2bed::{
     cli::Args,
-    utils::{convert, initialize},
+    freshness::{is_up_to_date, write_sidecar},
+    hints::{detect, hints},
+    logging,
+    plan::{plan, DRY_RUN_LINES},
+    progress::stderr_hook,
+    remote::stage,
+    run_with_progress,
+    utils::initialize,
+    Config, Gxf2BedError, Phase,
 };
 
 fn main() {
-    initialize();
     let st = std::time::Instant::now();
-    simple_logger::init_with_level(Level::Info).unwrap();
+    let matches = Args::matches_with_env(std::env::args_os(), |var| std::env::var(var).ok())
+        .unwrap_or_else(|e| e.exit());
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
-    let args: Args = Args::parse();
-    args.check().unwrap_or_else(|e| {
-        log::error!("{}", e);
-        std::process::exit(1);
-    });
+    if let Some(command) = &args.command {
+        command
+            .run(&mut std::io::stdout().lock())
+            .unwrap_or_else(|e| {
+                eprintln!("ERROR: {}", e);
+                std::process::exit(e.exit_code());
+            });
+        return;
+    }
 
-    log::info!("{:?}", args);
-    rayon::ThreadPoolBuilder::new()
-        .num_threads(args.threads)
-        .build()
-        .unwrap();
+    logging::init(args.log_format, args.log_level(), args.color).unwrap();
+    if args.show_banner() {
+        initialize();
+    }
+    args.apply_config_file(&matches)
+        .and_then(|_| {
+            args.apply_preset(&matches);
+            args.check()
+        })
+        .unwrap_or_else(|e| fail(e.into()));
 
-    convert(args);
+    log::debug!("{:?}", args);
+    let (dry_run, skip_if_newer, show_config) =
+        (args.dry_run, args.skip_if_newer, args.show_config);
+    let fixed = args.fixed_layout(&matches);
+    let mut config = Config::from(args);
+    let given_input = config.input.clone();
+    // INFO: remote inputs are downloaded once, before anything reads them
+    let _staged = stage(&mut config, &*stderr_hook()).unwrap_or_else(|e| fail(e));
+    if fixed.len() < 4 {
+        // INFO: an unreadable input is reported by the run itself
+        if let Ok(changes) = detect(&mut config, DRY_RUN_LINES, &fixed) {
+            for change in changes {
+                log::info!("Detected {} from the input", change);
+            }
+        }
+    }
+    config.validate().unwrap_or_else(|e| fail(e.into()));
 
-    log::info!("Elapsed: {:.4?} secs", st.elapsed().as_secs_f32());
+    if show_config {
+        config.input = given_input;
+        print!("{}", config.to_toml());
+        return;
+    }
+
+    if dry_run {
+        let plan = plan(&config, DRY_RUN_LINES).unwrap_or_else(|e| fail(e.in_file(&config.input)));
+        println!("{}", plan);
+        return;
+    }
+
+    if skip_if_newer && is_up_to_date(&config) {
+        log::info!("{:?} is up to date, skipping", config.output_path());
+        return;
+    }
+
+    let hook = stderr_hook();
+    let stats =
+        run_with_progress(&config, &*hook).unwrap_or_else(|e| fail(e.in_file(&config.input)));
+    if skip_if_newer {
+        write_sidecar(&config).unwrap_or_else(|e| fail(e));
+    }
+
+    log::info!(
+        written = stats.written,
+        records_per_sec = stats.records_per_sec(),
+        input_mb_per_sec = stats.input_mb_per_sec(),
+        output_mb_per_sec = stats.output_mb_per_sec();
+        "{} records written ({:.0} records/s, {:.1} MB/s in, {:.1} MB/s out)",
+        stats.written,
+        stats.records_per_sec(),
+        stats.input_mb_per_sec(),
+        stats.output_mb_per_sec()
+    );
+    if let (Some(algorithm), Some(checksum)) = (config.checksum, &stats.checksum) {
+        log::info!(
+            checksum = checksum.as_str(), algorithm = algorithm.name();
+            "{} {:?}: {}",
+            algorithm.name(),
+            config.output_path(),
+            checksum
+        );
+    }
+    log::warn!(skipped = stats.skipped; "Skipped {} records with no childs!", stats.skipped);
+    if stats.written == 0 {
+        match hints(&config, DRY_RUN_LINES) {
+            Ok(hints) => hints.iter().for_each(|hint| log::warn!("{}", hint)),
+            Err(err) => log::debug!("could not scan the input for hints: {}", err),
+        }
+    }
+    if stats.merged > 0 {
+        log::info!(merged = stats.merged; "Merged overlapping blocks in {} records", stats.merged);
+    }
+    if stats.cds_exons > 0 {
+        log::info!(cds_exons = stats.cds_exons; "Built the blocks of {} records from their CDS", stats.cds_exons);
+    }
+    if stats.escaped > 0 {
+        log::warn!(
+            escaped = stats.escaped;
+            "{} records had tabs, newlines or spaces in their name or attribute columns ({:?}, see --escape)",
+            stats.escaped,
+            config.escape
+        );
+    }
+    if stats.degenerate > 0 {
+        log::warn!(
+            degenerate = stats.degenerate;
+            "{} records had zero- or negative-length features ({:?})",
+            stats.degenerate,
+            config.degenerate
+        );
+    }
+    if stats.duplicate_ids > 0 {
+        log::warn!(
+            duplicate_ids = stats.duplicate_ids;
+            "{} records share their ID with another parent line ({:?})",
+            stats.duplicate_ids,
+            config.duplicate_ids
+        );
+    }
+    if stats.mixed_loci > 0 {
+        log::warn!(
+            mixed_loci = stats.mixed_loci;
+            "{} records share their ID with features on other chromosomes or strands ({:?})",
+            stats.mixed_loci,
+            config.mixed_loci
+        );
+    }
+    if stats.mixed_strand > 0 {
+        log::warn!(
+            mixed_strand = stats.mixed_strand;
+            "{} records have features on both strands of a chromosome ({:?})",
+            stats.mixed_strand,
+            config.mixed_strand
+        );
+    }
+    if stats.off_chrom > 0 {
+        log::warn!(
+            off_chrom = stats.off_chrom;
+            "{} records do not fit within their chromosome ({:?})",
+            stats.off_chrom,
+            config.chrom_bounds
+        );
+    }
+    if stats.out_of_frame > 0 {
+        log::warn!(
+            out_of_frame = stats.out_of_frame;
+            "{} records have a CDS length that is not a multiple of 3",
+            stats.out_of_frame
+        );
+    }
+    if config.check_codons {
+        log::info!(
+            missing_codons = stats.missing_codons, misplaced_codons = stats.misplaced_codons;
+            "{} coding records lack a start or stop codon, {} have one away from their CDS ends",
+            stats.missing_codons,
+            stats.misplaced_codons
+        );
+    }
+    if config.check_orf {
+        log::info!(
+            bad_orfs = stats.bad_orfs;
+            "{} coding records are not a clean ORF in the --fasta sequence",
+            stats.bad_orfs
+        );
+    }
+    if let Some(report) = config
+        .containment_report
+        .as_ref()
+        .filter(|_| stats.uncontained > 0)
+    {
+        log::warn!(
+            uncontained = stats.uncontained;
+            "{} records have exons outside their transcript or CDS outside their exons (see {:?})",
+            stats.uncontained,
+            report
+        );
+    }
+    if stats.out_of_bounds > 0 {
+        log::warn!(
+            out_of_bounds = stats.out_of_bounds;
+            "{} records had blocks outside their parent span ({:?})",
+            stats.out_of_bounds,
+            config.out_of_bounds
+        );
+    }
+    log::info!(
+        written = stats.written, skipped = stats.skipped, elapsed_secs = st.elapsed().as_secs_f64();
+        "Elapsed: {:.4?} secs", st.elapsed().as_secs_f32()
+    );
+    if let Some(profile) = &stats.profile {
+        for (phase, line) in Phase::ALL.iter().zip(profile.to_string().lines()) {
+            log::info!(
+                phase = phase.name(),
+                secs = profile.time(*phase).as_secs_f64(),
+                peak_mb = profile.peak_mb(*phase);
+                "Profile: {}", line
+            );
+        }
+    }
+}
+
+/// Logs a conversion error, with the offending line number when known and
+/// the line itself in text logs, and exits with the code of its error class.
+fn fail(err: Gxf2BedError) -> ! {
+    match &err {
+        Gxf2BedError::Parse { line, .. } => log::error!(line = *line; "{}", err),
+        _ => log::error!("{}", err),
+    }
+    if let Some(span) = err.span().filter(|_| logging::is_text()) {
+        eprintln!("{}", span);
+    }
+    std::process::exit(err.exit_code());
 }
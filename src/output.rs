@@ -0,0 +1,599 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "parallel")]
+use std::collections::BTreeMap;
+#[cfg(feature = "parallel")]
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::bed::{BedEmitter, BedType, OneBased, Tsv};
+use crate::codec::unavailable;
+#[cfg(feature = "parallel")]
+use crate::convert::in_sized_pool;
+use crate::detect::Compression;
+
+/// Plain bytes per independently compressed member in parallel mode.
+#[cfg(feature = "parallel")]
+const CHUNK_BYTES: usize = 1 << 20;
+
+/// Returns the compression implied by an output path (`.gz`, `.zst`, `.bz2`).
+pub fn output_compression<P: AsRef<Path>>(path: P) -> Compression {
+    path.as_ref()
+        .extension()
+        .and_then(|s| s.to_str())
+        .and_then(Compression::from_extension)
+        .unwrap_or(Compression::None)
+}
+
+/// Derives `<stem>.bed` from an input path, dropping any compression and
+/// annotation extension (`foo.gtf.gz` becomes `foo.bed`).
+pub fn derive_output_name<P: AsRef<Path>>(input: P) -> PathBuf {
+    let input = input.as_ref();
+    let mut stem = PathBuf::from(input.file_name().unwrap_or_default());
+    if let Some(ext) = stem.extension().and_then(|s| s.to_str()) {
+        if Compression::from_extension(ext).is_some() {
+            stem.set_extension("");
+        }
+    }
+
+    stem.with_extension("bed")
+}
+
+/// Writer that compresses everything written to it with the chosen codec.
+pub enum Encoder<W: Write> {
+    Plain(W),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<W>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<'static, W>),
+    #[cfg(feature = "bzip2")]
+    Bzip2(bzip2::write::BzEncoder<W>),
+    #[cfg(feature = "parallel")]
+    Parallel(ParallelEncoder<W>),
+}
+
+impl<W: Write> Encoder<W> {
+    /// Fails if the codec for `compression` was not compiled in.
+    pub fn new(inner: W, compression: Compression) -> std::io::Result<Self> {
+        Self::with_level(inner, compression, None)
+    }
+
+    /// Same as [`Encoder::new`] with an explicit codec `level`; `None` keeps
+    /// the fast defaults (gzip/bzip2 level 1, zstd level 3).
+    #[cfg_attr(
+        not(any(feature = "gzip", feature = "zstd", feature = "bzip2")),
+        allow(unused_variables)
+    )]
+    pub fn with_level(
+        inner: W,
+        compression: Compression,
+        level: Option<u32>,
+    ) -> std::io::Result<Self> {
+        match compression {
+            Compression::None => Ok(Encoder::Plain(inner)),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Ok(Encoder::Gzip(flate2::write::GzEncoder::new(
+                inner,
+                level.map_or(flate2::Compression::fast(), flate2::Compression::new),
+            ))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Ok(Encoder::Zstd(zstd::Encoder::new(
+                inner,
+                level.map_or(0, |l| l as i32),
+            )?)),
+            #[cfg(feature = "bzip2")]
+            Compression::Bzip2 => Ok(Encoder::Bzip2(bzip2::write::BzEncoder::new(
+                inner,
+                level.map_or(bzip2::Compression::fast(), bzip2::Compression::new),
+            ))),
+            #[allow(unreachable_patterns)]
+            other => Err(unavailable(other)),
+        }
+    }
+
+    /// Same as [`Encoder::with_level`], compressing on `threads` workers when
+    /// more than one is given (see [`ParallelEncoder`]).
+    #[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+    pub fn parallel(
+        inner: W,
+        compression: Compression,
+        level: Option<u32>,
+        threads: usize,
+    ) -> std::io::Result<Self> {
+        #[cfg(feature = "parallel")]
+        if threads > 1 && compression != Compression::None {
+            return Ok(Encoder::Parallel(ParallelEncoder::new(
+                inner,
+                compression,
+                level,
+                threads,
+            )?));
+        }
+
+        Self::with_level(inner, compression, level)
+    }
+
+    /// Writes any pending compressed data and returns the inner writer.
+    pub fn finish(self) -> std::io::Result<W> {
+        match self {
+            Encoder::Plain(mut inner) => {
+                inner.flush()?;
+                Ok(inner)
+            }
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.finish(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.finish(),
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(encoder) => encoder.finish(),
+            #[cfg(feature = "parallel")]
+            Encoder::Parallel(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Encoder::Plain(inner) => inner.write(buf),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.write(buf),
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(encoder) => encoder.write(buf),
+            #[cfg(feature = "parallel")]
+            Encoder::Parallel(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Encoder::Plain(inner) => inner.flush(),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.flush(),
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(encoder) => encoder.flush(),
+            #[cfg(feature = "parallel")]
+            Encoder::Parallel(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Compresses fixed-size chunks of output concurrently, pigz-style.
+///
+/// Each chunk becomes an independent gzip member, zstd frame or bzip2
+/// stream; their concatenation is a valid file for every standard decoder.
+/// Chunks are handed to the pool as soon as they fill up and chunk `i` is
+/// written once it and all its predecessors are compressed, so at most two
+/// chunks per worker are in flight and memory does not grow with the output.
+#[cfg(feature = "parallel")]
+pub struct ParallelEncoder<W: Write> {
+    inner: W,
+    compression: Compression,
+    level: Option<u32>,
+    /// Own pool, or `None` to spawn on the pool the encoder was created in.
+    pool: Option<rayon::ThreadPool>,
+    threads: usize,
+    chunk: Vec<u8>,
+    tx: Sender<(usize, std::io::Result<Vec<u8>>)>,
+    rx: Receiver<(usize, std::io::Result<Vec<u8>>)>,
+    /// Compressed members that arrived ahead of the next one to write.
+    ready: BTreeMap<usize, Vec<u8>>,
+    submitted: usize,
+    written: usize,
+}
+
+#[cfg(feature = "parallel")]
+impl<W: Write> ParallelEncoder<W> {
+    pub fn new(
+        inner: W,
+        compression: Compression,
+        level: Option<u32>,
+        threads: usize,
+    ) -> std::io::Result<Self> {
+        // INFO: fails early if the codec is not compiled in
+        Encoder::with_level(std::io::sink(), compression, level)?;
+        let pool = match in_sized_pool(threads) {
+            true => None,
+            false => Some(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .map_err(std::io::Error::other)?,
+            ),
+        };
+        let (tx, rx) = channel();
+
+        Ok(Self {
+            inner,
+            compression,
+            level,
+            pool,
+            threads,
+            chunk: Vec::with_capacity(CHUNK_BYTES),
+            tx,
+            rx,
+            ready: BTreeMap::new(),
+            submitted: 0,
+            written: 0,
+        })
+    }
+
+    /// Hands `chunk` to the pool, then writes whatever is ready in order,
+    /// blocking only while the in-flight window is full.
+    fn submit(&mut self, chunk: Vec<u8>) -> std::io::Result<()> {
+        let (compression, level) = (self.compression, self.level);
+        let (idx, tx) = (self.submitted, self.tx.clone());
+        let job = move || {
+            let member = Encoder::with_level(Vec::new(), compression, level).and_then(|mut e| {
+                e.write_all(&chunk)?;
+                e.finish()
+            });
+            // INFO: the receiver is only gone if writing already failed
+            let _ = tx.send((idx, member));
+        };
+        match &self.pool {
+            Some(pool) => pool.spawn(job),
+            None => rayon::spawn(job),
+        }
+        self.submitted += 1;
+
+        while let Ok(done) = self.rx.try_recv() {
+            self.accept(done)?;
+        }
+        while self.submitted - self.written >= 2 * self.threads {
+            self.wait()?;
+        }
+        Ok(())
+    }
+
+    /// Blocks for the next compressed member to come back from the pool.
+    fn wait(&mut self) -> std::io::Result<()> {
+        let done = self
+            .rx
+            .recv()
+            .map_err(|_| std::io::Error::other("compression worker disconnected"))?;
+        self.accept(done)
+    }
+
+    /// Stores a finished member and writes every member that is now next in line.
+    fn accept(&mut self, (idx, member): (usize, std::io::Result<Vec<u8>>)) -> std::io::Result<()> {
+        self.ready.insert(idx, member?);
+        while let Some(member) = self.ready.remove(&self.written) {
+            self.inner.write_all(&member)?;
+            self.written += 1;
+        }
+        Ok(())
+    }
+
+    /// Waits until every submitted chunk has been written.
+    fn drain(&mut self) -> std::io::Result<()> {
+        while self.written < self.submitted {
+            self.wait()?;
+        }
+        Ok(())
+    }
+
+    /// Compresses what is left and returns the inner writer.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        // INFO: an empty output still gets one member so it decodes cleanly
+        if !self.chunk.is_empty() || self.submitted == 0 {
+            let chunk = std::mem::take(&mut self.chunk);
+            self.submit(chunk)?;
+        }
+        self.drain()?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<W: Write> Write for ParallelEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.chunk.extend_from_slice(buf);
+        if self.chunk.len() >= CHUNK_BYTES {
+            let chunk = std::mem::replace(&mut self.chunk, Vec::with_capacity(CHUNK_BYTES));
+            self.submit(chunk)?;
+        }
+        Ok(buf.len())
+    }
+
+    /// Writes out complete chunks only; a partial chunk waits for more data.
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.drain()?;
+        self.inner.flush()
+    }
+}
+
+/// Buffered, possibly compressed output sink.
+pub type OutputWriter<W> = BufWriter<Encoder<W>>;
+
+/// Wraps `inner` with the compression implied by `path`, at `level` if given,
+/// compressing on `threads` workers.
+pub fn wrap_output<W: Write, P: AsRef<Path>>(
+    path: P,
+    inner: W,
+    level: Option<u32>,
+    threads: usize,
+) -> std::io::Result<OutputWriter<W>> {
+    Ok(BufWriter::new(Encoder::parallel(
+        inner,
+        output_compression(path),
+        level,
+        threads,
+    )?))
+}
+
+/// Creates `path` and wraps it with the compression implied by its extension.
+pub fn create_output<P: AsRef<Path>>(
+    path: P,
+    level: Option<u32>,
+    threads: usize,
+) -> std::io::Result<OutputWriter<File>> {
+    let file = File::create(path.as_ref())?;
+    wrap_output(path, file, level, threads)
+}
+
+/// Stdout copy of the output (`--tee`). A reader that goes away early
+/// (e.g. `| head`) ends the copy but not the conversion.
+pub(crate) struct Tee<W: Write> {
+    inner: Option<W>,
+}
+
+impl<W: Write> Tee<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self { inner: Some(inner) }
+    }
+
+    fn closed(&mut self, result: std::io::Result<()>) -> std::io::Result<()> {
+        match result {
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {
+                log::warn!("stdout was closed; writing the remaining records to files only");
+                self.inner = None;
+                Ok(())
+            }
+            result => result,
+        }
+    }
+}
+
+impl<W: Write> Write for Tee<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        let Some(inner) = &mut self.inner else {
+            return Ok(());
+        };
+        let result = inner.write_all(buf);
+        self.closed(result)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let Some(inner) = &mut self.inner else {
+            return Ok(());
+        };
+        let result = inner.flush();
+        self.closed(result)
+    }
+}
+
+/// Layout of an extra output (`--out FORMAT=PATH`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Bed3,
+    Bed4,
+    Bed5,
+    Bed6,
+    Bed9,
+    Bed12,
+    /// Tab-separated table with a header row (see [`Tsv`]).
+    Tsv,
+}
+
+impl OutputFormat {
+    /// Emitter for this layout; BED layouts follow `one_based`.
+    pub fn emitter(&self, one_based: bool) -> Box<dyn BedEmitter> {
+        let bed_type = match self {
+            OutputFormat::Bed3 => BedType::Bed3,
+            OutputFormat::Bed4 => BedType::Bed4,
+            OutputFormat::Bed5 => BedType::Bed5,
+            OutputFormat::Bed6 => BedType::Bed6,
+            OutputFormat::Bed9 => BedType::Bed9,
+            OutputFormat::Bed12 => BedType::Bed12,
+            OutputFormat::Tsv => return Box::new(Tsv),
+        };
+
+        match one_based {
+            true => Box::new(OneBased(bed_type)),
+            false => Box::new(bed_type),
+        }
+    }
+}
+
+/// An extra output written from the same parse as the main one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputSpec {
+    pub format: OutputFormat,
+    /// Destination; a `.gz`, `.zst` or `.bz2` extension enables compression.
+    pub path: PathBuf,
+}
+
+impl std::str::FromStr for OutputSpec {
+    type Err = String;
+
+    /// Parses `FORMAT=PATH`, e.g. `bed6=exons.bed.gz`.
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let Some((format, path)) = spec.split_once('=').filter(|(_, p)| !p.is_empty()) else {
+            return Err(format!("invalid output {:?}, expected FORMAT=PATH", spec));
+        };
+
+        Ok(Self {
+            format: OutputFormat::from_str(format.trim(), true)?,
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+/// A BED output of its own child feature types, written from the same
+/// parse as the main one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChildOutput {
+    /// Feature type turned into blocks; a comma-separated list unions them,
+    /// as with `Config::child`.
+    pub child: String,
+    /// Destination; a `.gz`, `.zst` or `.bz2` extension enables compression.
+    pub path: PathBuf,
+}
+
+impl std::str::FromStr for ChildOutput {
+    type Err = String;
+
+    /// Parses `CHILD:PATH`, e.g. `CDS:cds.bed.gz`.
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let Some((child, path)) = spec
+            .split_once(':')
+            .filter(|(c, p)| !c.is_empty() && !p.is_empty())
+        else {
+            return Err(format!(
+                "invalid child output {:?}, expected CHILD:PATH",
+                spec
+            ));
+        };
+
+        Ok(Self {
+            child: child.to_string(),
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+impl std::fmt::Display for ChildOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.child, self.path.display())
+    }
+}
+
+/// Flushes the buffer, finishes compression and returns the inner writer.
+pub fn finish_output<W: Write>(writer: OutputWriter<W>) -> std::io::Result<W> {
+    writer.into_inner().map_err(|e| e.into_error())?.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::decode;
+
+    fn roundtrip(path: &str) -> String {
+        let mut writer = wrap_output(path, Vec::new(), None, 1).unwrap();
+        writer.write_all(b"chr1\t0\t10\n").unwrap();
+        let bytes = finish_output(writer).unwrap();
+
+        decode(&bytes[..], output_compression(path)).unwrap()
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_wrap_output_gzip_roundtrip() {
+        assert_eq!(roundtrip("out.bed.gz"), "chr1\t0\t10\n");
+    }
+
+    #[cfg(all(feature = "zstd", feature = "bzip2"))]
+    #[test]
+    fn test_wrap_output_zstd_bzip2_roundtrip() {
+        assert_eq!(roundtrip("out.bed.zst"), "chr1\t0\t10\n");
+        assert_eq!(roundtrip("out.bed.bz2"), "chr1\t0\t10\n");
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_wrap_output_gzip_level() {
+        let data = "chr1\t0\t10\ttx\n".repeat(1000);
+        let size = |level| {
+            let mut writer = wrap_output("out.bed.gz", Vec::new(), level, 1).unwrap();
+            writer.write_all(data.as_bytes()).unwrap();
+            finish_output(writer).unwrap().len()
+        };
+
+        assert!(size(Some(0)) > size(Some(9)));
+    }
+
+    #[cfg(all(feature = "parallel", feature = "gzip"))]
+    #[test]
+    fn test_parallel_gzip_members() {
+        let data = (0..200_000)
+            .map(|i| format!("chr1\t{}\t{}\ttx{}\n", i, i + 10, i))
+            .collect::<String>();
+
+        assert!(data.len() > 4 * CHUNK_BYTES);
+        // INFO: two workers keep a window of four chunks, so members are
+        // written while later ones are still compressing
+        for threads in [2, 4] {
+            let mut writer = wrap_output("out.bed.gz", Vec::new(), None, threads).unwrap();
+            writer.write_all(data.as_bytes()).unwrap();
+            let bytes = finish_output(writer).unwrap();
+
+            assert_eq!(decode(&bytes[..], Compression::Gzip).unwrap(), data);
+        }
+
+        let empty = finish_output(wrap_output("out.bed.gz", Vec::new(), None, 4).unwrap()).unwrap();
+        assert_eq!(decode(&empty[..], Compression::Gzip).unwrap(), "");
+    }
+
+    #[test]
+    fn test_derive_output_name() {
+        assert_eq!(
+            derive_output_name("dir/foo.gtf.gz"),
+            PathBuf::from("foo.bed")
+        );
+        assert_eq!(derive_output_name("foo.gff3"), PathBuf::from("foo.bed"));
+        assert_eq!(
+            derive_output_name("foo.v2.gtf"),
+            PathBuf::from("foo.v2.bed")
+        );
+    }
+
+    #[test]
+    fn test_wrap_output_plain() {
+        assert_eq!(roundtrip("out.bed"), "chr1\t0\t10\n");
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn test_wrap_output_missing_codec() {
+        let err = wrap_output("out.bed.zst", Vec::new(), None, 1)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn test_tee_survives_closed_reader() {
+        struct Closed;
+        impl Write for Closed {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::BrokenPipe.into())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut tee = Tee::new(Closed);
+        assert!(tee.write_all(b"chr1\t0\t10\n").is_ok());
+        assert!(tee.inner.is_none());
+        assert!(tee.flush().is_ok());
+    }
+}
@@ -0,0 +1,125 @@
+use hashbrown::HashMap;
+
+use crate::error::Result;
+use crate::gxf::GenePred;
+
+/// Suffix GENCODE gives the chrY copies of pseudoautosomal (PAR) features.
+const PAR_Y_SUFFIX: &str = "_PAR_Y";
+
+/// Marks chrY grouping keys while grouping; it sorts right after the bare
+/// ID, so a name-ordered stream sees the chrX copy first.
+const MARK: &str = "\0Y";
+
+/// Grouping key for feature `id` on `chrom`.
+///
+/// PAR genes are annotated on both chrX and chrY, with `_PAR_Y` IDs (older
+/// GENCODE) or with the very same IDs (newer GENCODE, other sources). Keying
+/// chrY features apart keeps the two copies from merging into one model;
+/// [`resolve`] then picks their output names.
+#[inline]
+pub(crate) fn key(id: &str, chrom: &str) -> String {
+    let mut key = id.to_owned();
+    if matches!(chrom, "chrY" | "Y") && !id.ends_with(PAR_Y_SUFFIX) {
+        key.push_str(MARK);
+    }
+    key
+}
+
+/// `key` without its chrY mark.
+pub(crate) fn bare(key: &str) -> &str {
+    key.strip_suffix(MARK).unwrap_or(key)
+}
+
+/// Output name for the chrY `key`: `<id>_PAR_Y` when `<id>` is also used
+/// elsewhere, `<id>` otherwise.
+fn name(key: String, shared: impl FnOnce(&str) -> bool) -> String {
+    let Some(id) = key.strip_suffix(MARK) else {
+        return key;
+    };
+
+    match shared(id) {
+        true => format!("{}{}", id, PAR_Y_SUFFIX),
+        false => id.to_string(),
+    }
+}
+
+/// Renames the chrY groups of `groups` to their output names.
+pub(crate) fn resolve(groups: &mut HashMap<String, GenePred>) {
+    let marked = groups
+        .keys()
+        .filter(|key| key.ends_with(MARK))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    for key in marked {
+        let info = groups.remove(&key).expect("ERROR: key was just listed");
+        let name = name(key, |id| groups.contains_key(id));
+        groups.insert(name, info);
+    }
+}
+
+/// Same as [`resolve`] for groups streamed in name order.
+pub(crate) struct Resolve<I> {
+    inner: I,
+    last: String,
+}
+
+impl<I> Resolve<I> {
+    pub(crate) fn new(inner: I) -> Self {
+        Self {
+            inner,
+            last: String::new(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<(String, GenePred)>>> Iterator for Resolve<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, info) = match self.inner.next()? {
+            Ok(group) => group,
+            Err(err) => return Some(Err(err)),
+        };
+
+        if !key.ends_with(MARK) {
+            self.last.clone_from(&key);
+            return Some(Ok((key, info)));
+        }
+        let last = &self.last;
+        Some(Ok((name(key, |id| id == last), info)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_x_and_y_copies_stay_apart() {
+        let keys = [
+            key("ENST1", "chrX"),
+            key("ENST1", "chrY"),
+            key("SRY", "chrY"),
+            key("ENST2_PAR_Y", "chrY"),
+        ];
+        assert_eq!(keys[0], "ENST1");
+        assert_ne!(keys[0], keys[1]);
+
+        let mut groups = keys
+            .iter()
+            .map(|k| (k.clone(), GenePred::new()))
+            .collect::<HashMap<_, _>>();
+        resolve(&mut groups);
+        let mut names = groups.into_keys().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["ENST1", "ENST1_PAR_Y", "ENST2_PAR_Y", "SRY"]);
+
+        let mut sorted = keys.to_vec();
+        sorted.sort();
+        let streamed = Resolve::new(sorted.into_iter().map(|k| Ok((k, GenePred::new()))))
+            .map(|group| group.unwrap().0)
+            .collect::<Vec<_>>();
+        assert_eq!(streamed, ["ENST1", "ENST1_PAR_Y", "ENST2_PAR_Y", "SRY"]);
+    }
+}
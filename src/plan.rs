@@ -0,0 +1,201 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::PathBuf;
+
+use hashbrown::HashSet;
+
+use crate::bed::BedType;
+use crate::codec::decoder;
+use crate::config::Config;
+use crate::detect::{detect_input_kind, InputKind};
+use crate::error::Result;
+use crate::output::{output_compression, ChildOutput, OutputSpec};
+use crate::source::GxfReader;
+
+/// Number of feature lines scanned by `--dry-run`.
+pub const DRY_RUN_LINES: usize = 10_000;
+
+/// What a conversion would do, resolved from a [`Config`] and a quick scan of
+/// the start of the input. Nothing is written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    pub input: PathBuf,
+    pub kind: InputKind,
+    pub output: PathBuf,
+    pub output_kind: &'static str,
+    /// Extra outputs (`Config::outputs`).
+    pub outputs: Vec<OutputSpec>,
+    /// Per-child-type outputs (`Config::child_outputs`).
+    pub child_outputs: Vec<ChildOutput>,
+    pub parent: String,
+    pub child: String,
+    pub feature: String,
+    pub bed_type: BedType,
+    /// Feature lines inspected.
+    pub scanned: usize,
+    /// Lines in the scan that failed to parse.
+    pub malformed: usize,
+    /// Scanned features whose type matched `parent`.
+    pub parents: usize,
+    /// Scanned features whose type matched `child`.
+    pub children: usize,
+    /// Whether the scan reached the end of the input.
+    pub complete: bool,
+    /// Records the full run should write, extrapolated from the scan by input size.
+    pub estimated_records: u64,
+}
+
+/// Resolves `config` and scans up to `lines` features of its input.
+pub fn plan(config: &Config, lines: usize) -> Result<Plan> {
+    let kind = detect_input_kind(&config.input)?;
+    let file = File::open(&config.input)?;
+    let size = file.metadata()?.len();
+
+    let mut counter = ByteCounter {
+        inner: file,
+        bytes: 0,
+    };
+    let reader = BufReader::new(decoder(&mut counter, kind.compression)?);
+    let mut features = GxfReader::new(reader, kind.format, config.feature_key())
+        .input_coords(config.input_coords)
+        .attr_sep(config.separator(kind.format))
+        .comment_chars(&config.comment_chars);
+
+    let (mut scanned, mut malformed, mut parents, mut children) = (0, 0, 0, 0);
+    let mut ids = HashSet::new();
+    let mut complete = true;
+
+    while scanned < lines {
+        let Some(feature) = features.next() else {
+            break;
+        };
+        scanned += 1;
+
+        match feature {
+            Ok(feature) if config.is_child(&feature.kind) => {
+                children += 1;
+                ids.insert(feature.id);
+            }
+            Ok(feature) if feature.kind == config.parent => parents += 1,
+            Ok(_) => {}
+            Err(_) => malformed += 1,
+        }
+
+        if scanned == lines {
+            complete = features.next().is_none();
+        }
+    }
+    drop(features);
+
+    let estimated_records = if complete || counter.bytes == 0 {
+        ids.len() as u64
+    } else {
+        (ids.len() as f64 * size as f64 / counter.bytes as f64).round() as u64
+    };
+
+    let output = config.output_path();
+    Ok(Plan {
+        input: config.input.clone(),
+        kind,
+        output_kind: output_compression(&output).name(),
+        output,
+        outputs: config.outputs.clone(),
+        child_outputs: config.child_outputs.clone(),
+        parent: config.parent.clone(),
+        child: config.child.clone(),
+        feature: config.feature.clone(),
+        bed_type: config.bed_type,
+        scanned,
+        malformed,
+        parents,
+        children,
+        complete,
+        estimated_records,
+    })
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let estimate = if self.complete { "" } else { "~" };
+
+        writeln!(f, "input:     {:?}", self.input)?;
+        writeln!(
+            f,
+            "format:    {:?} ({})",
+            self.kind.format,
+            self.kind.compression.name()
+        )?;
+        writeln!(f, "output:    {:?} ({})", self.output, self.output_kind)?;
+        for out in &self.outputs {
+            writeln!(
+                f,
+                "also:      {:?} ({:?}, {})",
+                out.path,
+                out.format,
+                output_compression(&out.path).name()
+            )?;
+        }
+        for out in &self.child_outputs {
+            writeln!(
+                f,
+                "child:     {:?} ({:?}, {})",
+                out.path,
+                out.child,
+                output_compression(&out.path).name()
+            )?;
+        }
+        writeln!(f, "bed type:  {:?}", self.bed_type)?;
+        writeln!(
+            f,
+            "grouping:  parent {:?}, child {:?}, attribute {:?}",
+            self.parent, self.child, self.feature
+        )?;
+        writeln!(
+            f,
+            "scanned:   {} lines ({} parents, {} children, {} malformed)",
+            self.scanned, self.parents, self.children, self.malformed
+        )?;
+        write!(f, "records:   {}{}", estimate, self.estimated_records)
+    }
+}
+
+/// Counts compressed bytes pulled from the file, to scale the sample.
+struct ByteCounter<R> {
+    inner: R,
+    bytes: u64,
+}
+
+impl<R: Read> Read for ByteCounter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes += n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_small_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("plan.gtf");
+        std::fs::write(
+            &input,
+            "chr1\tHAVANA\ttranscript\t1\t60\t.\t+\t.\ttranscript_id \"A\";\n\
+             chr1\tHAVANA\texon\t1\t20\t.\t+\t.\ttranscript_id \"A\";\n\
+             chr1\tHAVANA\texon\t41\t60\t.\t+\t.\ttranscript_id \"A\";\n\
+             chr1\tbroken\n",
+        )
+        .unwrap();
+
+        let plan = plan(&Config::new(&input, "out.bed.gz"), DRY_RUN_LINES).unwrap();
+
+        assert!(plan.complete);
+        assert_eq!((plan.parents, plan.children, plan.malformed), (1, 2, 1));
+        assert_eq!(plan.estimated_records, 1);
+        assert_eq!(plan.output_kind, "gzip");
+    }
+}
@@ -0,0 +1,152 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+/// Settings bundles for annotation sources whose layout differs from the
+/// GENCODE/Ensembl transcript/exon default.
+///
+/// A preset only changes options that were not given explicitly: on the
+/// command line flags win, and with [`crate::ConfigBuilder::preset`] later
+/// builder calls do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    /// Prokka/Bakta bacterial GFF3: CDS grouped directly under gene, named
+    /// by `locus_tag`.
+    Prokaryotic,
+    /// StringTie/Cufflinks assembled transcripts: named after the matched
+    /// reference transcript or gene when there is one, scored by `TPM`
+    /// (or `FPKM`).
+    Stringtie,
+    /// AUGUSTUS/BRAKER gene predictions: CDS-only models grouped by
+    /// `transcript_id`, whether or not `transcript` lines are present.
+    Augustus,
+    /// MAKER GFF3: mRNA models built from their exons through `ID`/`Parent`;
+    /// `match`/`match_part` evidence alignments are left out.
+    Maker,
+}
+
+impl Preset {
+    /// Overwrites the options this preset sets in `config`.
+    pub fn apply(&self, config: &mut Config) {
+        match self {
+            Preset::Prokaryotic => {
+                config.parent = "gene".to_string();
+                config.child = "CDS".to_string();
+                config.feature = "locus_tag".to_string();
+            }
+            Preset::Stringtie => {
+                config.name_attrs = vec!["reference_id".to_string(), "ref_gene_name".to_string()];
+                config.score_attrs = vec!["TPM".to_string(), "FPKM".to_string()];
+            }
+            Preset::Augustus => {
+                config.parent = "transcript".to_string();
+                config.child = "CDS".to_string();
+                config.feature = "transcript_id".to_string();
+            }
+            Preset::Maker => {
+                config.parent = "mRNA".to_string();
+                config.child = "exon".to_string();
+                config.feature = "ID".to_string();
+                config.child_feature = Some("Parent".to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::convert_str;
+    use crate::detect::InputFormat;
+
+    #[test]
+    fn test_prokaryotic() {
+        let gff = "##gff-version 3\n\
+            contig_1\tProkka\tgene\t101\t400\t.\t+\t.\tID=PROKKA_00001_gene;locus_tag=PROKKA_00001\n\
+            contig_1\tProkka\tCDS\t101\t400\t.\t+\t0\tID=PROKKA_00001;Parent=PROKKA_00001_gene;locus_tag=PROKKA_00001\n\
+            ##FASTA\n\
+            >contig_1\n\
+            ATGC\n";
+
+        let config = Config::builder()
+            .preset(Preset::Prokaryotic)
+            .bed_type(crate::BedType::Bed4)
+            .build()
+            .unwrap();
+        let bed = convert_str(gff, InputFormat::Gff, &config).unwrap();
+        assert_eq!(bed, "contig_1\t100\t400\tPROKKA_00001\n");
+    }
+
+    #[test]
+    fn test_stringtie() {
+        let gtf = "# StringTie version 2.2.1\n\
+            chr1\tStringTie\ttranscript\t101\t300\t1000\t+\t.\tgene_id \"STRG.1\"; transcript_id \"STRG.1.1\"; reference_id \"ENST01\"; ref_gene_id \"ENSG01\"; ref_gene_name \"DDX11L1\"; cov \"12.5\"; FPKM \"3.1\"; TPM \"7.6\";\n\
+            chr1\tStringTie\texon\t101\t150\t1000\t+\t.\tgene_id \"STRG.1\"; transcript_id \"STRG.1.1\"; exon_number \"1\"; reference_id \"ENST01\"; cov \"10.0\";\n\
+            chr1\tStringTie\texon\t201\t300\t1000\t+\t.\tgene_id \"STRG.1\"; transcript_id \"STRG.1.1\"; exon_number \"2\"; reference_id \"ENST01\"; cov \"13.0\";\n\
+            chr1\tCufflinks\ttranscript\t501\t600\t1000\t-\t.\tgene_id \"CUFF.2\"; transcript_id \"CUFF.2.1\"; FPKM \"2400.0\";\n\
+            chr1\tCufflinks\texon\t501\t600\t1000\t-\t.\tgene_id \"CUFF.2\"; transcript_id \"CUFF.2.1\"; exon_number \"1\";\n";
+
+        let config = Config::builder()
+            .preset(Preset::Stringtie)
+            .bed_type(crate::BedType::Bed6)
+            .build()
+            .unwrap();
+        let bed = convert_str(gtf, InputFormat::Gtf, &config).unwrap();
+        assert_eq!(
+            bed,
+            "chr1\t100\t300\tENST01\t8\t+\n\
+             chr1\t500\t600\tCUFF.2.1\t1000\t-\n"
+        );
+    }
+
+    #[test]
+    fn test_augustus() {
+        let gtf = "# start gene g1\n\
+            chr1\tAUGUSTUS\tgene\t1001\t2000\t0.45\t+\t.\tg1\n\
+            chr1\tAUGUSTUS\ttranscript\t1001\t2000\t0.45\t+\t.\tg1.t1\n\
+            chr1\tAUGUSTUS\tstart_codon\t1001\t1003\t.\t+\t0\ttranscript_id \"g1.t1\"; gene_id \"g1\";\n\
+            chr1\tAUGUSTUS\tCDS\t1001\t1200\t0.9\t+\t0\ttranscript_id \"g1.t1\"; gene_id \"g1\";\n\
+            chr1\tAUGUSTUS\tintron\t1201\t1799\t1\t+\t.\ttranscript_id \"g1.t1\"; gene_id \"g1\";\n\
+            chr1\tAUGUSTUS\tCDS\t1800\t2000\t0.8\t+\t1\ttranscript_id \"g1.t1\"; gene_id \"g1\";\n\
+            # end gene g1\n\
+            chr2\tGeneMark.hmm3\tCDS\t901\t1000\t.\t-\t0\ttranscript_id g2.t1; gene_id g2;\n\
+            chr2\tGeneMark.hmm3\tCDS\t501\t800\t.\t-\t0\ttranscript_id g2.t1; gene_id g2;\n";
+
+        let config = Config::builder().preset(Preset::Augustus).build().unwrap();
+        let bed = convert_str(gtf, InputFormat::Gtf, &config).unwrap();
+        assert_eq!(
+            bed,
+            "chr1\t1000\t2000\tg1.t1\t0\t+\t1000\t2000\t0\t2\t200,201,\t0,799,\n\
+             chr2\t500\t1000\tg2.t1\t0\t-\t500\t1000\t0\t2\t300,100,\t0,400,\n"
+        );
+    }
+
+    #[test]
+    fn test_maker() {
+        let gff = "##gff-version 3\n\
+            ctg1\tmaker\tgene\t101\t900\t.\t+\t.\tID=maker-ctg1-gene-0.0;Name=maker-ctg1-gene-0.0\n\
+            ctg1\tmaker\tmRNA\t101\t900\t.\t+\t.\tID=maker-ctg1-gene-0.0-mRNA-1;Parent=maker-ctg1-gene-0.0;_AED=0.12\n\
+            ctg1\tmaker\texon\t101\t300\t.\t+\t.\tID=maker-ctg1-gene-0.0-mRNA-1:1;Parent=maker-ctg1-gene-0.0-mRNA-1\n\
+            ctg1\tmaker\texon\t701\t900\t.\t+\t.\tID=maker-ctg1-gene-0.0-mRNA-1:2;Parent=maker-ctg1-gene-0.0-mRNA-1,maker-ctg1-gene-0.0-mRNA-2\n\
+            ctg1\tmaker\tmRNA\t501\t900\t.\t+\t.\tID=maker-ctg1-gene-0.0-mRNA-2;Parent=maker-ctg1-gene-0.0\n\
+            ctg1\tmaker\texon\t501\t600\t.\t+\t.\tID=maker-ctg1-gene-0.0-mRNA-2:1;Parent=maker-ctg1-gene-0.0-mRNA-2\n\
+            ctg1\tmaker\tCDS\t151\t300\t.\t+\t0\tID=maker-ctg1-gene-0.0-mRNA-1:cds;Parent=maker-ctg1-gene-0.0-mRNA-1\n\
+            ctg1\tblastx\tprotein_match\t120\t880\t55\t+\t.\tID=ctg1:hit:1;Name=sp|P12345\n\
+            ctg1\tblastx\tmatch_part\t120\t310\t55\t+\t.\tID=ctg1:hsp:1;Parent=ctg1:hit:1;Target=sp|P12345 1 60 +\n\
+            ctg1\test2genome\texpressed_sequence_match\t90\t320\t.\t+\t.\tID=ctg1:est:1;Name=EST1\n";
+
+        let config = Config::builder()
+            .preset(Preset::Maker)
+            .bed_type(crate::BedType::Bed4)
+            .build()
+            .unwrap();
+        let bed = convert_str(gff, InputFormat::Gff, &config).unwrap();
+        assert_eq!(
+            bed,
+            "ctg1\t100\t900\tmaker-ctg1-gene-0.0-mRNA-1\n\
+             ctg1\t500\t900\tmaker-ctg1-gene-0.0-mRNA-2\n"
+        );
+    }
+}
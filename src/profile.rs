@@ -0,0 +1,137 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Conversion phases timed with `Config::profile`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Reading and decompressing the input.
+    Read,
+    /// Parsing lines and grouping them into sorted models; with
+    /// `Config::max_memory` the input is streamed, so reading counts here.
+    Parse,
+    /// Applying per-record fixes and formatting BED lines.
+    Render,
+    /// Writing the outputs, compression included.
+    Write,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 4] = [Phase::Read, Phase::Parse, Phase::Render, Phase::Write];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Read => "read/decompress",
+            Phase::Parse => "parse/group",
+            Phase::Render => "render",
+            Phase::Write => "write",
+        }
+    }
+}
+
+/// Wall time and peak memory per [`Phase`] of a run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    /// Wall time per phase, in [`Phase::ALL`] order.
+    pub times: [Duration; 4],
+    /// Peak resident memory of the process (MiB) when each phase ended;
+    /// needs the `rusage` feature.
+    pub peak_mb: [Option<f64>; 4],
+}
+
+impl Profile {
+    pub fn time(&self, phase: Phase) -> Duration {
+        self.times[phase as usize]
+    }
+
+    pub fn peak_mb(&self, phase: Phase) -> Option<f64> {
+        self.peak_mb[phase as usize]
+    }
+
+    /// Adds `time` to `phase`, noting the peak memory so far.
+    pub(crate) fn add(&mut self, phase: Phase, time: Duration) {
+        self.times[phase as usize] += time;
+        self.peak_mb[phase as usize] = peak_mb();
+    }
+
+    /// Adds the times of `other`, e.g. another output of the same run,
+    /// keeping the higher peaks.
+    pub(crate) fn merge(&mut self, other: &Profile) {
+        for phase in Phase::ALL {
+            let idx = phase as usize;
+            self.times[idx] += other.times[idx];
+            self.peak_mb[idx] = match (self.peak_mb[idx], other.peak_mb[idx]) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                (a, b) => a.or(b),
+            };
+        }
+    }
+}
+
+/// Runs `op` as `phase` of `profile`, if profiling; the clock is only read
+/// then, so unprofiled runs work where there is none (WebAssembly).
+pub(crate) fn timed<T>(profile: &mut Option<Profile>, phase: Phase, op: impl FnOnce() -> T) -> T {
+    let Some(profile) = profile else {
+        return op();
+    };
+    let started = Instant::now();
+    let out = op();
+    profile.add(phase, started.elapsed());
+    out
+}
+
+#[cfg(feature = "rusage")]
+fn peak_mb() -> Option<f64> {
+    Some(crate::utils::max_mem_usage_mb())
+}
+
+#[cfg(not(feature = "rusage"))]
+fn peak_mb() -> Option<f64> {
+    None
+}
+
+impl fmt::Display for Profile {
+    /// One line per phase with its time, share of the total and peak memory.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.times.iter().sum::<Duration>().as_secs_f64();
+        for (idx, phase) in Phase::ALL.iter().enumerate() {
+            let secs = self.time(*phase).as_secs_f64();
+            let share = match total > 0.0 {
+                true => 100.0 * secs / total,
+                false => 0.0,
+            };
+            write!(f, "{:<16} {:>9.3} s {:>5.1}%", phase.name(), secs, share)?;
+            if let Some(peak) = self.peak_mb(*phase) {
+                write!(f, "  peak {:.1} MiB", peak)?;
+            }
+            if idx + 1 < Phase::ALL.len() {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile() {
+        let mut profile = Some(Profile::default());
+        assert_eq!(timed(&mut profile, Phase::Parse, || 7), 7);
+        let mut profile = profile.unwrap();
+        profile.times[Phase::Read as usize] = Duration::from_millis(300);
+        profile.times[Phase::Parse as usize] = Duration::from_millis(100);
+        profile.peak_mb = [None; 4];
+
+        let mut total = Profile::default();
+        total.merge(&profile);
+        total.merge(&profile);
+        assert_eq!(total.time(Phase::Read), Duration::from_millis(600));
+        assert_eq!(
+            total.to_string().lines().next(),
+            Some("read/decompress      0.600 s  75.0%")
+        );
+        assert_eq!(timed(&mut None, Phase::Write, || "untimed"), "untimed");
+    }
+}
@@ -0,0 +1,131 @@
+#[cfg(feature = "progress")]
+use std::io::IsTerminal;
+use std::io::Read;
+
+#[cfg(feature = "progress")]
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+#[cfg(feature = "progress")]
+const TEMPLATE: &str =
+    "{spinner:.magenta} [{elapsed_precise}] [{bar:40.magenta/white}] {percent:>3}% {bytes}/{total_bytes} ({bytes_per_sec})";
+
+/// Receives notifications as input bytes are consumed during a conversion.
+///
+/// All methods have no-op defaults, so implementors only need to override
+/// the events they care about.
+pub trait ProgressHook: Send + Sync {
+    /// Called once before reading starts, with the input size in bytes if known.
+    fn start(&self, _total: Option<u64>) {}
+
+    /// Called every time `bytes` more bytes of input have been consumed.
+    fn advance(&self, _bytes: u64) {}
+
+    /// Called once after the whole input has been consumed.
+    fn finish(&self) {}
+}
+
+/// A hook that ignores every event.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoProgress;
+
+impl ProgressHook for NoProgress {}
+
+/// Terminal progress bar showing the percentage of input consumed and throughput.
+#[cfg(feature = "progress")]
+pub struct ProgressBarHook {
+    bar: ProgressBar,
+}
+
+#[cfg(feature = "progress")]
+impl ProgressBarHook {
+    /// Builds a progress bar drawn to stderr, or `None` if stderr is not a TTY.
+    pub fn stderr() -> Option<Self> {
+        if !std::io::stderr().is_terminal() {
+            return None;
+        }
+
+        let bar = ProgressBar::with_draw_target(None, ProgressDrawTarget::stderr());
+        bar.set_style(
+            ProgressStyle::with_template(TEMPLATE)
+                .expect("ERROR: Invalid progress bar template")
+                .progress_chars("=> "),
+        );
+
+        Some(Self { bar })
+    }
+}
+
+#[cfg(feature = "progress")]
+impl ProgressHook for ProgressBarHook {
+    fn start(&self, total: Option<u64>) {
+        if let Some(total) = total {
+            self.bar.set_length(total);
+        }
+        self.bar.reset_elapsed();
+    }
+
+    fn advance(&self, bytes: u64) {
+        self.bar.inc(bytes);
+    }
+
+    fn finish(&self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+/// Returns a stderr progress bar when stderr is a TTY, or [`NoProgress`] otherwise.
+pub fn stderr_hook() -> Box<dyn ProgressHook> {
+    #[cfg(feature = "progress")]
+    if let Some(bar) = ProgressBarHook::stderr() {
+        return Box::new(bar);
+    }
+
+    Box::new(NoProgress)
+}
+
+/// Wraps a reader and reports every successful read to a [`ProgressHook`].
+pub struct ProgressReader<'a, R> {
+    inner: R,
+    hook: &'a dyn ProgressHook,
+}
+
+impl<'a, R: Read> ProgressReader<'a, R> {
+    pub fn new(inner: R, hook: &'a dyn ProgressHook) -> Self {
+        Self { inner, hook }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hook.advance(n as u64);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[derive(Default)]
+    struct Counter(AtomicU64);
+
+    impl ProgressHook for Counter {
+        fn advance(&self, bytes: u64) {
+            self.0.fetch_add(bytes, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_progress_reader_counts_bytes() {
+        let hook = Counter::default();
+        let data = b"chr1\tHAVANA\texon\t1\t10\t.\t+\t.\ttranscript_id \"A\";\n".repeat(100);
+
+        let mut reader = ProgressReader::new(&data[..], &hook);
+        let mut out = String::new();
+        reader.read_to_string(&mut out).unwrap();
+
+        assert_eq!(hook.0.load(Ordering::Relaxed), data.len() as u64);
+    }
+}
@@ -0,0 +1,493 @@
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::error::Result;
+use crate::progress::ProgressHook;
+
+/// URL schemes read as remote inputs.
+const SCHEMES: [&str; 3] = ["http://", "https://", "s3://"];
+
+/// Whether `input` is a URL (`http://`, `https://` or `s3://`) rather than
+/// a local path.
+pub fn is_remote(input: &Path) -> bool {
+    input
+        .to_str()
+        .is_some_and(|input| SCHEMES.iter().any(|scheme| input.starts_with(scheme)))
+}
+
+/// HTTP(S) URL of `input`; `s3://bucket/key` maps to the bucket's public
+/// endpoint, so only publicly readable objects can be fetched.
+pub fn http_url(input: &str) -> String {
+    match input.strip_prefix("s3://") {
+        Some(rest) => {
+            let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+            format!("https://{}.s3.amazonaws.com/{}", bucket, key)
+        }
+        None => input.to_string(),
+    }
+}
+
+/// Last path segment of `url`, query and fragment removed.
+fn file_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    match path.rsplit('/').next() {
+        Some(name) if !name.is_empty() => name,
+        _ => "input",
+    }
+}
+
+/// A remote input downloaded to a temporary directory, removed on drop.
+pub struct Staged {
+    _dir: tempfile::TempDir,
+    /// The downloaded copy, named like the remote file so that format
+    /// detection and derived output names still work.
+    pub path: PathBuf,
+}
+
+/// Downloads `config.input` when it is a URL (see [`is_remote`]) and points
+/// `config.input` at the local copy; the copy lives as long as the
+/// returned guard. Local inputs are left alone.
+pub fn stage(config: &mut Config, hook: &dyn ProgressHook) -> Result<Option<Staged>> {
+    let Some(input) = config.input.to_str().filter(|_| is_remote(&config.input)) else {
+        return Ok(None);
+    };
+
+    let url = http_url(input);
+    let dir = tempfile::Builder::new().prefix("gxf2bed-").tempdir()?;
+    let path = dir.path().join(file_name(&url));
+    log::info!(url = url.as_str(); "Downloading {}", url);
+    let bytes = fetch(&url, &path, config.retries, hook)?;
+    log::info!(bytes = bytes; "Downloaded {} bytes from {}", bytes, url);
+
+    config.input.clone_from(&path);
+    Ok(Some(Staged { _dir: dir, path }))
+}
+
+/// Downloads `url` into `dest`, retrying failed requests up to `retries`
+/// times with exponential backoff. An interrupted transfer resumes from the
+/// bytes already written with a range request, or starts over when the
+/// server does not honor ranges. Returns the size of the download.
+#[cfg(feature = "remote")]
+pub fn fetch(url: &str, dest: &Path, retries: u32, hook: &dyn ProgressHook) -> Result<u64> {
+    http::fetch(
+        url,
+        dest,
+        retries,
+        http::BACKOFF,
+        http::ATTEMPT_TIMEOUT,
+        hook,
+    )
+}
+
+#[cfg(not(feature = "remote"))]
+pub fn fetch(url: &str, _dest: &Path, _retries: u32, _hook: &dyn ProgressHook) -> Result<u64> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!(
+            "cannot read {}: gxf2bed was built without the `remote` feature",
+            url
+        ),
+    )
+    .into())
+}
+
+#[cfg(feature = "remote")]
+mod http {
+    use std::fs::File;
+    use std::io::{Read, Seek, Write};
+    use std::path::Path;
+    use std::time::Duration;
+
+    use ureq::Agent;
+
+    use crate::error::Result;
+    use crate::progress::ProgressHook;
+
+    /// Wait before the first retry; doubled for each later one.
+    pub(super) const BACKOFF: Duration = Duration::from_secs(1);
+
+    /// Longest wait between retries.
+    const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    /// Longest a request may take, body included, so a connection stalled
+    /// mid-transfer fails and resumes instead of hanging; a slow but healthy
+    /// transfer resumes past it as well.
+    pub(super) const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(600);
+
+    /// Why a request failed: worth retrying (network errors, 5xx, 408, 429)
+    /// or not (other 4xx, local I/O).
+    enum Failure {
+        Transient(String),
+        Fatal(std::io::Error),
+    }
+
+    impl From<std::io::Error> for Failure {
+        fn from(err: std::io::Error) -> Self {
+            Failure::Fatal(err)
+        }
+    }
+
+    pub(super) fn fetch(
+        url: &str,
+        dest: &Path,
+        retries: u32,
+        backoff: Duration,
+        attempt_timeout: Duration,
+        hook: &dyn ProgressHook,
+    ) -> Result<u64> {
+        let agent: Agent = Agent::config_builder()
+            .http_status_as_error(false)
+            .timeout_connect(Some(Duration::from_secs(30)))
+            .timeout_global(Some(attempt_timeout))
+            .build()
+            .into();
+        let mut file = File::create(dest)?;
+        let mut transfer = Transfer {
+            written: 0,
+            started: false,
+        };
+
+        let mut attempt = 0;
+        loop {
+            match transfer.request(&agent, url, &mut file, hook) {
+                Ok(()) => break,
+                Err(Failure::Transient(reason)) if attempt < retries => {
+                    attempt += 1;
+                    let delay = backoff
+                        .saturating_mul(1 << (attempt - 1).min(16))
+                        .min(MAX_BACKOFF);
+                    log::warn!(
+                        url = url, attempt = attempt, written = transfer.written;
+                        "{}: {}; retrying in {:?} ({}/{}, resuming at byte {})",
+                        url, reason, delay, attempt, retries, transfer.written
+                    );
+                    std::thread::sleep(delay);
+                }
+                Err(Failure::Transient(reason)) => {
+                    let reason = format!("{}: {} (gave up after {} retries)", url, reason, retries);
+                    return Err(std::io::Error::other(reason).into());
+                }
+                Err(Failure::Fatal(err)) => return Err(err.into()),
+            }
+        }
+        hook.finish();
+        file.flush()?;
+
+        Ok(transfer.written)
+    }
+
+    /// Progress of one download across its attempts.
+    struct Transfer {
+        /// Bytes of the file already in `dest`.
+        written: u64,
+        /// Whether the progress hook got the total size.
+        started: bool,
+    }
+
+    impl Transfer {
+        /// Requests the bytes not written yet and appends them to `file`.
+        fn request(
+            &mut self,
+            agent: &Agent,
+            url: &str,
+            file: &mut File,
+            hook: &dyn ProgressHook,
+        ) -> std::result::Result<(), Failure> {
+            let mut request = agent.get(url);
+            if self.written > 0 {
+                request = request.header("Range", format!("bytes={}-", self.written));
+            }
+            let response = request
+                .call()
+                .map_err(|err| Failure::Transient(err.to_string()))?;
+
+            let status = response.status().as_u16();
+            let length = response
+                .headers()
+                .get("content-length")
+                .and_then(|len| len.to_str().ok()?.parse::<u64>().ok());
+            match status {
+                206 => {
+                    let start = response
+                        .headers()
+                        .get("content-range")
+                        .and_then(|range| range_start(range.to_str().ok()?));
+                    if start != Some(self.written) {
+                        // INFO: appending bytes from elsewhere would corrupt the file
+                        let reason = format!(
+                            "partial content starts at byte {}, not {}; restarting",
+                            start.map_or("?".to_string(), |start| start.to_string()),
+                            self.written
+                        );
+                        file.set_len(0)?;
+                        file.rewind()?;
+                        self.written = 0;
+                        return Err(Failure::Transient(reason));
+                    }
+                }
+                200 if self.written > 0 => {
+                    // INFO: the server ignored the range, so the whole file comes again
+                    log::warn!(url = url; "{} does not support range requests; restarting", url);
+                    file.set_len(0)?;
+                    file.rewind()?;
+                    self.written = 0;
+                }
+                200 => {}
+                416 if self.written > 0 => return Ok(()),
+                408 | 429 | 500..=599 => {
+                    return Err(Failure::Transient(format!("HTTP status {}", status)));
+                }
+                _ => {
+                    let err = format!("{}: HTTP status {}", url, status);
+                    return Err(Failure::Fatal(std::io::Error::other(err)));
+                }
+            }
+
+            if !self.started {
+                hook.start(length.map(|len| len + self.written));
+                self.started = true;
+            }
+            let expected = length.map(|len| len + self.written);
+            let mut body = response.into_body().into_reader();
+            let mut buf = vec![0; 1 << 16];
+            loop {
+                let n = match body.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(Failure::Transient(err.to_string())),
+                };
+                file.write_all(&buf[..n])?;
+                self.written += n as u64;
+                hook.advance(n as u64);
+            }
+
+            match expected {
+                Some(expected) if self.written < expected => Err(Failure::Transient(format!(
+                    "connection closed after {} of {} bytes",
+                    self.written, expected
+                ))),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    /// First byte of a `Content-Range: bytes <start>-<end>/<size>` value.
+    fn range_start(range: &str) -> Option<u64> {
+        let (start, _) = range.strip_prefix("bytes ")?.split_once('-')?;
+        start.trim().parse().ok()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::io::{BufRead, BufReader};
+        use std::net::TcpListener;
+
+        use super::*;
+        use crate::progress::NoProgress;
+
+        const BODY: &[u8] = b"chr1\thavana\tgene\t1\t100\n";
+
+        /// Serves one canned response per connection, returning the requests.
+        fn serve(responses: Vec<Vec<u8>>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+            serve_stalling(responses.into_iter().map(|r| (r, Duration::ZERO)).collect())
+        }
+
+        /// Same as [`serve`], holding each connection open for its duration
+        /// after the response, as a stalled server would.
+        fn serve_stalling(
+            responses: Vec<(Vec<u8>, Duration)>,
+        ) -> (String, std::thread::JoinHandle<Vec<String>>) {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/data/a.gtf", listener.local_addr().unwrap());
+            let server = std::thread::spawn(move || {
+                let mut requests = Vec::new();
+                for (response, stall) in responses {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let mut request = String::new();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    while reader.read_line(&mut request).unwrap() > 2 {}
+                    stream.write_all(&response).unwrap();
+                    requests.push(request.to_lowercase());
+                    std::thread::spawn(move || {
+                        std::thread::sleep(stall);
+                        drop(stream);
+                    });
+                }
+                requests
+            });
+            (url, server)
+        }
+
+        fn response(status: &str, headers: &str, body: &[u8]) -> Vec<u8> {
+            let mut out = format!(
+                "HTTP/1.1 {}\r\nconnection: close\r\n{}\r\n",
+                status, headers
+            )
+            .into_bytes();
+            out.extend_from_slice(body);
+            out
+        }
+
+        #[test]
+        fn test_fetch_resumes() {
+            let cut = 8;
+            let (url, server) = serve(vec![
+                response(
+                    "200 OK",
+                    &format!("content-length: {}\r\n", BODY.len()),
+                    &BODY[..cut],
+                ),
+                response("503 Service Unavailable", "content-length: 0\r\n", b""),
+                response("206 Partial Content", &partial(cut), &BODY[cut..]),
+            ]);
+            let dest = tempfile::NamedTempFile::new().unwrap();
+
+            let n = fetch(
+                &url,
+                dest.path(),
+                3,
+                Duration::ZERO,
+                ATTEMPT_TIMEOUT,
+                &NoProgress,
+            )
+            .unwrap();
+            assert_eq!(n, BODY.len() as u64);
+            assert_eq!(std::fs::read(dest.path()).unwrap(), BODY);
+            let requests = server.join().unwrap();
+            assert!(!requests[0].contains("range:"));
+            assert!(requests[2].contains(&format!("range: bytes={}-\r\n", cut)));
+        }
+
+        /// Headers of a `206` response carrying `BODY` from byte `start`.
+        fn partial(start: usize) -> String {
+            format!(
+                "content-length: {}\r\ncontent-range: bytes {}-{}/{}\r\n",
+                BODY.len() - start,
+                start,
+                BODY.len() - 1,
+                BODY.len()
+            )
+        }
+
+        #[test]
+        fn test_fetch_stalled_body() {
+            let cut = 8;
+            let (url, server) = serve_stalling(vec![
+                (
+                    response(
+                        "200 OK",
+                        &format!("content-length: {}\r\n", BODY.len()),
+                        &BODY[..cut],
+                    ),
+                    Duration::from_secs(5),
+                ),
+                (
+                    response("206 Partial Content", &partial(cut), &BODY[cut..]),
+                    Duration::ZERO,
+                ),
+            ]);
+            let dest = tempfile::NamedTempFile::new().unwrap();
+
+            let started = std::time::Instant::now();
+            let timeout = Duration::from_millis(200);
+            let n = fetch(&url, dest.path(), 1, Duration::ZERO, timeout, &NoProgress).unwrap();
+            // INFO: resumed once the body timed out, not when the server let go
+            assert!(started.elapsed() < Duration::from_secs(2));
+            assert_eq!(n, BODY.len() as u64);
+            assert_eq!(std::fs::read(dest.path()).unwrap(), BODY);
+            server.join().unwrap();
+        }
+
+        #[test]
+        fn test_fetch_checks_content_range() {
+            let cut = 8;
+            let (url, server) = serve(vec![
+                response(
+                    "200 OK",
+                    &format!("content-length: {}\r\n", BODY.len()),
+                    &BODY[..cut],
+                ),
+                // INFO: claims to resume but sends the file from its start
+                response("206 Partial Content", &partial(0), BODY),
+                response(
+                    "200 OK",
+                    &format!("content-length: {}\r\n", BODY.len()),
+                    BODY,
+                ),
+            ]);
+            let dest = tempfile::NamedTempFile::new().unwrap();
+
+            let n = fetch(
+                &url,
+                dest.path(),
+                3,
+                Duration::ZERO,
+                ATTEMPT_TIMEOUT,
+                &NoProgress,
+            )
+            .unwrap();
+            assert_eq!(n, BODY.len() as u64);
+            assert_eq!(std::fs::read(dest.path()).unwrap(), BODY);
+            let requests = server.join().unwrap();
+            assert!(!requests[2].contains("range:"));
+        }
+
+        #[test]
+        fn test_fetch_fails() {
+            let (url, server) = serve(vec![response(
+                "404 Not Found",
+                "content-length: 0\r\n",
+                b"",
+            )]);
+            let dest = tempfile::NamedTempFile::new().unwrap();
+            let err = fetch(
+                &url,
+                dest.path(),
+                3,
+                Duration::ZERO,
+                ATTEMPT_TIMEOUT,
+                &NoProgress,
+            )
+            .unwrap_err();
+            assert!(err.to_string().contains("HTTP status 404"));
+            assert_eq!(server.join().unwrap().len(), 1);
+
+            let (url, server) = serve(vec![
+                response("500 Internal Server Error", "content-length: 0\r\n", b""),
+                response("500 Internal Server Error", "content-length: 0\r\n", b""),
+            ]);
+            let err = fetch(
+                &url,
+                dest.path(),
+                1,
+                Duration::ZERO,
+                ATTEMPT_TIMEOUT,
+                &NoProgress,
+            )
+            .unwrap_err();
+            assert!(err.to_string().contains("gave up after 1 retries"));
+            server.join().unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_urls() {
+        assert!(is_remote(Path::new("https://ftp.ensembl.org/a.gtf.gz")));
+        assert!(is_remote(Path::new("s3://bucket/a.gff3")));
+        assert!(!is_remote(Path::new("data/https.gtf")));
+
+        assert_eq!(
+            http_url("s3://ngi-igenomes/Homo_sapiens/genes.gtf"),
+            "https://ngi-igenomes.s3.amazonaws.com/Homo_sapiens/genes.gtf"
+        );
+        assert_eq!(http_url("http://host/a.gtf"), "http://host/a.gtf");
+        assert_eq!(file_name("https://host/pub/a.gtf.gz?raw=1"), "a.gtf.gz");
+        assert_eq!(file_name("https://host/"), "input");
+    }
+}
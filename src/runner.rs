@@ -0,0 +1,179 @@
+use std::path::Path;
+use std::sync::{Condvar, Mutex};
+
+use crate::config::Config;
+use crate::convert::{run, RunStats};
+use crate::error::Result;
+
+/// Converts many files with one set of options.
+///
+/// The options are validated and the worker pool is built once, in
+/// [`Runner::new`]; each [`Runner::convert`] call then only swaps the input
+/// and output paths. Meant for services converting many files per process:
+/// a runner can be shared by threads converting at once, sharing its pool.
+pub struct Runner {
+    config: Config,
+    #[cfg(feature = "parallel")]
+    pool: rayon::ThreadPool,
+    slots: Option<Slots>,
+}
+
+impl Runner {
+    /// Validates `config` and builds its pool; `config.input` and
+    /// `config.output` are ignored.
+    pub fn new(config: &Config) -> Result<Self> {
+        config.validate()?;
+
+        Ok(Self {
+            config: config.clone(),
+            #[cfg(feature = "parallel")]
+            pool: rayon::ThreadPoolBuilder::new()
+                .num_threads(config.effective_threads())
+                .build()
+                .map_err(|e| std::io::Error::other(e.to_string()))?,
+            slots: None,
+        })
+    }
+
+    /// Lets at most `limit` (at least 1) conversions run at once, the others
+    /// waiting in [`Runner::convert`]; as each holds one input in memory (or
+    /// `max_memory` with spilling), this bounds their combined memory.
+    pub fn max_running(mut self, limit: usize) -> Self {
+        self.slots = Some(Slots {
+            free: Mutex::new(limit.max(1)),
+            freed: Condvar::new(),
+        });
+        self
+    }
+
+    /// Options shared by every conversion.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Converts `input` into `output` (a file or a directory) with the
+    /// runner's options.
+    pub fn convert<P: AsRef<Path>, Q: AsRef<Path>>(&self, input: P, output: Q) -> Result<RunStats> {
+        let mut config = self.config.clone();
+        config.input = input.as_ref().to_path_buf();
+        config.output = output.as_ref().to_path_buf();
+        // INFO: only the compression level depends on the output path
+        if config.compress_level.is_some() {
+            config.validate()?;
+        }
+
+        let _slot = self.slots.as_ref().map(Slots::acquire);
+        #[cfg(feature = "parallel")]
+        return self.pool.install(|| run(&config));
+        #[cfg(not(feature = "parallel"))]
+        run(&config)
+    }
+}
+
+/// Counting semaphore for [`Runner::max_running`].
+struct Slots {
+    free: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl Slots {
+    /// Waits for a free slot, held until the guard drops.
+    fn acquire(&self) -> Slot<'_> {
+        // INFO: the count stays consistent even if a holder panicked
+        let mut free = self.free.lock().unwrap_or_else(|e| e.into_inner());
+        while *free == 0 {
+            free = self.freed.wait(free).unwrap_or_else(|e| e.into_inner());
+        }
+        *free -= 1;
+        Slot(self)
+    }
+}
+
+struct Slot<'a>(&'a Slots);
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        *self.0.free.lock().unwrap_or_else(|e| e.into_inner()) += 1;
+        self.0.freed.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::output_compression;
+
+    #[test]
+    fn test_convert_many() {
+        let dir = tempfile::tempdir().unwrap();
+        let gtf = "chr1\tHAVANA\ttranscript\t11\t60\t.\t+\t.\ttranscript_id \"A\";\n\
+                   chr1\tHAVANA\texon\t11\t60\t.\t+\t.\ttranscript_id \"A\";\n";
+        let runner = Runner::new(&Config::builder().threads(2).build().unwrap()).unwrap();
+
+        for (idx, ext) in ["bed", "bed", "bed.gz"].iter().enumerate() {
+            #[cfg(not(feature = "gzip"))]
+            if ext.ends_with("gz") {
+                continue;
+            }
+            let input = dir.path().join(format!("runner{}.gtf", idx));
+            let output = input.with_extension(ext);
+            std::fs::write(&input, gtf).unwrap();
+
+            let stats = runner.convert(&input, &output).unwrap();
+            assert_eq!(stats.written, 1);
+            let bed = std::fs::read(&output).unwrap();
+            let bed = crate::convert::decode(&bed[..], output_compression(&output)).unwrap();
+            assert!(bed.starts_with("chr1\t10\t60\tA\t"));
+        }
+
+        let level = Config::builder().compress_level(30).build().unwrap();
+        assert!(Runner::new(&level)
+            .unwrap()
+            .convert("a.gtf", "a.bed.gz")
+            .is_err());
+    }
+
+    #[test]
+    fn test_concurrent_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = (0..8)
+            .map(|idx| {
+                let input = dir.path().join(format!("concurrent{}.gtf", idx));
+                let gtf = format!(
+                    "chr1\tHAVANA\ttranscript\t11\t60\t.\t+\t.\ttranscript_id \"T{idx}\";\n\
+                     chr1\tHAVANA\texon\t11\t60\t.\t+\t.\ttranscript_id \"T{idx}\";\n"
+                );
+                std::fs::write(&input, gtf).unwrap();
+                (input.clone(), input.with_extension("bed"))
+            })
+            .collect::<Vec<_>>();
+        let config = Config::builder().threads(2).max_memory(1).build().unwrap();
+        let runner = Runner::new(&config).unwrap().max_running(3);
+
+        std::thread::scope(|scope| {
+            for (idx, (input, output)) in files.iter().enumerate() {
+                let (runner, config) = (&runner, &config);
+                scope.spawn(move || {
+                    let stats = match idx % 2 {
+                        0 => runner.convert(input, output).unwrap(),
+                        _ => {
+                            let mut config = config.clone();
+                            config.input.clone_from(input);
+                            config.output.clone_from(output);
+                            run(&config).unwrap()
+                        }
+                    };
+                    assert_eq!(stats.written, 1);
+                });
+            }
+        });
+
+        for (idx, (_, output)) in files.into_iter().enumerate() {
+            let bed = std::fs::read_to_string(&output).unwrap();
+            assert!(
+                bed.starts_with(&format!("chr1\t10\t60\tT{}\t", idx)),
+                "{bed}"
+            );
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use std::io::BufRead;
+
+use hashbrown::HashMap;
+
+use crate::config::Config;
+use crate::detect::InputFormat;
+use crate::error::{Gxf2BedError, Result};
+use crate::gxf::{
+    is_comment, GenePred, GxfRecord, InputCoords, KeyMatcher, Strand, FASTA_DIRECTIVE,
+};
+use crate::loci;
+use crate::utf8::Lines;
+
+/// A single annotation feature, as consumed by the grouping stage.
+///
+/// Coordinates are 0-based, half-open.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feature {
+    pub chrom: String,
+    /// Feature type, matched against `Config::parent` and `Config::child`.
+    pub kind: String,
+    pub start: u64,
+    pub end: u64,
+    pub strand: Strand,
+    /// Grouping key; also used as the BED name.
+    pub id: String,
+}
+
+/// Anything that yields [`Feature`]s can be fed into the conversion pipeline
+/// through [`crate::convert::records_from_source`].
+///
+/// Implemented for every `IntoIterator<Item = Result<Feature>>`, so a `Vec`,
+/// an iterator adapter or a database cursor wrapper all qualify.
+pub trait AnnotationSource {
+    type Features: Iterator<Item = Result<Feature>>;
+
+    fn features(self) -> Self::Features;
+}
+
+impl<I: IntoIterator<Item = Result<Feature>>> AnnotationSource for I {
+    type Features = I::IntoIter;
+
+    fn features(self) -> Self::Features {
+        self.into_iter()
+    }
+}
+
+/// Sequential GTF/GFF reader yielding [`Feature`]s grouped by `attribute`,
+/// or by the first key present of a comma-separated fallback list.
+///
+/// Comment and blank lines are skipped; malformed lines, including invalid
+/// UTF-8, are reported as [`Gxf2BedError::Parse`] with their 1-based line
+/// number.
+pub struct GxfReader<R> {
+    reader: R,
+    keys: KeyMatcher,
+    sep: u8,
+    coords: InputCoords,
+    comment_chars: Vec<String>,
+    line: Vec<u8>,
+    lines: Lines,
+    line_number: usize,
+}
+
+impl<R: BufRead> GxfReader<R> {
+    pub fn new<S: AsRef<str>>(reader: R, format: InputFormat, attribute: S) -> Self {
+        Self {
+            reader,
+            keys: KeyMatcher::fallback(attribute.as_ref()),
+            sep: format.separator(),
+            coords: InputCoords::default(),
+            comment_chars: Vec::new(),
+            line: Vec::new(),
+            lines: Lines::default(),
+            line_number: 0,
+        }
+    }
+
+    /// Reads starts in `coords` instead of the usual 1-based convention.
+    pub fn input_coords(mut self, coords: InputCoords) -> Self {
+        self.coords = coords;
+        self
+    }
+
+    /// Splits attribute keys from values at `sep` instead of the format's
+    /// usual separator.
+    pub fn attr_sep(mut self, sep: u8) -> Self {
+        self.sep = sep;
+        self
+    }
+
+    /// Also skips lines starting with any of `prefixes`, like `#` comments.
+    pub fn comment_chars<S: Into<String>>(mut self, prefixes: impl IntoIterator<Item = S>) -> Self {
+        self.comment_chars = prefixes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    fn parse_line(&self, line: &str) -> std::result::Result<Feature, &'static str> {
+        let line = line.trim_end_matches(['\n', '\r']);
+        let record = GxfRecord::parse_with(line, &self.keys, self.sep, self.coords)?;
+
+        Ok(Feature {
+            chrom: record.chr,
+            kind: record.feature.to_string(),
+            start: record.start,
+            end: record.end,
+            strand: record.strand,
+            id: record.attr.feature().to_string(),
+        })
+    }
+}
+
+impl<R: BufRead> Iterator for GxfReader<R> {
+    type Item = Result<Feature>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.reader.read_until(b'\n', &mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => self.line_number += 1,
+                Err(err) => return Some(Err(err.into())),
+            }
+
+            let line = match self.lines.next(&self.line, false) {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+            if line.starts_with(FASTA_DIRECTIVE) {
+                return None;
+            }
+            if is_comment(&line, &self.comment_chars) || line.trim().is_empty() {
+                continue;
+            }
+
+            return Some(
+                self.parse_line(&line)
+                    .map_err(|e| Gxf2BedError::record(self.line_number, &line, e)),
+            );
+        }
+    }
+}
+
+/// Groups features from `source` the same way the built-in GTF/GFF parser does.
+pub(crate) fn group_source<S: AnnotationSource>(
+    config: &Config,
+    source: S,
+) -> Result<HashMap<String, GenePred>> {
+    let mut groups: HashMap<String, GenePred> = HashMap::new();
+
+    for feature in source.features() {
+        let feature = feature?;
+        if feature.kind == config.parent {
+            let strand = loci::key_strand(config.mixed_strand, feature.strand);
+            let key = loci::key(&feature.id, &feature.chrom, strand);
+            groups.entry(key).or_default().set_parent(
+                &feature.chrom,
+                feature.start,
+                feature.end,
+                feature.strand,
+            );
+        } else if config.is_child(&feature.kind) {
+            let strand = loci::key_strand(config.mixed_strand, feature.strand);
+            let key = loci::key(&feature.id, &feature.chrom, strand);
+            groups.entry(key).or_default().add_child(
+                &feature.chrom,
+                feature.start,
+                feature.end,
+                feature.strand,
+            );
+        }
+    }
+
+    loci::resolve(&mut groups);
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gxf_reader() {
+        let gff = "##gff-version 3\n\
+            chr1\t.\tmRNA\t1\t100\t.\t+\t.\tID=tx1\n\
+            \n\
+            chr1\t.\texon\t1\t50\t.\t+\t.\tParent=tx1\n\
+            chr1\tbroken\n";
+
+        let features = GxfReader::new(gff.as_bytes(), InputFormat::Gff, "ID").collect::<Vec<_>>();
+
+        assert_eq!(features.len(), 3);
+        let first = features[0].as_ref().unwrap();
+        assert_eq!((first.kind.as_str(), first.id.as_str()), ("mRNA", "tx1"));
+        assert_eq!((first.start, first.end), (0, 100));
+        assert!(matches!(
+            &features[2],
+            Err(Gxf2BedError::Parse { line: 5, .. })
+        ));
+    }
+}
@@ -0,0 +1,553 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+
+use hashbrown::HashMap;
+use tempfile::TempDir;
+
+use crate::codec::is_truncated;
+use crate::config::Config;
+use crate::convert::{by_position, truncated, Groups};
+use crate::detect::InputFormat;
+use crate::error::{Gxf2BedError, Result};
+use crate::gxf::{is_comment, Coding, GenePred, KeyMatcher, RecordType, Strand, FASTA_DIRECTIVE};
+use crate::loci;
+use crate::par_y;
+use crate::utf8::Lines;
+use crate::utils::{add_record, parse_row, Grouping};
+
+type Group = (String, GenePred);
+type Order = fn(&Group, &Group) -> Ordering;
+
+/// Rough heap cost of a group entry (key, chromosome, map slot, block set).
+const GROUP_BYTES: u64 = 128;
+/// Rough heap cost of one block in a group's block set.
+const BLOCK_BYTES: u64 = 32;
+/// Separates captured attribute values in a run file line.
+const ATTR_SEP: &str = "\x1f";
+
+/// Parses a memory size such as `4G`, `512M`, `64k` or `1048576` (bytes).
+/// Units are binary (1K = 1024 bytes); a trailing `B`/`iB` is accepted.
+pub fn parse_size(size: &str) -> std::result::Result<u64, String> {
+    let upper = size.trim().to_ascii_uppercase();
+    let digits = upper.trim_end_matches("IB").trim_end_matches('B');
+    let (number, shift) = match digits.chars().last() {
+        Some('K') => (&digits[..digits.len() - 1], 10),
+        Some('M') => (&digits[..digits.len() - 1], 20),
+        Some('G') => (&digits[..digits.len() - 1], 30),
+        Some('T') => (&digits[..digits.len() - 1], 40),
+        _ => (digits, 0),
+    };
+
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(1 << shift))
+        .ok_or_else(|| format!("invalid size {:?}, expected e.g. 4G, 512M or bytes", size))
+}
+
+/// Groups `reader` like the in-memory parser, but keeps at most about
+/// `budget` bytes of grouped data in memory: whenever the budget is exceeded
+/// the partial groups are written to a temporary run file. Runs are merged by
+/// name, then sorted by position the same way, so the result has the usual
+/// (chromosome, start, end, name) order.
+///
+/// Inputs that fit in the budget never touch the disk. With
+/// `Config::keep_comments`, leading `#` lines go to `header`.
+pub(crate) fn group_with_budget<R: BufRead>(
+    config: &Config,
+    mut reader: R,
+    format: InputFormat,
+    budget: u64,
+    header: &mut Vec<String>,
+) -> Result<Groups> {
+    let grouping = Grouping::from(config);
+    let keys = KeyMatcher::fallback(&grouping.feature);
+    let sep = config.separator(format);
+    let mut spill = Spill::default();
+    let mut runs = Vec::new();
+    let mut groups: HashMap<String, GenePred> = HashMap::new();
+    let mut used = 0;
+    let mut records = 0;
+    let mut lines = Lines::default();
+    let mut buf = Vec::new();
+
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(err) if is_truncated(&err) => {
+                truncated(err, records, config.allow_truncated)?;
+                break;
+            }
+            Err(err) => return Err(err.into()),
+        }
+        let line = lines.next(&buf, config.lossy_utf8)?;
+        if line.starts_with(FASTA_DIRECTIVE) {
+            break;
+        }
+        if is_comment(&line, &grouping.comment_chars) || line.trim().is_empty() {
+            if config.keep_comments && records == 0 && line.starts_with('#') {
+                header.push(line.trim_end_matches(['\n', '\r']).to_string());
+            }
+            continue;
+        }
+        records += 1;
+
+        let row = line.trim_end_matches(['\n', '\r']);
+        let record = parse_row(row, &keys, sep, config.input_coords)
+            .map_err(|e| Gxf2BedError::record(lines.line(), row, e))?;
+        let id = record.attr.feature();
+        if !groups.contains_key(id) {
+            used += GROUP_BYTES + (id.len() + record.chr.len()) as u64;
+        }
+        used += BLOCK_BYTES;
+        groups = add_record(groups, &record, &grouping);
+
+        if used > budget {
+            let mut batch = groups.drain().collect::<Vec<_>>();
+            batch.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+            runs.push(spill.write(&batch)?);
+            used = 0;
+        }
+    }
+
+    if runs.is_empty() {
+        loci::resolve(&mut groups);
+        par_y::resolve(&mut groups);
+        let groups = groups.into_iter().collect::<Vec<_>>();
+        return Ok(match config.unordered {
+            true => Box::new(groups.into_iter().map(Ok)),
+            false => Box::new(sorted(groups).map(Ok)),
+        });
+    }
+
+    let mut batch = groups.into_iter().collect::<Vec<_>>();
+    batch.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    runs.push(spill.write(&batch)?);
+    log::info!(runs = runs.len(); "Spilled grouped data to {} runs", runs.len());
+
+    // INFO: unordered output streams the merge by name, which then owns the runs
+    let dir = match config.unordered {
+        true => spill.dir.take(),
+        false => None,
+    };
+    let merged = par_y::Resolve::new(loci::Resolve::new(Combine {
+        inner: Merge::new(runs, by_name, dir)?,
+        next: None,
+    }));
+    if config.unordered {
+        return Ok(Box::new(merged));
+    }
+    sort_with_budget(merged, budget, spill)
+}
+
+/// Sorts complete groups by position, spilling sorted runs past `budget`.
+fn sort_with_budget<I: Iterator<Item = Result<Group>>>(
+    groups: I,
+    budget: u64,
+    mut spill: Spill,
+) -> Result<Groups> {
+    let mut runs = Vec::new();
+    let mut batch = Vec::new();
+    let mut used = 0;
+
+    for group in groups {
+        let group = group?;
+        used += GROUP_BYTES
+            + (group.0.len() + group.1.chr.len()) as u64
+            + BLOCK_BYTES * group.1.exons.len() as u64;
+        batch.push(group);
+
+        if used > budget {
+            runs.push(spill.write(&sorted(std::mem::take(&mut batch)).collect::<Vec<_>>())?);
+            used = 0;
+        }
+    }
+
+    if runs.is_empty() {
+        return Ok(Box::new(sorted(batch).map(Ok)));
+    }
+
+    runs.push(spill.write(&sorted(batch).collect::<Vec<_>>())?);
+    Ok(Box::new(Merge::new(runs, by_position, spill.dir)?))
+}
+
+fn sorted(mut groups: Vec<Group>) -> std::vec::IntoIter<Group> {
+    groups.sort_unstable_by(by_position);
+    groups.into_iter()
+}
+
+fn by_name(a: &Group, b: &Group) -> Ordering {
+    a.0.cmp(&b.0)
+}
+
+/// Temporary directory holding the run files of one conversion, created on
+/// the first spill and removed on drop.
+#[derive(Default)]
+struct Spill {
+    dir: Option<TempDir>,
+    written: usize,
+}
+
+impl Spill {
+    /// Writes `groups`, already in run order, to a new run file.
+    fn write(&mut self, groups: &[Group]) -> Result<PathBuf> {
+        let dir = match &mut self.dir {
+            Some(dir) => dir,
+            dir => dir.insert(tempfile::Builder::new().prefix("gxf2bed-").tempdir()?),
+        };
+        let path = dir.path().join(format!("run-{}", self.written));
+        self.written += 1;
+
+        let mut writer = BufWriter::new(File::create(&path)?);
+        for (name, info) in groups {
+            let kind = match info.record_type {
+                RecordType::Parent => 'P',
+                RecordType::Child => 'C',
+                RecordType::Unknown => 'U',
+            };
+            write!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}:{}\t",
+                name,
+                info.chr,
+                info.start,
+                info.end,
+                info.strand,
+                kind,
+                info.duplicates,
+                info.loci,
+                info.strands[0],
+                info.strands[1]
+            )?;
+            for (start, size) in &info.exons {
+                write!(writer, "{}:{},", start, size)?;
+            }
+            write!(writer, "\t")?;
+            if let Some(coding) = &info.coding {
+                write_coding(&mut writer, coding)?;
+            }
+            write!(writer, "\t")?;
+            for (start, number) in &info.exon_numbers {
+                write!(writer, "{}:{},", start, number)?;
+            }
+            // INFO: GTF/GFF values cannot hold tabs or newlines; missing ones are left empty
+            for (idx, value) in info.attrs.iter().enumerate() {
+                let sep = if idx == 0 { "\t" } else { ATTR_SEP };
+                write!(writer, "{}{}", sep, value.as_deref().unwrap_or_default())?;
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()?;
+
+        Ok(path)
+    }
+}
+
+/// Writes `coding` as `<cds blocks>;<stop codon>;<first frame>;<last frame>;<start codon>`,
+/// with blocks like the exons and the rest as `a:b` pairs (empty when unset).
+fn write_coding<W: Write>(writer: &mut W, coding: &Coding) -> std::io::Result<()> {
+    for (start, size) in &coding.cds {
+        write!(writer, "{}:{},", start, size)?;
+    }
+    let pairs = [
+        coding.stop_codon,
+        coding.first_frame.map(|(pos, frame)| (pos, frame as u64)),
+        coding.last_frame.map(|(pos, frame)| (pos, frame as u64)),
+        coding.start_codon,
+    ];
+    for pair in pairs {
+        write!(writer, ";")?;
+        if let Some((a, b)) = pair {
+            write!(writer, "{}:{}", a, b)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_coding(field: &str) -> Option<Coding> {
+    fn pair(field: &str) -> Option<Option<(u64, u64)>> {
+        match field.split_once(':') {
+            Some((a, b)) => Some(Some((a.parse().ok()?, b.parse().ok()?))),
+            None => field.is_empty().then_some(None),
+        }
+    }
+    let frame = |pair: Option<(u64, u64)>| pair.map(|(pos, frame)| (pos, frame as u8));
+
+    let mut parts = field.split(';');
+    let mut coding = Coding::default();
+    for block in parts.next()?.split_terminator(',') {
+        coding.cds.insert(pair(block)??);
+    }
+    coding.stop_codon = pair(parts.next()?)?;
+    coding.first_frame = frame(pair(parts.next()?)?);
+    coding.last_frame = frame(pair(parts.next()?)?);
+    coding.start_codon = pair(parts.next()?)?;
+
+    Some(coding)
+}
+
+/// Reads groups back from a run file.
+struct Run {
+    reader: BufReader<File>,
+    line: String,
+}
+
+impl Run {
+    fn open(path: &PathBuf) -> Result<Self> {
+        Ok(Self {
+            reader: BufReader::new(File::open(path)?),
+            line: String::new(),
+        })
+    }
+
+    fn parse(line: &str) -> Option<Group> {
+        let mut fields = line.trim_end_matches('\n').split('\t');
+        let name = fields.next()?.to_string();
+
+        let mut info = GenePred::new();
+        info.chr = fields.next()?.to_string();
+        info.start = fields.next()?.parse().ok()?;
+        info.end = fields.next()?.parse().ok()?;
+        info.strand = match fields.next()? {
+            "+" => Strand::Forward,
+            "-" => Strand::Reverse,
+            _ => Strand::Unknown,
+        };
+        info.record_type = match fields.next()? {
+            "P" => RecordType::Parent,
+            "C" => RecordType::Child,
+            _ => RecordType::Unknown,
+        };
+        info.duplicates = fields.next()?.parse().ok()?;
+        info.loci = fields.next()?.parse().ok()?;
+        let (forward, reverse) = fields.next()?.split_once(':')?;
+        info.strands = [forward.parse().ok()?, reverse.parse().ok()?];
+        for block in fields.next()?.split_terminator(',') {
+            let (start, size) = block.split_once(':')?;
+            info.exons.insert((start.parse().ok()?, size.parse().ok()?));
+        }
+        match fields.next()? {
+            "" => (),
+            coding => info.coding = Some(Box::new(parse_coding(coding)?)),
+        }
+        for number in fields.next()?.split_terminator(',') {
+            let (start, number) = number.split_once(':')?;
+            info.exon_numbers
+                .push((start.parse().ok()?, number.parse().ok()?));
+        }
+        if let Some(attrs) = fields.next() {
+            info.attrs = attrs
+                .split(ATTR_SEP)
+                .map(|value| (!value.is_empty()).then(|| value.to_string()))
+                .collect();
+        }
+
+        Some((name, info))
+    }
+}
+
+impl Iterator for Run {
+    type Item = Result<Group>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.line.clear();
+        match self.reader.read_line(&mut self.line) {
+            Ok(0) => None,
+            Ok(_) => Some(Self::parse(&self.line).ok_or_else(|| {
+                Gxf2BedError::Io(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("corrupt spill run line {:?}", self.line),
+                ))
+            })),
+            Err(err) => Some(Err(err.into())),
+        }
+    }
+}
+
+/// Next group of one run, ordered so that [`BinaryHeap`] pops the smallest.
+struct Head {
+    group: Group,
+    run: usize,
+    order: Order,
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.order)(&other.group, &self.group).then(other.run.cmp(&self.run))
+    }
+}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+/// K-way merge of run files sorted by `order`.
+struct Merge {
+    runs: Vec<Run>,
+    heap: BinaryHeap<Head>,
+    order: Order,
+    // INFO: keeps the run files alive while the final merge is consumed
+    _dir: Option<TempDir>,
+}
+
+impl Merge {
+    fn new(paths: Vec<PathBuf>, order: Order, dir: Option<TempDir>) -> Result<Self> {
+        let mut merge = Self {
+            runs: paths.iter().map(Run::open).collect::<Result<_>>()?,
+            heap: BinaryHeap::new(),
+            order,
+            _dir: dir,
+        };
+        for run in 0..merge.runs.len() {
+            merge.refill(run)?;
+        }
+
+        Ok(merge)
+    }
+
+    fn refill(&mut self, run: usize) -> Result<()> {
+        if let Some(group) = self.runs[run].next().transpose()? {
+            self.heap.push(Head {
+                group,
+                run,
+                order: self.order,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for Merge {
+    type Item = Result<Group>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let head = self.heap.pop()?;
+        if let Err(err) = self.refill(head.run) {
+            return Some(Err(err));
+        }
+        Some(Ok(head.group))
+    }
+}
+
+/// Folds consecutive partial groups with the same name, as the parallel
+/// reduce step does for in-memory maps.
+struct Combine {
+    inner: Merge,
+    next: Option<Group>,
+}
+
+impl Iterator for Combine {
+    type Item = Result<Group>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (name, first) = match self.next.take() {
+            Some(group) => group,
+            None => match self.inner.next()? {
+                Ok(group) => group,
+                Err(err) => return Some(Err(err)),
+            },
+        };
+
+        let mut info = GenePred::new();
+        info.merge(first);
+        for group in self.inner.by_ref() {
+            match group {
+                Ok((other, part)) if other == name => info.merge(part),
+                Ok(group) => {
+                    self.next = Some(group);
+                    break;
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
+
+        Some(Ok((name, info)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4G"), Ok(4 << 30));
+        assert_eq!(parse_size("512mb"), Ok(512 << 20));
+        assert_eq!(parse_size("1KiB"), Ok(1024));
+        assert_eq!(parse_size("100"), Ok(100));
+        assert!(parse_size("lots").is_err());
+    }
+
+    #[test]
+    fn test_group_with_budget_matches_in_memory() {
+        let gtf = (0..50)
+            .rev()
+            .flat_map(|i| {
+                let start = 1000 * (i % 7) + i;
+                [
+                    format!(
+                        "chr{}\t.\ttranscript\t{}\t{}\t.\t+\t.\ttranscript_id \"T{}\";\n",
+                        i % 3,
+                        start + 1,
+                        start + 300,
+                        i
+                    ),
+                    format!(
+                        "chr{}\t.\texon\t{}\t{}\t.\t+\t.\ttranscript_id \"T{}\";\n",
+                        i % 3,
+                        start + 1,
+                        start + 100,
+                        i
+                    ),
+                    format!(
+                        "chr{}\t.\texon\t{}\t{}\t.\t+\t.\ttranscript_id \"T{}\";\n",
+                        i % 3,
+                        start + 201,
+                        start + 300,
+                        i
+                    ),
+                ]
+            })
+            .collect::<String>();
+        let gtf = format!("#!genome-build GRCh38\n{}", gtf);
+        let mut config = Config::new("unused.gtf", "unused.bed");
+        config.keep_comments = true;
+
+        let collect = |budget| {
+            let mut header = Vec::new();
+            let groups = group_with_budget(
+                &config,
+                gtf.as_bytes(),
+                InputFormat::Gtf,
+                budget,
+                &mut header,
+            )
+            .unwrap()
+            .map(|g| g.unwrap())
+            .collect::<Vec<_>>();
+            assert_eq!(header, ["#!genome-build GRCh38"]);
+            groups
+        };
+
+        let in_memory = collect(u64::MAX);
+        assert_eq!(in_memory.len(), 50);
+        assert_eq!(collect(512), in_memory);
+    }
+}
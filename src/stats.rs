@@ -0,0 +1,659 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::chrom::ChromSizes;
+use crate::config::Config;
+use crate::detect::detect_input_kind;
+use crate::error::Result;
+use crate::gxf::{is_comment, lines, until_fasta, GenePred};
+use crate::input::read_input;
+use crate::progress::NoProgress;
+use crate::utils::{to_bed, Grouping};
+
+/// Attribute `gxf2bed stats` splits its distributions by (GENCODE naming).
+pub const DEFAULT_STATS_BY: &str = "transcript_type";
+
+/// Length buckets of the histograms, as upper bounds; the last bucket is open.
+const LENGTH_EDGES: [u64; 5] = [100, 1_000, 10_000, 100_000, 1_000_000];
+const LENGTH_LABELS: [&str; 6] = ["<100", "100-1k", "1k-10k", "10k-100k", "100k-1M", ">=1M"];
+/// Exon and intron count buckets, likewise.
+const COUNT_EDGES: [u64; 6] = [1, 2, 3, 6, 11, 21];
+const COUNT_LABELS: [&str; 7] = ["0", "1", "2", "3-5", "6-10", "11-20", ">20"];
+
+/// How `gxf2bed stats` prints its tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsFormat {
+    /// Aligned columns for reading.
+    #[default]
+    Text,
+    /// Tab-separated tables, each after a `#title` line.
+    Tsv,
+    /// One JSON object, for QC dashboards.
+    Json,
+}
+
+/// Values of one metric across records, sorted once collected.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Distribution {
+    values: Vec<u64>,
+}
+
+impl Distribution {
+    fn push(&mut self, value: u64) {
+        self.values.push(value);
+    }
+
+    fn finish(&mut self) {
+        self.values.sort_unstable();
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn min(&self) -> Option<u64> {
+        self.values.first().copied()
+    }
+
+    pub fn max(&self) -> Option<u64> {
+        self.values.last().copied()
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        let sum = self.values.iter().map(|v| *v as f64).sum::<f64>();
+        (!self.is_empty()).then(|| sum / self.len() as f64)
+    }
+
+    /// Middle value, or the mean of the two middle ones.
+    pub fn median(&self) -> Option<f64> {
+        let mid = self.len() / 2;
+        match self.len() {
+            0 => None,
+            n if n % 2 == 1 => Some(self.values[mid] as f64),
+            _ => Some((self.values[mid - 1] + self.values[mid]) as f64 / 2.0),
+        }
+    }
+
+    /// Number of values below each of `edges`, and at or above the previous
+    /// one; the last count holds values at or above the last edge.
+    pub fn histogram(&self, edges: &[u64]) -> Vec<usize> {
+        let mut below = edges
+            .iter()
+            .map(|edge| self.values.partition_point(|v| v < edge))
+            .collect::<Vec<_>>();
+        below.push(self.len());
+
+        let mut last = 0;
+        below
+            .into_iter()
+            .map(|count| {
+                let bucket = count - last;
+                last = count;
+                bucket
+            })
+            .collect()
+    }
+}
+
+/// Picks one distribution out of a [`Metrics`].
+type Pick = fn(&Metrics) -> &Distribution;
+
+/// Distributions over one set of records.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metrics {
+    /// Genomic span, `chromEnd - chromStart`.
+    pub span: Distribution,
+    /// Sum of the block sizes.
+    pub spliced: Distribution,
+    /// Number of blocks.
+    pub exons: Distribution,
+    /// Number of gaps between consecutive blocks; overlapping or adjacent
+    /// blocks have none.
+    pub introns: Distribution,
+}
+
+impl Metrics {
+    fn add(&mut self, info: &GenePred) {
+        self.span.push(info.end.saturating_sub(info.start));
+        self.spliced.push(info.get_exon_sizes().iter().sum::<u64>());
+        self.exons.push(info.get_exon_count() as u64);
+
+        let mut end = None;
+        let mut introns = 0;
+        for (start, size) in &info.exons {
+            if end.is_some_and(|end| *start > end) {
+                introns += 1;
+            }
+            end = end.max(Some(start + size));
+        }
+        self.introns.push(introns);
+    }
+
+    fn finish(&mut self) {
+        self.span.finish();
+        self.spliced.finish();
+        self.exons.finish();
+        self.introns.finish();
+    }
+}
+
+/// Summary of an annotation's records, printed by `gxf2bed stats`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    /// Attribute the records are split by.
+    pub by: String,
+    /// Every record with blocks.
+    pub all: Metrics,
+    /// Records per `by` value; `.` holds those without one.
+    pub groups: BTreeMap<String, Metrics>,
+    /// Feature lines per chromosome and feature type, every type counted.
+    pub features: BTreeMap<String, BTreeMap<String, usize>>,
+    /// Bases covered per chromosome with records.
+    pub footprint: BTreeMap<String, Footprint>,
+    /// Bases covered genome-wide; with `Config::chrom_sizes`, chromosomes
+    /// without records count as intergenic.
+    pub genome: Footprint,
+}
+
+/// Bases of a chromosome or genome covered by the records, each counted once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Footprint {
+    /// Within some block.
+    pub exonic: u64,
+    /// Within some record span but no block.
+    pub intronic: u64,
+    /// Outside every record span; known with `Config::chrom_sizes` only.
+    pub intergenic: Option<u64>,
+}
+
+impl Footprint {
+    /// Footprint of a chromosome of `size` bases with record spans `spans`
+    /// and blocks `blocks`, as unsorted, possibly overlapping intervals.
+    fn new(mut spans: Vec<(u64, u64)>, mut blocks: Vec<(u64, u64)>, size: Option<u64>) -> Self {
+        let exonic = merged_len(&mut blocks);
+        let genic = merged_len(&mut spans);
+        Self {
+            exonic,
+            intronic: genic.saturating_sub(exonic),
+            intergenic: size.map(|size| size.saturating_sub(genic)),
+        }
+    }
+
+    fn genic(&self) -> u64 {
+        self.exonic + self.intronic
+    }
+
+    /// Share of the chromosome or genome within some record span.
+    pub fn covered(&self) -> Option<f64> {
+        let size = self.genic() + self.intergenic?;
+        Some(self.genic() as f64 / size.max(1) as f64)
+    }
+}
+
+/// Total length of the union of `intervals`.
+fn merged_len(intervals: &mut [(u64, u64)]) -> u64 {
+    intervals.sort_unstable();
+
+    let (mut total, mut reach) = (0, 0);
+    for (start, end) in intervals.iter() {
+        let start = (*start).max(reach);
+        if *end > start {
+            total += end - start;
+            reach = *end;
+        }
+    }
+    total
+}
+
+/// Groups `config.input` like a conversion and collects the metrics of
+/// each record, overall and per value of the `by` attribute, along with
+/// the feature lines of each type per chromosome and the bases covered
+/// per chromosome (intergenic ones given `Config::chrom_sizes`).
+///
+/// Spans cover every block, as with the default `--out-of-bounds expand`;
+/// groups without child features are left out, as they are not written.
+pub fn stats(config: &Config, by: &str) -> Result<Stats> {
+    let kind = detect_input_kind(&config.input)?;
+    let contents = read_input(config, kind, &NoProgress)?;
+    let grouping = Grouping {
+        attrs: vec![config.attr_key(by)],
+        ..Grouping::from(config)
+    };
+    let groups = to_bed(contents.as_str(), config.separator(kind.format), &grouping)?;
+
+    let mut stats = Stats {
+        by: by.to_string(),
+        features: count_features(contents.as_str(), &config.comment_chars),
+        ..Default::default()
+    };
+    let mut intervals: BTreeMap<String, (Vec<_>, Vec<_>)> = BTreeMap::new();
+    for mut info in groups.into_values().filter(|info| !info.exons.is_empty()) {
+        info.expand_to_blocks();
+        let value = info.attrs.pop().flatten().unwrap_or_else(|| ".".into());
+
+        stats.all.add(&info);
+        stats.groups.entry(value).or_default().add(&info);
+
+        let (spans, blocks) = intervals.entry(std::mem::take(&mut info.chr)).or_default();
+        spans.push((info.start, info.end));
+        blocks.extend(
+            info.exons
+                .iter()
+                .map(|(start, size)| (*start, start + size)),
+        );
+    }
+
+    let sizes = config
+        .chrom_sizes
+        .as_ref()
+        .map(ChromSizes::from_file)
+        .transpose()?;
+    for (chrom, (spans, blocks)) in intervals {
+        let size = sizes.as_ref().and_then(|sizes| sizes.get(&chrom));
+        let footprint = Footprint::new(spans, blocks, size);
+        stats.genome.exonic += footprint.exonic;
+        stats.genome.intronic += footprint.intronic;
+        stats.footprint.insert(chrom, footprint);
+    }
+    // INFO: records on chromosomes missing from the sizes are left out of the genome share
+    stats.genome.intergenic = sizes.map(|sizes| {
+        let genic = stats
+            .footprint
+            .values()
+            .filter(|footprint| footprint.intergenic.is_some())
+            .map(Footprint::genic)
+            .sum::<u64>();
+        sizes.total().saturating_sub(genic)
+    });
+
+    stats.all.finish();
+    stats.groups.values_mut().for_each(Metrics::finish);
+    Ok(stats)
+}
+
+/// One distribution of [`Metrics`] as printed: JSON key, table title and
+/// histogram buckets.
+struct Metric {
+    key: &'static str,
+    title: &'static str,
+    pick: Pick,
+    edges: &'static [u64],
+    labels: &'static [&'static str],
+}
+
+/// Metrics in output order.
+const METRICS: [Metric; 4] = [
+    Metric {
+        key: "span",
+        title: "span (bp)",
+        pick: |m| &m.span,
+        edges: &LENGTH_EDGES,
+        labels: &LENGTH_LABELS,
+    },
+    Metric {
+        key: "spliced",
+        title: "spliced length (bp)",
+        pick: |m| &m.spliced,
+        edges: &LENGTH_EDGES,
+        labels: &LENGTH_LABELS,
+    },
+    Metric {
+        key: "exons",
+        title: "exons per record",
+        pick: |m| &m.exons,
+        edges: &COUNT_EDGES,
+        labels: &COUNT_LABELS,
+    },
+    Metric {
+        key: "introns",
+        title: "introns per record",
+        pick: |m| &m.introns,
+        edges: &COUNT_EDGES,
+        labels: &COUNT_LABELS,
+    },
+];
+
+/// A titled table of the text and TSV renderings.
+struct Table {
+    title: String,
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Stats {
+    /// Renders the stats as `format`.
+    pub fn render(&self, format: StatsFormat) -> String {
+        match format {
+            StatsFormat::Text => self.to_string(),
+            StatsFormat::Tsv => self
+                .tables()
+                .iter()
+                .map(|table| {
+                    std::iter::once(&table.header)
+                        .chain(&table.rows)
+                        .map(|row| row.join("\t") + "\n")
+                        .fold(format!("#{}\n", table.title), |out, line| out + &line)
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            StatsFormat::Json => self.to_json().to_string() + "\n",
+        }
+    }
+
+    /// Rows of `metric` overall, then per group.
+    fn rows(&self, metric: Pick) -> Vec<(&str, &Distribution)> {
+        std::iter::once(("all", metric(&self.all)))
+            .chain(
+                self.groups
+                    .iter()
+                    .map(|(name, m)| (name.as_str(), metric(m))),
+            )
+            .collect()
+    }
+
+    fn tables(&self) -> Vec<Table> {
+        let mut tables = Vec::new();
+
+        let total = self.all.span.len();
+        tables.push(Table {
+            title: format!("records per {}", self.by),
+            header: vec![self.by.clone(), "records".into(), "percent".into()],
+            rows: self
+                .rows(|m| &m.span)
+                .into_iter()
+                .map(|(name, dist)| {
+                    let percent = 100.0 * dist.len() as f64 / total.max(1) as f64;
+                    vec![
+                        name.into(),
+                        dist.len().to_string(),
+                        format!("{:.1}", percent),
+                    ]
+                })
+                .collect(),
+        });
+
+        let kinds = self
+            .features
+            .values()
+            .flat_map(|counts| counts.keys())
+            .collect::<BTreeSet<_>>();
+        tables.push(Table {
+            title: "features per chromosome".into(),
+            header: std::iter::once("chrom")
+                .chain(kinds.iter().map(|kind| kind.as_str()))
+                .map(String::from)
+                .collect(),
+            rows: self
+                .features
+                .iter()
+                .map(|(chrom, counts)| {
+                    std::iter::once(chrom.clone())
+                        .chain(
+                            kinds.iter().map(|kind| {
+                                counts.get(*kind).copied().unwrap_or_default().to_string()
+                            }),
+                        )
+                        .collect()
+                })
+                .collect(),
+        });
+
+        let dash_or = |value: Option<String>| value.unwrap_or_else(|| "-".into());
+        tables.push(Table {
+            title: "footprint (bp)".into(),
+            header: ["chrom", "exonic", "intronic", "intergenic", "covered"]
+                .map(String::from)
+                .into(),
+            rows: self
+                .footprint
+                .iter()
+                .chain(std::iter::once((&"genome".to_string(), &self.genome)))
+                .map(|(chrom, footprint)| {
+                    vec![
+                        chrom.clone(),
+                        footprint.exonic.to_string(),
+                        footprint.intronic.to_string(),
+                        dash_or(footprint.intergenic.map(|n| n.to_string())),
+                        dash_or(footprint.covered().map(|f| format!("{:.2}%", 100.0 * f))),
+                    ]
+                })
+                .collect(),
+        });
+
+        for metric in &METRICS {
+            tables.push(Table {
+                title: metric.title.into(),
+                header: [self.by.as_str(), "count", "min", "median", "mean", "max"]
+                    .into_iter()
+                    .chain(metric.labels.iter().copied())
+                    .map(String::from)
+                    .collect(),
+                rows: self
+                    .rows(metric.pick)
+                    .into_iter()
+                    .map(|(name, dist)| summary_row(name, dist, metric.edges))
+                    .collect(),
+            });
+        }
+
+        tables
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let records = self
+            .rows(|m| &m.span)
+            .into_iter()
+            .map(|(name, dist)| (name.to_string(), json!(dist.len())))
+            .collect::<serde_json::Map<_, _>>();
+
+        let mut distributions = serde_json::Map::new();
+        for metric in &METRICS {
+            let groups = self
+                .rows(metric.pick)
+                .into_iter()
+                .map(|(name, dist)| {
+                    let histogram = metric
+                        .labels
+                        .iter()
+                        .zip(dist.histogram(metric.edges))
+                        .map(|(label, count)| (label.to_string(), json!(count)))
+                        .collect::<serde_json::Map<_, _>>();
+                    let summary = json!({
+                        "count": dist.len(),
+                        "min": dist.min(),
+                        "median": dist.median(),
+                        "mean": dist.mean(),
+                        "max": dist.max(),
+                        "histogram": histogram,
+                    });
+                    (name.to_string(), summary)
+                })
+                .collect::<serde_json::Map<_, _>>();
+            distributions.insert(metric.key.to_string(), groups.into());
+        }
+
+        json!({
+            "by": self.by,
+            "records": records,
+            "features": self.features,
+            "footprint": self.footprint,
+            "genome": self.genome,
+            "distributions": distributions,
+        })
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, table) in self.tables().into_iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "{}", table.title)?;
+            write_table(f, table.header, table.rows)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Counts the feature lines of `contents` per chromosome and type
+/// (first and third columns), FASTA sections excluded.
+fn count_features(
+    contents: &str,
+    comment_chars: &[String],
+) -> BTreeMap<String, BTreeMap<String, usize>> {
+    let mut features: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+    for line in lines(until_fasta(contents)).filter(|line| !is_comment(line, comment_chars)) {
+        let mut fields = line.split('\t');
+        if let (Some(chrom), Some(_), Some(kind)) = (fields.next(), fields.next(), fields.next()) {
+            *features
+                .entry(chrom.to_string())
+                .or_default()
+                .entry(kind.to_string())
+                .or_default() += 1;
+        }
+    }
+
+    features
+}
+
+/// Count, min, median, mean, max and histogram of `dist`, `-` when empty.
+fn summary_row(name: &str, dist: &Distribution, edges: &[u64]) -> Vec<String> {
+    let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".into());
+
+    [
+        name.to_string(),
+        dist.len().to_string(),
+        or_dash(dist.min().map(|v| v.to_string())),
+        or_dash(dist.median().map(|v| v.to_string())),
+        or_dash(dist.mean().map(|v| format!("{:.1}", v))),
+        or_dash(dist.max().map(|v| v.to_string())),
+    ]
+    .into_iter()
+    .chain(dist.histogram(edges).iter().map(|n| n.to_string()))
+    .collect()
+}
+
+/// Writes `rows` under `header`, the first column left-aligned and the
+/// others right-aligned to their widest cell.
+fn write_table(
+    f: &mut fmt::Formatter<'_>,
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+) -> fmt::Result {
+    let mut widths = header.iter().map(String::len).collect::<Vec<_>>();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    for row in std::iter::once(&header).chain(&rows) {
+        let mut line = String::new();
+        for (idx, (cell, width)) in row.iter().zip(&widths).enumerate() {
+            match idx {
+                0 => line.push_str(&format!("{:<width$}", cell, width = width)),
+                _ => line.push_str(&format!("  {:>width$}", cell, width = width)),
+            }
+        }
+        writeln!(f, "{}", line.trim_end())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distribution() {
+        let mut dist = Distribution::default();
+        [5000, 50, 150, 120].into_iter().for_each(|v| dist.push(v));
+        dist.finish();
+
+        assert_eq!((dist.min(), dist.max()), (Some(50), Some(5000)));
+        assert_eq!(dist.median(), Some(135.0));
+        assert_eq!(dist.mean(), Some(1330.0));
+        assert_eq!(dist.histogram(&LENGTH_EDGES), vec![1, 2, 1, 0, 0, 0]);
+        assert_eq!(Distribution::default().median(), None);
+    }
+
+    #[test]
+    fn test_stats_by_biotype() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("stats.gtf");
+        std::fs::write(
+            &input,
+            "chr1\tHAVANA\ttranscript\t1\t60\t.\t+\t.\ttranscript_id \"A\"; transcript_type \"protein_coding\";\n\
+             chr1\tHAVANA\texon\t1\t20\t.\t+\t.\ttranscript_id \"A\";\n\
+             chr1\tHAVANA\texon\t41\t60\t.\t+\t.\ttranscript_id \"A\";\n\
+             chr2\tHAVANA\ttranscript\t1\t300\t.\t-\t.\ttranscript_id \"B\"; transcript_type \"lncRNA\";\n\
+             chr2\tHAVANA\texon\t1\t300\t.\t-\t.\ttranscript_id \"B\";\n\
+             chr2\tHAVANA\texon\t101\t130\t.\t-\t.\ttranscript_id \"C\";\n\
+             chr2\tHAVANA\ttranscript\t1\t9\t.\t-\t.\ttranscript_id \"D\";\n",
+        )
+        .unwrap();
+
+        let stats = stats(&Config::new(&input, "unused.bed"), DEFAULT_STATS_BY).unwrap();
+
+        assert_eq!(stats.all.span.len(), 3);
+        assert_eq!(stats.all.spliced.max(), Some(300));
+        assert_eq!(stats.groups["protein_coding"].spliced.min(), Some(40));
+        assert_eq!(stats.groups["protein_coding"].span.min(), Some(60));
+        assert_eq!(stats.groups["."].span.len(), 1);
+        assert_eq!(stats.groups["protein_coding"].introns.max(), Some(1));
+        assert_eq!(
+            stats.all.exons.histogram(&COUNT_EDGES),
+            vec![0, 2, 1, 0, 0, 0, 0]
+        );
+        assert_eq!(stats.features["chr2"]["exon"], 2);
+        assert_eq!(stats.features["chr1"].get("CDS"), None);
+        assert!(stats.to_string().starts_with(
+            "records per transcript_type\n\
+             transcript_type  records  percent\n\
+             all                    3    100.0\n"
+        ));
+        assert!(stats.render(StatsFormat::Tsv).contains(
+            "#features per chromosome\nchrom\texon\ttranscript\nchr1\t2\t1\nchr2\t2\t2\n"
+        ));
+        let json: serde_json::Value =
+            serde_json::from_str(&stats.render(StatsFormat::Json)).unwrap();
+        assert_eq!(json["distributions"]["span"]["lncRNA"]["max"], 300);
+        assert_eq!(json["footprint"]["chr1"]["intronic"], 20);
+    }
+
+    #[test]
+    fn test_footprint() {
+        assert_eq!(merged_len(&mut [(50, 80), (0, 20), (10, 30), (30, 40)]), 70);
+
+        let footprint = Footprint::new(
+            vec![(100, 400), (300, 500)],
+            vec![(100, 150), (120, 200), (450, 500)],
+            Some(1000),
+        );
+        assert_eq!(
+            footprint,
+            Footprint {
+                exonic: 150,
+                intronic: 250,
+                intergenic: Some(600),
+            }
+        );
+        assert_eq!(footprint.covered(), Some(0.4));
+        assert_eq!(
+            Footprint::new(vec![(0, 10)], vec![(0, 10)], None).covered(),
+            None
+        );
+    }
+}
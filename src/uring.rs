@@ -0,0 +1,276 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+use io_uring::{opcode, types, IoUring};
+
+use crate::progress::ProgressHook;
+
+/// Bytes per read or write request.
+const BLOCK: usize = 1 << 20;
+/// Requests kept in flight at once.
+const DEPTH: usize = 8;
+
+/// Sets up a ring, or `None` when the kernel (or a seccomp profile, as in
+/// many containers) does not allow io_uring; callers then use std I/O.
+fn ring() -> Option<IoUring> {
+    match IoUring::new(DEPTH as u32) {
+        Ok(ring) => Some(ring),
+        Err(err) => {
+            log::debug!("io_uring unavailable ({}), using blocking I/O", err);
+            None
+        }
+    }
+}
+
+fn completion_error(result: i32) -> Option<io::Error> {
+    match result {
+        r if r < 0 => Some(io::Error::from_raw_os_error(-r)),
+        0 => Some(io::ErrorKind::UnexpectedEof.into()),
+        _ => None,
+    }
+}
+
+/// Reads the whole file at `path` with up to [`DEPTH`] block reads in flight.
+///
+/// Returns `Ok(None)` if io_uring is unavailable.
+pub(crate) fn read_file(path: &Path, hook: &dyn ProgressHook) -> io::Result<Option<Vec<u8>>> {
+    let Some(mut ring) = ring() else {
+        return Ok(None);
+    };
+    let file = File::open(path)?;
+    let len = file.metadata()?.len() as usize;
+    hook.start(Some(len as u64));
+
+    let mut buf = vec![0u8; len];
+    // INFO: (offset, len) per in-flight request, indexed by user_data
+    let mut slots: [Option<(usize, usize)>; DEPTH] = [None; DEPTH];
+    let mut queued = Vec::new();
+    let mut next = 0;
+    let mut error = None;
+
+    loop {
+        for (idx, slot) in slots.iter_mut().enumerate() {
+            if slot.is_some() || error.is_some() {
+                continue;
+            }
+            let (offset, n) = match queued.pop() {
+                Some(range) => range,
+                None if next < len => {
+                    let n = BLOCK.min(len - next);
+                    next += n;
+                    (next - n, n)
+                }
+                None => break,
+            };
+            let entry = opcode::Read::new(
+                types::Fd(file.as_raw_fd()),
+                unsafe { buf.as_mut_ptr().add(offset) },
+                n as u32,
+            )
+            .offset(offset as u64)
+            .build()
+            .user_data(idx as u64);
+            // SAFETY: `buf` is neither moved nor dropped until every request is reaped
+            unsafe { ring.submission().push(&entry) }.map_err(io::Error::other)?;
+            *slot = Some((offset, n));
+        }
+
+        if slots.iter().all(Option::is_none) {
+            break;
+        }
+        ring.submit_and_wait(1)?;
+
+        for cqe in ring.completion() {
+            let idx = cqe.user_data() as usize;
+            let (offset, n) = slots[idx].take().expect("ERROR: Unknown io_uring request");
+            if let Some(err) = completion_error(cqe.result()) {
+                error.get_or_insert(err);
+                continue;
+            }
+            let read = cqe.result() as usize;
+            if read < n {
+                queued.push((offset + read, n - read));
+            }
+            hook.advance(read as u64);
+        }
+    }
+
+    match error {
+        Some(err) => Err(err),
+        None => Ok(Some(buf)),
+    }
+}
+
+/// File writer that hands full blocks to io_uring, keeping up to [`DEPTH`]
+/// writes in flight while the next block fills.
+pub(crate) struct UringWriter {
+    file: File,
+    ring: IoUring,
+    block: Vec<u8>,
+    /// (buffer, bytes already written, file offset) per in-flight write.
+    slots: [Option<(Vec<u8>, usize, u64)>; DEPTH],
+    offset: u64,
+    spare: Vec<Vec<u8>>,
+}
+
+impl UringWriter {
+    /// Creates `path`; `Ok(None)` if io_uring is unavailable.
+    pub(crate) fn create<P: AsRef<Path>>(path: P) -> io::Result<Option<Self>> {
+        let Some(ring) = ring() else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            file: File::create(path)?,
+            ring,
+            block: Vec::with_capacity(BLOCK),
+            slots: Default::default(),
+            offset: 0,
+            spare: Vec::new(),
+        }))
+    }
+
+    fn submit(&mut self, idx: usize) -> io::Result<()> {
+        let (buf, written, offset) = self.slots[idx]
+            .as_ref()
+            .expect("ERROR: Empty io_uring slot");
+        let entry = opcode::Write::new(
+            types::Fd(self.file.as_raw_fd()),
+            unsafe { buf.as_ptr().add(*written) },
+            (buf.len() - written) as u32,
+        )
+        .offset(offset + *written as u64)
+        .build()
+        .user_data(idx as u64);
+        // SAFETY: the buffer stays in its slot until its write is reaped
+        unsafe { self.ring.submission().push(&entry) }.map_err(io::Error::other)?;
+        Ok(())
+    }
+
+    /// Waits for at least one write; short writes are resubmitted.
+    fn reap(&mut self) -> io::Result<()> {
+        self.ring.submit_and_wait(1)?;
+
+        let done = self
+            .ring
+            .completion()
+            .map(|cqe| (cqe.user_data() as usize, cqe.result()))
+            .collect::<Vec<_>>();
+        let mut error = None;
+        for (idx, result) in done {
+            if let Some(err) = completion_error(result) {
+                self.slots[idx] = None;
+                error.get_or_insert(err);
+                continue;
+            }
+            let slot = self.slots[idx]
+                .as_mut()
+                .expect("ERROR: Unknown io_uring request");
+            slot.1 += result as usize;
+            if slot.1 < slot.0.len() {
+                self.submit(idx)?;
+            } else if let Some((mut buf, _, _)) = self.slots[idx].take() {
+                buf.clear();
+                self.spare.push(buf);
+            }
+        }
+
+        error.map_or(Ok(()), Err)
+    }
+
+    /// Queues the current block for writing.
+    fn send(&mut self) -> io::Result<()> {
+        let idx = loop {
+            match self.slots.iter().position(Option::is_none) {
+                Some(idx) => break idx,
+                None => self.reap()?,
+            }
+        };
+
+        let fresh = self
+            .spare
+            .pop()
+            .unwrap_or_else(|| Vec::with_capacity(BLOCK));
+        let block = std::mem::replace(&mut self.block, fresh);
+        let len = block.len() as u64;
+        self.slots[idx] = Some((block, 0, self.offset));
+        self.offset += len;
+        self.submit(idx)
+    }
+
+    fn in_flight(&self) -> bool {
+        self.slots.iter().any(Option::is_some)
+    }
+}
+
+impl Write for UringWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(BLOCK - self.block.len());
+        self.block.extend_from_slice(&buf[..n]);
+        if self.block.len() == BLOCK {
+            self.send()?;
+        }
+        Ok(n)
+    }
+
+    /// Waits until everything written so far is in the file.
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.block.is_empty() {
+            self.send()?;
+        }
+        while self.in_flight() {
+            self.reap()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for UringWriter {
+    fn drop(&mut self) {
+        // INFO: the kernel may still read from the buffers, so wait for them
+        while self.in_flight() {
+            if self.ring.submit_and_wait(1).is_err() {
+                break;
+            }
+            let done = self
+                .ring
+                .completion()
+                .map(|cqe| cqe.user_data() as usize)
+                .collect::<Vec<_>>();
+            for idx in done {
+                self.slots[idx] = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::NoProgress;
+
+    #[test]
+    fn test_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("uring.bed");
+        let data = (0..300_000)
+            .map(|i| format!("chr1\t{}\t{}\ttx{}\n", i, i + 10, i))
+            .collect::<String>();
+
+        let Some(mut writer) = UringWriter::create(&path).unwrap() else {
+            return;
+        };
+        for line in data.as_bytes().chunks(777) {
+            writer.write_all(line).unwrap();
+        }
+        writer.flush().unwrap();
+        drop(writer);
+
+        assert!(data.len() > 2 * BLOCK);
+        assert_eq!(std::fs::read(&path).unwrap(), data.as_bytes());
+        let read = read_file(&path, &NoProgress).unwrap().unwrap();
+        assert_eq!(read, data.as_bytes());
+    }
+}
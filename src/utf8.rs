@@ -0,0 +1,168 @@
+use std::borrow::Cow;
+
+use crate::diagnostic::Span;
+use crate::error::Gxf2BedError;
+
+/// Turns decoded input into text. Invalid UTF-8 (e.g. Latin-1 gene
+/// descriptions) fails with its line and byte offset, or with `lossy` is
+/// replaced by U+FFFD after a warning.
+pub(crate) fn into_text(bytes: Vec<u8>, lossy: bool) -> Result<String, Gxf2BedError> {
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok(text),
+        Err(err) => {
+            let bad = BadUtf8::locate(err.as_bytes(), err.utf8_error().valid_up_to(), 1, 0);
+            if !lossy {
+                return Err(bad.into());
+            }
+
+            bad.warn();
+            Ok(String::from_utf8_lossy(err.as_bytes()).into_owned())
+        }
+    }
+}
+
+/// Position of the first invalid UTF-8 sequence in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BadUtf8 {
+    /// 1-based line number.
+    pub line: usize,
+    /// Byte offset from the start of the decoded input.
+    pub offset: u64,
+    pub byte: u8,
+    /// The line, bad bytes replaced by U+FFFD, and where the first one is.
+    pub text: String,
+    pub range: std::ops::Range<usize>,
+}
+
+impl BadUtf8 {
+    /// Locates byte `valid_up_to` of `bytes`, a chunk of the input starting
+    /// at line `line` and byte offset `offset`.
+    pub(crate) fn locate(bytes: &[u8], valid_up_to: usize, line: usize, offset: u64) -> Self {
+        let start = memchr::memrchr(b'\n', &bytes[..valid_up_to]).map_or(0, |idx| idx + 1);
+        let end = memchr::memchr(b'\n', &bytes[valid_up_to..])
+            .map_or(bytes.len(), |idx| valid_up_to + idx);
+        let text = String::from_utf8_lossy(&bytes[start..end])
+            .trim_end_matches('\r')
+            .to_string();
+        // INFO: the line is valid up to the bad byte, so its offset survives the replacement
+        let column = valid_up_to - start;
+
+        Self {
+            line: line + memchr::memchr_iter(b'\n', &bytes[..valid_up_to]).count(),
+            offset: offset + valid_up_to as u64,
+            byte: bytes[valid_up_to],
+            range: column..(column + char::REPLACEMENT_CHARACTER.len_utf8()).min(text.len()),
+            text,
+        }
+    }
+
+    /// Logs that bad bytes are being replaced.
+    pub(crate) fn warn(&self) {
+        log::warn!(
+            line = self.line, offset = self.offset;
+            "INVALID UTF-8: byte 0x{:02X} at line {} (byte offset {}); invalid sequences are replaced with U+FFFD",
+            self.byte,
+            self.line,
+            self.offset
+        );
+    }
+}
+
+impl From<BadUtf8> for Gxf2BedError {
+    fn from(bad: BadUtf8) -> Self {
+        Gxf2BedError::Parse {
+            line: bad.line,
+            message: format!(
+                "invalid UTF-8 byte 0x{:02X} at byte offset {} (use --lossy-utf8 to replace bad bytes)",
+                bad.byte, bad.offset
+            ),
+            span: Some(Box::new(Span {
+                file: None,
+                line: bad.line,
+                text: bad.text,
+                range: bad.range,
+                hint: Some(
+                    "the file may be Latin-1; re-encode it with `iconv -f latin1 -t utf8`".to_string(),
+                ),
+            })),
+        }
+    }
+}
+
+/// Line-by-line counterpart of [`into_text`] for streamed input; warns only
+/// for the first bad line.
+#[derive(Debug, Default)]
+pub(crate) struct Lines {
+    line: usize,
+    offset: u64,
+    warned: bool,
+}
+
+impl Lines {
+    /// 1-based number of the last line read.
+    pub(crate) fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Text of the next raw line `bytes` (terminator included).
+    pub(crate) fn next<'a>(
+        &mut self,
+        bytes: &'a [u8],
+        lossy: bool,
+    ) -> Result<Cow<'a, str>, Gxf2BedError> {
+        self.line += 1;
+        let offset = self.offset;
+        self.offset += bytes.len() as u64;
+
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Ok(Cow::Borrowed(text)),
+            Err(err) => {
+                let bad = BadUtf8::locate(bytes, err.valid_up_to(), self.line, offset);
+                if !lossy {
+                    return Err(bad.into());
+                }
+                if !std::mem::replace(&mut self.warned, true) {
+                    bad.warn();
+                }
+                Ok(String::from_utf8_lossy(bytes))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_line_and_offset() {
+        let latin1 = b"chr1\tA\n#note\nchr1\tgene_name \"Caf\xe9\";\n".to_vec();
+
+        match into_text(latin1.clone(), false).unwrap_err() {
+            Gxf2BedError::Parse { line, message, .. } => {
+                assert_eq!(line, 3);
+                assert!(message.contains("0xE9 at byte offset 32"), "{message}");
+            }
+            err => panic!("unexpected error {err}"),
+        }
+        let err = into_text(latin1.clone(), false).unwrap_err();
+        let span = err.in_file("a.gtf").span().cloned().unwrap();
+        assert_eq!(span.text, "chr1\tgene_name \"Caf\u{FFFD}\";");
+        assert_eq!((span.range, span.file), (19..22, Some("a.gtf".into())));
+        assert!(into_text(latin1.clone(), true)
+            .unwrap()
+            .contains("Caf\u{FFFD}\";"));
+
+        let mut lines = Lines::default();
+        let mut texts = latin1.split_inclusive(|b| *b == b'\n');
+        assert!(lines.next(texts.next().unwrap(), false).is_ok());
+        assert!(lines.next(texts.next().unwrap(), false).is_ok());
+        assert_eq!(
+            lines
+                .next(texts.next().unwrap(), false)
+                .unwrap_err()
+                .to_string(),
+            into_text(latin1, false).unwrap_err().to_string()
+        );
+    }
+}
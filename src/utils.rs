@@ -1,26 +1,42 @@
 use std::error::Error;
 use std::fmt::Debug;
 use std::fs::File;
-use std::io::{BufWriter, Read, Write};
+use std::io::{Read, Write};
 use std::path::Path;
 
 use colored::Colorize;
-use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use hashbrown::HashMap;
 use indoc::indoc;
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
 use crate::cli::Args;
-use crate::gxf::{GenePred, GxfRecord, RecordType};
+use crate::codec::decoder;
+use crate::config::Config;
+use crate::detect::Compression;
+use crate::error::Gxf2BedError;
+#[cfg(feature = "parallel")]
+use crate::gxf::line_chunks;
+use crate::gxf::{
+    is_comment, lines, until_fasta, GenePred, GxfRecord, InputCoords, KeyMatcher, MixedStrand,
+    Strand,
+};
+use crate::isoforms::IsoformRank;
+use crate::loci;
+use crate::output::{finish_output, wrap_output};
+use crate::progress::{stderr_hook, ProgressHook, ProgressReader};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub fn convert(args: Args) {
     let mut sep = b' ';
+    let hook = stderr_hook();
+    let gxf = args.gxf.expect("ERROR: No input file given");
+    let output = args.output.expect("ERROR: No output file given");
 
-    let contents = match args.gxf.extension().and_then(|s| s.to_str()) {
+    let contents = match gxf.extension().and_then(|s| s.to_str()) {
         Some("gz") => {
-            match Path::new(args.gxf.file_stem().unwrap())
+            match Path::new(gxf.file_stem().unwrap())
                 .extension()
                 .expect("ERROR: No extension found")
                 .to_str()
@@ -30,97 +46,345 @@ pub fn convert(args: Args) {
                 }
                 _ => (),
             };
-            with_gz(&args.gxf).expect("ERROR: Could not read GZ file")
+            with_gz(&gxf, &*hook).expect("ERROR: Could not read GZ file")
         }
-        Some("gtf") => raw(&args.gxf).expect("ERROR: Could not read GTF file"),
+        Some("gtf") => raw(&gxf, &*hook).expect("ERROR: Could not read GTF file"),
         Some("gff") | Some("gff3") => {
             sep = b'=';
-            raw(&args.gxf).expect("ERROR: Could not read GFF file")
+            raw(&gxf, &*hook).expect("ERROR: Could not read GFF file")
         }
         _ => panic!("ERROR: Not a GTF/GFF. Wrong file format!"),
     };
 
-    let data = to_bed(&contents, args.parent, args.child, args.feature, sep)
-        .expect("ERROR: Could not parse GTF/GFF file");
+    if let Some(attr_sep) = args.attr_sep {
+        sep = attr_sep as u8;
+    }
+    let grouping = Grouping {
+        child_feature: args.child_feature,
+        comment_chars: args.comment_chars,
+        coords: args.input_coords,
+        ..Grouping::new(args.parent, args.child.join(","), args.feature)
+    };
+    let data = to_bed(&contents, sep, &grouping).expect("ERROR: Could not parse GTF/GFF file");
     log::info!("{} records parsed", data.len());
 
-    write_obj(&args.output, data);
+    write_obj(&output, data);
+}
+
+/// How features are grouped into models.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grouping {
+    /// Feature type whose span defines each model.
+    pub parent: String,
+    /// Feature type turned into blocks; a comma-separated list unions
+    /// several types.
+    pub child: String,
+    /// Attribute holding the grouping key.
+    pub feature: String,
+    /// Attribute holding the grouping key on child lines when it differs
+    /// from `feature`, e.g. `Parent` in GFF3; comma-separated values add
+    /// the child to each listed model.
+    pub child_feature: Option<String>,
+    pub coords: InputCoords,
+    /// Attributes captured per model (see [`GenePred::attrs`]).
+    pub attrs: Vec<String>,
+    /// Captured attribute whose repeated values are all kept, joined by
+    /// commas (GENCODE `tag`).
+    pub repeated: Option<String>,
+    /// Line prefixes skipped like `#` comments.
+    pub comment_chars: Vec<String>,
+    /// Whether `CDS`, `start_codon` and `stop_codon` lines are kept (see [`GenePred::coding`]).
+    pub coding: bool,
+    /// Attribute holding child exon numbers, kept per model when set (see
+    /// [`GenePred::exon_numbers`]).
+    pub exon_number: Option<String>,
+    /// Whether the whole attribute column of the captured line is kept as
+    /// well, as JSON after `attrs`.
+    pub attrs_json: bool,
+    /// How features of an ID on both strands of a chromosome are grouped;
+    /// anything but `Split` keys them together (see [`GenePred::strands`]).
+    pub mixed_strand: MixedStrand,
+}
+
+impl Grouping {
+    pub fn new<P: Into<String>, C: Into<String>, F: Into<String>>(
+        parent: P,
+        child: C,
+        feature: F,
+    ) -> Self {
+        Self {
+            parent: parent.into(),
+            child: child.into(),
+            feature: feature.into(),
+            child_feature: None,
+            coords: InputCoords::default(),
+            attrs: Vec::new(),
+            repeated: None,
+            comment_chars: Vec::new(),
+            coding: false,
+            exon_number: None,
+            attrs_json: false,
+            mixed_strand: MixedStrand::default(),
+        }
+    }
+
+    /// Strand part of the grouping key of a feature on `strand`.
+    #[inline]
+    pub(crate) fn key_strand(&self, strand: Strand) -> Strand {
+        loci::key_strand(self.mixed_strand, strand)
+    }
+
+    /// Whether attribute values are captured per model.
+    fn captures(&self) -> bool {
+        !self.attrs.is_empty() || self.attrs_json
+    }
+
+    /// Whether `feature` is one of the comma-separated `child` types.
+    #[inline]
+    pub fn is_child(&self, feature: &str) -> bool {
+        self.child.split(',').any(|child| child == feature)
+    }
+}
+
+impl From<&Config> for Grouping {
+    fn from(config: &Config) -> Self {
+        Self {
+            child_feature: config.child_feature.as_deref().map(|f| config.attr_key(f)),
+            coords: config.input_coords,
+            attrs: config.captured_attrs(),
+            repeated: (config.max_transcripts_per_gene.is_some()
+                && config.rank_isoforms == IsoformRank::Tags)
+                .then(|| config.attr_key("tag")),
+            comment_chars: config.comment_chars.clone(),
+            coding: config.compat.is_some()
+                || config.check_frame
+                || config.check_codons
+                || config.check_orf
+                || config.containment_report.is_some()
+                || config.cds_exons,
+            exon_number: config.exon_ranks.then(|| config.attr_key("exon_number")),
+            attrs_json: config.attrs_json,
+            mixed_strand: config.mixed_strand,
+            ..Self::new(&config.parent, &config.child, config.feature_key())
+        }
+    }
 }
 
-pub fn to_bed<'a>(
+pub fn to_bed(
     content: &str,
-    parent: String,
-    child: String,
-    feature: String,
     sep: u8,
-) -> Result<HashMap<String, GenePred>, &'static str> {
-    let rs = content
-        .par_lines()
-        .filter(|row| !row.starts_with("#"))
-        .filter_map(|row| match sep {
-            b' ' => GxfRecord::parse::<b' '>(row, &feature).ok(),
-            b'=' => GxfRecord::parse::<b'='>(row, &feature).ok(),
-            _ => None,
-        })
-        .fold(
-            || HashMap::new(),
-            |mut acc, record| {
-                let feature = record.attr.feature().to_owned();
-                let entry = acc.entry(feature).or_insert_with(GenePred::new);
-
-                if record.feature == parent {
-                    entry.chr = record.chr.to_owned();
-                    entry.start = record.start;
-                    entry.end = record.end;
-                    entry.strand = record.strand;
-                    entry.record_type = RecordType::Parent;
-                } else if record.feature == child {
-                    entry.chr = record.chr.to_owned();
-                    entry.strand = record.strand;
-                    entry.start = record.start.min(entry.start);
-                    entry.end = record.end.max(entry.end);
-                    entry
-                        .exons
-                        .insert((record.start, record.end - record.start));
-                    if entry.record_type != RecordType::Parent {
-                        entry.record_type = RecordType::Child;
-                    }
-                }
+    grouping: &Grouping,
+) -> Result<HashMap<String, GenePred>, Gxf2BedError> {
+    let mut rs = to_bed_many(content, sep, std::slice::from_ref(grouping))?;
+    Ok(rs.pop().unwrap_or_default())
+}
 
-                acc
-            },
-        )
-        .reduce(
-            || HashMap::new(),
-            |mut left, right| {
+/// Same as [`to_bed`] for several groupings sharing `feature`, `coords`,
+/// `attrs` and `comment_chars` (e.g. one per child type), parsing each
+/// line once; the maps are in the order of `groupings`.
+///
+/// Fails on the first feature line that cannot be parsed, naming its line
+/// number; blank lines are skipped.
+pub fn to_bed_many(
+    content: &str,
+    sep: u8,
+    groupings: &[Grouping],
+) -> Result<Vec<HashMap<String, GenePred>>, Gxf2BedError> {
+    let Some(first) = groupings.first() else {
+        return Ok(Vec::new());
+    };
+    let keys = KeyMatcher::fallback(&first.feature);
+    let coords = first.coords;
+    // INFO: GTF has no FASTA section; GFF3 and custom dialects may
+    let content = match sep {
+        b' ' => content,
+        _ => until_fasta(content),
+    };
+    let empty = || groupings.iter().map(|_| HashMap::new()).collect::<Vec<_>>();
+    let add = |mut accs: Vec<HashMap<String, GenePred>>, row: &str| -> Result<_, Gxf2BedError> {
+        let record = parse_row(row, &keys, sep, coords)
+            .map_err(|e| Gxf2BedError::record(line_of(content, row), row, e))?;
+        for (acc, grouping) in accs.iter_mut().zip(groupings) {
+            *acc = add_record(std::mem::take(acc), &record, grouping);
+        }
+        Ok(accs)
+    };
+    let skip = |row: &&str| is_comment(row, &first.comment_chars) || row.trim().is_empty();
+
+    #[cfg(feature = "parallel")]
+    let mut rs = line_chunks(content, 4 * rayon::current_num_threads())
+        .par_iter()
+        .flat_map_iter(|chunk| lines(chunk))
+        .filter(|row| !skip(row))
+        .try_fold(empty, add)
+        .try_reduce(empty, |mut lefts, rights| {
+            for (left, right) in lefts.iter_mut().zip(rights) {
                 for (feature, info) in right {
                     let entry = left.entry(feature).or_insert_with(GenePred::new);
                     entry.merge(info);
                 }
-                left
-            },
-        );
+            }
+            Ok(lefts)
+        })?;
+
+    #[cfg(not(feature = "parallel"))]
+    let mut rs = lines(content)
+        .filter(|row| !skip(row))
+        .try_fold(empty(), add)?;
 
+    rs.iter_mut().for_each(loci::resolve);
     Ok(rs)
 }
 
-pub fn raw<P: AsRef<Path> + Debug>(f: P) -> Result<String, Box<dyn Error>> {
-    let mut file = File::open(f)?;
+pub(crate) fn parse_row<'a>(
+    row: &'a str,
+    keys: &KeyMatcher,
+    sep: u8,
+    coords: InputCoords,
+) -> Result<GxfRecord<'a>, &'static str> {
+    GxfRecord::parse_with(row, keys, sep, coords)
+}
+
+/// 1-based line number of `row`, a line sliced from `content`.
+fn line_of(content: &str, row: &str) -> usize {
+    let offset = row.as_ptr() as usize - content.as_ptr() as usize;
+    memchr::memchr_iter(b'\n', &content.as_bytes()[..offset]).count() + 1
+}
+
+pub(crate) fn add_record(
+    mut acc: HashMap<String, GenePred>,
+    record: &GxfRecord,
+    grouping: &Grouping,
+) -> HashMap<String, GenePred> {
+    // INFO: lines without the grouping attribute would all merge into one nameless record
+    if record.feature == grouping.parent && !record.attr.feature().is_empty() {
+        let entry = acc
+            .entry(loci::key(
+                record.attr.feature(),
+                &record.chr,
+                grouping.key_strand(record.strand),
+            ))
+            .or_default();
+        entry.set_parent(&record.chr, record.start, record.end, record.strand);
+        if grouping.captures() {
+            entry.attrs = attr_values(record, grouping);
+        }
+    } else if grouping.is_child(record.feature) {
+        let exon_number = grouping
+            .exon_number
+            .as_deref()
+            .and_then(|key| record.attr.get(key)?.parse::<u32>().ok());
+        for id in child_ids(record, grouping) {
+            let entry = acc
+                .entry(loci::key(
+                    id,
+                    &record.chr,
+                    grouping.key_strand(record.strand),
+                ))
+                .or_default();
+            entry.add_child(&record.chr, record.start, record.end, record.strand);
+            if entry.attrs.is_empty() && grouping.captures() {
+                entry.attrs = attr_values(record, grouping);
+            }
+            if let Some(number) = exon_number {
+                entry.exon_numbers.push((record.start, number));
+            }
+        }
+    }
+
+    // INFO: may also be the child feature (CDS-only models), so checked apart
+    if grouping.coding && matches!(record.feature, "CDS" | "start_codon" | "stop_codon") {
+        for id in child_ids(record, grouping) {
+            let entry = acc
+                .entry(loci::key(
+                    id,
+                    &record.chr,
+                    grouping.key_strand(record.strand),
+                ))
+                .or_default();
+            // INFO: locates models with no other lines, in case their CDS become the blocks
+            if entry.chr.is_empty() {
+                entry.chr.clone_from(&record.chr);
+                entry.strand = record.strand;
+            }
+            let coding = entry.coding_mut();
+            match record.feature {
+                "CDS" => coding.add_cds(record.start, record.end, &record.frame),
+                "start_codon" => coding.add_start_codon(record.start, record.end),
+                _ => coding.add_stop_codon(record.start, record.end),
+            }
+        }
+    }
+
+    acc
+}
+
+/// Grouping keys a child-level `record` belongs to; none without the
+/// grouping attribute.
+pub(crate) fn child_ids<'a>(
+    record: &'a GxfRecord,
+    grouping: &Grouping,
+) -> impl Iterator<Item = &'a str> {
+    let (ids, sep) = match &grouping.child_feature {
+        Some(key) => (record.attr.get(key).unwrap_or_default(), ','),
+        // INFO: '\n' never occurs within a line, so the key stays whole
+        None => (record.attr.feature(), '\n'),
+    };
+    ids.split(sep).filter(|id| !id.is_empty())
+}
+
+/// Values of `grouping.attrs` on `record`, then its whole column as JSON
+/// with `grouping.attrs_json`; empty values count as missing.
+fn attr_values(record: &GxfRecord, grouping: &Grouping) -> Vec<Option<String>> {
+    let mut values = grouping
+        .attrs
+        .iter()
+        .map(|key| match grouping.repeated.as_ref() == Some(key) {
+            true => Some(record.attr.get_all(key).join(",")).filter(|value| !value.is_empty()),
+            false => record
+                .attr
+                .get(key)
+                .filter(|value| !value.is_empty())
+                .map(str::to_owned),
+        })
+        .collect::<Vec<_>>();
+    if grouping.attrs_json {
+        values.push(Some(record.attr.to_json()));
+    }
+    values
+}
+
+pub fn raw<P: AsRef<Path> + Debug>(
+    f: P,
+    hook: &dyn ProgressHook,
+) -> Result<String, Box<dyn Error>> {
+    let file = File::open(f)?;
+    hook.start(file.metadata().ok().map(|m| m.len()));
+
     let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
+    ProgressReader::new(file, hook).read_to_string(&mut contents)?;
+    hook.finish();
 
     Ok(contents)
 }
 
-pub fn with_gz<P: AsRef<Path> + Debug>(f: P) -> Result<String, Box<dyn Error>> {
+pub fn with_gz<P: AsRef<Path> + Debug>(
+    f: P,
+    hook: &dyn ProgressHook,
+) -> Result<String, Box<dyn Error>> {
     let file = File::open(f)?;
-    let mut decoder = GzDecoder::new(file);
+    hook.start(file.metadata().ok().map(|m| m.len()));
+
+    // INFO: progress is tracked on compressed bytes, so the percentage matches the file size
+    let mut reader = decoder(ProgressReader::new(file, hook), Compression::Gzip)?;
     let mut contents = String::new();
 
-    decoder.read_to_string(&mut contents)?;
+    reader.read_to_string(&mut contents)?;
+    hook.finish();
     Ok(contents)
 }
 
+#[cfg(feature = "rusage")]
 pub fn max_mem_usage_mb() -> f64 {
     let rusage = unsafe {
         let mut rusage = std::mem::MaybeUninit::uninit();
@@ -142,11 +406,9 @@ pub fn write_obj<P: AsRef<Path> + Debug>(filename: P, data: HashMap<String, Gene
     };
     log::info!("Writing to {:?}", filename);
 
-    let mut writer: Box<dyn Write> = match filename.as_ref().extension() {
-        Some(ext) if ext == "gz" => {
-            Box::new(BufWriter::new(GzEncoder::new(f, Compression::fast())))
-        }
-        _ => Box::new(BufWriter::new(f)),
+    let mut writer = match wrap_output(&filename, f, None, 1) {
+        Err(err) => panic!("couldn't write file {:?}: {}", filename, err),
+        Ok(writer) => writer,
     };
 
     let mut skips = 0;
@@ -181,13 +443,15 @@ pub fn write_obj<P: AsRef<Path> + Debug>(filename: P, data: HashMap<String, Gene
         );
         writeln!(writer, "{}", line).unwrap();
     }
+    finish_output(writer).unwrap();
 
     log::warn!("Skipped {} records with no childs!", skips);
     log::info!("Done writing!");
 }
 
+/// Prints the banner to stderr, keeping stdout free for piped output.
 pub fn initialize() {
-    println!(
+    eprintln!(
         "{}\n{}\n{}\n",
         "\n##### GXF2BED #####".bright_magenta().bold(),
         indoc!(
@@ -215,11 +479,9 @@ mod test {
         chr1	HAVANA	three_prime_utr	92841863	92841924	.	+	.	gene_symbol "RPL5"; gene_id "ENSG00000122406.14"; gene_name "RPL5"; transcript_id "RPL5-202"; transcript_name "RPL5-202";"#;
 
         let data = to_bed(
-            &content,
-            "transcript".to_string(),
-            "exon".to_string(),
-            "transcript_id".to_string(),
+            content,
             b' ',
+            &Grouping::new("transcript", "exon", "transcript_id"),
         )
         .expect("ERROR: Could not parse GTF file");
 
@@ -254,11 +516,9 @@ mod test {
         chr1	HAVANA	three_prime_utr	92841863	92841924	.	+	.	gene_symbol "RPL5"; gene_id "ENSG00000122406.14"; gene_name "RPL5"; transcript_id "RPL5-202"; transcript_name "RPL5-202";"#;
 
         let data = to_bed(
-            &content,
-            "transcript".to_string(),
-            "CDS".to_string(),
-            "transcript_id".to_string(),
+            content,
             b' ',
+            &Grouping::new("transcript", "CDS", "transcript_id"),
         )
         .expect("ERROR: Could not parse GTF file");
 
@@ -293,11 +553,9 @@ mod test {
         chr1	HAVANA	three_prime_utr	92841863	92841924	.	+	.	gene_symbol "RPL5"; gene_id "ENSG00000122406.14"; gene_name "RPL5"; transcript_id "RPL5-202"; transcript_name "RPL5-202";"#;
 
         let data = to_bed(
-            &content,
-            "transcript".to_string(),
-            "five_prime_utr".to_string(),
-            "transcript_id".to_string(),
+            content,
             b' ',
+            &Grouping::new("transcript", "five_prime_utr", "transcript_id"),
         )
         .expect("ERROR: Could not parse GTF file");
 
@@ -332,11 +590,9 @@ mod test {
         chr1	HAVANA	three_prime_utr	92841863	92841924	.	+	.	gene_symbol "RPL5"; gene_id "ENSG00000122406.14"; gene_name "RPL5"; transcript_id "RPL5-202"; transcript_name "RPL5-202";"#;
 
         let data = to_bed(
-            &content,
-            "transcript".to_string(),
-            "three_prime_utr".to_string(),
-            "transcript_id".to_string(),
+            content,
             b' ',
+            &Grouping::new("transcript", "three_prime_utr", "transcript_id"),
         )
         .expect("ERROR: Could not parse GTF file");
 
@@ -0,0 +1,67 @@
+use std::process::{Command, Output};
+
+fn gxf2bed(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gxf2bed"))
+        .args(args)
+        .args(["--color", "never", "--quiet"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_malformed_coordinate() {
+    let dir = tempfile::tempdir().unwrap();
+    let (gtf, bed) = (dir.path().join("a.gtf"), dir.path().join("a.bed"));
+    std::fs::write(
+        &gtf,
+        "chr1\t.\ttranscript\t1\t100\t.\t+\t.\tgene_id \"G\"; transcript_id \"A\";\n\
+         chr1\t.\texon\tXX\t50\t.\t+\t.\tgene_id \"G\"; transcript_id \"A\";\n",
+    )
+    .unwrap();
+
+    // INFO: in memory, then streamed
+    for extra in [&[][..], &["--max-memory", "1M"]] {
+        let (gtf, bed) = (gtf.to_str().unwrap(), bed.to_str().unwrap());
+        let output = gxf2bed(&[&["-i", gtf, "-o", bed], extra].concat());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert_eq!(output.status.code(), Some(4), "{stderr}");
+        assert!(
+            stderr.contains("Parse error: line 2: Invalid start"),
+            "{stderr}"
+        );
+        assert!(
+            stderr.contains(&format!(
+                " --> {}:2:13\n  |\n\
+                 2 | chr1    .    exon    XX    50    .    +    .    gene_id \"G\"; transcript_id \"A\";\n  \
+                 |                      ^^\n",
+                gtf
+            )),
+            "{stderr}"
+        );
+    }
+    assert!(!bed.exists());
+}
+
+#[test]
+fn test_missing_input() {
+    let dir = tempfile::tempdir().unwrap();
+    let (gtf, bed) = (dir.path().join("a.gtf"), dir.path().join("a.bed"));
+
+    let output = gxf2bed(&["-i", gtf.to_str().unwrap(), "-o", bed.to_str().unwrap()]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(5), "{stderr}");
+    assert!(stderr.contains("does not exist"), "{stderr}");
+
+    std::fs::write(&gtf, "").unwrap();
+    let output = gxf2bed(&["-i", gtf.to_str().unwrap(), "-o", bed.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(5));
+
+    std::fs::write(
+        &gtf,
+        "chr1\t.\texon\t1\t50\t.\t+\t.\ttranscript_id \"A\";\n",
+    )
+    .unwrap();
+    let output = gxf2bed(&["-i", gtf.to_str().unwrap(), "-o", "a.txt"]);
+    assert_eq!(output.status.code(), Some(2));
+}